@@ -0,0 +1,72 @@
+// Throughput of concurrent dispatcher→worker messages over TCP and QUIC
+// Every message takes SIMULATED_LATENCY to handle on the receiving side, standing in for a slow link
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::future::join_all;
+use octaskly::protocol::Message;
+use octaskly::transport::{MessageHandler, Transport, TransportBackend};
+use octaskly::transport_quic::{QuicConfig, QuicTransport};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SIMULATED_LATENCY: Duration = Duration::from_millis(100);
+const CONCURRENT_TASKS: usize = 10;
+
+fn slow_handler() -> MessageHandler {
+    Arc::new(|_msg, _peer| {
+        Box::pin(async {
+            tokio::time::sleep(SIMULATED_LATENCY).await;
+            Ok(None)
+        })
+    })
+}
+
+async fn tcp_pair() -> (Arc<dyn TransportBackend>, SocketAddr) {
+    let mut server = Transport::default();
+    server.listen("127.0.0.1", 0).await.unwrap();
+    let server = Arc::new(server);
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move { server.serve(slow_handler()).await });
+    (Arc::new(Transport::default()), addr)
+}
+
+async fn quic_pair() -> (Arc<dyn TransportBackend>, SocketAddr) {
+    let config = QuicConfig {
+        local_addr: "127.0.0.1:0".parse().unwrap(),
+        insecure: true,
+        ..Default::default()
+    };
+    let server = Arc::new(QuicTransport::new(config.clone(), true).await.unwrap());
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move { server.serve(slow_handler()).await });
+    (Arc::new(QuicTransport::new(config, false).await.unwrap()), addr)
+}
+
+/// Send CONCURRENT_TASKS assignments at once and wait for every ack
+async fn assign_concurrently(client: &dyn TransportBackend, worker: SocketAddr) {
+    let sends = (0..CONCURRENT_TASKS).map(|i| async move {
+        let message = Message::CancelTask {
+            task_id: format!("task-{}", i),
+        };
+        client.send_message_with_ack(worker, &message).await.unwrap();
+    });
+    join_all(sends).await;
+}
+
+fn bench_transports(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("concurrent_assignments");
+    group.sample_size(10).measurement_time(Duration::from_secs(5));
+
+    let (tcp, tcp_addr) = runtime.block_on(tcp_pair());
+    group.bench_function("tcp", |b| b.to_async(&runtime).iter(|| assign_concurrently(tcp.as_ref(), tcp_addr)));
+
+    let (quic, quic_addr) = runtime.block_on(quic_pair());
+    group.bench_function("quic", |b| b.to_async(&runtime).iter(|| assign_concurrently(quic.as_ref(), quic_addr)));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_transports);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+use vergen::EmitBuilder;
+
+// Embed the commit, build time, rustc version and target as VERGEN_* variables for `build_info`
+// Sematkan commit, waktu build, versi rustc dan target sebagai variabel VERGEN_* untuk `build_info`
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    EmitBuilder::builder()
+        .build_timestamp()
+        .cargo_target_triple()
+        .git_sha(true)
+        .rustc_semver()
+        .emit()?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 // Example: Using OCTASKLY as a library
 use octaskly::protocol::Task;
-use octaskly::scheduler::Scheduler;
-use octaskly::executor::Executor;
+use octaskly::scheduler::{Scheduler, SchedulerAlgorithm};
+use octaskly::executor::{CommandPolicy, Executor};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -16,13 +16,13 @@ async fn main() -> anyhow::Result<()> {
     println!("Created task: {} - {}", task2.id, task2.command);
 
     println!("\nExample 2: Queuing tasks with scheduler");
-    let scheduler = Arc::new(Scheduler::new());
+    let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
     scheduler.enqueue(task1).await;
     scheduler.enqueue(task2).await;
     println!("Queue size: {}", scheduler.queue_size().await);
 
     println!("\nExample 3: Executing task directly");
-    let executor = Executor::new(PathBuf::from("/tmp"), true);
+    let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
     let task = Task::new("echo 'Hello from OCTASKLY'".to_string());
     
     match executor.execute_with_timeout(&task).await {
@@ -51,7 +51,7 @@ async fn main() -> anyhow::Result<()> {
     println!("Created message: {:?}", msg);
 
     println!("\nExample 5: Command validation");
-    let executor = Executor::new(PathBuf::from("/tmp"), true);
+    let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
     
     let safe_cmd = "echo 'safe command'";
     let dangerous_cmd = "rm -rf /";
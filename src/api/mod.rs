@@ -1,31 +1,74 @@
 use axum::{
-    extract::{Path, State, Json},
-    http::StatusCode,
+    extract::{
+        ws::{self, WebSocket, WebSocketUpgrade},
+        Path, Query, State, Json,
+    },
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
-    response::Response,
-    routing::{get, post},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, post, put},
     Router,
     http::Request,
     body::Body,
 };
+use futures::Stream;
 use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use serde::{Deserialize, Serialize};
 use tower_http::cors::CorsLayer;
 use anyhow::Result;
 
-use crate::protocol::Task;
-use crate::scheduler::Scheduler;
+use crate::protocol::{parse_cron, FileSpec, Message, Task, TaskStatus, WorkerInfo, WorkerTaskStats};
+use crate::scheduler::{Scheduler, WorkerMetrics};
 use crate::state::DispatcherState;
-use crate::auth::AuthManager;
-use crate::persistence::PersistentStore;
+use crate::auth::{ApiKeyRecord, AuthManager, Claims, Role, TokenPair};
+use crate::metrics::Metrics;
+use crate::persistence::{self, AuditEvent, StoreBackend, StoredTask, TaskFilter};
+use crate::state::events::{DispatcherEvent, EventFilter, TaskEvent, TaskEventHub};
+use crate::util::build_info;
+use crate::transport::TransportBackend;
+
+pub mod rate_limit;
+
+pub use rate_limit::{RateLimitLayer, RateLimiter};
+
+/// Permission required by the worker management endpoints
+pub const MANAGE_WORKERS: &str = "manage_workers";
+
+/// Permission required by the API key management endpoints
+pub const MANAGE_USERS: &str = "manage_users";
+
+/// Header carrying a static API key, accepted instead of a bearer token
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Page size of `GET /api/v1/tasks` when none is given
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Largest page size `GET /api/v1/tasks` will return
+pub const MAX_PAGE_LIMIT: usize = 500;
+
+/// How long `GET /api/v1/workers/:id` waits for the worker's own totals
+pub const WORKER_QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
 
 #[derive(Clone)]
 pub struct ApiState {
     pub scheduler: Arc<Scheduler>,
     pub dispatcher: Arc<DispatcherState>,
     pub auth: Arc<AuthManager>,
-    pub store: Arc<PersistentStore>,
+    pub store: Arc<dyn StoreBackend + Send + Sync>,
+    pub metrics: Arc<Metrics>,
+    pub task_events: Arc<TaskEventHub>,
+    /// Most tasks accepted by the batch endpoint
+    pub max_batch_size: usize,
+    /// Per-client-IP limit on task submissions
+    pub rate_limiter: RateLimiter,
+    /// Reaches workers for cancellations and task totals
+    pub transport: Arc<dyn TransportBackend>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +77,157 @@ pub struct CreateTaskRequest {
     pub timeout: Option<u64>,
     pub inputs: Option<Vec<String>>,
     pub outputs: Option<Vec<String>>,
+    pub dependencies: Option<Vec<String>>,
+    pub max_retries: Option<u32>,
+    pub retry_delay_ms: Option<u64>,
+    pub required_tags: Option<Vec<String>>,
+    pub container_image: Option<String>,
+    pub labels: Option<HashMap<String, String>>,
+    /// Unix timestamp to hold the task back until
+    pub scheduled_at: Option<i64>,
+    /// Cron schedule to repeat the task on; the first run defaults to its next occurrence
+    pub cron_expression: Option<String>,
+    /// http(s) URL the result is POSTed to once the task finishes
+    pub callback_url: Option<String>,
+    /// Files under `files/` in the dispatcher's workdir copied into the task directory before it runs
+    pub input_files: Option<Vec<String>>,
+    /// Files copied back from the task directory to `files/outputs/<task id>/` in the dispatcher's workdir
+    pub output_files: Option<Vec<String>>,
+}
+
+/// A task waiting in the queue, as listed by `GET /api/v1/queue`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueueEntry {
+    /// Number of tasks that will be assigned before this one
+    pub position: usize,
+    pub task_id: String,
+    pub command: String,
+    pub queued_at: Option<i64>,
+    /// `position * avg_task_duration_secs / idle_worker_count`; `None` while no worker is idle
+    pub estimated_wait_secs: Option<f64>,
+}
+
+/// Fields of a resubmitted task that differ from the original
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResubmitTaskRequest {
+    pub command: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+    pub inputs: Option<Vec<String>>,
+    pub outputs: Option<Vec<String>>,
+    pub timeout: Option<u64>,
+    pub required_tags: Option<Vec<String>>,
+}
+
+/// Tasks submitted together, with defaults shared by every item
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchCreateRequest {
+    pub tasks: Vec<CreateTaskRequest>,
+    /// Used by items that set no timeout of their own
+    pub timeout: Option<u64>,
+    /// Used by items that set no required tags of their own
+    pub required_tags: Option<Vec<String>>,
+    /// Added to every item; an item's own labels win on conflicting keys
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// Query string of `GET /api/v1/tasks`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskListParams {
+    /// 1-based page number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+}
+
+impl TaskListParams {
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Requested page size, clamped to `1..=MAX_PAGE_LIMIT`
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    fn filter(&self) -> TaskFilter {
+        let mut filter = TaskFilter::new();
+        if let Some(status) = &self.status {
+            filter = filter.with_status(status.clone());
+        }
+        if let Some(worker_id) = &self.worker_id {
+            filter = filter.with_worker(worker_id.clone());
+        }
+        filter
+    }
+
+    /// `Link` header value pointing at the neighbouring pages, if there are any
+    fn link_header(&self, total: usize) -> Option<String> {
+        let (page, limit) = (self.page(), self.limit());
+        let link = |page: usize, rel: &str| {
+            let params = TaskListParams {
+                page: Some(page),
+                limit: Some(limit),
+                ..self.clone()
+            };
+            let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+            format!("</api/v1/tasks?{}>; rel=\"{}\"", query, rel)
+        };
+
+        let mut links = Vec::new();
+        if page * limit < total {
+            links.push(link(page + 1, "next"));
+        }
+        if page > 1 {
+            links.push(link(page - 1, "prev"));
+        }
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+/// Query string of `GET /api/v1/tasks/export`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TaskExportParams {
+    /// Output format; only `csv` is supported
+    pub format: Option<String>,
+    pub status: Option<String>,
+    pub worker_id: Option<String>,
+}
+
+impl TaskExportParams {
+    fn filter(&self) -> TaskFilter {
+        let mut filter = TaskFilter::all();
+        if let Some(status) = &self.status {
+            filter = filter.with_status(status.clone());
+        }
+        if let Some(worker_id) = &self.worker_id {
+            filter = filter.with_worker(worker_id.clone());
+        }
+        filter
+    }
+}
+
+/// A registered worker with what it is running right now
+#[derive(Debug, Serialize)]
+pub struct WorkerDetails {
+    #[serde(flatten)]
+    pub worker: WorkerInfo,
+    pub current_tasks: Vec<Task>,
+    pub metrics: Option<WorkerMetrics>,
+    /// Totals reported by the worker itself; `None` if it did not answer
+    pub task_stats: Option<WorkerTaskStats>,
+    pub uptime_secs: i64,
+}
+
+/// Why one item of a batch was rejected
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchItemError {
+    pub index: usize,
+    pub error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,6 +248,344 @@ pub struct AuthRequest {
 pub struct AuthResponse {
     pub token: String,
     pub expires_in: i64,
+    pub refresh_token: Option<String>,
+}
+
+impl From<TokenPair> for AuthResponse {
+    fn from(pair: TokenPair) -> Self {
+        Self {
+            token: pair.access_token,
+            expires_in: pair.expires_in,
+            refresh_token: Some(pair.refresh_token),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Dispatcher event as sent on `GET /api/v1/events`: an SSE event name and its JSON body
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiEvent {
+    pub name: &'static str,
+    pub data: serde_json::Value,
+}
+
+impl ApiEvent {
+    /// The SSE form of a dispatcher event, or `None` for events the stream leaves out
+    pub fn from_dispatcher(event: &DispatcherEvent) -> Option<Self> {
+        let (name, data) = match event {
+            DispatcherEvent::TaskStatus { task_id, status, worker_id } => {
+                let name = match status {
+                    TaskStatus::Pending => "task.submitted",
+                    TaskStatus::Running => "task.assigned",
+                    TaskStatus::Completed => "task.completed",
+                    TaskStatus::Failed | TaskStatus::TimedOut => "task.failed",
+                    _ => return None,
+                };
+                (name, json!({ "task_id": task_id, "status": status, "worker_id": worker_id }))
+            }
+            DispatcherEvent::WorkerConnected { worker_id, name } => {
+                ("worker.connected", json!({ "worker_id": worker_id, "name": name }))
+            }
+            DispatcherEvent::WorkerDisconnected { worker_id } => ("worker.disconnected", json!({ "worker_id": worker_id })),
+            DispatcherEvent::QueueDepth { depth } => ("queue.depth_changed", json!({ "depth": depth })),
+            DispatcherEvent::SchedulerPaused { .. } => return None,
+        };
+        Some(Self { name, data })
+    }
+
+    fn into_sse(self) -> Event {
+        Event::default().event(self.name).data(self.data.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enable: bool,
+}
+
+/// Body of `POST /api/v1/auth/api-keys`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub label: String,
+    pub role: Role,
+    /// Defaults to the role's default permissions
+    pub permissions: Option<Vec<String>>,
+}
+
+/// A newly created API key; this is the only time the key itself is returned
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyResponse {
+    pub label: String,
+    pub key: String,
+    pub role: Role,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeUserRequest {
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntrospectRequest {
+    pub token: String,
+}
+
+/// RFC 7662 introspection result; only `active` is present for an inactive token
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<String>>,
+}
+
+impl From<Option<Claims>> for IntrospectResponse {
+    fn from(claims: Option<Claims>) -> Self {
+        match claims {
+            Some(claims) => Self {
+                active: true,
+                sub: Some(claims.sub),
+                role: Some(claims.role),
+                exp: Some(claims.exp),
+                permissions: Some(claims.permissions),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Token from an `Authorization: Bearer` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+/// Write `event` to the audit log without holding up the request
+fn audit(state: &ApiState, event: AuditEvent) {
+    let dispatcher = state.dispatcher.clone();
+    tokio::spawn(async move { dispatcher.record_event(event).await });
+}
+
+/// Claims of the request's `X-API-Key` header or, failing that, its bearer token
+///
+/// Every attempt is audited; the user of a rejected credential is not known, so it is recorded as `unknown`.
+fn authenticate(state: &ApiState, headers: &HeaderMap) -> Result<Claims, (StatusCode, String)> {
+    let result = match headers.get(API_KEY_HEADER).and_then(|h| h.to_str().ok()) {
+        Some(key) => state
+            .auth
+            .verify_api_key(key)
+            .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid API key".to_string())),
+        None => bearer_token(headers)
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing bearer token".to_string()))
+            .and_then(|token| {
+                state
+                    .auth
+                    .verify_token(token)
+                    .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".to_string()))
+            }),
+    };
+    audit_authentication(state, &result);
+    result
+}
+
+/// Audit the outcome of checking a credential
+fn audit_authentication(state: &ApiState, result: &Result<Claims, (StatusCode, String)>) {
+    audit(
+        state,
+        match result {
+            Ok(claims) => AuditEvent::AuthSuccess {
+                user: claims.sub.clone(),
+                role: claims.role.clone(),
+            },
+            Err((_, reason)) => AuditEvent::AuthFailure {
+                user: "unknown".to_string(),
+                reason: reason.clone(),
+            },
+        },
+    );
+}
+
+/// Authenticate the request and check it grants `permission`
+fn require_permission(
+    state: &ApiState,
+    headers: &HeaderMap,
+    permission: &str,
+) -> Result<Claims, (StatusCode, String)> {
+    let claims = authenticate(state, headers)?;
+    if !state.auth.has_permission(&claims, permission) {
+        return Err((StatusCode::FORBIDDEN, format!("Missing {} permission", permission)));
+    }
+    Ok(claims)
+}
+
+/// Authenticate the request and check it comes from an admin
+fn require_admin(state: &ApiState, headers: &HeaderMap, action: &str) -> Result<Claims, (StatusCode, String)> {
+    let claims = authenticate(state, headers)?;
+    if !state.auth.has_role(&claims, &Role::Admin.to_string()) {
+        return Err((StatusCode::FORBIDDEN, format!("{} requires an admin token", action)));
+    }
+    Ok(claims)
+}
+
+/// Log in with a username and password, returning an access token and the refresh token that renews it
+///
+/// Failed logins are audited like any other rejected credential.
+async fn login(
+    State(state): State<ApiState>,
+    Json(req): Json<AuthRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, String)> {
+    match state.auth.login(&req.username, &req.password) {
+        Ok(pair) => Ok(Json(pair.into())),
+        Err(e) => {
+            audit(
+                &state,
+                AuditEvent::AuthFailure {
+                    user: req.username,
+                    reason: e.to_string(),
+                },
+            );
+            Err((StatusCode::UNAUTHORIZED, e.to_string()))
+        }
+    }
+}
+
+/// Trade a refresh token for a new access token and a rotated refresh token
+async fn refresh_token(
+    State(state): State<ApiState>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, String)> {
+    match state.auth.rotate_refresh_token(&req.refresh_token) {
+        Ok(pair) => Ok(Json(pair.into())),
+        Err(e) => Err((StatusCode::UNAUTHORIZED, format!("Invalid refresh token: {}", e))),
+    }
+}
+
+/// Tell another service whether a token this dispatcher issued is active (RFC 7662)
+///
+/// Only admins may introspect, so the endpoint cannot be used to probe tokens anonymously.
+async fn introspect_token(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<IntrospectRequest>,
+) -> Result<Json<IntrospectResponse>, (StatusCode, String)> {
+    require_admin(&state, &headers, "Introspection")?;
+    Ok(Json(state.auth.introspect(&req.token).into()))
+}
+
+/// Revoke every token issued to a user, e.g. a worker whose node was compromised
+async fn revoke_user_tokens(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<RevokeUserRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_admin(&state, &headers, "Revoking tokens")?;
+    let revoked = state.auth.bulk_revoke_by_user(&req.user_id);
+    tracing::warn!("Revoked {} token(s) of {}", revoked, req.user_id);
+    Ok(Json(json!({ "revoked": revoked })))
+}
+
+/// Stop assigning queued tasks until `resume`; running tasks are left to finish
+async fn pause_scheduler(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_admin(&state, &headers, "Pausing the scheduler")?;
+    state.scheduler.pause();
+    state.dispatcher.publish_event(DispatcherEvent::SchedulerPaused { paused: true });
+    Ok(Json(json!({ "paused": true })))
+}
+
+/// Start assigning queued tasks again
+async fn resume_scheduler(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_admin(&state, &headers, "Resuming the scheduler")?;
+    state.scheduler.resume();
+    state.dispatcher.publish_event(DispatcherEvent::SchedulerPaused { paused: false });
+    Ok(Json(json!({ "paused": false })))
+}
+
+/// List API keys (hashes only)
+async fn list_api_keys(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ApiKeyRecord>>, (StatusCode, String)> {
+    require_permission(&state, &headers, MANAGE_USERS)?;
+    Ok(Json(state.auth.list_api_keys()))
+}
+
+/// Create an API key and save it to the task history database so it survives a restart
+///
+/// The key may not grant anything the caller's own credential does not.
+async fn create_api_key(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), (StatusCode, String)> {
+    let claims = require_permission(&state, &headers, MANAGE_USERS)?;
+    if req.label.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "label must not be empty".to_string()));
+    }
+    let permissions = req.permissions.unwrap_or_else(|| req.role.default_permissions());
+    let within_own_role = claims.role.parse::<Role>().is_ok_and(|role| role.implies(req.role));
+    if !within_own_role || !permissions.iter().all(|p| state.auth.has_permission(&claims, p)) {
+        return Err((StatusCode::FORBIDDEN, "API key would grant more than the caller has".to_string()));
+    }
+
+    let key = state
+        .auth
+        .create_api_key(&req.label, req.role, permissions.clone())
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+    let saved = match state.auth.api_key(&req.label) {
+        Some(record) => state.store.add_api_key(&record).await.is_ok(),
+        None => false,
+    };
+    if !saved {
+        state.auth.revoke_api_key(&req.label);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()));
+    }
+
+    tracing::info!("{} created API key {}", claims.sub, req.label);
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            label: req.label,
+            key,
+            role: req.role,
+            permissions,
+        }),
+    ))
+}
+
+/// Revoke an API key by label
+async fn revoke_api_key(
+    State(state): State<ApiState>,
+    Path(label): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_permission(&state, &headers, MANAGE_USERS)?;
+    if !state.auth.revoke_api_key(&label) {
+        return Err((StatusCode::NOT_FOUND, format!("API key not found: {}", label)));
+    }
+    state
+        .store
+        .remove_api_key(&label)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 /// Middleware for JWT verification
@@ -63,36 +595,20 @@ async fn auth_middleware(
     request: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let auth_header = request
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .map(|h| h.to_string());
-
-    if let Some(header) = auth_header {
-        if let Some(token) = header.strip_prefix("Bearer ") {
-            match state.auth.verify_token(token) {
-                Ok(_claims) => {
-                    // Token is valid, proceed
-                    return Ok(next.run(request).await);
-                }
-                Err(_) => {
-                    return Err(StatusCode::UNAUTHORIZED);
-                }
-            }
-        }
+    match authenticate(&state, request.headers()) {
+        // Key or token is valid, proceed
+        Ok(_claims) => Ok(next.run(request).await),
+        Err(_) => Err(StatusCode::UNAUTHORIZED),
     }
-
-    Err(StatusCode::UNAUTHORIZED)
 }
 
-/// Create a new task
-async fn create_task(
-    State(state): State<ApiState>,
-    Json(req): Json<CreateTaskRequest>,
-) -> Result<(StatusCode, Json<TaskResponse>), (StatusCode, String)> {
-    let mut task = Task::new(req.command.clone());
-    
+/// Validate a task request and build the task it describes
+fn build_task(req: CreateTaskRequest) -> Result<Task, String> {
+    if req.command.trim().is_empty() {
+        return Err("command must not be empty".to_string());
+    }
+
+    let mut task = Task::new(req.command);
     if let Some(inputs) = req.inputs {
         task.inputs = inputs;
     }
@@ -100,19 +616,163 @@ async fn create_task(
         task.outputs = outputs;
     }
     if let Some(timeout) = req.timeout {
+        if timeout == 0 {
+            return Err("timeout must be greater than zero".to_string());
+        }
         task.timeout = timeout;
     }
+    if let Some(dependencies) = req.dependencies {
+        task.dependencies = dependencies;
+    }
+    if let Some(max_retries) = req.max_retries {
+        task.max_retries = max_retries;
+    }
+    if let Some(retry_delay_ms) = req.retry_delay_ms {
+        task.retry_delay_ms = retry_delay_ms;
+    }
+    if let Some(required_tags) = req.required_tags {
+        task.required_tags = required_tags;
+    }
+    if let Some(labels) = req.labels {
+        task.labels = labels;
+    }
+    task.container_image = req.container_image;
+    if let Some(expression) = req.cron_expression {
+        parse_cron(&expression).map_err(|e| format!("invalid cron_expression: {}", e))?;
+        task.cron_expression = Some(expression);
+    }
+    task.scheduled_at = match req.scheduled_at {
+        Some(at) => Some(at),
+        None => task.next_occurrence(chrono::Local::now().timestamp()),
+    };
+    if task.cron_expression.is_some() && task.scheduled_at.is_none() {
+        return Err("cron_expression never fires".to_string());
+    }
+    if let Some(url) = req.callback_url {
+        match reqwest::Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => task.callback_url = Some(url),
+            _ => return Err("callback_url must be an http(s) URL".to_string()),
+        }
+    }
+    for (field, paths, files) in [
+        ("input_files", req.input_files, &mut task.input_files),
+        ("output_files", req.output_files, &mut task.output_files),
+    ] {
+        for path in paths.unwrap_or_default() {
+            crate::util::files::resolve(std::path::Path::new(""), &path).map_err(|e| format!("invalid {}: {}", field, e))?;
+            files.push(FileSpec::new(path));
+        }
+    }
+    Ok(task)
+}
 
-    state.scheduler.enqueue(task.clone()).await;
-    
-    let response = TaskResponse {
-        id: task.id,
-        command: task.command,
+fn task_response(task: &Task) -> TaskResponse {
+    TaskResponse {
+        id: task.id.clone(),
+        command: task.command.clone(),
         status: "Pending".to_string(),
         created_at: chrono::Local::now().to_rfc3339(),
-    };
+    }
+}
+
+/// Create a new task
+async fn create_task(
+    State(state): State<ApiState>,
+    Json(req): Json<CreateTaskRequest>,
+) -> Result<(StatusCode, Json<TaskResponse>), (StatusCode, String)> {
+    let task = build_task(req).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    state
+        .scheduler
+        .submit(task.clone())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    audit(
+        &state,
+        AuditEvent::TaskSubmitted {
+            task_id: task.id.clone(),
+            command: task.command.clone(),
+        },
+    );
+    publish_submitted(&state, &task.id);
 
-    Ok((StatusCode::CREATED, Json(response)))
+    Ok((StatusCode::CREATED, Json(task_response(&task))))
+}
+
+/// Create several tasks at once; nothing is queued unless every item is valid
+async fn create_task_batch(
+    State(state): State<ApiState>,
+    Json(batch): Json<BatchCreateRequest>,
+) -> Result<(StatusCode, Json<Vec<TaskResponse>>), (StatusCode, Json<serde_json::Value>)> {
+    let reject = |errors: Vec<BatchItemError>| (StatusCode::BAD_REQUEST, Json(json!({ "errors": errors })));
+
+    if batch.tasks.is_empty() || batch.tasks.len() > state.max_batch_size {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": format!("batch must contain 1 to {} tasks, got {}", state.max_batch_size, batch.tasks.len()),
+            })),
+        ));
+    }
+
+    let mut tasks = Vec::with_capacity(batch.tasks.len());
+    let mut errors = Vec::new();
+    for (index, mut item) in batch.tasks.into_iter().enumerate() {
+        item.timeout = item.timeout.or(batch.timeout);
+        if item.required_tags.is_none() {
+            item.required_tags = batch.required_tags.clone();
+        }
+        if let Some(shared) = &batch.labels {
+            let mut labels = shared.clone();
+            labels.extend(item.labels.take().unwrap_or_default());
+            item.labels = Some(labels);
+        }
+
+        match build_task(item) {
+            Ok(task) => tasks.push(task),
+            Err(error) => errors.push(BatchItemError { index, error }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(reject(errors));
+    }
+
+    let responses = tasks.iter().map(task_response).collect();
+    let pending: Vec<StoredTask> = tasks.iter().map(StoredTask::pending).collect();
+    let submitted: Vec<AuditEvent> = tasks
+        .iter()
+        .map(|task| AuditEvent::TaskSubmitted {
+            task_id: task.id.clone(),
+            command: task.command.clone(),
+        })
+        .collect();
+    state.scheduler.submit_batch(tasks).await.map_err(|e| {
+        reject(vec![BatchItemError {
+            index: 0,
+            error: e.to_string(),
+        }])
+    })?;
+    // The whole batch goes into the history in one transaction
+    if let Err(e) = state.store.bulk_insert_tasks(&pending).await {
+        tracing::warn!("Failed to record {} batch task(s) in the history: {}", pending.len(), e);
+    }
+    for event in submitted {
+        if let AuditEvent::TaskSubmitted { task_id, .. } = &event {
+            publish_submitted(&state, task_id);
+        }
+        audit(&state, event);
+    }
+
+    Ok((StatusCode::ACCEPTED, Json(responses)))
+}
+
+/// Tell event stream subscribers about a newly queued task
+fn publish_submitted(state: &ApiState, task_id: &str) {
+    state.dispatcher.publish_event(DispatcherEvent::TaskStatus {
+        task_id: task_id.to_string(),
+        status: TaskStatus::Pending,
+        worker_id: None,
+    });
 }
 
 /// Get task details
@@ -120,7 +780,19 @@ async fn get_task(
     State(state): State<ApiState>,
     Path(task_id): Path<String>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match state.store.get_task(&task_id) {
+    let progress = state.dispatcher.get_progress(&task_id).await;
+    // A batch task is in the history as Pending from submission, so running ones are looked up first
+    if let Some(task) = state.dispatcher.get_dispatched(&task_id).await {
+        return Ok(Json(json!({
+            "id": task.id,
+            "command": task.command,
+            "status": "Running",
+            "worker_id": state.dispatcher.dispatched_worker(&task_id).await,
+            "created_at": chrono::DateTime::from_timestamp(task.created_at, 0).map(|at| at.to_rfc3339()),
+            "progress": progress,
+        })));
+    }
+    match state.store.get_task(&task_id).await {
         Ok(Some(task)) => Ok(Json(json!({
             "id": task.id,
             "command": task.command,
@@ -132,19 +804,109 @@ async fn get_task(
             "duration_ms": task.duration_ms,
             "created_at": task.created_at,
             "completed_at": task.completed_at,
+            "max_rss_kb": task.max_rss_kb,
+            "resubmitted_as": task.resubmitted_as,
+            "progress": progress,
         }))),
         Ok(None) => Err((StatusCode::NOT_FOUND, "Task not found".to_string())),
         Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())),
     }
 }
 
-/// List all tasks
+/// Queue a finished task again under a new ID, linking the original to it
+///
+/// The new task is built from the definition kept in the history; records written
+/// before definitions were kept fall back to the dead letter queue, or to the command
+/// alone. The body overrides any field.
+async fn resubmit_task(
+    State(state): State<ApiState>,
+    Path(task_id): Path<String>,
+    body: Option<Json<ResubmitTaskRequest>>,
+) -> Result<(StatusCode, Json<TaskResponse>), (StatusCode, String)> {
+    let stored = state
+        .store
+        .get_task(&task_id)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Task not found".to_string()))?;
+    let overrides = body.map(|Json(req)| req).unwrap_or_default();
+
+    let original = match stored.definition {
+        Some(definition) => Some(definition),
+        None => state.scheduler.dead_letter_tasks().await.into_iter().find(|t| t.id == task_id),
+    };
+    let mut task = Task::new(stored.command);
+    if let Some(original) = original {
+        // A fresh run: no retries used, not held back, and not another recurring series
+        task = Task {
+            id: task.id,
+            created_at: task.created_at,
+            retry_count: 0,
+            scheduled_at: None,
+            cron_expression: None,
+            queued_at: None,
+            ..original
+        };
+    }
+    if let Some(command) = overrides.command {
+        task.command = command;
+    }
+    if task.command.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "command must not be empty".to_string()));
+    }
+    if let Some(env) = overrides.env {
+        task.env = env;
+    }
+    if let Some(inputs) = overrides.inputs {
+        task.inputs = inputs;
+    }
+    if let Some(outputs) = overrides.outputs {
+        task.outputs = outputs;
+    }
+    if let Some(timeout) = overrides.timeout {
+        if timeout == 0 {
+            return Err((StatusCode::BAD_REQUEST, "timeout must be greater than zero".to_string()));
+        }
+        task.timeout = timeout;
+    }
+    if let Some(required_tags) = overrides.required_tags {
+        task.required_tags = required_tags;
+    }
+
+    state
+        .scheduler
+        .submit(task.clone())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if let Err(e) = state.store.set_resubmitted_as(&task_id, &task.id).await {
+        tracing::warn!("Failed to link task {} to its resubmission {}: {}", task_id, task.id, e);
+    }
+    audit(
+        &state,
+        AuditEvent::TaskSubmitted {
+            task_id: task.id.clone(),
+            command: task.command.clone(),
+        },
+    );
+    publish_submitted(&state, &task.id);
+
+    Ok((StatusCode::CREATED, Json(task_response(&task))))
+}
+
+/// List one page of tasks, newest first
+///
+/// The total match count is in `X-Total-Count` and the neighbouring pages in `Link`.
 async fn list_tasks(
     State(state): State<ApiState>,
-) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, String)> {
-    match state.store.get_all_tasks() {
-        Ok(tasks) => {
-            let response = tasks
+    Query(params): Query<TaskListParams>,
+) -> Result<Response, (StatusCode, String)> {
+    match state
+        .store
+        .get_tasks_paginated(params.filter(), params.page(), params.limit())
+        .await
+    {
+        Ok((tasks, total)) => {
+            let response: Vec<serde_json::Value> = tasks
                 .iter()
                 .map(|t| {
                     json!({
@@ -157,21 +919,449 @@ async fn list_tasks(
                     })
                 })
                 .collect();
-            Ok(Json(response))
+
+            let mut headers = HeaderMap::new();
+            headers.insert("x-total-count", total.into());
+            if let Some(link) = params.link_header(total).and_then(|l| l.parse().ok()) {
+                headers.insert(header::LINK, link);
+            }
+            Ok((headers, Json(response)).into_response())
         }
         Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())),
     }
 }
 
+/// Download the task history matching the query, newest first, as a CSV file
+async fn export_tasks(
+    State(state): State<ApiState>,
+    Query(params): Query<TaskExportParams>,
+) -> Result<Response, (StatusCode, String)> {
+    match params.format.as_deref().unwrap_or("csv") {
+        "csv" => {}
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unsupported export format: {}", other))),
+    }
+
+    let tasks = state
+        .store
+        .search_tasks(params.filter())
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()))?;
+    let mut csv = Vec::new();
+    persistence::write_csv(&mut csv, &tasks)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write CSV: {}", e)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"tasks.csv\""),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// One task's event subscription; frees the task's channel when the client goes away
+struct EventSubscription {
+    hub: Arc<TaskEventHub>,
+    task_id: String,
+    events: Option<broadcast::Receiver<TaskEvent>>,
+}
+
+impl EventSubscription {
+    async fn next(&mut self) -> Option<TaskEvent> {
+        loop {
+            match self.events.as_mut()?.recv().await {
+                Ok(event) => {
+                    if event.is_final() {
+                        self.events = None;
+                    }
+                    return Some(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream for task {} skipped {} events", self.task_id, skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.events = None;
+        self.hub.release(&self.task_id);
+    }
+}
+
+/// Stream a task's progress, output and completion as Server-Sent Events
+async fn task_events(
+    State(state): State<ApiState>,
+    Path(task_id): Path<String>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    // Subscribe before checking for a result so a completion in between is not missed
+    let mut subscription = EventSubscription {
+        events: Some(state.task_events.subscribe(&task_id)),
+        hub: state.task_events.clone(),
+        task_id: task_id.clone(),
+    };
+
+    let finished = match state.dispatcher.get_result(&task_id).await {
+        Some(result) => Some(result),
+        None => state.dispatcher.stored_result(&task_id).await,
+    };
+    let first = finished.map(|result| {
+        subscription.events = None;
+        TaskEvent::Completed(result)
+    });
+
+    let stream = futures::stream::unfold((first, subscription), |(first, mut subscription)| async move {
+        let event = match first {
+            Some(event) => event,
+            None => subscription.next().await?,
+        };
+        Some((Event::default().json_data(&event), (None, subscription)))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Message a WebSocket client sends to narrow its event stream
+#[derive(Debug, Deserialize)]
+struct EventStreamRequest {
+    filter: EventFilter,
+}
+
+/// Upgrade to a WebSocket that pushes dispatcher events as JSON
+///
+/// The JWT goes in an `Authorization: Bearer` header, or in a `token` query
+/// parameter for browser clients that cannot set headers.
+async fn dispatcher_events_ws(
+    State(state): State<ApiState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !stream_authorized(&state, &params, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    upgrade.on_upgrade(move |socket| stream_dispatcher_events(socket, state))
+}
+
+/// Whether a client may follow dispatcher events, by `token` query parameter or, without one, by header
+///
+/// Only the credential actually used is audited, so a query token does not also log a missing header.
+fn stream_authorized(state: &ApiState, params: &HashMap<String, String>, headers: &HeaderMap) -> bool {
+    let Some(token) = params.get("token") else {
+        return authenticate(state, headers).is_ok();
+    };
+    let result = state
+        .auth
+        .verify_token(token)
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".to_string()));
+    audit_authentication(state, &result);
+    result.is_ok()
+}
+
+/// Stream task, worker and queue events of the whole dispatcher as Server-Sent Events
+///
+/// Authenticates like the WebSocket endpoint, so browser `EventSource` clients can pass `token`.
+async fn global_events(
+    State(state): State<ApiState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if !stream_authorized(&state, &params, &headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let events = state.dispatcher.subscribe_events();
+    let stream = futures::stream::unfold(events, |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => match ApiEvent::from_dispatcher(&event) {
+                    Some(event) => return Some((Ok::<_, axum::Error>(event.into_sse()), events)),
+                    None => continue,
+                },
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event stream client skipped {} dispatcher events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn stream_dispatcher_events(mut socket: WebSocket, state: ApiState) {
+    let mut events = state.dispatcher.subscribe_events();
+    let mut filter = EventFilter::default();
+    state.metrics.websocket_opened();
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if filter.matches(&event) => {
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(ws::Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client skipped {} dispatcher events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(ws::Message::Text(text))) => match serde_json::from_str::<EventStreamRequest>(&text) {
+                    Ok(request) => filter = request.filter,
+                    Err(e) => tracing::debug!("Ignoring WebSocket message: {}", e),
+                },
+                Some(Ok(ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    state.metrics.websocket_closed();
+}
+
 /// Cancel a task
+///
+/// A task still queued is cancelled at once; one already dispatched is forwarded
+/// to its worker as `Message::CancelTask`, which kills it and reports it cancelled.
 async fn cancel_task(
-    State(_state): State<ApiState>,
+    State(state): State<ApiState>,
+    Path(task_id): Path<String>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    if state.scheduler.cancel_queued_task(&task_id, &state.dispatcher).await {
+        return Ok((
+            StatusCode::OK,
+            Json(json!({
+                "id": task_id,
+                "status": "cancelled",
+                "message": "Task cancelled successfully",
+            })),
+        ));
+    }
+
+    if let Some(worker_id) = state.dispatcher.dispatched_worker(&task_id).await {
+        let worker = state
+            .scheduler
+            .get_worker(&worker_id)
+            .await
+            .ok_or((StatusCode::CONFLICT, "Task worker is no longer registered".to_string()))?;
+        let addr = format!("{}:{}", worker.address, worker.port)
+            .parse()
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Invalid worker address".to_string()))?;
+        state
+            .transport
+            .send_message(addr, &Message::CancelTask { task_id: task_id.clone() })
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Failed to reach worker: {}", e)))?;
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "id": task_id,
+                "status": "cancelling",
+                "worker_id": worker_id,
+                "message": "Cancellation sent to worker",
+            })),
+        ));
+    }
+
+    let finished = state.dispatcher.get_result(&task_id).await.is_some()
+        || matches!(state.store.get_task(&task_id).await, Ok(Some(_)));
+    if finished {
+        return Err((StatusCode::CONFLICT, "Cannot cancel finished task".to_string()));
+    }
+    Err((StatusCode::NOT_FOUND, "Task not found".to_string()))
+}
+
+/// Queued tasks in assignment order, with a rough estimate of how long each will wait
+async fn list_queue(State(state): State<ApiState>) -> Result<Json<Vec<QueueEntry>>, (StatusCode, String)> {
+    let (_, _, _, avg_duration_ms) = state
+        .store
+        .get_stats()
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Stats error".to_string()))?;
+    let idle_workers = state.scheduler.get_workers().await.iter().filter(|w| w.is_idle()).count();
+    let avg_task_duration_secs = avg_duration_ms as f64 / 1000.0;
+
+    let entries = state
+        .scheduler
+        .queued_tasks()
+        .await
+        .into_iter()
+        .enumerate()
+        .map(|(position, task)| QueueEntry {
+            position,
+            estimated_wait_secs: (idle_workers > 0)
+                .then(|| position as f64 * avg_task_duration_secs / idle_workers as f64),
+            task_id: task.id,
+            command: task.command,
+            queued_at: task.queued_at,
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
+/// Cancel every queued task
+async fn flush_queue(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    require_admin(&state, &headers, "Flushing the queue")?;
+    let tasks = state.scheduler.flush_queue().await;
+    state.dispatcher.mark_all_cancelled(&tasks).await;
+    Ok(Json(json!({ "cancelled": tasks.len() })))
+}
+
+/// Tasks held back until a later time, soonest first
+async fn list_schedules(State(state): State<ApiState>) -> Json<Vec<Task>> {
+    Json(state.scheduler.scheduled_tasks().await)
+}
+
+/// Tasks that failed after exhausting their retries, oldest failure first
+async fn list_dead_letters(State(state): State<ApiState>) -> Json<Vec<Task>> {
+    Json(state.scheduler.dead_letter_tasks().await)
+}
+
+/// Queue a dead letter task again with its retry count reset
+async fn requeue_dead_letter(
+    State(state): State<ApiState>,
     Path(task_id): Path<String>,
+) -> Result<Json<TaskResponse>, (StatusCode, String)> {
+    let task = state
+        .scheduler
+        .requeue_dead_letter(&task_id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Task not in dead letter queue".to_string()))?;
+    Ok(Json(task_response(&task)))
+}
+
+/// List registered workers
+async fn list_workers(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<WorkerInfo>>, (StatusCode, String)> {
+    require_permission(&state, &headers, MANAGE_WORKERS)?;
+    Ok(Json(state.scheduler.get_workers().await))
+}
+
+/// One worker with its running tasks, load report, task totals and uptime
+async fn get_worker(
+    State(state): State<ApiState>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<WorkerDetails>, (StatusCode, String)> {
+    require_permission(&state, &headers, MANAGE_WORKERS)?;
+    let worker = state
+        .scheduler
+        .get_worker(&worker_id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Worker not found".to_string()))?;
+
+    Ok(Json(WorkerDetails {
+        current_tasks: state.dispatcher.dispatched_to(&worker_id).await,
+        metrics: state.scheduler.get_worker_metrics(&worker_id).await,
+        task_stats: query_task_stats(state.transport.as_ref(), &worker).await,
+        uptime_secs: chrono::Local::now().timestamp() - worker.connected_at,
+        worker,
+    }))
+}
+
+/// Ask a worker for the totals of the tasks it has run
+async fn query_task_stats(transport: &dyn TransportBackend, worker: &WorkerInfo) -> Option<WorkerTaskStats> {
+    let addr = format!("{}:{}", worker.address, worker.port).parse().ok()?;
+    let query = Message::QueryWorkerMetrics {
+        worker_id: worker.id.clone(),
+    };
+    match transport.request_with_timeout(addr, &query, Some(WORKER_QUERY_TIMEOUT)).await
+    {
+        Ok(Message::WorkerMetrics { stats, .. }) => Some(stats),
+        Ok(other) => {
+            tracing::debug!("Unexpected reply from worker {}: {:?}", worker.id, other);
+            None
+        }
+        Err(e) => {
+            tracing::debug!("Worker {} did not report its totals: {}", worker.id, e);
+            None
+        }
+    }
+}
+
+/// Unregister a worker, cancel its tasks there and queue them again
+async fn remove_worker(
+    State(state): State<ApiState>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    // TODO: Implement task cancellation
-    // For now, just return OK
-    println!("Cancel task: {}", task_id);
-    Ok(StatusCode::OK)
+    require_permission(&state, &headers, MANAGE_WORKERS)?;
+    let worker = state
+        .scheduler
+        .remove_worker(&worker_id)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Worker not found".to_string()))?;
+
+    let mut cancelled = Vec::new();
+    for task in state.dispatcher.dispatched_to(&worker_id).await {
+        if let Some(task) = state.dispatcher.take_dispatched(&task.id).await {
+            cancelled.push(task.id.clone());
+            state.scheduler.enqueue(task).await;
+        }
+    }
+    audit(&state, AuditEvent::WorkerOffline { worker_id: worker_id.clone() });
+    state.dispatcher.publish_event(DispatcherEvent::WorkerDisconnected { worker_id });
+
+    // The worker may already be unreachable, so don't hold up the response
+    if let Ok(addr) = format!("{}:{}", worker.address, worker.port).parse() {
+        let transport = state.transport.clone();
+        tokio::spawn(async move {
+            for task_id in cancelled {
+                if let Err(e) = transport.send_message(addr, &Message::CancelTask { task_id }).await {
+                    tracing::debug!("Failed to cancel task on removed worker {}: {}", worker.id, e);
+                    break;
+                }
+            }
+            transport.forget_worker(&worker.id);
+        });
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Turn a worker's maintenance mode on or off; it keeps its running tasks but gets no new ones
+async fn set_worker_maintenance(
+    State(state): State<ApiState>,
+    Path(worker_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<MaintenanceRequest>,
+) -> Result<Json<WorkerInfo>, (StatusCode, String)> {
+    require_permission(&state, &headers, MANAGE_WORKERS)?;
+    let worker = state
+        .scheduler
+        .set_worker_maintenance(&worker_id, req.enable)
+        .await
+        .ok_or((StatusCode::NOT_FOUND, "Worker not found".to_string()))?;
+
+    // The scheduler already skips the worker, so don't wait for it to hear about it
+    if let Ok(addr) = format!("{}:{}", worker.address, worker.port).parse() {
+        let transport = state.transport.clone();
+        let message = Message::WorkerMaintenanceMode {
+            worker_id,
+            enable: req.enable,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = transport.send_message(addr, &message).await {
+                tracing::debug!("Failed to notify worker of maintenance mode: {}", e);
+            }
+        });
+    }
+
+    Ok(Json(worker))
 }
 
 /// Get dispatcher stats
@@ -181,8 +1371,8 @@ async fn get_stats(
     let workers = state.scheduler.get_workers().await;
     let queue_size = state.scheduler.queue_size().await;
     
-    match state.store.get_stats() {
-        Ok((total, completed, failed)) => {
+    match state.store.get_stats().await {
+        Ok((total, completed, failed, avg_duration_ms)) => {
             Ok(Json(json!({
                 "workers_count": workers.len(),
                 "idle_workers": workers.iter().filter(|w| w.is_idle()).count(),
@@ -190,32 +1380,83 @@ async fn get_stats(
                 "total_tasks": total,
                 "completed_tasks": completed,
                 "failed_tasks": failed,
+                "avg_duration_ms": avg_duration_ms,
             })))
         }
         Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Stats error".to_string())),
     }
 }
 
+/// Prometheus scrape endpoint
+async fn get_metrics(
+    State(state): State<ApiState>,
+) -> Result<([(header::HeaderName, &'static str); 1], String), (StatusCode, String)> {
+    state.metrics.set_queue_depth(state.scheduler.queue_size().await);
+    state.metrics.set_workers(&state.scheduler.get_workers().await);
+    state
+        .metrics
+        .set_results_retained(&state.dispatcher.get_results_count_by_status().await);
+
+    let body = state
+        .metrics
+        .render()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body))
+}
+
 /// Health check
 async fn health_check() -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     Ok(Json(json!({
         "status": "healthy",
-        "version": "1.0.0",
+        "version": build_info::VERSION,
+        "commit": build_info::GIT_SHA,
+        "built_at": build_info::BUILD_TIMESTAMP,
     })))
 }
 
 /// Create API router
 pub fn create_router(state: ApiState) -> Router {
+    let rate_limit = RateLimitLayer::new(state.rate_limiter.clone());
+
     Router::new()
         // Public endpoints
         .route("/health", get(health_check))
+        .route("/api/v1/health", get(health_check))
+        .route("/metrics", get(get_metrics))
         
-        // Task endpoints
-        .route("/api/v1/tasks", post(create_task).get(list_tasks))
+        // Auth endpoints
+        .route("/api/v1/auth/login", post(login))
+        .route("/api/v1/auth/refresh", post(refresh_token))
+        .route("/api/v1/auth/introspect", post(introspect_token))
+        .route("/api/v1/auth/revoke-all-user", post(revoke_user_tokens))
+        .route("/api/v1/auth/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/api/v1/auth/api-keys/:label", delete(revoke_api_key))
+
+        // Task endpoints; submissions are rate limited per client IP
+        .route("/api/v1/tasks", post(create_task).layer(rate_limit.clone()).get(list_tasks))
+        .route("/api/v1/tasks/batch", post(create_task_batch).layer(rate_limit.clone()))
+        .route("/api/v1/tasks/:id/resubmit", post(resubmit_task).layer(rate_limit))
+        .route("/api/v1/tasks/export", get(export_tasks))
         .route("/api/v1/tasks/:id", get(get_task).delete(cancel_task))
+        .route("/api/v1/tasks/:id/events", get(task_events))
+        .route("/api/v1/queue", get(list_queue).delete(flush_queue))
+        .route("/api/v1/schedules", get(list_schedules))
+        .route("/api/v1/scheduler/pause", post(pause_scheduler))
+        .route("/api/v1/scheduler/resume", post(resume_scheduler))
+        .route("/api/v1/dlq", get(list_dead_letters))
+        .route("/api/v1/dlq/:id/requeue", post(requeue_dead_letter))
         
+        // Worker endpoints
+        .route("/api/v1/workers", get(list_workers))
+        .route("/api/v1/workers/:id", get(get_worker).delete(remove_worker))
+        .route("/api/v1/workers/:id/maintenance", put(set_worker_maintenance))
+
         // Stats endpoint
         .route("/api/v1/stats", get(get_stats))
+
+        // Live dispatcher events
+        .route("/api/v1/events", get(global_events))
+        .route("/api/v1/ws", get(dispatcher_events_ws))
         
         .with_state(state)
         .layer(CorsLayer::permissive())
@@ -226,19 +1467,26 @@ pub async fn start_api_server(
     addr: &str,
     state: ApiState,
 ) -> Result<()> {
-    let app = create_router(state);
-    
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!("REST API listening on http://{}", addr);
     
-    axum::serve(listener, app).await?;
-    
+    serve_api(listener, state).await
+}
+
+/// Serve the API on an already bound listener, recording each client's address
+pub async fn serve_api(listener: tokio::net::TcpListener, state: ApiState) -> Result<()> {
+    let _cleanup = state.rate_limiter.start_cleanup();
+    let app = create_router(state);
+
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::Transport;
 
     #[test]
     fn test_create_task_request() {
@@ -247,9 +1495,912 @@ mod tests {
             timeout: Some(60),
             inputs: None,
             outputs: None,
+            dependencies: None,
+            max_retries: None,
+            retry_delay_ms: None,
+            required_tags: None,
+            container_image: None,
+            labels: None,
+            scheduled_at: None,
+            cron_expression: None,
+            callback_url: None,
+            input_files: None,
+            output_files: None,
         };
         
         assert_eq!(req.command, "echo test");
         assert_eq!(req.timeout, Some(60));
     }
+
+    /// Serve the API on an ephemeral port; the temp dir holds the history database
+    async fn serve_test_api() -> (std::net::SocketAddr, ApiState, tempfile::TempDir) {
+        use crate::persistence::SqliteStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store: Arc<dyn StoreBackend + Send + Sync> =
+            Arc::new(SqliteStore::new(db_path.to_str().unwrap()).await.unwrap());
+        let scheduler = Arc::new(Scheduler::default());
+        let dispatcher = Arc::new(
+            DispatcherState::new("test".to_string(), 0)
+                .with_scheduler(scheduler.clone())
+                .with_store(store.clone()),
+        );
+        let state = ApiState {
+            scheduler,
+            dispatcher: dispatcher.clone(),
+            auth: Arc::new(AuthManager::new("secret".to_string()).with_admin_password("hunter2")),
+            store,
+            metrics: dispatcher.metrics().clone(),
+            task_events: dispatcher.task_events().clone(),
+            max_batch_size: 3,
+            rate_limiter: RateLimiter::new(0, 0.0),
+            transport: Arc::new(Transport::default()),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_api(listener, state.clone()));
+        (addr, state, dir)
+    }
+
+    #[tokio::test]
+    async fn test_recurring_task_listed_in_schedules() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let tasks_url = format!("http://{}/api/v1/tasks", addr);
+
+        let response = client
+            .post(&tasks_url)
+            .json(&json!({ "command": "echo tick", "cron_expression": "0 0 * * * *" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        assert_eq!(state.scheduler.queue_size().await, 0);
+
+        let schedules: Vec<Task> = reqwest::get(format!("http://{}/api/v1/schedules", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].cron_expression.as_deref(), Some("0 0 * * * *"));
+        assert!(schedules[0].scheduled_at.unwrap() > chrono::Local::now().timestamp());
+
+        let response = client
+            .post(&tasks_url)
+            .json(&json!({ "command": "echo tick", "cron_expression": "every hour" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_build_info() {
+        let (addr, _state, _dir) = serve_test_api().await;
+        let url = format!("http://{}/api/v1/health", addr);
+        let body: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(body["commit"], build_info::GIT_SHA);
+        assert!(!build_info::BUILD_TIMESTAMP.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_task_reports_progress_while_running() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let task = Task::new("sleep 60".to_string());
+        let url = format!("http://{}/api/v1/tasks/{}", addr, task.id);
+        state.dispatcher.track_dispatched(task.clone(), "worker-1").await;
+        state.dispatcher.set_progress(&task.id, 0.25).await;
+
+        let body: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+        assert_eq!(body["status"], "Running");
+        assert_eq!(body["worker_id"], "worker-1");
+        assert_eq!(body["progress"], 0.25);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_task() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let task = Task::new("sleep 60".to_string());
+        let task_id = task.id.clone();
+        state.scheduler.enqueue(task).await;
+
+        let url = format!("http://{}/api/v1/tasks/{}", addr, task_id);
+        let response = client.delete(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(state.scheduler.queue_size().await, 0);
+
+        let stored: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(stored["status"], "Cancelled");
+        assert_eq!(stored["worker_id"], serde_json::Value::Null);
+
+        let response = client.delete(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+        let missing = format!("http://{}/api/v1/tasks/no-such-task", addr);
+        assert_eq!(client.delete(&missing).send().await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_resubmit_failed_task() {
+        use crate::persistence::StoredTask;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let mut original = Task::new("exit 1".to_string());
+        original.env.insert("MODE".to_string(), "strict".to_string());
+        original.required_tags = vec!["gpu".to_string()];
+        original.max_retries = 1;
+        state.scheduler.move_to_dlq(original.clone()).await;
+        let stored = StoredTask {
+            id: original.id.clone(),
+            command: original.command.clone(),
+            status: "Failed".to_string(),
+            worker_id: Some("worker-1".to_string()),
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(1),
+            duration_ms: 7,
+            created_at: "2024-06-01T00:00:00+00:00".to_string(),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: None,
+        };
+        state.store.store_task(&stored).await.unwrap();
+
+        let url = format!("http://{}/api/v1/tasks/{}/resubmit", addr, original.id);
+        let response = client.post(&url).json(&json!({ "timeout": 5 })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let created: serde_json::Value = response.json().await.unwrap();
+        assert_ne!(created["id"], original.id.as_str());
+
+        let resubmitted = state.scheduler.dequeue().await.unwrap();
+        assert_eq!(resubmitted.id, created["id"]);
+        assert_eq!(resubmitted.command, "exit 1");
+        assert_eq!(resubmitted.env["MODE"], "strict");
+        assert_eq!(resubmitted.required_tags, vec!["gpu".to_string()]);
+        assert_eq!(resubmitted.timeout, 5);
+
+        let url = format!("http://{}/api/v1/tasks/{}", addr, original.id);
+        let body: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["resubmitted_as"], created["id"]);
+
+        let missing = format!("http://{}/api/v1/tasks/no-such-task/resubmit", addr);
+        assert_eq!(client.post(&missing).send().await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_resubmit_keeps_definition_of_task_outside_dlq() {
+        use crate::persistence::StoredTask;
+        use crate::protocol::{TaskResult, TaskStatus};
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let mut original = Task::new("exit 1".to_string());
+        original.env.insert("MODE".to_string(), "strict".to_string());
+        original.required_tags = vec!["gpu".to_string()];
+        original.timeout = 42;
+        original.retry_count = 2;
+        let result = TaskResult {
+            task_id: original.id.clone(),
+            worker_id: "worker-1".to_string(),
+            status: TaskStatus::Failed,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(1),
+            duration_ms: 7,
+            completed_at: 0,
+            resource_usage: None,
+        };
+        state.store.store_task(&StoredTask::from_result(&original, &result)).await.unwrap();
+        assert!(state.scheduler.dead_letter_tasks().await.is_empty());
+
+        let url = format!("http://{}/api/v1/tasks/{}/resubmit", addr, original.id);
+        let response = reqwest::Client::new().post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+
+        let resubmitted = state.scheduler.dequeue().await.unwrap();
+        assert_ne!(resubmitted.id, original.id);
+        assert_eq!(resubmitted.env["MODE"], "strict");
+        assert_eq!(resubmitted.required_tags, vec!["gpu".to_string()]);
+        assert_eq!(resubmitted.timeout, 42);
+        assert_eq!(resubmitted.retry_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_task_events_stream_in_order() {
+        use crate::protocol::{TaskResult, TaskStatus};
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let dispatcher = state.dispatcher.clone();
+        let url = format!("http://{}/api/v1/tasks/task-1/events", addr);
+
+        let read_events = |body: String| -> Vec<serde_json::Value> {
+            body.split("\n\n")
+                .filter_map(|event| event.strip_prefix("data: "))
+                .map(|data| serde_json::from_str(data).unwrap())
+                .collect()
+        };
+
+        let mut response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        dispatcher.task_events().publish("task-1", TaskEvent::Progress(0.5));
+        dispatcher.append_output("task-1", "hello".to_string(), false).await;
+        dispatcher.append_output("task-1", "oops".to_string(), true).await;
+        dispatcher
+            .store_result(TaskResult {
+                task_id: "task-1".to_string(),
+                worker_id: "worker-1".to_string(),
+                status: TaskStatus::Completed,
+                stdout: "hello\n".to_string(),
+                stderr: "oops\n".to_string(),
+                exit_code: Some(0),
+                duration_ms: 12,
+                completed_at: 0,
+                resource_usage: None,
+            })
+            .await;
+
+        // The stream ends by itself after the completed event
+        let mut body = String::new();
+        while let Some(chunk) = response.chunk().await.unwrap() {
+            body.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        let events = read_events(body);
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["progress", "stdout", "stderr", "completed"]);
+        assert_eq!(events[1]["data"], "hello");
+        assert_eq!(events[3]["data"]["exit_code"], 0);
+
+        // A finished task gets a single synthetic completed event
+        let events = read_events(reqwest::get(&url).await.unwrap().text().await.unwrap());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "completed");
+        assert_eq!(dispatcher.task_events().channel_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_pushes_dispatcher_events() {
+        use crate::auth::Claims;
+        use crate::state::events::DispatcherEvent;
+        use futures::StreamExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let url = format!("ws://{}/api/v1/ws", addr);
+
+        assert!(tokio_tungstenite::connect_async(&url).await.is_err());
+
+        let token = state
+            .auth
+            .generate_token(&Claims::new("ops".to_string(), "admin".to_string(), vec!["*".to_string()]))
+            .unwrap();
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("{}?token={}", url, token))
+            .await
+            .unwrap();
+
+        // The server subscribes before counting the connection
+        while state.metrics.websocket_connections() == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(state.metrics.render().unwrap().contains("octaskly_websocket_connections 1"));
+
+        let sent = [
+            DispatcherEvent::WorkerConnected {
+                worker_id: "worker-1".to_string(),
+                name: "build-box".to_string(),
+            },
+            DispatcherEvent::QueueDepth { depth: 2 },
+        ];
+        for event in &sent {
+            state.dispatcher.publish_event(event.clone());
+        }
+
+        for expected in &sent {
+            let Some(Ok(WsMessage::Text(text))) = socket.next().await else {
+                panic!("expected a text frame");
+            };
+            assert_eq!(&serde_json::from_str::<DispatcherEvent>(&text).unwrap(), expected);
+        }
+
+        drop(socket);
+        while state.metrics.websocket_connections() > 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_token_is_audited_alone() {
+        use crate::auth::Claims;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let token = state
+            .auth
+            .generate_token(&Claims::new("ops".to_string(), "admin".to_string(), vec!["*".to_string()]))
+            .unwrap();
+        let response = reqwest::get(format!("http://{}/api/v1/events?token={}", addr, token)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        while state.store.get_audit_events_by_type("auth_success", 10).await.unwrap().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(state.store.get_audit_events_by_type("auth_failure", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_submission() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let url = format!("http://{}/api/v1/tasks/batch", addr);
+        let client = reqwest::Client::new();
+
+        // One bad item rejects the whole batch
+        let response = client
+            .post(&url)
+            .json(&json!({ "tasks": [{ "command": "echo ok" }, { "command": "  " }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["errors"][0]["index"], 1);
+        assert_eq!(state.scheduler.queue_size().await, 0);
+
+        let too_many = json!({ "tasks": vec![json!({ "command": "echo" }); 4] });
+        let response = client.post(&url).json(&too_many).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(&url)
+            .json(&json!({
+                "timeout": 30,
+                "labels": { "team": "build", "env": "ci" },
+                "tasks": [
+                    { "command": "make test", "labels": { "env": "nightly" } },
+                    { "command": "make lint", "timeout": 5 }
+                ]
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+        let created: Vec<TaskResponse> = response.json().await.unwrap();
+        assert_eq!(created.len(), 2);
+        for task in &created {
+            let stored = state.store.get_task(&task.id).await.unwrap().unwrap();
+            assert_eq!(stored.status, "Pending");
+        }
+
+        let first = state.scheduler.dequeue().await.unwrap();
+        assert_eq!(first.id, created[0].id);
+        assert_eq!(first.timeout, 30);
+        assert_eq!(first.labels["team"], "build");
+        assert_eq!(first.labels["env"], "nightly");
+        assert_eq!(state.scheduler.dequeue().await.unwrap().timeout, 5);
+
+        // Once dispatched, the live status wins over the Pending history row
+        state.dispatcher.track_dispatched(first.clone(), "worker-1").await;
+        let task_url = format!("http://{}/api/v1/tasks/{}", addr, first.id);
+        let task: serde_json::Value = client.get(&task_url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(task["status"], "Running");
+        assert_eq!(task["worker_id"], "worker-1");
+    }
+
+    #[tokio::test]
+    async fn test_task_list_pagination() {
+        use crate::persistence::StoredTask;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        for i in 0..5 {
+            let task = StoredTask {
+                id: format!("task-{}", i),
+                command: "true".to_string(),
+                status: if i % 2 == 0 { "Completed" } else { "Failed" }.to_string(),
+                worker_id: Some("worker-1".to_string()),
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                duration_ms: 1,
+                created_at: format!("2024-06-0{}T00:00:00+00:00", i + 1),
+                completed_at: None,
+                max_rss_kb: None,
+                resubmitted_as: None,
+                definition: None,
+            };
+            state.store.store_task(&task).await.unwrap();
+        }
+
+        let url = format!("http://{}/api/v1/tasks?page=2&limit=2", addr);
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.headers()["x-total-count"], "5");
+        assert_eq!(
+            response.headers()["link"],
+            "</api/v1/tasks?page=3&limit=2>; rel=\"next\", </api/v1/tasks?page=1&limit=2>; rel=\"prev\""
+        );
+        let tasks: Vec<serde_json::Value> = response.json().await.unwrap();
+        let ids: Vec<_> = tasks.iter().map(|t| t["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["task-2", "task-1"]);
+
+        // Filters carry over into the links; the last page has no next
+        let url = format!("http://{}/api/v1/tasks?status=Completed&limit=2&page=2", addr);
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.headers()["x-total-count"], "3");
+        assert_eq!(
+            response.headers()["link"],
+            "</api/v1/tasks?page=1&limit=2&status=Completed>; rel=\"prev\""
+        );
+
+        let params = TaskListParams {
+            limit: Some(10_000),
+            ..Default::default()
+        };
+        assert_eq!(params.limit(), MAX_PAGE_LIMIT);
+        assert_eq!(TaskListParams::default().limit(), DEFAULT_PAGE_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_export_tasks_csv() {
+        use crate::persistence::StoredTask;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        for (id, status) in [("task-a", "Completed"), ("task-b", "Failed")] {
+            let task = StoredTask {
+                id: id.to_string(),
+                command: "echo \"a, b\"".to_string(),
+                status: status.to_string(),
+                worker_id: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                duration_ms: 7,
+                created_at: "2024-06-01T00:00:00+00:00".to_string(),
+                completed_at: None,
+                max_rss_kb: None,
+                resubmitted_as: None,
+                definition: None,
+            };
+            state.store.store_task(&task).await.unwrap();
+        }
+
+        let url = format!("http://{}/api/v1/tasks/export?format=csv&status=Failed", addr);
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/csv");
+        let body = response.text().await.unwrap();
+        let lines: Vec<_> = body.lines().collect();
+        assert_eq!(lines[0], "id,command,status,worker_id,stdout,stderr,exit_code,duration_ms,created_at,completed_at,max_rss_kb,resubmitted_as");
+        assert_eq!(lines[1], "task-b,\"echo \"\"a, b\"\"\",Failed,,,,0,7,2024-06-01T00:00:00+00:00,,,");
+        assert_eq!(lines.len(), 2);
+
+        let url = format!("http://{}/api/v1/tasks/export?format=xlsx", addr);
+        assert_eq!(reqwest::get(&url).await.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_login_issues_token_pair() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let login = |username: &str, password: &str| {
+            client
+                .post(format!("http://{}/api/v1/auth/login", addr))
+                .json(&json!({ "username": username, "password": password }))
+                .send()
+        };
+
+        let response = login("admin", "wrong").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = login("admin", "hunter2").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: AuthResponse = response.json().await.unwrap();
+        assert_eq!(body.expires_in, crate::auth::ACCESS_TOKEN_TTL_SECS);
+        assert_eq!(state.auth.verify_token(&body.token).unwrap().role, "admin");
+
+        // The refresh token from login renews the session
+        let response = client
+            .post(format!("http://{}/api/v1/auth/refresh", addr))
+            .json(&json!({ "refresh_token": body.refresh_token }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_endpoint_rotates_tokens() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let url = format!("http://{}/api/v1/auth/refresh", addr);
+        let client = reqwest::Client::new();
+        let pair = state
+            .auth
+            .issue_token_pair("ops".to_string(), "admin".to_string(), vec!["*".to_string()])
+            .unwrap();
+
+        let response = client
+            .post(&url)
+            .json(&json!({ "refresh_token": pair.refresh_token }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: AuthResponse = response.json().await.unwrap();
+        assert_eq!(state.auth.verify_token(&body.token).unwrap().sub, "ops");
+        assert!(body.refresh_token.is_some_and(|t| t != pair.refresh_token));
+
+        // Replaying the rotated-out token fails
+        let response = client
+            .post(&url)
+            .json(&json!({ "refresh_token": pair.refresh_token }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_introspect_endpoint() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let url = format!("http://{}/api/v1/auth/introspect", addr);
+        let client = reqwest::Client::new();
+        let token = |role: &str| {
+            state
+                .auth
+                .generate_token(&Claims::new("worker-1".to_string(), role.to_string(), vec!["execute_task".to_string()]))
+                .unwrap()
+        };
+        let (admin, worker) = (token("admin"), token("worker"));
+
+        let response = client.post(&url).json(&json!({ "token": worker })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client.post(&url).bearer_auth(&worker).json(&json!({ "token": worker })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let body: serde_json::Value =
+            client.post(&url).bearer_auth(&admin).json(&json!({ "token": worker })).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["active"], true);
+        assert_eq!(body["sub"], "worker-1");
+        assert_eq!(body["role"], "worker");
+        assert_eq!(body["permissions"], json!(["execute_task"]));
+        assert!(body["exp"].is_i64());
+
+        let body: serde_json::Value =
+            client.post(&url).bearer_auth(&admin).json(&json!({ "token": "bogus" })).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body, json!({ "active": false }));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_user_tokens() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let url = format!("http://{}/api/v1/auth/revoke-all-user", addr);
+        let client = reqwest::Client::new();
+        let token = |sub: &str, role: &str| {
+            state
+                .auth
+                .generate_token(&Claims::new(sub.to_string(), role.to_string(), vec![]))
+                .unwrap()
+        };
+        let (admin, worker) = (token("ops", "admin"), token("worker-1", "worker"));
+
+        let response = client.post(&url).bearer_auth(&worker).json(&json!({ "user_id": "worker-1" })).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let body: serde_json::Value = client
+            .post(&url)
+            .bearer_auth(&admin)
+            .json(&json!({ "user_id": "worker-1" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["revoked"], 1);
+        assert!(state.auth.verify_token(&worker).is_err());
+        assert!(state.auth.verify_token(&admin).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_scheduler() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let token = |role: &str| {
+            state
+                .auth
+                .generate_token(&Claims::new("ops".to_string(), role.to_string(), vec![]))
+                .unwrap()
+        };
+        let mut events = state.dispatcher.subscribe_events();
+        let pause_url = format!("http://{}/api/v1/scheduler/pause", addr);
+
+        let response = client.post(&pause_url).bearer_auth(token("client")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        assert!(!state.scheduler.is_paused());
+
+        let body: serde_json::Value =
+            client.post(&pause_url).bearer_auth(token("admin")).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["paused"], true);
+        assert!(state.scheduler.is_paused());
+        assert_eq!(events.recv().await.unwrap(), DispatcherEvent::SchedulerPaused { paused: true });
+
+        let resume_url = format!("http://{}/api/v1/scheduler/resume", addr);
+        let response = client.post(&resume_url).bearer_auth(token("admin")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(!state.scheduler.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_flush_queue() {
+        use crate::persistence::StoredTask;
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let token = |role: &str| {
+            state
+                .auth
+                .generate_token(&Claims::new("ops".to_string(), role.to_string(), vec![]))
+                .unwrap()
+        };
+        let finished = StoredTask {
+            id: "done".to_string(),
+            command: "true".to_string(),
+            status: "Completed".to_string(),
+            worker_id: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 4000,
+            created_at: "2024-06-01T00:00:00+00:00".to_string(),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: None,
+        };
+        state.store.store_task(&finished).await.unwrap();
+        for _ in 0..2 {
+            let worker = WorkerInfo::new("build-box".to_string(), "127.0.0.1".to_string(), 1, 1);
+            state.scheduler.register_worker(worker).await;
+        }
+        let low = Task::new("echo low".to_string());
+        let mut high = Task::new("echo high".to_string());
+        high.priority = 9;
+        state.scheduler.enqueue(low.clone()).await;
+        state.scheduler.enqueue(high.clone()).await;
+
+        let url = format!("http://{}/api/v1/queue", addr);
+        let entries: Vec<QueueEntry> = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].position, entries[0].task_id.as_str()), (0, high.id.as_str()));
+        assert_eq!((entries[1].position, entries[1].task_id.as_str()), (1, low.id.as_str()));
+        assert_eq!(entries[0].estimated_wait_secs, Some(0.0));
+        assert_eq!(entries[1].estimated_wait_secs, Some(2.0));
+        assert!(entries[1].queued_at.is_some());
+
+        let response = client.delete(&url).bearer_auth(token("client")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        let body: serde_json::Value =
+            client.delete(&url).bearer_auth(token("admin")).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["cancelled"], 2);
+        assert_eq!(state.scheduler.queue_size().await, 0);
+        let cancelled = state.store.get_task(&low.id).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, "Cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_api_key_authentication() {
+
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let admin_key = state
+            .auth
+            .create_api_key("admin", Role::Admin, Role::Admin.default_permissions())
+            .unwrap();
+        let ci_key = state
+            .auth
+            .create_api_key("ci", Role::Client, Role::Client.default_permissions())
+            .unwrap();
+        let keys_url = format!("http://{}/api/v1/auth/api-keys", addr);
+
+        let response = client.get(&keys_url).header("X-API-Key", "bogus").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client.get(&keys_url).header("X-API-Key", &ci_key).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let records: Vec<ApiKeyRecord> =
+            client.get(&keys_url).header("X-API-Key", &admin_key).send().await.unwrap().json().await.unwrap();
+        let labels: Vec<_> = records.iter().map(|r| r.label.as_str()).collect();
+        assert_eq!(labels, ["admin", "ci"]);
+
+        // API keys work on any authenticated endpoint
+        let workers_url = format!("http://{}/api/v1/workers", addr);
+        let response = client.get(&workers_url).header("X-API-Key", &admin_key).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .delete(format!("{}/ci", keys_url))
+            .header("X-API-Key", &admin_key)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        let response = client.get(&keys_url).header("X-API-Key", &ci_key).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let token = |role: &str, permissions: &[&str]| {
+            let permissions = permissions.iter().map(|p| p.to_string()).collect();
+            state
+                .auth
+                .generate_token(&Claims::new("ops".to_string(), role.to_string(), permissions))
+                .unwrap()
+        };
+        let url = format!("http://{}/api/v1/auth/api-keys", addr);
+
+        let response = client
+            .post(&url)
+            .bearer_auth(token("client", &[]))
+            .json(&json!({ "label": "ci", "role": "client" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        // manage_users alone does not let a client mint an admin key
+        let manager = token("client", &[MANAGE_USERS]);
+        let response = client
+            .post(&url)
+            .bearer_auth(&manager)
+            .json(&json!({ "label": "root", "role": "admin" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let response = client
+            .post(&url)
+            .bearer_auth(&manager)
+            .json(&json!({ "label": "ci", "role": "client" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let created: CreateApiKeyResponse = response.json().await.unwrap();
+        assert_eq!(created.permissions, Role::Client.default_permissions());
+        assert_eq!(state.auth.verify_api_key(&created.key).unwrap().sub, "ci");
+
+        // Saved, so a restarted dispatcher still accepts it
+        let saved = state.store.load_api_keys().await.unwrap();
+        assert_eq!(saved.len(), 1);
+        let restarted = AuthManager::new("secret".to_string());
+        restarted.restore_api_keys(saved);
+        assert!(restarted.verify_api_key(&created.key).is_ok());
+
+        let response = client
+            .post(&url)
+            .bearer_auth(&manager)
+            .json(&json!({ "label": "ci", "role": "client" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+
+        let response = client.delete(format!("{}/ci", url)).bearer_auth(&manager).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert!(state.store.load_api_keys().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_worker_endpoints_require_permission() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let client = reqwest::Client::new();
+        let token = |permissions: &[&str]| {
+            let permissions = permissions.iter().map(|p| p.to_string()).collect();
+            state
+                .auth
+                .generate_token(&Claims::new("ops".to_string(), "client".to_string(), permissions))
+                .unwrap()
+        };
+        let admin = token(&[MANAGE_WORKERS]);
+
+        let worker = WorkerInfo::new("build-box".to_string(), "127.0.0.1".to_string(), 1, 2);
+        let worker_id = worker.id.clone();
+        state.scheduler.register_worker(worker).await;
+        let task = Task::new("sleep 60".to_string());
+        let task_id = task.id.clone();
+        state.dispatcher.track_dispatched(task, &worker_id).await;
+
+        let url = format!("http://{}/api/v1/workers", addr);
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client.get(&url).bearer_auth(token(&["view_logs"])).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        let workers: Vec<WorkerInfo> = client.get(&url).bearer_auth(&admin).send().await.unwrap().json().await.unwrap();
+        assert_eq!(workers.len(), 1);
+
+        let worker_url = format!("{}/{}", url, worker_id);
+        let details: serde_json::Value =
+            client.get(&worker_url).bearer_auth(&admin).send().await.unwrap().json().await.unwrap();
+        assert_eq!(details["name"], "build-box");
+        assert_eq!(details["current_tasks"][0]["id"], task_id.as_str());
+        assert!(details["task_stats"].is_null());
+
+        // Maintenance keeps the worker registered but out of scheduling
+        let maintenance_url = format!("{}/maintenance", worker_url);
+        let body = serde_json::json!({ "enable": true });
+        let response = client.put(&maintenance_url).bearer_auth(token(&["view_logs"])).json(&body).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        let updated: WorkerInfo =
+            client.put(&maintenance_url).bearer_auth(&admin).json(&body).send().await.unwrap().json().await.unwrap();
+        assert!(updated.maintenance);
+        assert!(state.scheduler.get_idle_worker().await.is_none());
+        assert_eq!(state.scheduler.get_workers().await.len(), 1);
+
+        // Removing the worker puts its task back in the queue
+        let response = client.delete(&worker_url).bearer_auth(&admin).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        assert_eq!(state.scheduler.dequeue().await.unwrap().id, task_id);
+        assert!(state.scheduler.get_workers().await.is_empty());
+
+        let response = client.delete(&worker_url).bearer_auth(&admin).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        let response = client.put(&maintenance_url).bearer_auth(&admin).json(&body).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_worker_includes_task_stats() {
+        let (addr, state, _dir) = serve_test_api().await;
+        let admin = state
+            .auth
+            .generate_token(&Claims::new("ops".to_string(), "admin".to_string(), vec![MANAGE_WORKERS.to_string()]))
+            .unwrap();
+
+        // Stand-in worker answering the metrics query
+        let stats = WorkerTaskStats {
+            tasks_completed: 3,
+            tasks_failed: 1,
+            total_duration_ms: 400,
+            throughput_per_minute: 2.0,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = Transport::handle_connection(stream, move |msg| {
+                Box::pin(async move {
+                    Ok(match msg {
+                        Message::QueryWorkerMetrics { worker_id } => Some(Message::WorkerMetrics { worker_id, stats }),
+                        _ => None,
+                    })
+                })
+            })
+            .await;
+        });
+
+        let worker = WorkerInfo::new("build-box".to_string(), "127.0.0.1".to_string(), port, 2);
+        let url = format!("http://{}/api/v1/workers/{}", addr, worker.id);
+        state.scheduler.register_worker(worker).await;
+
+        let details: serde_json::Value = reqwest::Client::new()
+            .get(&url)
+            .bearer_auth(&admin)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let reported: WorkerTaskStats = serde_json::from_value(details["task_stats"].clone()).unwrap();
+        assert_eq!(reported, stats);
+        assert_eq!(reported.error_rate(), 0.25);
+    }
 }
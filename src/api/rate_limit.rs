@@ -0,0 +1,204 @@
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tower::{Layer, Service};
+
+/// Buckets untouched for this long are dropped by the cleanup task
+pub const BUCKET_IDLE_TTL: Duration = Duration::from_secs(600);
+
+/// Requests one client may still make; refills continuously up to the capacity
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn full(capacity: u32, now: Instant) -> Self {
+        Self {
+            tokens: capacity as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Take one token, or return how long until one is available
+    fn try_take(&mut self, capacity: u32, refill_per_sec: f64, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * refill_per_sec).min(capacity as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        if refill_per_sec <= 0.0 {
+            return Err(BUCKET_IDLE_TTL);
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / refill_per_sec))
+    }
+}
+
+/// Per-source-IP token buckets shared by every clone of the layer
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<IpAddr, TokenBucket>>>,
+    capacity: u32,
+    refill_per_sec: f64,
+}
+
+impl RateLimiter {
+    /// Allow bursts of `capacity` requests per IP, refilled at `refill_per_sec`
+    ///
+    /// A capacity of 0 disables limiting.
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+            capacity,
+            refill_per_sec,
+        }
+    }
+
+    /// Count a request from `ip`; `Err` holds how long it should wait
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        self.buckets
+            .lock()
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::full(self.capacity, now))
+            .try_take(self.capacity, self.refill_per_sec, now)
+    }
+
+    /// Clients with a bucket
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.lock().len()
+    }
+
+    /// Periodically drop buckets idle for longer than `BUCKET_IDLE_TTL`
+    ///
+    /// The task ends once every clone of this limiter is dropped.
+    pub fn start_cleanup(&self) -> JoinHandle<()> {
+        let buckets: Weak<_> = Arc::downgrade(&self.buckets);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(BUCKET_IDLE_TTL / 10);
+            loop {
+                interval.tick().await;
+                match buckets.upgrade() {
+                    Some(buckets) => Self::remove_idle(&buckets, BUCKET_IDLE_TTL),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    fn remove_idle(buckets: &Mutex<HashMap<IpAddr, TokenBucket>>, idle_ttl: Duration) {
+        buckets.lock().retain(|_, bucket| bucket.last_refill.elapsed() < idle_ttl);
+    }
+}
+
+/// `tower::Layer` answering `429 Too Many Requests` once a client IP runs out of tokens
+///
+/// The client IP comes from axum's `ConnectInfo<SocketAddr>`; requests without it pass through.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+/// Service produced by `RateLimitLayer`
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let client = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+        if let Some(Err(wait)) = client.map(|ip| self.limiter.check(ip)) {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            let response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                "Rate limit exceeded",
+            )
+                .into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(2, start);
+        assert!(bucket.try_take(2, 1.0, start).is_ok());
+        assert!(bucket.try_take(2, 1.0, start).is_ok());
+
+        let wait = bucket.try_take(2, 1.0, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(bucket.try_take(2, 1.0, start + Duration::from_millis(1500)).is_ok());
+    }
+
+    #[test]
+    fn test_limits_each_ip_separately() {
+        let limiter = RateLimiter::new(1, 0.5);
+        let (a, b) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        assert!(limiter.check(a).is_ok());
+        assert!(limiter.check(a).is_err());
+        assert!(limiter.check(b).is_ok());
+        assert_eq!(limiter.tracked_clients(), 2);
+
+        RateLimiter::remove_idle(&limiter.buckets, Duration::ZERO);
+        assert_eq!(limiter.tracked_clients(), 0);
+        assert!(RateLimiter::new(0, 0.0).check(a).is_ok());
+    }
+}
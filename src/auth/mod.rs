@@ -1,10 +1,32 @@
-use anyhow::Result;
+use crate::error::{AuthError, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use chrono::{Utc, Duration};
+use rand::Rng;
+use crate::util::sha256_hex;
+
+/// Lifetime of access tokens from `issue_token_pair`
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 60 * 60;
+
+/// Lifetime of refresh tokens
+pub const REFRESH_TOKEN_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Username that logs in with the admin password
+pub const ADMIN_USER: &str = "admin";
+
+/// What a token may be used for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenUse {
+    /// Authenticates API requests
+    #[default]
+    Access,
+    /// Only exchanged for a new token pair
+    Refresh,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -13,27 +35,62 @@ pub struct Claims {
     pub iat: i64,              // issued at
     pub role: String,          // role (dispatcher, worker, admin)
     pub permissions: Vec<String>, // specific permissions
+    #[serde(default)]
+    pub jti: String,           // unique token ID
+    #[serde(default)]
+    pub token_use: TokenUse,   // access or refresh
 }
 
 impl Claims {
     pub fn new(sub: String, role: String, permissions: Vec<String>) -> Self {
+        Self::with_ttl(sub, role, permissions, Duration::hours(24))
+    }
+
+    fn with_ttl(sub: String, role: String, permissions: Vec<String>, ttl: Duration) -> Self {
         let now = Utc::now();
-        let exp = now + Duration::hours(24);
-        
+        let exp = now + ttl;
+
         Self {
             sub,
             exp: exp.timestamp(),
             iat: now.timestamp(),
             role,
             permissions,
+            jti: uuid::Uuid::new_v4().to_string(),
+            token_use: TokenUse::Access,
         }
     }
 }
 
+/// Short-lived access token with the refresh token that renews it
+#[derive(Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+}
+
+/// A static API key; only the SHA-256 hash of the key itself is kept
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyRecord {
+    pub key_hash: String,
+    pub label: String,
+    pub role: Role,
+    pub permissions: Vec<String>,
+    pub created_at: i64,
+    pub last_used: Option<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthManager {
     secret: String,
     tokens: Arc<RwLock<HashMap<String, Claims>>>,
+    api_keys: Arc<RwLock<HashMap<String, ApiKeyRecord>>>, // keyed by key hash
+    introspections: Arc<RwLock<HashMap<String, Claims>>>, // keyed by token hash
+    user_tokens: Arc<RwLock<HashMap<String, HashMap<String, i64>>>>, // sub -> token ID -> expiry
+    revoked: Arc<RwLock<HashMap<String, i64>>>, // token ID -> expiry; rejected until then
+    admin_password_hash: Option<String>,
 }
 
 impl AuthManager {
@@ -41,35 +98,244 @@ impl AuthManager {
         Self {
             secret,
             tokens: Arc::new(RwLock::new(HashMap::new())),
+            api_keys: Arc::new(RwLock::new(HashMap::new())),
+            introspections: Arc::new(RwLock::new(HashMap::new())),
+            user_tokens: Arc::new(RwLock::new(HashMap::new())),
+            revoked: Arc::new(RwLock::new(HashMap::new())),
+            admin_password_hash: None,
         }
     }
 
-    /// Generate JWT token
+    /// Let `ADMIN_USER` log in with `password`
+    pub fn with_admin_password(mut self, password: &str) -> Self {
+        self.admin_password_hash = Some(sha256_hex(password));
+        self
+    }
+
+    /// Generate JWT token, indexing its ID under the subject for `bulk_revoke_by_user`
     pub fn generate_token(&self, claims: &Claims) -> Result<String> {
         let encoding_key = EncodingKey::from_secret(self.secret.as_bytes());
         let token = encode(&Header::default(), claims, &encoding_key)?;
+        self.user_tokens
+            .write()
+            .entry(claims.sub.clone())
+            .or_default()
+            .insert(claims.jti.clone(), claims.exp);
         Ok(token)
     }
 
     /// Verify and decode JWT token
+    ///
+    /// Refresh tokens are rejected; they only work with `rotate_refresh_token`.
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
+        let claims = self.decode_token(token)?;
+        if claims.token_use != TokenUse::Access {
+            return Err(AuthError::NotAccessToken.into());
+        }
+        Ok(claims)
+    }
+
+    fn decode_token(&self, token: &str) -> Result<Claims> {
         let decoding_key = DecodingKey::from_secret(self.secret.as_bytes());
         let token_data: TokenData<Claims> = decode(
             token,
             &decoding_key,
             &Validation::default(),
         )?;
+        if self.revoked.read().contains_key(&token_data.claims.jti) {
+            return Err(AuthError::TokenRevoked.into());
+        }
         Ok(token_data.claims)
     }
 
+    /// Claims of an active access token, or `None` if it is invalid or expired
+    ///
+    /// Results are cached until the token expires, so repeated introspection of the
+    /// same token skips signature verification.
+    pub fn introspect(&self, token: &str) -> Option<Claims> {
+        let now = Utc::now().timestamp();
+        let token_hash = sha256_hex(token);
+        if let Some(claims) = self.introspections.read().get(&token_hash) {
+            return (claims.exp > now && !self.revoked.read().contains_key(&claims.jti)).then(|| claims.clone());
+        }
+
+        let claims = self.verify_token(token).ok()?;
+        let mut introspections = self.introspections.write();
+        introspections.retain(|_, cached| cached.exp > now);
+        introspections.insert(token_hash, claims.clone());
+        Some(claims)
+    }
+
+    /// Issue an access token and a 7-day refresh token for the same subject
+    ///
+    /// The refresh token is registered under its JTI so it can be revoked.
+    pub fn issue_token_pair(&self, sub: String, role: String, permissions: Vec<String>) -> Result<TokenPair> {
+        let access = Claims::with_ttl(sub, role, permissions, Duration::seconds(ACCESS_TOKEN_TTL_SECS));
+        let mut refresh = Claims::with_ttl(
+            access.sub.clone(),
+            access.role.clone(),
+            access.permissions.clone(),
+            Duration::seconds(REFRESH_TOKEN_TTL_SECS),
+        );
+        refresh.token_use = TokenUse::Refresh;
+
+        let pair = TokenPair {
+            access_token: self.generate_token(&access)?,
+            refresh_token: self.generate_token(&refresh)?,
+            expires_in: ACCESS_TOKEN_TTL_SECS,
+        };
+        self.register_token(refresh.jti.clone(), refresh);
+        Ok(pair)
+    }
+
+    /// Exchange a username and password for a token pair
+    ///
+    /// `ADMIN_USER` logs in with the admin password; any other user is the label of an
+    /// API key, with the key as its password.
+    pub fn login(&self, username: &str, password: &str) -> Result<TokenPair> {
+        let password_hash = sha256_hex(password);
+        if username == ADMIN_USER && self.admin_password_hash.as_ref() == Some(&password_hash) {
+            return self.issue_token_pair(ADMIN_USER.to_string(), Role::Admin.to_string(), Role::Admin.default_permissions());
+        }
+
+        let (role, permissions) = {
+            let mut api_keys = self.api_keys.write();
+            match api_keys.get_mut(&password_hash) {
+                Some(record) if record.label == username => {
+                    record.last_used = Some(Utc::now().timestamp());
+                    (record.role, record.permissions.clone())
+                }
+                _ => return Err(AuthError::InvalidCredentials.into()),
+            }
+        };
+        self.issue_token_pair(username.to_string(), role.to_string(), permissions)
+    }
+
+    /// Exchange a valid refresh token for a new token pair, revoking the old one
+    pub fn rotate_refresh_token(&self, refresh_token: &str) -> Result<TokenPair> {
+        let claims = self.decode_token(refresh_token)?;
+        if claims.token_use != TokenUse::Refresh {
+            return Err(AuthError::NotRefreshToken.into());
+        }
+        // Removing under the write lock lets only one of two concurrent rotations win
+        if self.tokens.write().remove(&claims.jti).is_none() {
+            return Err(AuthError::TokenRevoked.into());
+        }
+        self.issue_token_pair(claims.sub, claims.role, claims.permissions)
+    }
+
+    /// Create an API key and return it; it cannot be retrieved again later
+    ///
+    /// Labels are unique and name the key in listings and revocation.
+    pub fn create_api_key(&self, label: &str, role: Role, permissions: Vec<String>) -> Result<String> {
+        let mut api_keys = self.api_keys.write();
+        if api_keys.values().any(|record| record.label == label) {
+            return Err(AuthError::DuplicateApiKey(label.to_string()).into());
+        }
+
+        let bytes: [u8; 32] = rand::thread_rng().gen();
+        let key = bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let key_hash = sha256_hex(&key);
+        api_keys.insert(
+            key_hash.clone(),
+            ApiKeyRecord {
+                key_hash,
+                label: label.to_string(),
+                role,
+                permissions,
+                created_at: Utc::now().timestamp(),
+                last_used: None,
+            },
+        );
+        Ok(key)
+    }
+
+    /// Check an API key and return claims equivalent to a token for it
+    pub fn verify_api_key(&self, key: &str) -> Result<Claims> {
+        let mut api_keys = self.api_keys.write();
+        let Some(record) = api_keys.get_mut(&sha256_hex(key)) else {
+            return Err(AuthError::UnknownApiKey.into());
+        };
+        record.last_used = Some(Utc::now().timestamp());
+        Ok(Claims::new(record.label.clone(), record.role.to_string(), record.permissions.clone()))
+    }
+
+    /// Every API key, ordered by label
+    pub fn list_api_keys(&self) -> Vec<ApiKeyRecord> {
+        let mut records: Vec<ApiKeyRecord> = self.api_keys.read().values().cloned().collect();
+        records.sort_by(|a, b| a.label.cmp(&b.label));
+        records
+    }
+
+    /// The API key with `label`, if there is one
+    pub fn api_key(&self, label: &str) -> Option<ApiKeyRecord> {
+        self.api_keys.read().values().find(|record| record.label == label).cloned()
+    }
+
+    /// Accept API keys saved by an earlier run, e.g. loaded from the task history database
+    pub fn restore_api_keys(&self, records: Vec<ApiKeyRecord>) {
+        let mut api_keys = self.api_keys.write();
+        for record in records {
+            api_keys.insert(record.key_hash.clone(), record);
+        }
+    }
+
+    /// Revoke the API key with `label`; false if there is none
+    pub fn revoke_api_key(&self, label: &str) -> bool {
+        let mut api_keys = self.api_keys.write();
+        let before = api_keys.len();
+        api_keys.retain(|_, record| record.label != label);
+        api_keys.len() < before
+    }
+
     /// Register a token (for revocation tracking)
     pub fn register_token(&self, token_id: String, claims: Claims) {
         self.tokens.write().insert(token_id, claims);
     }
 
-    /// Revoke a token
+    /// Revoke a token; it is rejected from then on even if it was never registered
+    ///
+    /// The revocation is kept until the token expires. Tokens this manager did not
+    /// generate are assumed to live no longer than a refresh token.
     pub fn revoke_token(&self, token_id: &str) {
+        let exp = self
+            .user_tokens
+            .read()
+            .values()
+            .find_map(|token_ids| token_ids.get(token_id).copied())
+            .unwrap_or_else(|| Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECS);
         self.tokens.write().remove(token_id);
+        self.revoked.write().insert(token_id.to_string(), exp);
+        self.prune_expired();
+    }
+
+    /// Revoke every token issued to `user_id`, returning how many there were
+    pub fn bulk_revoke_by_user(&self, user_id: &str) -> usize {
+        let token_ids = self.user_tokens.write().remove(user_id).unwrap_or_default();
+        {
+            let mut tokens = self.tokens.write();
+            let mut revoked = self.revoked.write();
+            for (token_id, exp) in &token_ids {
+                tokens.remove(token_id);
+                revoked.insert(token_id.clone(), *exp);
+            }
+        }
+        self.prune_expired();
+        token_ids.len()
+    }
+
+    /// Forget token IDs whose tokens have expired
+    ///
+    /// Expired tokens already fail their signature check, so neither the revocation
+    /// list nor the per-user index needs them.
+    pub fn prune_expired(&self) {
+        // Tokens are still accepted for the validation leeway after `exp`
+        let cutoff = Utc::now().timestamp() - Validation::default().leeway as i64;
+        self.revoked.write().retain(|_, exp| *exp >= cutoff);
+        self.tokens.write().retain(|_, claims| claims.exp >= cutoff);
+        let mut user_tokens = self.user_tokens.write();
+        user_tokens.values_mut().for_each(|token_ids| token_ids.retain(|_, exp| *exp >= cutoff));
+        user_tokens.retain(|_, token_ids| !token_ids.is_empty());
     }
 
     /// Check if token is revoked
@@ -77,10 +343,17 @@ impl AuthManager {
         !self.tokens.read().contains_key(token_id)
     }
 
-    /// Check if claims have permission
+    /// Check if claims have permission, either listed in the claims or among the
+    /// default permissions of their role and every role it implies
     pub fn has_permission(&self, claims: &Claims, permission: &str) -> bool {
-        claims.permissions.contains(&permission.to_string())
-        || claims.permissions.contains(&"*".to_string()) // wildcard
+        let grants = |permissions: &[String]| permissions.iter().any(|p| p == permission || p == "*"); // "*" is a wildcard
+        grants(&claims.permissions)
+            || claims.role.parse::<Role>().is_ok_and(|role| {
+                Role::ALL
+                    .into_iter()
+                    .filter(|other| role.implies(*other))
+                    .any(|other| grants(&other.default_permissions()))
+            })
     }
 
     /// Check if claims have role
@@ -96,7 +369,8 @@ impl Default for AuthManager {
 }
 
 /// Role-based access control
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Role {
     Admin,
     Dispatcher,
@@ -105,6 +379,23 @@ pub enum Role {
 }
 
 impl Role {
+    /// Every role, most privileged first
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Dispatcher, Role::Worker, Role::Client];
+
+    /// Whether this role may do everything `other` can: Admin > Dispatcher > Worker > Client
+    pub fn implies(&self, other: Role) -> bool {
+        self.rank() >= other.rank()
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Role::Admin => 3,
+            Role::Dispatcher => 2,
+            Role::Worker => 1,
+            Role::Client => 0,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             Role::Admin => "admin".to_string(),
@@ -145,6 +436,17 @@ impl Role {
     }
 }
 
+impl std::str::FromStr for Role {
+    type Err = AuthError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.to_string() == s)
+            .ok_or_else(|| AuthError::UnknownRole(s.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,6 +467,80 @@ mod tests {
         assert_eq!(verified.role, "worker");
     }
 
+    #[test]
+    fn test_refresh_token_rotation() {
+        let manager = AuthManager::new("test-secret".to_string());
+        let pair = manager
+            .issue_token_pair("user-1".to_string(), "client".to_string(), Role::Client.default_permissions())
+            .unwrap();
+
+        // Only the access token authenticates requests
+        assert_eq!(manager.verify_token(&pair.access_token).unwrap().sub, "user-1");
+        assert!(manager.verify_token(&pair.refresh_token).is_err());
+        assert!(manager.rotate_refresh_token(&pair.access_token).is_err());
+
+        let rotated = manager.rotate_refresh_token(&pair.refresh_token).unwrap();
+        assert_eq!(manager.verify_token(&rotated.access_token).unwrap().role, "client");
+
+        // The old refresh token is spent
+        assert!(manager.rotate_refresh_token(&pair.refresh_token).is_err());
+        assert!(manager.rotate_refresh_token(&rotated.refresh_token).is_ok());
+    }
+
+    #[test]
+    fn test_login() {
+        let manager = AuthManager::new("test-secret".to_string());
+        assert!(manager.login(ADMIN_USER, "hunter2").is_err());
+
+        let manager = manager.with_admin_password("hunter2");
+        let pair = manager.login(ADMIN_USER, "hunter2").unwrap();
+        assert_eq!(manager.verify_token(&pair.access_token).unwrap().role, "admin");
+        assert!(manager.rotate_refresh_token(&pair.refresh_token).is_ok());
+        assert!(manager.login(ADMIN_USER, "wrong").is_err());
+
+        // API keys log in under their label
+        let key = manager.create_api_key("ci", Role::Client, vec!["create_task".to_string()]).unwrap();
+        let claims = manager.verify_token(&manager.login("ci", &key).unwrap().access_token).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.role.as_str()), ("ci", "client"));
+        assert_eq!(claims.permissions, vec!["create_task".to_string()]);
+        assert!(manager.login("other", &key).is_err());
+    }
+
+    #[test]
+    fn test_api_key_lifecycle() {
+        let manager = AuthManager::new("test-secret".to_string());
+        let key = manager
+            .create_api_key("ci", Role::Client, Role::Client.default_permissions())
+            .unwrap();
+        assert_eq!(key.len(), 64);
+        assert!(manager.create_api_key("ci", Role::Admin, vec![]).is_err());
+
+        let claims = manager.verify_api_key(&key).unwrap();
+        assert_eq!((claims.sub.as_str(), claims.role.as_str()), ("ci", "client"));
+        assert!(manager.verify_api_key("not-a-key").is_err());
+
+        let records = manager.list_api_keys();
+        assert_eq!(records.len(), 1);
+        assert_ne!(records[0].key_hash, key);
+        assert!(records[0].last_used.is_some());
+
+        assert!(manager.revoke_api_key("ci"));
+        assert!(!manager.revoke_api_key("ci"));
+        assert!(manager.verify_api_key(&key).is_err());
+    }
+
+    #[test]
+    fn test_restored_api_key_still_verifies() {
+        let manager = AuthManager::new("test-secret".to_string());
+        let key = manager.create_api_key("ci", Role::Worker, vec![]).unwrap();
+        let record = manager.api_key("ci").unwrap();
+        assert!(manager.api_key("other").is_none());
+
+        let restarted = AuthManager::new("test-secret".to_string());
+        restarted.restore_api_keys(vec![record]);
+        assert_eq!(restarted.verify_api_key(&key).unwrap().role, "worker");
+    }
+
     #[test]
     fn test_role_permissions() {
         let admin_perms = Role::Admin.default_permissions();
@@ -187,4 +563,92 @@ mod tests {
         assert!(manager.has_permission(&claims, "manage_users"));
         assert!(manager.has_permission(&claims, "any_permission")); // admin has *
     }
+
+    #[test]
+    fn test_introspection() {
+        let auth = AuthManager::new("secret".to_string());
+        let claims = Claims::new("worker-1".to_string(), "worker".to_string(), vec!["execute_task".to_string()]);
+        let token = auth.generate_token(&claims).unwrap();
+
+        let introspected = auth.introspect(&token).unwrap();
+        assert_eq!(introspected.sub, "worker-1");
+        assert_eq!(auth.introspections.read().len(), 1);
+        assert_eq!(auth.introspect(&token).unwrap().jti, claims.jti);
+
+        let mut expired = claims.clone();
+        expired.exp = Utc::now().timestamp() - 3600;
+        assert!(auth.introspect(&auth.generate_token(&expired).unwrap()).is_none());
+        assert!(auth.introspect("not-a-token").is_none());
+
+        // Refresh tokens are not valid for API access, so they introspect as inactive
+        let pair = auth.issue_token_pair("ops".to_string(), "admin".to_string(), vec![]).unwrap();
+        assert!(auth.introspect(&pair.refresh_token).is_none());
+    }
+
+    #[test]
+    fn test_role_hierarchy() {
+        assert!(Role::Admin.implies(Role::Dispatcher));
+        assert!(Role::Dispatcher.implies(Role::Worker));
+        assert!(Role::Worker.implies(Role::Client));
+        assert!(Role::Worker.implies(Role::Worker));
+        assert!(!Role::Client.implies(Role::Worker));
+        assert_eq!("dispatcher".parse::<Role>().unwrap(), Role::Dispatcher);
+        assert!("root".parse::<Role>().is_err());
+
+        // Permissions come from the role's defaults and those of every role below it
+        let manager = AuthManager::default();
+        let dispatcher = Claims::new("d".to_string(), "dispatcher".to_string(), vec![]);
+        assert!(manager.has_permission(&dispatcher, "manage_workers"));
+        assert!(manager.has_permission(&dispatcher, "execute_task"));
+        assert!(!manager.has_permission(&dispatcher, "manage_users"));
+        let admin = Claims::new("a".to_string(), "admin".to_string(), vec![]);
+        assert!(manager.has_permission(&admin, "manage_users"));
+        let client = Claims::new("c".to_string(), "client".to_string(), vec!["view_logs".to_string()]);
+        assert!(manager.has_permission(&client, "view_logs"));
+        assert!(!manager.has_permission(&client, "execute_task"));
+        let unknown = Claims::new("u".to_string(), "guest".to_string(), vec![]);
+        assert!(!manager.has_permission(&unknown, "create_task"));
+    }
+
+    #[test]
+    fn test_bulk_revoke_by_user() {
+        let auth = AuthManager::new("secret".to_string());
+        let access = auth
+            .generate_token(&Claims::new("worker-1".to_string(), "worker".to_string(), vec![]))
+            .unwrap();
+        let pair = auth.issue_token_pair("worker-1".to_string(), "worker".to_string(), vec![]).unwrap();
+        let other = auth
+            .generate_token(&Claims::new("worker-2".to_string(), "worker".to_string(), vec![]))
+            .unwrap();
+        assert!(auth.introspect(&access).is_some());
+
+        assert_eq!(auth.bulk_revoke_by_user("worker-1"), 3);
+        assert!(auth.verify_token(&access).is_err());
+        assert!(auth.verify_token(&pair.access_token).is_err());
+        assert!(auth.rotate_refresh_token(&pair.refresh_token).is_err());
+        assert!(auth.introspect(&access).is_none());
+        assert!(auth.verify_token(&other).is_ok());
+        assert_eq!(auth.bulk_revoke_by_user("worker-1"), 0);
+    }
+
+    #[test]
+    fn test_expired_token_ids_are_pruned() {
+        let auth = AuthManager::new("secret".to_string());
+        let mut expired = Claims::new("worker-1".to_string(), "worker".to_string(), vec![]);
+        expired.exp = Utc::now().timestamp() - 3600;
+        auth.generate_token(&expired).unwrap();
+        let live = Claims::new("worker-2".to_string(), "worker".to_string(), vec![]);
+        let token = auth.generate_token(&live).unwrap();
+
+        auth.revoke_token(&expired.jti);
+        auth.revoke_token(&live.jti);
+        assert!(!auth.revoked.read().contains_key(&expired.jti));
+        assert_eq!(auth.revoked.read().get(&live.jti), Some(&live.exp));
+        assert!(auth.verify_token(&token).is_err());
+
+        auth.prune_expired();
+        let user_tokens = auth.user_tokens.read();
+        assert!(!user_tokens.contains_key("worker-1"));
+        assert!(user_tokens["worker-2"].contains_key(&live.jti));
+    }
 }
@@ -0,0 +1,343 @@
+use crate::persistence::StoredTask;
+use crate::protocol::{Message, OutputLine, Task, TaskResult, TaskStatus, WorkerInfo};
+use crate::transport::Transport;
+use anyhow::Result;
+use std::net::SocketAddr;
+
+/// Resolve a `host:port` dispatcher address
+pub async fn resolve(dispatcher: &str) -> Result<SocketAddr> {
+    tokio::net::lookup_host(dispatcher)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not resolve dispatcher address {}", dispatcher))
+}
+
+/// Queue a task on the dispatcher, returning its ID
+pub async fn submit_task(dispatcher_addr: SocketAddr, task: Task) -> Result<String> {
+    let task_id = task.id.clone();
+    let submit = Message::SubmitTask { task, wait: false };
+
+    match Transport::default().request(dispatcher_addr, &submit).await? {
+        Message::TaskStatus { status: TaskStatus::Pending, .. } => Ok(task_id),
+        Message::TaskStatus { status, .. } => Err(anyhow::anyhow!("Dispatcher rejected task {} ({:?})", task_id, status)),
+        other => Err(anyhow::anyhow!("Unexpected reply to task submission: {:?}", other)),
+    }
+}
+
+/// Queue a task on the dispatcher and wait until it finishes
+pub async fn submit_and_wait(dispatcher_addr: SocketAddr, task: Task) -> Result<TaskResult> {
+    let task_id = task.id.clone();
+    let submit = Message::SubmitTask { task, wait: true };
+
+    match Transport::default().request_with_timeout(dispatcher_addr, &submit, None).await? {
+        Message::TaskCompleted(result) => Ok(result),
+        Message::TaskStatus { status, .. } => Err(anyhow::anyhow!("Dispatcher rejected task {} ({:?})", task_id, status)),
+        other => Err(anyhow::anyhow!("Unexpected reply to task submission: {:?}", other)),
+    }
+}
+
+/// Ask the dispatcher for the live status of a task
+///
+/// Tasks the dispatcher is not running and has no result for are reported as `Pending`.
+pub async fn query_task(dispatcher_addr: SocketAddr, task_id: &str) -> Result<TaskStatus> {
+    let query = Message::QueryTask {
+        task_id: task_id.to_string(),
+    };
+
+    match Transport::default().request(dispatcher_addr, &query).await? {
+        Message::TaskStatus { task_id: id, status, .. } if id == task_id => Ok(status),
+        other => Err(anyhow::anyhow!("Unexpected reply to task query: {:?}", other)),
+    }
+}
+
+/// Fetch the full result of a finished task, or `None` if it has not finished yet
+pub async fn query_result(dispatcher_addr: SocketAddr, task_id: &str) -> Result<Option<TaskResult>> {
+    let query = Message::QueryResult {
+        task_id: task_id.to_string(),
+    };
+
+    match Transport::default().request(dispatcher_addr, &query).await? {
+        Message::TaskCompleted(result) if result.task_id == task_id => Ok(Some(result)),
+        Message::TaskStatus { task_id: id, .. } if id == task_id => Ok(None),
+        other => Err(anyhow::anyhow!("Unexpected reply to result query: {:?}", other)),
+    }
+}
+
+/// Fetch every worker currently registered with the dispatcher
+pub async fn list_workers(dispatcher_addr: SocketAddr) -> Result<Vec<WorkerInfo>> {
+    match Transport::default().request(dispatcher_addr, &Message::ListWorkers).await? {
+        Message::WorkerList(workers) => Ok(workers),
+        other => Err(anyhow::anyhow!("Unexpected reply to worker listing: {:?}", other)),
+    }
+}
+
+/// Follow a task's output until it finishes, calling `on_line` for each line
+///
+/// If the task had already finished (so nothing was streamed), its stored stdout
+/// and stderr are replayed instead.
+pub async fn watch_task(
+    dispatcher_addr: SocketAddr,
+    task_id: &str,
+    mut on_line: impl FnMut(&OutputLine),
+) -> Result<TaskResult> {
+    let transport = Transport::default();
+    let mut offset = 0;
+
+    loop {
+        let watch = Message::WatchTask {
+            task_id: task_id.to_string(),
+            offset,
+        };
+        match transport.request_with_timeout(dispatcher_addr, &watch, None).await? {
+            Message::TaskOutputLines { lines, next_offset, .. } => {
+                lines.iter().for_each(&mut on_line);
+                offset = next_offset;
+            }
+            Message::TaskCompleted(result) => {
+                if offset == 0 {
+                    replay_output(&result, &mut on_line);
+                }
+                return Ok(result);
+            }
+            other => return Err(anyhow::anyhow!("Unexpected reply while watching task: {:?}", other)),
+        }
+    }
+}
+
+/// Feed a finished task's captured output through `on_line`, stdout first
+fn replay_output(result: &TaskResult, on_line: &mut impl FnMut(&OutputLine)) {
+    let stdout = result.stdout.lines().map(|line| (line, false));
+    let stderr = result.stderr.lines().map(|line| (line, true));
+
+    for (line, is_stderr) in stdout.chain(stderr) {
+        on_line(&OutputLine {
+            line: line.to_string(),
+            is_stderr,
+            timestamp: result.completed_at,
+        });
+    }
+}
+
+/// Fetch a task's stored result from the REST API at `api_base` (e.g. `http://host:8080`)
+pub async fn fetch_result_from_api(api_base: &str, task_id: &str) -> Result<TaskResult> {
+    let url = format!("{}/api/v1/tasks/{}", api_base.trim_end_matches('/'), task_id);
+    let task: StoredTask = reqwest::get(&url).await?.error_for_status()?.json().await?;
+    task.to_result()
+        .ok_or_else(|| anyhow::anyhow!("Task {} has unknown status {}", task.id, task.status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_query_task() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(Transport::handle_connection(stream, |msg| {
+                    Box::pin(async move {
+                        let Message::QueryTask { task_id } = msg else {
+                            return Ok(None);
+                        };
+                        let status = if task_id == "done" { TaskStatus::Completed } else { TaskStatus::Pending };
+                        Ok(Some(Message::TaskStatus { task_id, status, progress: None }))
+                    })
+                }));
+            }
+        });
+
+        assert_eq!(query_task(addr, "done").await.unwrap(), TaskStatus::Completed);
+        assert_eq!(query_task(addr, "other").await.unwrap(), TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_submit_and_wait() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Fake dispatcher that "runs" submitted tasks by echoing their command
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(Transport::handle_connection(stream, |msg| {
+                    Box::pin(async move {
+                        let Message::SubmitTask { task, wait } = msg else {
+                            return Ok(None);
+                        };
+                        if !wait {
+                            return Ok(Some(Message::TaskStatus {
+                                task_id: task.id,
+                                status: TaskStatus::Pending,
+                                progress: None,
+                            }));
+                        }
+                        Ok(Some(Message::TaskCompleted(TaskResult {
+                            task_id: task.id,
+                            worker_id: "worker-1".to_string(),
+                            status: TaskStatus::Failed,
+                            stdout: task.command,
+                            stderr: String::new(),
+                            exit_code: Some(3),
+                            duration_ms: 1,
+                            completed_at: 0,
+                            resource_usage: None,
+                        })))
+                    })
+                }));
+            }
+        });
+
+        let task = Task::new("echo hi".to_string());
+        let task_id = task.id.clone();
+        assert_eq!(submit_task(addr, task).await.unwrap(), task_id);
+
+        let result = submit_and_wait(addr, Task::new("exit 3".to_string())).await.unwrap();
+        assert_eq!(result.stdout, "exit 3");
+        assert_eq!(result.exit_code, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_query_result() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(Transport::handle_connection(stream, |msg| {
+                    Box::pin(async move {
+                        let Message::QueryResult { task_id } = msg else {
+                            return Ok(None);
+                        };
+                        if task_id != "done" {
+                            return Ok(Some(Message::TaskStatus { task_id, status: TaskStatus::Running, progress: None }));
+                        }
+                        Ok(Some(Message::TaskCompleted(TaskResult {
+                            task_id,
+                            worker_id: "worker-1".to_string(),
+                            status: TaskStatus::Completed,
+                            stdout: "ok\n".to_string(),
+                            stderr: String::new(),
+                            exit_code: Some(0),
+                            duration_ms: 5,
+                            completed_at: 0,
+                            resource_usage: None,
+                        })))
+                    })
+                }));
+            }
+        });
+
+        let result = query_result(addr, "done").await.unwrap().unwrap();
+        assert_eq!(result.stdout, "ok\n");
+        assert!(query_result(addr, "running").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_api_task_into_result() {
+        let task: StoredTask = serde_json::from_value(serde_json::json!({
+            "id": "task-1",
+            "command": "false",
+            "status": "Failed",
+            "worker_id": null,
+            "stdout": "",
+            "stderr": "boom",
+            "exit_code": 1,
+            "duration_ms": 12,
+            "created_at": "2024-01-01T00:00:00+00:00",
+            "completed_at": "2024-01-01T00:00:01+00:00",
+        }))
+        .unwrap();
+
+        let result = task.to_result().unwrap();
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert_eq!(result.exit_code, Some(1));
+        assert_eq!(result.stderr, "boom");
+        assert_eq!(result.completed_at, 1_704_067_201);
+    }
+
+    #[tokio::test]
+    async fn test_list_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            Transport::handle_connection(stream, |msg| {
+                Box::pin(async move {
+                    let Message::ListWorkers = msg else {
+                        return Ok(None);
+                    };
+                    let worker = WorkerInfo::new("worker-1".to_string(), "10.0.0.5".to_string(), 7879, 4);
+                    Ok(Some(Message::WorkerList(vec![worker])))
+                })
+            })
+            .await
+            .unwrap();
+        });
+
+        let workers = list_workers(addr).await.unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].name, "worker-1");
+        assert_eq!(workers[0].max_jobs, 4);
+    }
+
+    #[tokio::test]
+    async fn test_watch_task() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // "live" streams one line per poll; "old" finished before anyone watched
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(Transport::handle_connection(stream, |msg| {
+                    Box::pin(async move {
+                        let Message::WatchTask { task_id, offset } = msg else {
+                            return Ok(None);
+                        };
+                        let streamed = ["building", "warning: unused"];
+                        if task_id == "live" && offset < streamed.len() {
+                            let line = OutputLine {
+                                line: streamed[offset].to_string(),
+                                is_stderr: offset == 1,
+                                timestamp: 0,
+                            };
+                            return Ok(Some(Message::TaskOutputLines { task_id, lines: vec![line], next_offset: offset + 1 }));
+                        }
+                        Ok(Some(Message::TaskCompleted(TaskResult {
+                            task_id,
+                            worker_id: "worker-1".to_string(),
+                            status: TaskStatus::Completed,
+                            stdout: "building\n".to_string(),
+                            stderr: "warning: unused\n".to_string(),
+                            exit_code: Some(0),
+                            duration_ms: 5,
+                            completed_at: 0,
+                            resource_usage: None,
+                        })))
+                    })
+                }));
+            }
+        });
+
+        for task_id in ["live", "old"] {
+            let mut seen = Vec::new();
+            let result = watch_task(addr, task_id, |line| seen.push((line.line.clone(), line.is_stderr)))
+                .await
+                .unwrap();
+            assert_eq!(result.exit_code, Some(0));
+            assert_eq!(
+                seen,
+                vec![("building".to_string(), false), ("warning: unused".to_string(), true)],
+                "output of {}",
+                task_id
+            );
+        }
+    }
+}
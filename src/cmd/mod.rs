@@ -1,4 +1,9 @@
 use clap::{Parser, Subcommand};
+use crate::config::Config;
+use crate::executor::ExecutorKind;
+use crate::scheduler::SchedulerAlgorithm;
+use crate::transport::TransportKind;
+use crate::util::build_info;
 use std::path::PathBuf;
 
 /// Validates input arguments for commands
@@ -72,7 +77,7 @@ impl Validator {
 #[command(name = "octaskly")]
 #[command(about = "Distributed Task Orchestration with P2P Resource Sharing")]
 #[command(long_about = "Octaskly - Distributed task scheduler for local networks\n\nTwo modes available:\n  dispatcher  - Central task coordinator\n  worker      - Task execution node")]
-#[command(version = "1.0.0")]
+#[command(version)]
 #[command(author = "Octaskly Contributors")]
 pub struct Cli {
     #[command(subcommand)]
@@ -85,6 +90,18 @@ pub struct Cli {
     /// Show verbose debug output
     #[arg(global = true, short = 'v', long)]
     pub verbose: bool,
+
+    /// TOML config file [default: ~/.config/octaskly/config.toml, then ./octaskly.toml]
+    #[arg(global = true, long)]
+    pub config: Option<PathBuf>,
+
+    /// Print the version, commit, build time, rustc version and target, then exit
+    #[arg(long)]
+    pub build_info: bool,
+
+    /// Export tracing spans to this OTLP gRPC endpoint (e.g. http://localhost:4317)
+    #[arg(global = true, long, value_name = "GRPC_URL")]
+    pub otel_endpoint: Option<String>,
 }
 
 /// Available application commands
@@ -94,16 +111,16 @@ pub enum Command {
     #[command(about = "Central task coordinator - manages workers and distributes tasks")]
     Dispatcher {
         /// Bind address for worker connections [default: 0.0.0.0]
-        #[arg(short = 'b', long, default_value = "0.0.0.0")]
-        bind: String,
+        #[arg(short = 'b', long)]
+        bind: Option<String>,
 
         /// TCP port for dispatcher to listen on [default: 7878]
-        #[arg(short = 'p', long, default_value = "7878")]
-        port: u16,
+        #[arg(short = 'p', long)]
+        port: Option<u16>,
 
         /// Directory for storing task data and results [default: ./tasks]
-        #[arg(long, default_value = "./tasks")]
-        workdir: PathBuf,
+        #[arg(long)]
+        workdir: Option<PathBuf>,
 
         /// Maximum number of worker connections [default: 10]
         #[arg(long, default_value = "10")]
@@ -124,6 +141,70 @@ pub enum Command {
         /// Enable interactive terminal UI dashboard
         #[arg(long)]
         ui: bool,
+
+        /// PEM certificate chain for TLS on worker connections (requires --tls-key)
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key matching --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+
+        /// Require workers to present a certificate from the CA in --workdir (created if missing)
+        #[arg(long)]
+        mtls: bool,
+
+        /// Advertise this dispatcher on the local network over mDNS
+        #[arg(long)]
+        mdns: bool,
+
+        /// Protocol for worker traffic: tcp or quic (on the same port, over UDP) [default: tcp]
+        #[arg(long, value_enum)]
+        transport: Option<TransportKind>,
+
+        /// Accept any QUIC worker certificate (development only; QUIC dispatchers need it for now)
+        #[arg(long)]
+        insecure: bool,
+
+        /// Only register workers started with the same --psk
+        #[arg(long)]
+        psk: Option<String>,
+
+        /// Task history database: a postgres:// URL or a SQLite file path [default: <workdir>/octaskly.db]
+        #[arg(long)]
+        db_url: Option<String>,
+
+        /// Serve the REST API on this port (disabled if not set)
+        #[arg(long)]
+        api_port: Option<u16>,
+
+        /// Directory for periodic backups of the SQLite history database (disabled if not set)
+        #[arg(long)]
+        backup_path: Option<PathBuf>,
+
+        /// Hours between history database backups [default: 24]
+        #[arg(long)]
+        backup_interval_hours: Option<u64>,
+
+        /// Number of history database backups to keep [default: 7]
+        #[arg(long)]
+        backup_retention: Option<usize>,
+
+        /// Weight of free job slots when ranking workers [default: 1.0]
+        #[arg(long)]
+        scheduling_weight_cpu: Option<f64>,
+
+        /// Weight of heartbeat latency when ranking workers [default: 1.0]
+        #[arg(long)]
+        scheduling_weight_latency: Option<f64>,
+
+        /// Weight of task success rate when ranking workers [default: 1.0]
+        #[arg(long)]
+        scheduling_weight_reliability: Option<f64>,
+
+        /// How to pick a worker for each task [default: fifo]
+        #[arg(long, value_enum)]
+        scheduler_algorithm: Option<SchedulerAlgorithm>,
     },
 
     /// Start as worker (task execution node)
@@ -133,17 +214,17 @@ pub enum Command {
         #[arg(short = 'n', long)]
         name: String,
 
-        /// Address of dispatcher to connect to [default: localhost]
+        /// Dispatcher address (HOST or HOST:PORT); repeat for failover, tried in order [default: localhost]
         #[arg(short = 'd', long, default_value = "localhost")]
-        dispatcher: String,
+        dispatcher: Vec<String>,
 
-        /// Port of dispatcher to connect to [default: 7878]
-        #[arg(short = 'p', long, default_value = "7878")]
-        dispatcher_port: u16,
+        /// Port of dispatchers given without one [default: 7878]
+        #[arg(short = 'p', long)]
+        dispatcher_port: Option<u16>,
 
         /// Maximum number of concurrent tasks this worker can execute [default: 4]
-        #[arg(short = 'j', long, default_value = "4")]
-        max_jobs: usize,
+        #[arg(short = 'j', long)]
+        max_jobs: Option<usize>,
 
         /// Number of CPU cores available on this worker (auto-detect if not set)
         #[arg(short = 'c', long)]
@@ -157,9 +238,136 @@ pub enum Command {
         #[arg(long, default_value = "false")]
         gpu: bool,
 
-        /// Allow execution of shell commands on this worker
-        #[arg(long, default_value = "true")]
-        allow_shell: bool,
+        /// Allow execution of shell commands on this worker [default: true]
+        #[arg(long)]
+        allow_shell: Option<bool>,
+
+        /// Comma-separated capability tags advertised to the dispatcher (e.g. gpu,rust)
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// PEM CA certificate used to verify a TLS-enabled dispatcher
+        #[arg(long)]
+        tls_ca: Option<PathBuf>,
+
+        /// PEM client certificate for an mTLS dispatcher; its common name becomes the worker ID
+        #[arg(long, requires_all = ["tls_key", "tls_ca"])]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key matching --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+
+        /// Backend used to run tasks: shell (default) or docker
+        #[arg(long, value_enum, default_value_t = ExecutorKind::Shell)]
+        executor: ExecutorKind,
+
+        /// Look for a dispatcher over mDNS first, falling back to --dispatcher
+        #[arg(long)]
+        mdns: bool,
+
+        /// Protocol for dispatcher traffic; must match the dispatcher's [default: tcp]
+        #[arg(long, value_enum)]
+        transport: Option<TransportKind>,
+
+        /// PEM certificate the QUIC dispatcher must present, e.g. its <workdir>/quic.pem
+        #[arg(long)]
+        pin_cert: Option<PathBuf>,
+
+        /// Accept any QUIC dispatcher certificate (development only)
+        #[arg(long)]
+        insecure: bool,
+
+        /// Pre-shared key the dispatcher requires to register this worker
+        #[arg(long)]
+        psk: Option<String>,
+
+        /// Stop tasks once their combined stdout and stderr exceed this many bytes [default: 10485760]
+        #[arg(long)]
+        max_output_bytes: Option<usize>,
+    },
+
+    /// Submit a task to a running dispatcher
+    #[command(about = "Send a task to a running dispatcher")]
+    Submit {
+        /// Dispatcher address [default: 127.0.0.1:7878]
+        #[arg(short = 'd', long, default_value = "127.0.0.1:7878")]
+        dispatcher: String,
+
+        /// Shell command to run
+        command: String,
+
+        /// Task execution timeout in seconds [default: 300]
+        #[arg(short = 't', long)]
+        timeout: Option<u64>,
+
+        /// Comma-separated tags a worker must have to run the task
+        #[arg(long, value_delimiter = ',')]
+        tags: Option<Vec<String>>,
+
+        /// Wait for the task to finish, print its output and exit with its exit code
+        #[arg(short = 'w', long)]
+        wait: bool,
+    },
+
+    /// Show the status of a submitted task
+    #[command(about = "Show the status of a task on a running dispatcher")]
+    Status {
+        /// Dispatcher address [default: 127.0.0.1:7878]
+        #[arg(short = 'd', long, default_value = "127.0.0.1:7878")]
+        dispatcher: String,
+
+        /// ID printed by `octaskly submit`
+        task_id: String,
+
+        /// Print machine-readable JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+
+        /// Fetch finished results from the REST API on this port instead
+        #[arg(long)]
+        api_port: Option<u16>,
+    },
+
+    /// Stream a task's output as it runs
+    #[command(about = "Stream live output of a task until it finishes")]
+    Watch {
+        /// Dispatcher address [default: 127.0.0.1:7878]
+        #[arg(short = 'd', long, default_value = "127.0.0.1:7878")]
+        dispatcher: String,
+
+        /// ID printed by `octaskly submit`
+        task_id: String,
+
+        /// Print output without ANSI colors
+        #[arg(long)]
+        no_color: bool,
+    },
+
+    /// List workers registered with a dispatcher
+    #[command(about = "List workers registered with a running dispatcher")]
+    Workers {
+        /// Dispatcher address [default: 127.0.0.1:7878]
+        #[arg(short = 'd', long, default_value = "127.0.0.1:7878")]
+        dispatcher: String,
+
+        /// Print a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Manage mTLS certificates
+    #[command(about = "Manage certificates for mTLS between dispatcher and workers")]
+    Cert {
+        #[command(subcommand)]
+        action: CertCommand,
+    },
+
+    /// Create or check a config file
+    #[command(about = "Generate a default config file or validate an existing one")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
     },
 
     /// Quick dispatcher launch
@@ -183,6 +391,49 @@ pub enum Command {
     },
 }
 
+/// Certificate management actions
+#[derive(Subcommand, Debug)]
+pub enum CertCommand {
+    /// Sign a certificate with the dispatcher's CA
+    #[command(about = "Issue a certificate signed by the dispatcher's CA")]
+    Issue {
+        /// Subject common name; must be the worker's name for worker certificates
+        #[arg(short = 'n', long)]
+        name: String,
+
+        /// Dispatcher directory holding the CA (created if missing) [default: ./tasks]
+        #[arg(long)]
+        workdir: Option<PathBuf>,
+
+        /// Directory to write <NAME>.pem and <NAME>-key.pem to [default: the workdir]
+        #[arg(short = 'o', long)]
+        out: Option<PathBuf>,
+
+        /// Extra comma-separated host names or IP addresses the certificate is valid for
+        #[arg(long, value_delimiter = ',')]
+        san: Vec<String>,
+    },
+}
+
+/// Config file actions
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write a config file with every setting at its default
+    #[command(about = "Write a commented config file with every setting at its default")]
+    Init {
+        /// File to write [default: ./octaskly.toml]
+        path: Option<PathBuf>,
+
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check the config file for unknown or invalid settings
+    #[command(about = "Check the --config file (or the one found by default) for unknown or invalid settings")]
+    Validate,
+}
+
 impl Cli {
     /// Parse CLI arguments and normalize command shortcuts with validation
     pub fn parse_and_run() -> Result<Command, anyhow::Error> {
@@ -197,10 +448,28 @@ impl Cli {
                 task_timeout, 
                 p2p_enabled, 
                 discovery_port, 
-                ui 
+                ui,
+                tls_cert,
+                tls_key,
+                mtls,
+                mdns,
+                transport,
+                insecure,
+                psk,
+                db_url,
+                api_port,
+                backup_path,
+                backup_interval_hours,
+                backup_retention,
+                scheduling_weight_cpu,
+                scheduling_weight_latency,
+                scheduling_weight_reliability,
+                scheduler_algorithm,
             }) => {
-                // Validate dispatcher arguments
-                if let Err(e) = Validator::dispatcher(&bind, port, max_workers) {
+                // Validate dispatcher arguments; unset ones come from the config file later
+                let defaults = Config::default();
+                let (bind_value, port_value) = (bind.as_deref().unwrap_or(&defaults.bind), port.unwrap_or(defaults.port));
+                if let Err(e) = Validator::dispatcher(bind_value, port_value, max_workers) {
                     eprintln!("❌ Dispatcher validation failed: {}", e);
                     std::process::exit(1);
                 }
@@ -213,6 +482,22 @@ impl Cli {
                     p2p_enabled,
                     discovery_port,
                     ui,
+                    tls_cert,
+                    tls_key,
+                    mtls,
+                    mdns,
+                    transport,
+                    insecure,
+                    psk,
+                    db_url,
+                    api_port,
+                    backup_path,
+                    backup_interval_hours,
+                    backup_retention,
+                    scheduling_weight_cpu,
+                    scheduling_weight_latency,
+                    scheduling_weight_reliability,
+                    scheduler_algorithm,
                 }
             }
             Some(Command::Worker { 
@@ -223,12 +508,27 @@ impl Cli {
                 cpu_cores, 
                 memory_mb, 
                 gpu, 
-                allow_shell 
+                allow_shell,
+                tags,
+                tls_ca,
+                tls_cert,
+                tls_key,
+                executor,
+                mdns,
+                transport,
+                pin_cert,
+                insecure,
+                psk,
+                max_output_bytes,
             }) => {
-                // Validate worker arguments
-                if let Err(e) = Validator::worker(&name, &dispatcher, dispatcher_port, max_jobs) {
-                    eprintln!("❌ Worker validation failed: {}", e);
-                    std::process::exit(1);
+                // Validate worker arguments; unset ones come from the config file later
+                let defaults = Config::default();
+                let port_value = dispatcher_port.unwrap_or(defaults.port);
+                for address in &dispatcher {
+                    if let Err(e) = Validator::worker(&name, address, port_value, max_jobs.unwrap_or(defaults.max_jobs)) {
+                        eprintln!("❌ Worker validation failed: {}", e);
+                        std::process::exit(1);
+                    }
                 }
                 Command::Worker {
                     name,
@@ -239,6 +539,17 @@ impl Cli {
                     memory_mb,
                     gpu,
                     allow_shell,
+                    tags,
+                    tls_ca,
+                    tls_cert,
+                    tls_key,
+                    executor,
+                    mdns,
+                    transport,
+                    pin_cert,
+                    insecure,
+                    psk,
+                    max_output_bytes,
                 }
             }
             Some(Command::D { bind, port, ui }) => {
@@ -248,14 +559,30 @@ impl Cli {
                     std::process::exit(1);
                 }
                 Command::Dispatcher {
-                    bind,
-                    port,
-                    workdir: PathBuf::from("./tasks"),
+                    bind: Some(bind),
+                    port: Some(port),
+                    workdir: None,
                     max_workers: 10,
                     task_timeout: 300,
                     p2p_enabled: true,
                     discovery_port: 5555,
                     ui,
+                    tls_cert: None,
+                    tls_key: None,
+                    mtls: false,
+                    mdns: false,
+                    transport: None,
+                    insecure: false,
+                    psk: None,
+                    db_url: None,
+                    api_port: None,
+                    backup_path: None,
+                    backup_interval_hours: None,
+                    backup_retention: None,
+                    scheduling_weight_cpu: None,
+                    scheduling_weight_latency: None,
+                    scheduling_weight_reliability: None,
+                    scheduler_algorithm: None,
                 }
             }
             Some(Command::W { name, max_jobs }) => {
@@ -266,15 +593,27 @@ impl Cli {
                 }
                 Command::Worker {
                     name,
-                    dispatcher: "localhost".to_string(),
-                    dispatcher_port: 7878,
-                    max_jobs,
+                    dispatcher: vec!["localhost".to_string()],
+                    dispatcher_port: None,
+                    max_jobs: Some(max_jobs),
                     cpu_cores: None,
                     memory_mb: None,
                     gpu: false,
-                    allow_shell: true,
+                    allow_shell: None,
+                    tags: Vec::new(),
+                    tls_ca: None,
+                    tls_cert: None,
+                    tls_key: None,
+                    executor: ExecutorKind::Shell,
+                    mdns: false,
+                    transport: None,
+                    pin_cert: None,
+                    insecure: false,
+                    psk: None,
+                    max_output_bytes: None,
                 }
             }
+            Some(other) => other,
             None => {
                 Self::show_default_help();
                 std::process::exit(1);
@@ -287,17 +626,25 @@ impl Cli {
     /// Display minimalist default help message
     fn show_default_help() {
         println!();
-        println!("octaskly v1.0.0 | Distributed Task Orchestration with P2P Sharing");
+        println!("octaskly v{} | Distributed Task Orchestration with P2P Sharing", build_info::VERSION);
         println!();
         println!("USAGE:");
         println!("  octaskly dispatcher [OPTIONS]   Central task coordinator");
         println!("  octaskly worker [OPTIONS]       Task execution node");
+        println!("  octaskly submit [OPTIONS] CMD   Send a task to a running dispatcher");
+        println!("  octaskly status [OPTIONS] ID    Show the status of a submitted task");
+        println!("  octaskly watch [OPTIONS] ID     Stream live output of a task");
+        println!("  octaskly workers [OPTIONS]      List registered workers and their load");
+        println!("  octaskly cert issue -n NAME     Issue an mTLS certificate from the dispatcher CA");
+        println!("  octaskly config init [PATH]     Write a default octaskly.toml");
+        println!("  octaskly config validate        Check a config file for mistakes");
         println!("  octaskly d [OPTIONS]            Quick dispatcher");
         println!("  octaskly w [OPTIONS]            Quick worker");
         println!();
         println!("OPTIONS (Global):");
         println!("  --monitor                       Enable real-time TUI dashboard");
         println!("  -v, --verbose                   Show debug output");
+        println!("  --config PATH                   TOML config file (env: OCTASKLY_*)");
         println!("  -h, --help                      Show detailed help");
         println!("  -V, --version                   Show version");
         println!();
@@ -310,22 +657,43 @@ impl Cli {
         println!("  --p2p-enabled BOOL              Enable P2P [default: true]");
         println!("  --discovery-port NUM            P2P port [default: 5555]");
         println!("  --ui                            Enable terminal UI dashboard");
+        println!("  --tls-cert PATH --tls-key PATH  Serve worker traffic over TLS");
+        println!("  --mtls                          Require CA-signed worker certificates");
+        println!("  --mdns                          Advertise on the local network via mDNS");
+        println!("  --transport tcp|quic            Protocol for worker traffic [default: tcp]");
+        println!("  --insecure                      Accept any QUIC worker certificate (development)");
+        println!("  --psk KEY                       Only register workers started with the same key");
+        println!("  --db-url URL                    postgres:// URL or SQLite path for task history");
+        println!("  --api-port NUM                  Serve the REST API on this port");
+        println!("  --scheduling-weight-* NUM       Weight of cpu, latency or reliability in worker ranking");
         println!();
         println!("WORKER OPTIONS:");
         println!("  -n, --name NAME                 Unique worker name (required)");
-        println!("  -d, --dispatcher ADDR           Dispatcher address [default: localhost]");
+        println!("  -d, --dispatcher ADDR           Dispatcher address; repeat for failover [default: localhost]");
         println!("  -p, --dispatcher-port NUM       Dispatcher port [default: 7878]");
         println!("  -j, --max-jobs NUM              Max concurrent jobs [default: 4]");
         println!("  -c, --cpu-cores NUM             CPU cores (auto-detect if empty)");
         println!("  -m, --memory-mb NUM             RAM in MB (auto-detect if empty)");
         println!("  --gpu BOOL                      GPU available [default: false]");
         println!("  --allow-shell BOOL              Allow shell exec [default: true]");
+        println!("  --tags TAG[,TAG...]             Capability tags (e.g. gpu,rust)");
+        println!("  --tls-ca PATH                   CA cert for a TLS dispatcher");
+        println!("  --tls-cert PATH --tls-key PATH  Client certificate for an mTLS dispatcher");
+        println!("  --executor shell|docker         Task backend [default: shell]");
+        println!("  --mdns                          Find the dispatcher via mDNS (5s, then -d)");
+        println!("  --transport tcp|quic            Protocol for dispatcher traffic [default: tcp]");
+        println!("  --pin-cert <FILE>               Certificate the QUIC dispatcher must present");
+        println!("  --insecure                      Accept any QUIC dispatcher certificate (development)");
+        println!("  --psk KEY                       Pre-shared key the dispatcher requires");
+        println!("  --max-output-bytes NUM          Stop tasks printing more [default: 10 MB]");
         println!();
         println!("EXAMPLES:");
         println!("  octaskly dispatcher --port 7878 --ui");
         println!("  octaskly worker -n worker-01");
         println!("  octaskly d --monitor");
         println!("  octaskly w -n prod-worker -d 192.168.1.10");
+        println!("  octaskly submit --wait --tags gpu \"cargo test\"");
+        println!("  octaskly status --json <TASK_ID>");
         println!();
         println!("Use 'octaskly --help' for full documentation");
         println!();
@@ -0,0 +1,588 @@
+use crate::scheduler::SchedulerAlgorithm;
+use crate::transport::TransportKind;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// File looked up in the working directory when no other config file exists
+pub const LOCAL_CONFIG_FILE: &str = "octaskly.toml";
+
+/// Prefix of environment variables that override config file values
+pub const ENV_PREFIX: &str = "OCTASKLY_";
+
+/// `(name, type, description)` of every `Config` field, in the order `template` writes them
+const FIELD_DOCS: &[(&str, &str, &str)] = &[
+    ("bind", "string", "Address the dispatcher listens on"),
+    ("port", "integer", "Dispatcher port; also the port workers connect to"),
+    ("workdir", "path", "Directory for task data and the default history database"),
+    ("max_jobs", "integer", "Concurrent tasks per worker"),
+    ("worker_id_file", "path", "File keeping a worker's ID across restarts [default: <workdir>/.worker_id]"),
+    ("allow_shell", "boolean", "Whether workers may run shell commands"),
+    ("tls_cert", "path", "PEM certificate chain for TLS on worker connections"),
+    ("tls_key", "path", "PEM private key matching tls_cert"),
+    ("mtls", "boolean", "Require workers to present a certificate signed by the dispatcher's CA"),
+    ("mdns", "boolean", "Advertise the dispatcher, or look one up as a worker, over mDNS"),
+    ("transport", "\"tcp\" or \"quic\"", "Protocol between the dispatcher and workers"),
+    ("insecure", "boolean", "Accept any certificate from QUIC peers; for development only"),
+    ("psk", "string", "Pre-shared key workers prove they hold when announcing; any worker may register when unset"),
+    ("db_url", "string", "Task history database: a postgres:// URL or a SQLite file path"),
+    ("api_port", "integer", "Port for the REST API; the API is not served when unset"),
+    ("api_secret", "string", "Secret signing REST API tokens; a random one per process when unset"),
+    ("api_admin_password", "string", "Password of the `admin` user on POST /api/v1/auth/login; admin login is disabled when unset"),
+    ("log_level", "string", "Default log filter when RUST_LOG is not set"),
+    ("heartbeat_interval_secs", "integer", "How often workers report their load to the dispatcher"),
+    ("worker_timeout_secs", "integer", "Workers silent for longer than this are dropped by the dispatcher"),
+    ("slow_heartbeat_ms", "integer", "Heartbeats slower than this are logged once they keep arriving late"),
+    ("scheduler_algorithm", "\"fifo\", \"weighted-least-connections\" or \"round-robin\"", "How the dispatcher picks a worker for each task"),
+    ("starvation_threshold_secs", "integer", "Queued tasks gain one priority level per this many seconds of waiting; 0 disables aging"),
+    ("scheduling_weight_cpu", "float", "Weight of free job slots in a worker's health score"),
+    ("scheduling_weight_latency", "float", "Weight of heartbeat latency in a worker's health score"),
+    ("scheduling_weight_reliability", "float", "Weight of task success rate in a worker's health score"),
+    ("result_ttl_secs", "integer", "Finished task results stay in dispatcher memory this long"),
+    ("max_batch_size", "integer", "Most tasks accepted in one POST /api/v1/tasks/batch request"),
+    ("rate_limit_burst", "integer", "Task submissions one client IP may burst to the REST API; 0 disables rate limiting"),
+    ("rate_limit_per_sec", "float", "Sustained task submissions per second allowed per client IP"),
+    ("webhook_timeout_secs", "integer", "How long the dispatcher waits for a task's callback URL to answer"),
+    ("read_timeout_secs", "integer", "Connections whose peer sends nothing for this long mid-read are dropped; 0 disables"),
+    ("backup_path", "path", "Directory for periodic backups of a SQLite history database; no backups when unset"),
+    ("backup_interval_hours", "integer", "Hours between backups"),
+    ("backup_retention", "integer", "Backups kept in backup_path; older ones are deleted"),
+    ("cleanup_on_success", "boolean", "Whether workers delete a task's working directory after it succeeds"),
+    ("max_output_bytes", "integer", "Combined stdout and stderr a task may print before the worker stops it"),
+    ("command_allowlist", "list of strings", "Globs a worker's commands must match one of; every command is allowed when unset"),
+    ("command_denylist", "list of strings", "Globs of commands a worker always rejects"),
+    ("sensitive_env_patterns", "list of strings", "Extra env var name patterns whose values workers mask in logs and task output"),
+];
+
+/// Settings shared by the dispatcher and worker
+///
+/// Values are layered: built-in defaults, then the config file, then
+/// `OCTASKLY_*` environment variables, then CLI flags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Address the dispatcher listens on
+    pub bind: String,
+    /// Dispatcher port; also the port workers connect to
+    pub port: u16,
+    /// Directory for task data and the default history database
+    pub workdir: PathBuf,
+    /// Concurrent tasks per worker
+    pub max_jobs: usize,
+    /// File keeping a worker's ID across restarts [default: `<workdir>/.worker_id`]
+    pub worker_id_file: Option<PathBuf>,
+    /// Whether workers may run shell commands
+    pub allow_shell: bool,
+    /// PEM certificate chain for TLS on worker connections
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key matching `tls_cert`
+    pub tls_key: Option<PathBuf>,
+    /// Require workers to present a certificate signed by the dispatcher's CA
+    pub mtls: bool,
+    /// Advertise the dispatcher, or look one up as a worker, over mDNS
+    pub mdns: bool,
+    /// Protocol between the dispatcher and workers; the dispatcher's TCP port stays open for CLI clients
+    pub transport: TransportKind,
+    /// Accept any certificate from QUIC peers; for development only
+    pub insecure: bool,
+    /// Pre-shared key workers prove they hold when announcing; any worker may register when unset
+    pub psk: Option<String>,
+    /// Task history database: a postgres:// URL or a SQLite file path
+    pub db_url: Option<String>,
+    /// Port for the REST API; the API is not served when unset
+    pub api_port: Option<u16>,
+    /// Secret signing REST API tokens; a random one per process when unset, so tokens do not outlive it
+    pub api_secret: Option<String>,
+    /// Password of the `admin` user on `POST /api/v1/auth/login`; admin login is disabled when unset
+    pub api_admin_password: Option<String>,
+    /// Default log filter when `RUST_LOG` is not set
+    pub log_level: String,
+    /// How often workers report their load to the dispatcher
+    pub heartbeat_interval_secs: u64,
+    /// Workers silent for longer than this are dropped by the dispatcher
+    pub worker_timeout_secs: u64,
+    /// Heartbeats slower than this are logged once they keep arriving late
+    pub slow_heartbeat_ms: u64,
+    /// How the dispatcher picks a worker for each task
+    pub scheduler_algorithm: SchedulerAlgorithm,
+    /// Queued tasks gain one priority level per this many seconds of waiting; 0 disables aging
+    pub starvation_threshold_secs: u64,
+    /// Weight of free job slots in a worker's health score
+    pub scheduling_weight_cpu: f64,
+    /// Weight of heartbeat latency in a worker's health score
+    pub scheduling_weight_latency: f64,
+    /// Weight of task success rate in a worker's health score
+    pub scheduling_weight_reliability: f64,
+    /// Finished task results stay in dispatcher memory this long; the history database keeps them after
+    pub result_ttl_secs: u64,
+    /// Most tasks accepted in one `POST /api/v1/tasks/batch` request
+    pub max_batch_size: usize,
+    /// Task submissions one client IP may burst to the REST API; 0 disables rate limiting
+    pub rate_limit_burst: u32,
+    /// Sustained task submissions per second allowed per client IP
+    pub rate_limit_per_sec: f64,
+    /// How long the dispatcher waits for a task's callback URL to answer
+    pub webhook_timeout_secs: u64,
+    /// Connections whose peer sends nothing for this long mid-read are dropped; 0 disables
+    pub read_timeout_secs: u64,
+    /// Directory for periodic backups of a SQLite history database; no backups when unset
+    pub backup_path: Option<PathBuf>,
+    /// Hours between backups
+    pub backup_interval_hours: u64,
+    /// Backups kept in `backup_path`; older ones are deleted
+    pub backup_retention: usize,
+    /// Whether workers delete a task's working directory after it succeeds
+    pub cleanup_on_success: bool,
+    /// Combined stdout and stderr a task may print before the worker stops it
+    pub max_output_bytes: usize,
+    /// Globs a worker's commands must match one of; every command is allowed when unset
+    pub command_allowlist: Option<Vec<String>>,
+    /// Globs of commands a worker always rejects
+    pub command_denylist: Vec<String>,
+    /// Extra env var name patterns, besides SECRET, PASSWORD, TOKEN, KEY and CREDENTIAL,
+    /// whose values workers mask in logs and task output
+    pub sensitive_env_patterns: Vec<String>,
+    /// Config file the values were read from, if any
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: 7878,
+            workdir: PathBuf::from("./tasks"),
+            worker_id_file: None,
+            max_jobs: 4,
+            allow_shell: true,
+            tls_cert: None,
+            tls_key: None,
+            mtls: false,
+            mdns: false,
+            transport: TransportKind::Tcp,
+            insecure: false,
+            psk: None,
+            db_url: None,
+            api_port: None,
+            api_secret: None,
+            api_admin_password: None,
+            log_level: "warn".to_string(),
+            heartbeat_interval_secs: 5,
+            worker_timeout_secs: 30,
+            slow_heartbeat_ms: 5000,
+            scheduler_algorithm: SchedulerAlgorithm::Fifo,
+            starvation_threshold_secs: 300,
+            scheduling_weight_cpu: 1.0,
+            scheduling_weight_latency: 1.0,
+            scheduling_weight_reliability: 1.0,
+            result_ttl_secs: 24 * 60 * 60,
+            max_batch_size: 100,
+            rate_limit_burst: 10,
+            rate_limit_per_sec: 5.0,
+            webhook_timeout_secs: 10,
+            read_timeout_secs: 120,
+            backup_path: None,
+            backup_interval_hours: 24,
+            backup_retention: 7,
+            cleanup_on_success: true,
+            max_output_bytes: crate::executor::DEFAULT_MAX_OUTPUT_BYTES,
+            command_allowlist: None,
+            command_denylist: Vec::new(),
+            sensitive_env_patterns: Vec::new(),
+            source: None,
+        }
+    }
+}
+
+/// One source of settings; unset fields leave lower layers untouched
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub bind: Option<String>,
+    pub port: Option<u16>,
+    pub workdir: Option<PathBuf>,
+    pub worker_id_file: Option<PathBuf>,
+    pub max_jobs: Option<usize>,
+    pub allow_shell: Option<bool>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub mtls: Option<bool>,
+    pub mdns: Option<bool>,
+    pub transport: Option<TransportKind>,
+    pub insecure: Option<bool>,
+    pub psk: Option<String>,
+    #[serde(alias = "db_path")]
+    pub db_url: Option<String>,
+    pub api_port: Option<u16>,
+    pub api_secret: Option<String>,
+    pub api_admin_password: Option<String>,
+    pub log_level: Option<String>,
+    pub heartbeat_interval_secs: Option<u64>,
+    pub worker_timeout_secs: Option<u64>,
+    pub slow_heartbeat_ms: Option<u64>,
+    pub scheduler_algorithm: Option<SchedulerAlgorithm>,
+    pub starvation_threshold_secs: Option<u64>,
+    pub scheduling_weight_cpu: Option<f64>,
+    pub scheduling_weight_latency: Option<f64>,
+    pub scheduling_weight_reliability: Option<f64>,
+    pub result_ttl_secs: Option<u64>,
+    pub max_batch_size: Option<usize>,
+    pub rate_limit_burst: Option<u32>,
+    pub rate_limit_per_sec: Option<f64>,
+    pub webhook_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    pub backup_path: Option<PathBuf>,
+    pub backup_interval_hours: Option<u64>,
+    pub backup_retention: Option<usize>,
+    pub cleanup_on_success: Option<bool>,
+    pub max_output_bytes: Option<usize>,
+    pub command_allowlist: Option<Vec<String>>,
+    pub command_denylist: Option<Vec<String>>,
+    pub sensitive_env_patterns: Option<Vec<String>>,
+}
+
+impl ConfigLayer {
+    /// Parse a TOML config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// Read `OCTASKLY_*` overrides from the process environment
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Read `OCTASKLY_*` overrides through `lookup` (e.g. `OCTASKLY_PORT`)
+    pub fn from_env_with(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |field: &str| lookup(&format!("{}{}", ENV_PREFIX, field.to_uppercase()));
+
+        Ok(Self {
+            bind: var("bind"),
+            port: parse_env(&var, "port")?,
+            workdir: var("workdir").map(PathBuf::from),
+            worker_id_file: var("worker_id_file").map(PathBuf::from),
+            max_jobs: parse_env(&var, "max_jobs")?,
+            allow_shell: parse_env(&var, "allow_shell")?,
+            tls_cert: var("tls_cert").map(PathBuf::from),
+            tls_key: var("tls_key").map(PathBuf::from),
+            mtls: parse_env(&var, "mtls")?,
+            mdns: parse_env(&var, "mdns")?,
+            transport: parse_env(&var, "transport")?,
+            insecure: parse_env(&var, "insecure")?,
+            psk: var("psk"),
+            db_url: var("db_url"),
+            api_port: parse_env(&var, "api_port")?,
+            api_secret: var("api_secret"),
+            api_admin_password: var("api_admin_password"),
+            log_level: var("log_level"),
+            heartbeat_interval_secs: parse_env(&var, "heartbeat_interval_secs")?,
+            worker_timeout_secs: parse_env(&var, "worker_timeout_secs")?,
+            slow_heartbeat_ms: parse_env(&var, "slow_heartbeat_ms")?,
+            scheduler_algorithm: parse_env(&var, "scheduler_algorithm")?,
+            starvation_threshold_secs: parse_env(&var, "starvation_threshold_secs")?,
+            scheduling_weight_cpu: parse_env(&var, "scheduling_weight_cpu")?,
+            scheduling_weight_latency: parse_env(&var, "scheduling_weight_latency")?,
+            scheduling_weight_reliability: parse_env(&var, "scheduling_weight_reliability")?,
+            result_ttl_secs: parse_env(&var, "result_ttl_secs")?,
+            max_batch_size: parse_env(&var, "max_batch_size")?,
+            rate_limit_burst: parse_env(&var, "rate_limit_burst")?,
+            rate_limit_per_sec: parse_env(&var, "rate_limit_per_sec")?,
+            webhook_timeout_secs: parse_env(&var, "webhook_timeout_secs")?,
+            read_timeout_secs: parse_env(&var, "read_timeout_secs")?,
+            backup_path: var("backup_path").map(PathBuf::from),
+            backup_interval_hours: parse_env(&var, "backup_interval_hours")?,
+            backup_retention: parse_env(&var, "backup_retention")?,
+            cleanup_on_success: parse_env(&var, "cleanup_on_success")?,
+            max_output_bytes: parse_env(&var, "max_output_bytes")?,
+            command_allowlist: list_env(&var, "command_allowlist"),
+            command_denylist: list_env(&var, "command_denylist"),
+            sensitive_env_patterns: list_env(&var, "sensitive_env_patterns"),
+        })
+    }
+}
+
+fn parse_env<T: FromStr>(var: &impl Fn(&str) -> Option<String>, field: &str) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    var(field)
+        .map(|value| {
+            value.parse().map_err(|e| {
+                anyhow::anyhow!("Invalid {}{}={:?}: {}", ENV_PREFIX, field.to_uppercase(), value, e)
+            })
+        })
+        .transpose()
+}
+
+/// Comma-separated list from the environment, e.g. `OCTASKLY_COMMAND_DENYLIST=sudo *,curl *`
+fn list_env(var: &impl Fn(&str) -> Option<String>, field: &str) -> Option<Vec<String>> {
+    var(field).map(|value| value.split(',').map(|item| item.trim().to_string()).collect())
+}
+
+impl Config {
+    /// Load defaults, the first config file found, and environment overrides
+    ///
+    /// Files are checked in order: `path` (which must exist if given),
+    /// `~/.config/octaskly/config.toml`, then `./octaskly.toml`.
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let mut config = Self::default();
+
+        let found = match path {
+            Some(path) => Some(path),
+            None => Self::search_paths().into_iter().find(|candidate| candidate.is_file()),
+        };
+        if let Some(path) = found {
+            config.merge(ConfigLayer::from_file(&path)?);
+            config.source = Some(path);
+        }
+
+        config.merge(ConfigLayer::from_env()?);
+        Ok(config)
+    }
+
+    /// Default config file locations, highest priority first
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join(".config").join("octaskly").join("config.toml"));
+        }
+        paths.push(PathBuf::from(LOCAL_CONFIG_FILE));
+        paths
+    }
+
+    /// Apply every value set in `layer` on top of this config
+    pub fn merge(&mut self, layer: ConfigLayer) {
+        fn set<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        set(&mut self.bind, layer.bind);
+        set(&mut self.port, layer.port);
+        set(&mut self.workdir, layer.workdir);
+        set(&mut self.worker_id_file, layer.worker_id_file.map(Some));
+        set(&mut self.max_jobs, layer.max_jobs);
+        set(&mut self.allow_shell, layer.allow_shell);
+        set(&mut self.mtls, layer.mtls);
+        set(&mut self.mdns, layer.mdns);
+        set(&mut self.transport, layer.transport);
+        set(&mut self.insecure, layer.insecure);
+        set(&mut self.log_level, layer.log_level);
+        set(&mut self.heartbeat_interval_secs, layer.heartbeat_interval_secs);
+        set(&mut self.worker_timeout_secs, layer.worker_timeout_secs);
+        set(&mut self.slow_heartbeat_ms, layer.slow_heartbeat_ms);
+        set(&mut self.scheduler_algorithm, layer.scheduler_algorithm);
+        set(&mut self.starvation_threshold_secs, layer.starvation_threshold_secs);
+        set(&mut self.scheduling_weight_cpu, layer.scheduling_weight_cpu);
+        set(&mut self.scheduling_weight_latency, layer.scheduling_weight_latency);
+        set(&mut self.scheduling_weight_reliability, layer.scheduling_weight_reliability);
+        set(&mut self.result_ttl_secs, layer.result_ttl_secs);
+        set(&mut self.max_batch_size, layer.max_batch_size);
+        set(&mut self.rate_limit_burst, layer.rate_limit_burst);
+        set(&mut self.rate_limit_per_sec, layer.rate_limit_per_sec);
+        set(&mut self.webhook_timeout_secs, layer.webhook_timeout_secs);
+        set(&mut self.read_timeout_secs, layer.read_timeout_secs);
+        set(&mut self.backup_interval_hours, layer.backup_interval_hours);
+        set(&mut self.backup_retention, layer.backup_retention);
+        set(&mut self.cleanup_on_success, layer.cleanup_on_success);
+        set(&mut self.max_output_bytes, layer.max_output_bytes);
+        set(&mut self.command_denylist, layer.command_denylist);
+        set(&mut self.sensitive_env_patterns, layer.sensitive_env_patterns);
+        set(&mut self.tls_cert, layer.tls_cert.map(Some));
+        set(&mut self.tls_key, layer.tls_key.map(Some));
+        set(&mut self.psk, layer.psk.map(Some));
+        set(&mut self.db_url, layer.db_url.map(Some));
+        set(&mut self.api_port, layer.api_port.map(Some));
+        set(&mut self.api_secret, layer.api_secret.map(Some));
+        set(&mut self.api_admin_password, layer.api_admin_password.map(Some));
+        set(&mut self.backup_path, layer.backup_path.map(Some));
+        set(&mut self.command_allowlist, layer.command_allowlist.map(Some));
+    }
+
+    /// Where a worker keeps its ID, `worker_id_file` or `<workdir>/.worker_id`
+    pub fn worker_id_file(&self) -> PathBuf {
+        self.worker_id_file.clone().unwrap_or_else(|| self.workdir.join(".worker_id"))
+    }
+
+    /// `read_timeout_secs` as a duration, or `None` when disabled
+    pub fn read_timeout(&self) -> Option<Duration> {
+        (self.read_timeout_secs > 0).then(|| Duration::from_secs(self.read_timeout_secs))
+    }
+
+    /// A config file setting every field to its default, each with a comment
+    ///
+    /// Fields unset by default are written commented out.
+    pub fn template() -> String {
+        let defaults = match toml::Value::try_from(Self::default()) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => unreachable!("Config serializes to a table"),
+        };
+
+        let mut template = String::from(
+            "# Octaskly configuration\n\
+             #\n\
+             # OCTASKLY_* environment variables and CLI flags override these values.\n\
+             # Settings that are commented out are unset by default.\n",
+        );
+        for (name, kind, description) in FIELD_DOCS {
+            template.push_str(&format!("\n# {} ({})\n", description, kind));
+            match defaults.get(*name) {
+                Some(value) => template.push_str(&format!("{} = {}\n", name, value)),
+                None => template.push_str(&format!("# {} = <{}>\n", name, kind)),
+            }
+        }
+        template
+    }
+
+    /// Settings that parse but cannot work, one message each; empty when the config is usable
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.port == 0 {
+            problems.push("port must be greater than 0".to_string());
+        }
+        if self.max_jobs == 0 {
+            problems.push("max_jobs must be at least 1".to_string());
+        }
+        if self.heartbeat_interval_secs == 0 {
+            problems.push("heartbeat_interval_secs must be greater than 0".to_string());
+        }
+        if self.worker_timeout_secs <= self.heartbeat_interval_secs {
+            problems.push("worker_timeout_secs must be longer than heartbeat_interval_secs".to_string());
+        }
+        if self.tls_cert.is_some() != self.tls_key.is_some() {
+            problems.push("tls_cert and tls_key must be set together".to_string());
+        }
+        if self.api_port == Some(0) {
+            problems.push("api_port must be greater than 0".to_string());
+        }
+        for (name, weight) in [
+            ("scheduling_weight_cpu", self.scheduling_weight_cpu),
+            ("scheduling_weight_latency", self.scheduling_weight_latency),
+            ("scheduling_weight_reliability", self.scheduling_weight_reliability),
+        ] {
+            if !weight.is_finite() || weight < 0.0 {
+                problems.push(format!("{} must be a non-negative number", name));
+            }
+        }
+        if self.rate_limit_burst > 0 && (self.rate_limit_per_sec.is_nan() || self.rate_limit_per_sec <= 0.0) {
+            problems.push("rate_limit_per_sec must be greater than 0 while rate limiting is enabled".to_string());
+        }
+        if self.max_batch_size == 0 {
+            problems.push("max_batch_size must be at least 1".to_string());
+        }
+        if self.backup_path.is_some() && self.backup_interval_hours == 0 {
+            problems.push("backup_interval_hours must be greater than 0 when backup_path is set".to_string());
+        }
+        problems
+    }
+
+    /// This config with `layer` applied on top, for chaining CLI flags
+    pub fn merged(mut self, layer: ConfigLayer) -> Self {
+        self.merge(layer);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let file: ConfigLayer = toml::from_str(
+            r#"
+            port = 9000
+            max_jobs = 8
+            db_path = "/var/lib/octaskly/history.db"
+            "#,
+        )
+        .unwrap();
+        let env: HashMap<&str, &str> = [
+            ("OCTASKLY_PORT", "9100"),
+            ("OCTASKLY_ALLOW_SHELL", "false"),
+            ("OCTASKLY_SCHEDULER_ALGORITHM", "round-robin"),
+        ]
+        .into();
+        let env = ConfigLayer::from_env_with(|name| env.get(name).map(|v| v.to_string())).unwrap();
+        let cli = ConfigLayer {
+            max_jobs: Some(2),
+            ..Default::default()
+        };
+
+        let config = Config::default().merged(file).merged(env).merged(cli);
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.max_jobs, 2);
+        assert!(!config.allow_shell);
+        assert_eq!(config.scheduler_algorithm, SchedulerAlgorithm::RoundRobin);
+        assert_eq!(config.db_url.as_deref(), Some("/var/lib/octaskly/history.db"));
+        assert_eq!(config.bind, "0.0.0.0");
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        assert!(toml::from_str::<ConfigLayer>("prot = 9000").is_err());
+        assert!(ConfigLayer::from_env_with(|name| (name == "OCTASKLY_PORT").then(|| "http".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_template_covers_every_field() {
+        let template = Config::template();
+        let layer: ConfigLayer = toml::from_str(&template).unwrap();
+        assert_eq!(Config::default().merged(layer), Config::default());
+
+        let every_field_set = Config {
+            worker_id_file: Some(PathBuf::from("id")),
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            tls_key: Some(PathBuf::from("key.pem")),
+            psk: Some("secret".to_string()),
+            db_url: Some("history.db".to_string()),
+            api_port: Some(8080),
+            api_secret: Some("secret".to_string()),
+            api_admin_password: Some("secret".to_string()),
+            backup_path: Some(PathBuf::from("backups")),
+            command_allowlist: Some(Vec::new()),
+            ..Default::default()
+        };
+        let toml::Value::Table(fields) = toml::Value::try_from(every_field_set).unwrap() else {
+            panic!("Config did not serialize to a table");
+        };
+        let documented: Vec<&str> = FIELD_DOCS.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(fields.len(), documented.len());
+        for name in fields.keys() {
+            assert!(documented.contains(&name.as_str()), "{} missing from the template", name);
+        }
+        assert!(template.contains("\n# psk = <string>\n"));
+    }
+
+    #[test]
+    fn test_problems() {
+        assert!(Config::default().problems().is_empty());
+        let config = Config {
+            max_jobs: 0,
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            scheduling_weight_cpu: -1.0,
+            ..Default::default()
+        };
+        assert_eq!(config.problems().len(), 3);
+    }
+
+    #[test]
+    fn test_load_explicit_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("octaskly.toml");
+        std::fs::write(&path, "bind = \"127.0.0.1\"\nheartbeat_interval_secs = 2\n").unwrap();
+
+        let config = Config::load(Some(path.clone())).unwrap();
+        assert_eq!(config.bind, "127.0.0.1");
+        assert_eq!(config.heartbeat_interval_secs, 2);
+        assert_eq!(config.source, Some(path));
+
+        assert!(Config::load(Some(dir.path().join("missing.toml"))).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use crate::scheduler::Scheduler;
+use anyhow::{Context, Result};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Service type dispatchers advertise and workers browse for
+pub const SERVICE_TYPE: &str = "_octaskly._tcp.local";
+
+/// Multicast group and port of mDNS (RFC 6762)
+pub const MDNS_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+/// Key of the TXT entry carrying the dispatcher's queue depth
+const QUEUE_DEPTH_KEY: &str = "queue_depth";
+
+/// How long answers may be cached by other resolvers
+const RECORD_TTL_SECS: u32 = 120;
+
+/// Delay between repeated browse queries
+const QUERY_INTERVAL: Duration = Duration::from_secs(1);
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Cache-flush bit set on records owned by a single host
+const CLASS_CACHE_FLUSH: u16 = 0x8000;
+const FLAGS_RESPONSE: u16 = 0x8400;
+
+/// A dispatcher found on the local network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatcherAd {
+    pub addr: SocketAddr,
+    pub queue_depth: usize,
+}
+
+/// Answers mDNS queries for `_octaskly._tcp.local` with this dispatcher's address
+pub struct MdnsAdvertiser {
+    instance: String,
+    host: String,
+    ip: Ipv4Addr,
+    port: u16,
+}
+
+impl MdnsAdvertiser {
+    /// Advertise a dispatcher reachable at `ip:port`
+    pub fn new(ip: Ipv4Addr, port: u16) -> Self {
+        let hostname = hostname::get()
+            .ok()
+            .and_then(|name| name.into_string().ok())
+            .unwrap_or_else(|| "octaskly".to_string());
+        let label: String = hostname
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+            .take(50)
+            .collect();
+
+        Self {
+            instance: format!("{}-{}.{}", label, port, SERVICE_TYPE),
+            host: format!("{}.local", label),
+            ip,
+            port,
+        }
+    }
+
+    /// Answer browse queries until the returned task is aborted
+    ///
+    /// The TXT record carries the scheduler's queue depth at the time of each answer.
+    pub fn start(self, scheduler: Arc<Scheduler>) -> Result<JoinHandle<()>> {
+        let socket = multicast_socket()?;
+        info!("[MDNS] Advertising {} at {}:{}", self.instance, self.ip, self.port);
+
+        Ok(tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            loop {
+                let (len, from) = match socket.recv_from(&mut buf).await {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!("[MDNS] Receive failed: {}", e);
+                        continue;
+                    }
+                };
+                let Some(query_id) = parse_service_query(&buf[..len]) else {
+                    continue;
+                };
+
+                let response = self.response(query_id, scheduler.queue_size().await);
+                // Queries from a port other than 5353 are one-shot and want a unicast reply (RFC 6762 6.7)
+                let target = if from.port() == MDNS_ADDR.port() { SocketAddr::V4(MDNS_ADDR) } else { from };
+                if let Err(e) = socket.send_to(&response, target).await {
+                    debug!("[MDNS] Failed to answer {}: {}", from, e);
+                }
+            }
+        }))
+    }
+
+    /// PTR answer plus SRV, TXT and A records for this dispatcher
+    fn response(&self, id: u16, queue_depth: usize) -> Vec<u8> {
+        let mut packet = header(id, FLAGS_RESPONSE, 0, 1, 3);
+
+        let mut ptr = Vec::new();
+        write_name(&mut ptr, &self.instance);
+        write_record(&mut packet, SERVICE_TYPE, TYPE_PTR, CLASS_IN, &ptr);
+
+        let mut srv = Vec::new();
+        srv.extend_from_slice(&[0, 0, 0, 0]);
+        srv.extend_from_slice(&self.port.to_be_bytes());
+        write_name(&mut srv, &self.host);
+        write_record(&mut packet, &self.instance, TYPE_SRV, CLASS_IN | CLASS_CACHE_FLUSH, &srv);
+
+        let entry = format!("{}={}", QUEUE_DEPTH_KEY, queue_depth);
+        let mut txt = vec![entry.len() as u8];
+        txt.extend_from_slice(entry.as_bytes());
+        write_record(&mut packet, &self.instance, TYPE_TXT, CLASS_IN | CLASS_CACHE_FLUSH, &txt);
+
+        write_record(&mut packet, &self.host, TYPE_A, CLASS_IN | CLASS_CACHE_FLUSH, &self.ip.octets());
+        packet
+    }
+}
+
+/// Browse for dispatchers for `timeout`, re-sending the query every second
+pub async fn browse(timeout: Duration) -> Result<Vec<DispatcherAd>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .context("Failed to bind mDNS query socket")?;
+    let query = service_query();
+
+    let mut found: HashMap<SocketAddr, usize> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut resend = tokio::time::interval(QUERY_INTERVAL);
+    let mut buf = [0u8; 1500];
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            _ = resend.tick() => {
+                if let Err(e) = socket.send_to(&query, MDNS_ADDR).await {
+                    debug!("[MDNS] Query failed: {}", e);
+                }
+            }
+            received = socket.recv_from(&mut buf) => {
+                let Ok((len, from)) = received else { continue };
+                for ad in parse_response(&buf[..len], from.ip()) {
+                    found.insert(ad.addr, ad.queue_depth);
+                }
+            }
+        }
+    }
+
+    let mut ads: Vec<_> = found
+        .into_iter()
+        .map(|(addr, queue_depth)| DispatcherAd { addr, queue_depth })
+        .collect();
+    ads.sort_by_key(|ad| (ad.queue_depth, ad.addr));
+    Ok(ads)
+}
+
+/// UDP socket joined to the mDNS group, sharing port 5353 with other responders
+fn multicast_socket() -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_ADDR.port())).into())
+        .context("Failed to bind mDNS port 5353")?;
+    socket.join_multicast_v4(MDNS_ADDR.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+fn header(id: u16, flags: u16, questions: u16, answers: u16, additional: u16) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(512);
+    for field in [id, flags, questions, answers, 0, additional] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+    packet
+}
+
+/// PTR question for the service type
+fn service_query() -> Vec<u8> {
+    let mut packet = header(0, 0, 1, 0, 0);
+    write_name(&mut packet, SERVICE_TYPE);
+    packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        out.push(label.len().min(63) as u8);
+        out.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    out.push(0);
+}
+
+fn write_record(out: &mut Vec<u8>, name: &str, rtype: u16, class: u16, data: &[u8]) {
+    write_name(out, name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&RECORD_TTL_SECS.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]))
+}
+
+/// Decode a possibly compressed name at `pos`; returns it and the offset just past it
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bound pointer chasing so a malicious packet cannot loop forever
+    for _ in 0..128 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let target = (read_u16(packet, pos)? & 0x3FFF) as usize;
+            end.get_or_insert(pos + 2);
+            pos = target;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    None
+}
+
+/// ID of a query asking for the service's PTR records, if `packet` is one
+fn parse_service_query(packet: &[u8]) -> Option<u16> {
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 != 0 {
+        return None;
+    }
+    let mut pos = 12;
+    for _ in 0..read_u16(packet, 4)? {
+        let (name, next) = read_name(packet, pos)?;
+        let qtype = read_u16(packet, next)?;
+        pos = next + 4;
+        if name.eq_ignore_ascii_case(SERVICE_TYPE) && (qtype == TYPE_PTR || qtype == TYPE_ANY) {
+            return read_u16(packet, 0);
+        }
+    }
+    None
+}
+
+struct Record {
+    name: String,
+    rtype: u16,
+    data_start: usize,
+    data: Vec<u8>,
+}
+
+/// Dispatchers announced in an mDNS response; `source` is used when no A record is given
+fn parse_response(packet: &[u8], source: IpAddr) -> Vec<DispatcherAd> {
+    let Some(records) = parse_records(packet) else {
+        return Vec::new();
+    };
+
+    let mut hosts = HashMap::new();
+    let mut depths = HashMap::new();
+    let mut services = HashMap::new();
+    let mut instances = Vec::new();
+    for record in &records {
+        match record.rtype {
+            TYPE_PTR if record.name.eq_ignore_ascii_case(SERVICE_TYPE) => {
+                if let Some((instance, _)) = read_name(packet, record.data_start) {
+                    instances.push(instance.to_lowercase());
+                }
+            }
+            TYPE_SRV if record.data.len() > 6 => {
+                let port = u16::from_be_bytes([record.data[4], record.data[5]]);
+                if let Some((target, _)) = read_name(packet, record.data_start + 6) {
+                    services.insert(record.name.to_lowercase(), (target.to_lowercase(), port));
+                }
+            }
+            TYPE_TXT => {
+                if let Some(depth) = txt_queue_depth(&record.data) {
+                    depths.insert(record.name.to_lowercase(), depth);
+                }
+            }
+            TYPE_A if record.data.len() == 4 => {
+                let ip = Ipv4Addr::new(record.data[0], record.data[1], record.data[2], record.data[3]);
+                hosts.insert(record.name.to_lowercase(), IpAddr::V4(ip));
+            }
+            _ => {}
+        }
+    }
+
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (target, port) = services.get(&instance)?;
+            let ip = hosts.get(target).copied().unwrap_or(source);
+            Some(DispatcherAd {
+                addr: SocketAddr::new(ip, *port),
+                queue_depth: depths.get(&instance).copied().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+fn parse_records(packet: &[u8]) -> Option<Vec<Record>> {
+    if read_u16(packet, 2)? & 0x8000 == 0 {
+        return None;
+    }
+    let mut pos = 12;
+    for _ in 0..read_u16(packet, 4)? {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+
+    let count = [6, 8, 10].iter().map(|&offset| read_u16(packet, offset).unwrap_or(0) as usize).sum::<usize>();
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (name, next) = read_name(packet, pos)?;
+        let rtype = read_u16(packet, next)?;
+        let len = read_u16(packet, next + 8)? as usize;
+        let data_start = next + 10;
+        let data = packet.get(data_start..data_start + len)?.to_vec();
+        records.push(Record { name, rtype, data_start, data });
+        pos = data_start + len;
+    }
+    Some(records)
+}
+
+fn txt_queue_depth(data: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    while let Some(&len) = data.get(pos) {
+        let entry = String::from_utf8_lossy(data.get(pos + 1..pos + 1 + len as usize)?);
+        if let Some(value) = entry.strip_prefix(QUEUE_DEPTH_KEY).and_then(|rest| rest.strip_prefix('=')) {
+            return value.parse().ok();
+        }
+        pos += 1 + len as usize;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_is_recognised() {
+        let mut query = service_query();
+        query[0..2].copy_from_slice(&7u16.to_be_bytes());
+        assert_eq!(parse_service_query(&query), Some(7));
+
+        let advertiser = MdnsAdvertiser::new(Ipv4Addr::new(10, 0, 0, 5), 7878);
+        assert_eq!(parse_service_query(&advertiser.response(7, 0)), None);
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let advertiser = MdnsAdvertiser::new(Ipv4Addr::new(10, 0, 0, 5), 7878);
+        let response = advertiser.response(0, 3);
+
+        let ads = parse_response(&response, "10.0.0.9".parse().unwrap());
+        assert_eq!(
+            ads,
+            vec![DispatcherAd {
+                addr: "10.0.0.5:7878".parse().unwrap(),
+                queue_depth: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_compressed_names() {
+        // "a.local" followed by a pointer back to "local"
+        let packet = [1, b'a', 5, b'l', b'o', b'c', b'a', b'l', 0, 1, b'b', 0xC0, 2];
+        assert_eq!(read_name(&packet, 0), Some(("a.local".to_string(), 9)));
+        assert_eq!(read_name(&packet, 9), Some(("b.local".to_string(), 13)));
+
+        let looping = [0xC0, 0];
+        assert_eq!(read_name(&looping, 0), None);
+    }
+}
@@ -4,6 +4,8 @@
 // Pure Rust implementation using UDP broadcast - no native dependencies
 // Implementasi Rust murni menggunakan siaran UDP - tanpa dependensi asli
 
+pub mod mdns;
+
 use crate::protocol::WorkerInfo;
 use anyhow::Result;
 use std::net::SocketAddr;
@@ -104,6 +106,14 @@ impl Discovery {
                                                     .unwrap()
                                                     .as_secs() as i64,
                                                 platform: "linux".to_string(),
+                                                tags: Vec::new(),
+                                                connected_at: chrono::Local::now().timestamp(),
+                                                draining: false,
+                                                last_rtt_ms: None,
+                                                psk_hash: None,
+                                                maintenance: false,
+                                                tasks_completed: 0,
+                                                tasks_failed: 0,
                                             };
                                             // Tambahkan atau perbarui worker dalam daftar
                                             let mut w = workers.write().await;
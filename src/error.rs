@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Result of the public APIs in `scheduler`, `auth`, `persistence` and `executor`
+pub type Result<T, E = OctasklyError> = std::result::Result<T, E>;
+
+/// Every error the library's public APIs return, grouped by the module it comes from
+///
+/// Messages are those of the wrapped error, so callers that only log errors see no difference.
+#[derive(Debug, Error)]
+pub enum OctasklyError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error(transparent)]
+    Scheduler(#[from] SchedulerError),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    Executor(#[from] ExecutorError),
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+}
+
+/// Messages and tasks that cannot be encoded or decoded
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    #[error("Invalid cron expression: {0}")]
+    InvalidCron(#[from] cron::error::Error),
+    #[error("Unexpected message: {0}")]
+    UnexpectedMessage(String),
+}
+
+/// Tasks the scheduler refuses to queue
+#[derive(Debug, Error)]
+pub enum SchedulerError {
+    #[error("Circular dependency detected for task {task_id}")]
+    CircularDependency { task_id: String },
+    #[error("Task queue is full ({capacity} tasks)")]
+    QueueFull { capacity: usize },
+    #[error("Task not found: {0}")]
+    TaskNotFound(String),
+}
+
+/// Tokens and API keys that do not grant access
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("Token has expired")]
+    TokenExpired,
+    #[error("Invalid token: {0}")]
+    InvalidToken(jsonwebtoken::errors::Error),
+    #[error("Not an access token")]
+    NotAccessToken,
+    #[error("Not a refresh token")]
+    NotRefreshToken,
+    #[error("Refresh token has been revoked")]
+    TokenRevoked,
+    #[error("Unknown API key")]
+    UnknownApiKey,
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+    #[error("API key '{0}' already exists")]
+    DuplicateApiKey(String),
+    #[error("Unknown role: {0}")]
+    UnknownRole(String),
+}
+
+impl From<jsonwebtoken::errors::Error> for AuthError {
+    fn from(error: jsonwebtoken::errors::Error) -> Self {
+        match error.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => Self::TokenExpired,
+            _ => Self::InvalidToken(error),
+        }
+    }
+}
+
+/// Failures of the task history database
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    SqliteConnection(#[from] tokio_rusqlite::Error),
+    #[error(transparent)]
+    Postgres(#[from] sqlx::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("Database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: u32, supported: u32 },
+    #[error("Worker {0} has no recorded history")]
+    WorkerNotFound(String),
+    #[error("Backup file {path}: {source}")]
+    Backup { path: PathBuf, source: std::io::Error },
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+/// Tasks that could not be run
+#[derive(Debug, Error)]
+pub enum ExecutorError {
+    #[error("Shell execution is not allowed")]
+    ShellNotAllowed,
+    #[error("Script not found: {}", .0.display())]
+    ScriptNotFound(PathBuf),
+    #[error("Script path {} must be relative and stay inside the workdir", .0.display())]
+    InvalidScriptPath(PathBuf),
+    #[error("Command rejected by policy: {0}")]
+    CommandRejected(String),
+    #[error("Invalid command pattern: {0}")]
+    InvalidPattern(#[from] glob::PatternError),
+    #[error("Task timed out after {0:?}")]
+    Timeout(Duration),
+    #[error(transparent)]
+    Docker(#[from] bollard::errors::Error),
+    #[error("Container {0} exited without a status")]
+    NoExitStatus(String),
+    #[error("Task runner stopped unexpectedly: {0}")]
+    Aborted(#[from] tokio::task::JoinError),
+}
+
+/// Messages that could not be delivered between nodes
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error("Timed out after {0:?} waiting for reply")]
+    Timeout(Duration),
+    #[error("Peer sent nothing for {0:?} while a frame was expected")]
+    ReadTimeout(Duration),
+    #[error("Invalid frame: {0}")]
+    InvalidFrame(String),
+    #[error("Peer announced a {len}-byte message, over the size limit")]
+    MessageTooLarge { len: usize },
+    #[error("Message delivery failed: {0}")]
+    DeliveryFailed(String),
+}
+
+macro_rules! from_nested {
+    ($($source:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$source> for OctasklyError {
+                fn from(error: $source) -> Self {
+                    Self::$variant(error.into())
+                }
+            }
+        )*
+    };
+}
+
+from_nested! {
+    serde_json::Error => Protocol,
+    rusqlite::Error => Storage,
+    tokio_rusqlite::Error => Storage,
+    sqlx::Error => Storage,
+    csv::Error => Storage,
+    jsonwebtoken::errors::Error => Auth,
+    bollard::errors::Error => Executor,
+    tokio::task::JoinError => Executor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_conversions_keep_messages() {
+        let error: OctasklyError = SchedulerError::CircularDependency { task_id: "t1".to_string() }.into();
+        assert_eq!(error.to_string(), "Circular dependency detected for task t1");
+        assert!(matches!(error, OctasklyError::Scheduler(SchedulerError::CircularDependency { .. })));
+
+        let error: OctasklyError = serde_json::from_str::<u32>("x").unwrap_err().into();
+        assert!(matches!(error, OctasklyError::Protocol(ProtocolError::Serialization(_))));
+    }
+
+    #[test]
+    fn test_expired_jwt_maps_to_token_expired() {
+        let expired = jsonwebtoken::errors::Error::from(jsonwebtoken::errors::ErrorKind::ExpiredSignature);
+        assert!(matches!(AuthError::from(expired), AuthError::TokenExpired));
+    }
+}
@@ -0,0 +1,318 @@
+use super::{command_line, with_limit_message, ExecutionResult, Executor, ExecutorBackend, ExecutorEvent, OutputBudget};
+use crate::protocol::{Task, TaskStatus};
+use crate::error::{ExecutorError, Result};
+use async_trait::async_trait;
+use bollard::container::{
+    Config, CreateContainerOptions, KillContainerOptions, LogOutput, LogsOptions,
+    RemoveContainerOptions, StartContainerOptions, WaitContainerOptions,
+};
+use bollard::image::CreateImageOptions;
+use bollard::service::HostConfig;
+use bollard::Docker;
+use futures::StreamExt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+// Image used for tasks that do not set `container_image`
+// Image yang dipakai untuk tugas tanpa `container_image`
+pub const DEFAULT_IMAGE: &str = "alpine:latest";
+
+// Mount point of the task workdir inside the container
+// Titik mount direktori kerja tugas di dalam container
+const WORKSPACE: &str = "/workspace";
+
+// Runs each task in a throwaway Docker container
+// Menjalankan setiap tugas di container Docker sekali pakai
+pub struct DockerExecutor {
+    docker: Docker,
+    default_image: String,
+}
+
+impl DockerExecutor {
+    // Connect to the local Docker daemon (socket or named pipe)
+    // Hubungkan ke daemon Docker lokal (socket atau named pipe)
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            docker: Docker::connect_with_local_defaults()?,
+            default_image: DEFAULT_IMAGE.to_string(),
+        })
+    }
+
+    // Override the image used when a task does not name one
+    // Ganti image yang dipakai saat tugas tidak menyebutkan image
+    pub fn with_default_image(mut self, image: impl Into<String>) -> Self {
+        self.default_image = image.into();
+        self
+    }
+
+    // Pull the image unless it is already present locally
+    // Tarik image kecuali sudah ada secara lokal
+    async fn ensure_image(&self, image: &str) -> Result<()> {
+        if self.docker.inspect_image(image).await.is_ok() {
+            return Ok(());
+        }
+
+        info!("Pulling image {}", image);
+        let options = CreateImageOptions {
+            from_image: image,
+            ..Default::default()
+        };
+        let mut pull = self.docker.create_image(Some(options), None, None);
+        while let Some(progress) = pull.next().await {
+            progress?;
+        }
+        Ok(())
+    }
+
+    // Start the container and follow its logs until it exits or times out
+    // Jalankan container dan ikuti log sampai selesai atau timeout
+    //
+    // A container printing more than `budget` allows is sent SIGTERM.
+    // Container yang mencetak melebihi `budget` dikirimi SIGTERM.
+    async fn run_container(
+        &self,
+        id: &str,
+        timeout: Option<Duration>,
+        budget: &OutputBudget,
+        events: &mpsc::Sender<ExecutorEvent>,
+    ) -> Result<Option<(String, String, i64)>> {
+        self.docker
+            .start_container(id, None::<StartContainerOptions<String>>)
+            .await?;
+
+        let follow = async {
+            let options = LogsOptions::<String> {
+                follow: true,
+                stdout: true,
+                stderr: true,
+                ..Default::default()
+            };
+            let mut logs = self.docker.logs(id, Some(options));
+            let mut stdout = LineCollector::new(ExecutorEvent::Stdout);
+            let mut stderr = LineCollector::new(ExecutorEvent::Stderr);
+
+            while let Some(chunk) = logs.next().await {
+                let (collector, message) = match chunk? {
+                    LogOutput::StdOut { message } => (&mut stdout, message),
+                    LogOutput::StdErr { message } => (&mut stderr, message),
+                    _ => continue,
+                };
+                if !budget.take(message.len()) {
+                    warn!("Container {} exceeded its output limit, terminating", id);
+                    let options = KillContainerOptions { signal: "SIGTERM" };
+                    if let Err(e) = self.docker.kill_container(id, Some(options)).await {
+                        warn!("Failed to terminate container {}: {}", id, e);
+                    }
+                    break;
+                }
+                collector.push(&message, events).await;
+            }
+            stdout.finish(events).await;
+            stderr.finish(events).await;
+
+            // Non-zero exits are reported by bollard as a wait error carrying the code
+            // Exit non-nol dilaporkan bollard sebagai error wait yang membawa kode
+            let mut wait = self.docker.wait_container(id, None::<WaitContainerOptions<String>>);
+            let exit_code = match wait.next().await {
+                Some(Ok(response)) => response.status_code,
+                Some(Err(bollard::errors::Error::DockerContainerWaitError { code, .. })) => code,
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err(ExecutorError::NoExitStatus(id.to_string()).into()),
+            };
+
+            Ok((stdout.collected, stderr.collected, exit_code))
+        };
+
+        let Some(limit) = timeout else {
+            return follow.await.map(Some);
+        };
+
+        match tokio::time::timeout(limit, follow).await {
+            Ok(outcome) => outcome.map(Some),
+            Err(_) => {
+                if let Err(e) = self
+                    .docker
+                    .kill_container(id, None::<KillContainerOptions<String>>)
+                    .await
+                {
+                    warn!("Failed to kill container {}: {}", id, e);
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutorBackend for DockerExecutor {
+    async fn run(
+        &self,
+        task: &Task,
+        workdir: &Path,
+        timeout: Option<Duration>,
+        max_output_bytes: usize,
+        events: mpsc::Sender<ExecutorEvent>,
+    ) -> Result<ExecutionResult> {
+        let start_time = Instant::now();
+        let image = task
+            .container_image
+            .clone()
+            .unwrap_or_else(|| self.default_image.clone());
+
+        // Docker requires an absolute host path for bind mounts
+        // Docker memerlukan path host absolut untuk bind mount
+        tokio::fs::create_dir_all(workdir).await?;
+        let host_dir = tokio::fs::canonicalize(workdir).await?;
+
+        self.ensure_image(&image).await?;
+
+        let config = Config {
+            image: Some(image.clone()),
+            cmd: Some(command_line(task, Path::new(WORKSPACE))),
+            env: Some(task.env.iter().map(|(k, v)| format!("{}={}", k, v)).collect()),
+            working_dir: Some(WORKSPACE.to_string()),
+            host_config: Some(HostConfig {
+                binds: Some(vec![format!("{}:{}", host_dir.display(), WORKSPACE)]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let options = CreateContainerOptions {
+            name: format!("octaskly-{}", task.id),
+            platform: None,
+        };
+        let container = self.docker.create_container(Some(options), config).await?;
+        info!("Task {} running in container {} ({})", task.id, container.id, image);
+        let mut guard = ContainerGuard {
+            docker: self.docker.clone(),
+            id: Some(container.id.clone()),
+        };
+
+        let budget = OutputBudget::new(max_output_bytes);
+        let outcome = self.run_container(&container.id, timeout, &budget, &events).await;
+
+        // Always remove the container, even after a failure or timeout
+        // Selalu hapus container, bahkan setelah gagal atau timeout
+        let remove = RemoveContainerOptions {
+            force: true,
+            ..Default::default()
+        };
+        if let Err(e) = self.docker.remove_container(&container.id, Some(remove)).await {
+            warn!("Failed to remove container {}: {}", container.id, e);
+        }
+        guard.id = None;
+
+        let Some((stdout, stderr, exit_code)) = outcome? else {
+            error!("Task {} timed out after {}s", task.id, task.timeout);
+            return Ok(Executor::timed_out_result(task));
+        };
+
+        let truncated = budget.is_exceeded();
+        Ok(ExecutionResult {
+            task_id: task.id.clone(),
+            status: if exit_code == 0 && !truncated {
+                TaskStatus::Completed
+            } else {
+                TaskStatus::Failed
+            },
+            stdout,
+            stderr: if truncated { with_limit_message(stderr) } else { stderr },
+            exit_code: Some(exit_code as i32),
+            duration_ms: start_time.elapsed().as_millis() as u64,
+            workdir: None,
+            truncated,
+            resource_usage: None,
+            output_files: Vec::new(),
+        })
+    }
+}
+
+// Force-removes the container when a run is dropped before removing it itself, e.g. on cancellation
+// Menghapus paksa container saat run dibuang sebelum menghapusnya sendiri, mis. saat dibatalkan
+struct ContainerGuard {
+    docker: Docker,
+    id: Option<String>,
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        let docker = self.docker.clone();
+        tokio::spawn(async move {
+            let remove = RemoveContainerOptions {
+                force: true,
+                ..Default::default()
+            };
+            if let Err(e) = docker.remove_container(&id, Some(remove)).await {
+                warn!("Failed to remove container {}: {}", id, e);
+            }
+        });
+    }
+}
+
+// Reassembles log chunks into lines, emitting each complete line as an event
+// Menyusun ulang potongan log menjadi baris dan mengirim tiap baris lengkap sebagai event
+struct LineCollector {
+    pending: String,
+    collected: String,
+    event: fn(String) -> ExecutorEvent,
+}
+
+impl LineCollector {
+    fn new(event: fn(String) -> ExecutorEvent) -> Self {
+        Self {
+            pending: String::new(),
+            collected: String::new(),
+            event,
+        }
+    }
+
+    async fn push(&mut self, chunk: &[u8], events: &mpsc::Sender<ExecutorEvent>) {
+        self.pending.push_str(&String::from_utf8_lossy(chunk));
+        while let Some(pos) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=pos).collect();
+            self.emit(line.trim_end_matches(['\n', '\r']).to_string(), events).await;
+        }
+    }
+
+    async fn finish(&mut self, events: &mpsc::Sender<ExecutorEvent>) {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.emit(line, events).await;
+        }
+    }
+
+    async fn emit(&mut self, line: String, events: &mpsc::Sender<ExecutorEvent>) {
+        self.collected.push_str(&line);
+        self.collected.push('\n');
+        let _ = events.send((self.event)(line)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_line_collector_reassembles_chunks() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let mut collector = LineCollector::new(ExecutorEvent::Stdout);
+
+        collector.push(b"hel", &tx).await;
+        collector.push(b"lo\nwor", &tx).await;
+        collector.push(b"ld\r\npartial", &tx).await;
+        collector.finish(&tx).await;
+        drop(tx);
+
+        let mut lines = Vec::new();
+        while let Some(ExecutorEvent::Stdout(line)) = rx.recv().await {
+            lines.push(line);
+        }
+        assert_eq!(lines, vec!["hello", "world", "partial"]);
+        assert_eq!(collector.collected, "hello\nworld\npartial\n");
+    }
+}
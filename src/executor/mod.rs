@@ -1,106 +1,488 @@
-use crate::protocol::{Task, TaskStatus};
-use anyhow::Result;
-use std::path::PathBuf;
+use crate::config::Config;
+use crate::protocol::{FileSpec, ResourceUsage, Task, TaskStatus};
+use crate::util;
+use crate::error::{ExecutorError, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::process::Stdio;
-use tokio::process::Command;
-use tracing::{error, info};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+pub mod docker;
+
+pub use docker::DockerExecutor;
+
+// Buffered output events per running task
+// Jumlah event output yang di-buffer per tugas yang berjalan
+const OUTPUT_CHANNEL_CAPACITY: usize = 256;
+
+// Directory under the workdir that scripts are copied to, one subdirectory per task
+// Direktori di bawah workdir tempat skrip disalin, satu subdirektori per tugas
+pub const SCRIPTS_DIR: &str = "scripts";
+
+// Combined stdout and stderr a task may print before it is stopped (10 MB)
+// Gabungan stdout dan stderr yang boleh dicetak tugas sebelum dihentikan (10 MB)
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 10 * 1024 * 1024;
+
+// Stderr line added to the result of a task stopped for printing too much
+// Baris stderr yang ditambahkan ke hasil tugas yang dihentikan karena output terlalu banyak
+pub const OUTPUT_LIMIT_MESSAGE: &str = "Output limit exceeded";
+
+// Progress updates a worker may have pending before new ones are dropped
+// Pembaruan progres yang boleh tertunda di worker sebelum yang baru dibuang
+pub const PROGRESS_CHANNEL_CAPACITY: usize = 64;
+
+// Stdout prefix a task prints to report how far along it is, e.g. `PROGRESS: 0.75`
+// Awalan stdout yang dicetak tugas untuk melaporkan kemajuannya, mis. `PROGRESS: 0.75`
+pub const PROGRESS_PREFIX: &str = "PROGRESS:";
+
+// Pluggable backend that actually runs a task
+// Backend yang dapat diganti untuk menjalankan tugas
+#[async_trait]
+pub trait ExecutorBackend: Send + Sync {
+    // Run task in `workdir` to completion, sending output lines on `events` as they are produced.
+    // When `timeout` elapses the task is killed and a `TimedOut` result is returned; once it
+    // prints more than `max_output_bytes` it is terminated and a truncated `Failed` result is returned.
+    // Jalankan tugas di `workdir` sampai selesai, kirim baris output lewat `events` saat dihasilkan.
+    async fn run(
+        &self,
+        task: &Task,
+        workdir: &Path,
+        timeout: Option<Duration>,
+        max_output_bytes: usize,
+        events: mpsc::Sender<ExecutorEvent>,
+    ) -> Result<ExecutionResult>;
+
+    // Run task and collect its output
+    // Jalankan tugas dan kumpulkan outputnya
+    async fn execute(&self, task: &Task, workdir: &Path) -> Result<ExecutionResult> {
+        let (tx, mut rx) = mpsc::channel(OUTPUT_CHANNEL_CAPACITY);
+        let drain = async { while rx.recv().await.is_some() {} };
+        let run = self.run(task, workdir, None, DEFAULT_MAX_OUTPUT_BYTES, tx);
+        let (result, _) = tokio::join!(run, drain);
+        result
+    }
+}
+
+// Which backend a worker runs tasks with
+// Backend yang digunakan worker untuk menjalankan tugas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExecutorKind {
+    #[default]
+    Shell,
+    Docker,
+}
+
+// Commands a worker accepts, matched as globs against the whole command line
+// Perintah yang diterima worker, dicocokkan sebagai glob terhadap seluruh baris perintah
+//
+// A command matching any `denylist` pattern is rejected. When `allowlist` is set,
+// a command must also match at least one of its patterns.
+// Perintah yang cocok dengan pola `denylist` ditolak; bila `allowlist` diatur, perintah harus cocok dengan salah satunya.
+#[derive(Debug, Clone, Default)]
+pub struct CommandPolicy {
+    pub allowlist: Option<Vec<glob::Pattern>>,
+    pub denylist: Vec<glob::Pattern>,
+}
+
+impl CommandPolicy {
+    // Compile the `command_allowlist` and `command_denylist` globs of a config
+    // Kompilasi glob `command_allowlist` dan `command_denylist` dari konfigurasi
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<glob::Pattern>> {
+            patterns
+                .iter()
+                .map(|pattern| Ok(glob::Pattern::new(pattern).map_err(ExecutorError::from)?))
+                .collect()
+        };
+
+        Ok(Self {
+            allowlist: config.command_allowlist.as_deref().map(compile).transpose()?,
+            denylist: compile(&config.command_denylist)?,
+        })
+    }
+
+    // Why `command` is not permitted, or None when it is
+    // Alasan `command` tidak diizinkan, atau None bila diizinkan
+    pub fn violation(&self, command: &str) -> Option<String> {
+        if let Some(pattern) = self.denylist.iter().find(|pattern| pattern.matches(command)) {
+            return Some(format!("matches denylist pattern `{}`", pattern));
+        }
+
+        match &self.allowlist {
+            Some(allowlist) if !allowlist.iter().any(|pattern| pattern.matches(command)) => {
+                Some("matches no allowlist pattern".to_string())
+            }
+            _ => None,
+        }
+    }
+}
 
 // Task execution engine for running shell commands
 // Mesin eksekusi tugas untuk menjalankan perintah shell
 pub struct Executor {
+    backend: Arc<dyn ExecutorBackend>,
     workdir: PathBuf,
     allow_shell: bool,
+    policy: CommandPolicy,
+    // Remove a task's isolated directory after it succeeds
+    // Hapus direktori terisolasi tugas setelah berhasil
+    cleanup_on_success: bool,
+    // Combined output a task may print before it is stopped
+    // Gabungan output yang boleh dicetak tugas sebelum dihentikan
+    max_output_bytes: usize,
+    // Extra name patterns of task env vars whose values are masked in output
+    // Pola nama tambahan variabel env tugas yang nilainya disamarkan di output
+    sensitive_env_patterns: Vec<String>,
+    // Receives `(task_id, progress)` for every `PROGRESS:` line a task prints
+    // Menerima `(task_id, progres)` untuk setiap baris `PROGRESS:` yang dicetak tugas
+    progress: Option<mpsc::Sender<(String, f32)>>,
 }
 
 impl Executor {
     // Create new executor instance with working directory and permissions
     // Buat instance executor baru dengan direktori kerja dan izin
-    pub fn new(workdir: PathBuf, allow_shell: bool) -> Self {
+    pub fn new(workdir: PathBuf, allow_shell: bool, policy: CommandPolicy) -> Self {
         Self {
+            backend: Arc::new(ShellExecutor),
             workdir,
             allow_shell,
+            policy,
+            cleanup_on_success: false,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            sensitive_env_patterns: Vec::new(),
+            progress: None,
         }
     }
 
+    // Report `PROGRESS: <fraction>` stdout lines on `tx` as `(task_id, progress)`
+    // Laporkan baris stdout `PROGRESS: <pecahan>` lewat `tx` sebagai `(task_id, progres)`
+    //
+    // Updates are dropped rather than stalling the task when `tx` is full
+    // Pembaruan dibuang alih-alih menahan tugas saat `tx` penuh
+    pub fn set_progress_channel(&mut self, tx: mpsc::Sender<(String, f32)>) {
+        self.progress = Some(tx);
+    }
+
+    // Run tasks with a different backend (e.g. Docker)
+    // Jalankan tugas dengan backend lain (misalnya Docker)
+    pub fn with_backend(mut self, backend: Arc<dyn ExecutorBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    // Remove isolated task directories once the task succeeds, unless it sets `keep_workdir`
+    // Hapus direktori tugas terisolasi setelah tugas berhasil, kecuali tugas mengatur `keep_workdir`
+    pub fn with_cleanup_on_success(mut self, cleanup: bool) -> Self {
+        self.cleanup_on_success = cleanup;
+        self
+    }
+
+    // Stop tasks once their combined stdout and stderr exceed `bytes`
+    // Hentikan tugas setelah gabungan stdout dan stderr melebihi `bytes`
+    pub fn with_max_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = bytes;
+        self
+    }
+
+    // Also mask env vars whose names contain one of `patterns`, on top of the built-in ones
+    // Samarkan juga variabel env yang namanya mengandung salah satu `patterns`, selain pola bawaan
+    pub fn with_sensitive_env_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.sensitive_env_patterns = patterns;
+        self
+    }
+
+    // Directory a task runs in when executed in isolation
+    // Direktori tempat tugas berjalan saat dieksekusi secara terisolasi
+    pub fn isolated_workdir(&self, task: &Task) -> PathBuf {
+        self.workdir.join(&task.id)
+    }
+
     // Execute task asynchronously with output capture
     // Jalankan tugas secara asinkron dengan penangkapan output
     pub async fn execute(&self, task: &Task) -> Result<ExecutionResult> {
-        if !self.allow_shell {
-            return Err(anyhow::anyhow!("Shell execution is not allowed"));
-        }
+        let (events, handle) = self.execute_streaming(task, None).await?;
+        Self::collect(events, handle).await
+    }
 
-        info!("Executing task {}: {}", task.id, task.command);
+    // Run a script file directly, without `sh -c`, under the task's timeout
+    // Jalankan file skrip secara langsung, tanpa `sh -c`, dengan timeout tugas
+    //
+    // `path` is resolved against the workdir and may not leave it. The script is copied to
+    // `scripts/<task id>/` under the directory the task runs in, made executable, run by the
+    // backend and removed afterwards.
+    // `path` diselesaikan terhadap workdir; skrip disalin, dibuat dapat dieksekusi, dijalankan, lalu dihapus.
+    pub async fn execute_script_file(&self, path: &Path, task: &Task) -> Result<ExecutionResult> {
+        let source = path
+            .to_str()
+            .and_then(|relative| util::files::resolve(&self.workdir, relative).ok())
+            .ok_or_else(|| ExecutorError::InvalidScriptPath(path.to_path_buf()))?;
+        let Ok(content) = tokio::fs::read(&source).await else {
+            return Err(ExecutorError::ScriptNotFound(source).into());
+        };
 
-        let start_time = std::time::Instant::now();
+        let mut task = task.clone();
+        task.script_path = Some(path.to_path_buf());
+        task.script_content = Some(content);
+        self.execute_with_timeout(&task).await
+    }
 
-        // Create working directory if needed
-        // Buat direktori kerja jika diperlukan
-        tokio::fs::create_dir_all(&self.workdir).await.ok();
+    // Write the task's shipped script under `workdir` and make it executable
+    // Tulis skrip kiriman tugas di bawah `workdir` dan jadikan dapat dieksekusi
+    async fn prepare_script(task: &Task, workdir: &Path) -> Result<ScriptCopy> {
+        let (Some(location), Some(content)) = (script_location(task), &task.script_content) else {
+            let path = task.script_path.clone().unwrap_or_default();
+            return Err(ExecutorError::ScriptNotFound(path).into());
+        };
 
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(&task.command)
-            .current_dir(&self.workdir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let script = workdir.join(location);
+        let copy = ScriptCopy {
+            dir: workdir.join(SCRIPTS_DIR),
+            task_id: task.id.clone(),
+        };
+        if let Some(parent) = script.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&script, content).await?;
 
-        let mut stdout = String::new();
-        let mut stderr = String::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            tokio::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).await?;
+        }
+        Ok(copy)
+    }
 
-        if let Some(mut out) = child.stdout.take() {
-            tokio::io::AsyncReadExt::read_to_string(&mut out, &mut stdout).await.ok();
+    // Whether the path and every command line of the task's script pass `validate_command`
+    // Apakah path dan setiap baris perintah skrip tugas lolos `validate_command`
+    fn validate_script(&self, path: &Path, content: Option<&[u8]>) -> std::result::Result<(), String> {
+        let path = path.to_string_lossy();
+        if !self.validate_command(&path) {
+            return Err(path.into_owned());
+        }
+        let content = String::from_utf8_lossy(content.unwrap_or_default());
+        let mut lines = content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+        match lines.find(|line| !self.validate_command(line)) {
+            Some(line) => Err(line.to_string()),
+            None => Ok(()),
         }
+    }
+
+    // Run task under its timeout in its own `{workdir}/{task_id}/` directory
+    // Jalankan tugas dengan timeout-nya di direktori `{workdir}/{task_id}/` miliknya sendiri
+    //
+    // Failed tasks keep their directory for debugging; successful ones lose it when
+    // cleanup is enabled. The directory is reported in `ExecutionResult::workdir`.
+    // Tugas yang gagal mempertahankan direktorinya; yang berhasil dihapus bila pembersihan aktif.
+    pub async fn execute_isolated(&self, task: &Task) -> Result<ExecutionResult> {
+        let timeout = Duration::from_secs(task.timeout);
+        let (events, handle) = self.execute_isolated_streaming(task, Some(timeout)).await?;
+        Self::collect(events, handle).await
+    }
+
+    // Streaming counterpart of `execute_isolated`
+    // Pasangan streaming dari `execute_isolated`
+    pub async fn execute_isolated_streaming(
+        &self,
+        task: &Task,
+        timeout: Option<Duration>,
+    ) -> Result<(mpsc::Receiver<ExecutorEvent>, JoinHandle<Result<ExecutionResult>>)> {
+        self.spawn(task, timeout, true).await
+    }
 
-        if let Some(mut err) = child.stderr.take() {
-            tokio::io::AsyncReadExt::read_to_string(&mut err, &mut stderr).await.ok();
+    // Drain output events until the process exits
+    // Kuras event output sampai proses selesai
+    async fn collect(
+        mut events: mpsc::Receiver<ExecutorEvent>,
+        handle: JoinHandle<Result<ExecutionResult>>,
+    ) -> Result<ExecutionResult> {
+        while let Some(event) = events.recv().await {
+            if let ExecutorEvent::Exited(result) = event {
+                return Ok(result);
+            }
         }
 
-        let status = child.wait().await?;
-        let exit_code = status.code();
-        let duration_ms = start_time.elapsed().as_millis() as u64;
+        handle.await?
+    }
+
+    // Start task and stream its output line by line while it runs
+    // Mulai tugas dan alirkan output baris demi baris selama berjalan
+    //
+    // Output is sent on the returned channel; the final event is `Exited` unless the
+    // backend fails, in which case the error is returned from the join handle.
+    // Values of secret env vars are replaced by `***` in the output and the result.
+    // Nilai variabel env rahasia diganti `***` di output dan hasil.
+    // Tasks with a `script_path` run their shipped script directly, through the same backend.
+    // Tugas dengan `script_path` menjalankan skrip kirimannya langsung, lewat backend yang sama.
+    // Aborting the join handle cancels the task and kills whatever it started.
+    // Membatalkan join handle menghentikan tugas dan mematikan apa pun yang dimulainya.
+    // The channel is bounded, so the caller must keep receiving or execution stalls.
+    // Output dikirim lewat channel; event terakhir adalah `Exited` kecuali backend gagal.
+    pub async fn execute_streaming(
+        &self,
+        task: &Task,
+        timeout: Option<Duration>,
+    ) -> Result<(mpsc::Receiver<ExecutorEvent>, JoinHandle<Result<ExecutionResult>>)> {
+        self.spawn(task, timeout, false).await
+    }
 
-        if exit_code == Some(0) {
-            info!("Task {} completed successfully in {}ms", task.id, duration_ms);
+    // Start task in the shared workdir, or in its own directory when `isolated`
+    // Mulai tugas di workdir bersama, atau di direktorinya sendiri bila `isolated`
+    async fn spawn(
+        &self,
+        task: &Task,
+        timeout: Option<Duration>,
+        isolated: bool,
+    ) -> Result<(mpsc::Receiver<ExecutorEvent>, JoinHandle<Result<ExecutionResult>>)> {
+        if !self.allow_shell {
+            return Err(ExecutorError::ShellNotAllowed.into());
+        }
+
+        // Scripts are checked by path and by each command line they contain
+        // Skrip diperiksa berdasarkan path dan setiap baris perintah di dalamnya
+        match &task.script_path {
+            Some(path) => self
+                .validate_script(path, task.script_content.as_deref())
+                .map_err(ExecutorError::CommandRejected)?,
+            None if !self.validate_command(&task.command) => {
+                return Err(ExecutorError::CommandRejected(task.command.clone()).into());
+            }
+            None => {}
+        }
+
+        let workdir = if isolated {
+            self.isolated_workdir(task)
         } else {
-            error!(
-                "Task {} failed with exit code {:?}",
-                task.id, exit_code
-            );
+            self.workdir.clone()
+        };
+        tokio::fs::create_dir_all(&workdir).await?;
+        let script = match &task.script_path {
+            Some(_) => Some(Self::prepare_script(task, &workdir).await?),
+            None => None,
+        };
+        info!("Executing task {} in {}: {}", task.id, workdir.display(), task.command);
+        if !task.env.is_empty() {
+            let env = util::mask_sensitive_env_with(&task.env, &self.sensitive_env_patterns);
+            debug!("Task {} environment: {:?}", task.id, env);
         }
 
-        Ok(ExecutionResult {
-            task_id: task.id.clone(),
-            status: if exit_code == Some(0) {
-                TaskStatus::Completed
+        let (tx, rx) = mpsc::channel(OUTPUT_CHANNEL_CAPACITY);
+        let tx = match self.progress.clone() {
+            Some(progress) => Self::relay_progress(task.id.clone(), tx, progress),
+            None => tx,
+        };
+        let secrets = util::sensitive_env_values(&task.env, &self.sensitive_env_patterns);
+        let tx = match secrets.is_empty() {
+            true => tx,
+            false => Self::relay_masked(secrets.clone(), tx),
+        };
+        let backend = self.backend.clone();
+        let max_output_bytes = self.max_output_bytes;
+        let cleanup = isolated && self.cleanup_on_success && !task.keep_workdir;
+        let task = task.clone();
+
+        let handle = tokio::spawn(async move {
+            let outcome = backend.run(&task, &workdir, timeout, max_output_bytes, tx.clone()).await;
+            drop(script);
+            let mut result = outcome?;
+
+            if isolated {
+                // Outputs are read before the directory may be removed below
+                // Output dibaca sebelum direktori mungkin dihapus di bawah
+                result.output_files = util::files::collect(&workdir, &task.output_files).await;
+                if cleanup && result.status == TaskStatus::Completed {
+                    if let Err(e) = tokio::fs::remove_dir_all(&workdir).await {
+                        warn!("Failed to remove workdir {} of task {}: {}", workdir.display(), task.id, e);
+                    }
+                }
+                result.workdir = Some(workdir);
+            }
+            result.stdout = util::mask_secrets(&result.stdout, &secrets);
+            result.stderr = util::mask_secrets(&result.stderr, &secrets);
+
+            if result.status == TaskStatus::Completed {
+                info!("Task {} completed successfully in {}ms", task.id, result.duration_ms);
             } else {
-                TaskStatus::Failed
-            },
-            stdout,
-            stderr,
-            exit_code,
-            duration_ms,
-        })
+                error!(
+                    "Task {} finished with status {:?} (exit code {:?})",
+                    task.id, result.status, result.exit_code
+                );
+            }
+
+            let _ = tx.send(ExecutorEvent::Exited(result.clone())).await;
+            Ok(result)
+        });
+
+        Ok((rx, handle))
+    }
+
+    // Pass events through to `events`, picking progress reports out of stdout on the way
+    // Teruskan event ke `events`, sambil mengambil laporan progres dari stdout
+    fn relay_progress(
+        task_id: String,
+        events: mpsc::Sender<ExecutorEvent>,
+        progress: mpsc::Sender<(String, f32)>,
+    ) -> mpsc::Sender<ExecutorEvent> {
+        let (tx, mut rx) = mpsc::channel(OUTPUT_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let ExecutorEvent::Stdout(line) = &event {
+                    if let Some(fraction) = parse_progress(line) {
+                        let _ = progress.try_send((task_id.clone(), fraction));
+                    }
+                }
+                let _ = events.send(event).await;
+            }
+        });
+        tx
+    }
+
+    // Pass events through to `events` with every `secrets` value masked
+    // Teruskan event ke `events` dengan setiap nilai `secrets` disamarkan
+    fn relay_masked(secrets: Vec<String>, events: mpsc::Sender<ExecutorEvent>) -> mpsc::Sender<ExecutorEvent> {
+        let (tx, mut rx) = mpsc::channel(OUTPUT_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let event = match event {
+                    ExecutorEvent::Stdout(line) => ExecutorEvent::Stdout(util::mask_secrets(&line, &secrets)),
+                    ExecutorEvent::Stderr(line) => ExecutorEvent::Stderr(util::mask_secrets(&line, &secrets)),
+                    exited => exited,
+                };
+                let _ = events.send(event).await;
+            }
+        });
+        tx
     }
 
     // Execute task with timeout protection to prevent infinite runs
     // Jalankan tugas dengan perlindungan timeout untuk mencegah proses tak terbatas
+    #[tracing::instrument(skip(self, task), fields(task.id = %task.id, task.command = %task.command))]
     pub async fn execute_with_timeout(&self, task: &Task) -> Result<ExecutionResult> {
-        let timeout_duration = std::time::Duration::from_secs(task.timeout);
-        
-        match tokio::time::timeout(timeout_duration, self.execute(task)).await {
-            Ok(result) => result,
-            Err(_) => {
-                error!("Task {} timed out after {}s", task.id, task.timeout);
-                Ok(ExecutionResult {
-                    task_id: task.id.clone(),
-                    status: TaskStatus::TimedOut,
-                    stdout: String::new(),
-                    stderr: format!("Task timed out after {} seconds", task.timeout),
-                    exit_code: None,
-                    duration_ms: task.timeout * 1000,
-                })
-            }
+        let timeout_duration = Duration::from_secs(task.timeout);
+        let (events, handle) = self.execute_streaming(task, Some(timeout_duration)).await?;
+        Self::collect(events, handle).await
+    }
+
+    // Result reported for a task that exceeded its timeout
+    // Hasil yang dilaporkan untuk tugas yang melewati batas waktu
+    pub fn timed_out_result(task: &Task) -> ExecutionResult {
+        ExecutionResult {
+            task_id: task.id.clone(),
+            status: TaskStatus::TimedOut,
+            stdout: String::new(),
+            stderr: format!("Task timed out after {} seconds", task.timeout),
+            exit_code: None,
+            duration_ms: task.timeout * 1000,
+            workdir: None,
+            truncated: false,
+            resource_usage: None,
+            output_files: Vec::new(),
         }
     }
 
@@ -121,16 +503,407 @@ impl Executor {
 
         for pattern in &dangerous_patterns {
             if command.contains(pattern) {
+                warn!("Rejected command {:?}: contains `{}`", command, pattern);
                 return false;
             }
         }
 
+        if let Some(reason) = self.policy.violation(command) {
+            warn!("Rejected command {:?}: {}", command, reason);
+            return false;
+        }
+
         true
     }
 }
 
+// Runs tasks with `sh -c` directly on the worker
+// Menjalankan tugas dengan `sh -c` langsung di worker
+#[derive(Debug, Default)]
+pub struct ShellExecutor;
+
+#[async_trait]
+impl ExecutorBackend for ShellExecutor {
+    async fn run(
+        &self,
+        task: &Task,
+        workdir: &Path,
+        timeout: Option<Duration>,
+        max_output_bytes: usize,
+        events: mpsc::Sender<ExecutorEvent>,
+    ) -> Result<ExecutionResult> {
+        // Create working directory if needed
+        // Buat direktori kerja jika diperlukan
+        tokio::fs::create_dir_all(workdir).await.ok();
+
+        let program = command_line(task, &std::path::absolute(workdir)?);
+        let mut command = Command::new(&program[0]);
+        command.args(&program[1..]).current_dir(workdir);
+        run_process(command, task, timeout, max_output_bytes, events).await
+    }
+}
+
+// Where a script task's script is copied to, relative to the directory it runs in
+// Lokasi salinan skrip tugas skrip, relatif terhadap direktori tempat tugas berjalan
+pub fn script_location(task: &Task) -> Option<PathBuf> {
+    let file_name = task.script_path.as_deref()?.file_name()?;
+    Some(Path::new(SCRIPTS_DIR).join(&task.id).join(file_name))
+}
+
+// Program and arguments a backend runs for `task` in `workdir`: the copied script
+// itself for script tasks, `sh -c <command>` for everything else
+// Program dan argumen yang dijalankan backend untuk `task` di `workdir`: salinan skrip
+// untuk tugas skrip, `sh -c <command>` untuk yang lain
+pub fn command_line(task: &Task, workdir: &Path) -> Vec<String> {
+    match script_location(task) {
+        Some(script) => vec![workdir.join(script).to_string_lossy().into_owned()],
+        None => vec!["sh".to_string(), "-c".to_string(), task.command.clone()],
+    }
+}
+
+// A script copied for one task; the copy is removed when this is dropped,
+// including when the run is aborted
+// Skrip yang disalin untuk satu tugas; salinan dihapus saat ini dibuang,
+// termasuk saat eksekusi dibatalkan
+struct ScriptCopy {
+    dir: PathBuf,
+    task_id: String,
+}
+
+impl Drop for ScriptCopy {
+    fn drop(&mut self) {
+        let task_dir = self.dir.join(&self.task_id);
+        if let Err(e) = std::fs::remove_dir_all(&task_dir) {
+            warn!("Failed to remove script copy {}: {}", task_dir.display(), e);
+        }
+        // Only succeeds once no other task's script is left
+        // Hanya berhasil bila tidak ada skrip tugas lain yang tersisa
+        let _ = std::fs::remove_dir(&self.dir);
+    }
+}
+
+// Run a prepared command, streaming its output, and kill its process group on timeout
+// Jalankan perintah yang sudah disiapkan, alirkan outputnya, dan matikan grup prosesnya saat timeout
+async fn run_process(
+    mut command: Command,
+    task: &Task,
+    timeout: Option<Duration>,
+    max_output_bytes: usize,
+    events: mpsc::Sender<ExecutorEvent>,
+) -> Result<ExecutionResult> {
+    let start_time = std::time::Instant::now();
+    command.envs(&task.env).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    // Run in a new process group so a timeout can kill every descendant
+    // Jalankan di grup proses baru agar timeout dapat mematikan semua turunan
+    #[cfg(unix)]
+    command.process_group(0);
+
+    // Dropping the run before the child exits, e.g. when the task is cancelled, kills it too
+    // Membuang proses run sebelum anak keluar, mis. saat tugas dibatalkan, ikut mematikannya
+    command.kill_on_drop(true);
+    let mut child = command.spawn()?;
+    let mut group = ProcessGroupGuard::new(&child);
+    let stdout_pipe = child.stdout.take();
+    let stderr_pipe = child.stderr.take();
+
+    let budget = OutputBudget::new(max_output_bytes);
+    let output = async {
+        let readers = async {
+            tokio::join!(
+                forward_lines(stdout_pipe, events.clone(), ExecutorEvent::Stdout, &budget),
+                forward_lines(stderr_pipe, events.clone(), ExecutorEvent::Stderr, &budget),
+            )
+        };
+        tokio::pin!(readers);
+        tokio::select! {
+            output = &mut readers => output,
+            _ = budget.exceeded() => {
+                warn!("Task {} exceeded {} bytes of output, terminating", task.id, max_output_bytes);
+                if let Err(e) = terminate_process_group(&mut child) {
+                    warn!("Failed to terminate process group of task {}: {}", task.id, e);
+                }
+                // The pipes close once the process exits, ending both readers
+                // Pipe tertutup setelah proses keluar, sehingga kedua pembaca berakhir
+                readers.await
+            }
+        }
+    };
+    let output = match timeout {
+        Some(limit) => tokio::time::timeout(limit, output).await.ok(),
+        None => Some(output.await),
+    };
+
+    let Some((stdout, stderr)) = output else {
+        error!("Task {} timed out after {}s", task.id, task.timeout);
+        if let Err(e) = kill_process_group(&mut child) {
+            warn!("Failed to kill process group of task {}: {}", task.id, e);
+        }
+        // Reap the killed child so it does not linger as a zombie
+        // Ambil status anak yang dimatikan agar tidak menjadi zombie
+        child.wait().await.ok();
+        group.disarm();
+        return Ok(Executor::timed_out_result(task));
+    };
+
+    let (status, resource_usage) = wait_with_usage(&mut child).await?;
+    group.disarm();
+    let exit_code = status.code();
+    let truncated = budget.is_exceeded();
+
+    Ok(ExecutionResult {
+        task_id: task.id.clone(),
+        status: if exit_code == Some(0) && !truncated {
+            TaskStatus::Completed
+        } else {
+            TaskStatus::Failed
+        },
+        stdout,
+        stderr: if truncated { with_limit_message(stderr) } else { stderr },
+        exit_code,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        workdir: None,
+        truncated,
+        resource_usage,
+        output_files: Vec::new(),
+    })
+}
+
+// Kills the child's process group when dropped while still armed, so descendants
+// of a cancelled task do not outlive it (`kill_on_drop` only reaches the child)
+// Mematikan grup proses anak saat dibuang selagi masih aktif, agar turunan tugas
+// yang dibatalkan tidak hidup lebih lama (`kill_on_drop` hanya menjangkau anak)
+struct ProcessGroupGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn new(child: &Child) -> Self {
+        Self { pid: child.id() }
+    }
+
+    // The child has been reaped; its PID may be reused from here on
+    // Anak sudah diambil statusnya; PID-nya bisa dipakai ulang mulai sekarang
+    fn disarm(&mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            let _ = signal_process_group(pid, libc::SIGKILL);
+        }
+    }
+}
+
+// Reap the child with `wait4`, collecting the resources it and its descendants used
+// Ambil status anak dengan `wait4`, sambil mengumpulkan sumber daya yang dipakainya
+#[cfg(target_os = "linux")]
+async fn wait_with_usage(child: &mut Child) -> Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let Some(pid) = child.id() else {
+        return Ok((child.wait().await?, None));
+    };
+
+    // Blocks until the child exits, so it runs off the async threads
+    // Memblokir sampai anak keluar, sehingga dijalankan di luar thread async
+    let (status, usage) = tokio::task::spawn_blocking(move || {
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            let ret = unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, &mut usage) };
+            if ret >= 0 {
+                return Ok((status, usage));
+            }
+            let error = std::io::Error::last_os_error();
+            if error.kind() != std::io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+    })
+    .await
+    .map_err(ExecutorError::from)??;
+
+    let millis = |time: libc::timeval| time.tv_sec as u64 * 1000 + time.tv_usec as u64 / 1000;
+    let usage = ResourceUsage {
+        user_cpu_ms: millis(usage.ru_utime),
+        sys_cpu_ms: millis(usage.ru_stime),
+        // Linux reports ru_maxrss in kilobytes
+        // Linux melaporkan ru_maxrss dalam kilobyte
+        max_rss_kb: usage.ru_maxrss as u64,
+        context_switches: (usage.ru_nvcsw + usage.ru_nivcsw) as u64,
+    };
+    Ok((std::process::ExitStatus::from_raw(status), Some(usage)))
+}
+
+// Resource usage is only collected on Linux
+// Penggunaan sumber daya hanya dikumpulkan di Linux
+#[cfg(not(target_os = "linux"))]
+async fn wait_with_usage(child: &mut Child) -> Result<(std::process::ExitStatus, Option<ResourceUsage>)> {
+    Ok((child.wait().await?, None))
+}
+
+// Kill the child and every process in its group
+// Matikan proses anak beserta seluruh proses dalam grupnya
+#[cfg(unix)]
+pub fn kill_process_group(child: &mut Child) -> std::io::Result<()> {
+    let Some(pid) = child.id() else {
+        // Already reaped
+        // Sudah selesai dan diambil statusnya
+        return Ok(());
+    };
+
+    signal_process_group(pid, libc::SIGKILL)
+}
+
+// Ask the child and every process in its group to exit
+// Minta proses anak beserta seluruh proses dalam grupnya untuk keluar
+#[cfg(unix)]
+pub fn terminate_process_group(child: &mut Child) -> std::io::Result<()> {
+    match child.id() {
+        Some(pid) => signal_process_group(pid, libc::SIGTERM),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn signal_process_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // A negative PID signals the whole process group led by the child
+    // PID negatif mengirim sinyal ke seluruh grup proses yang dipimpin anak
+    let ret = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+// Terminate the child process
+// Hentikan proses anak
+#[cfg(windows)]
+pub fn kill_process_group(child: &mut Child) -> std::io::Result<()> {
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::Threading::TerminateProcess;
+
+    let Some(handle) = child.raw_handle() else {
+        return Ok(());
+    };
+
+    if unsafe { TerminateProcess(handle as HANDLE, 1) } != 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+// Windows has no SIGTERM; terminating is the same as killing
+// Windows tidak memiliki SIGTERM; menghentikan sama dengan mematikan
+#[cfg(windows)]
+pub fn terminate_process_group(child: &mut Child) -> std::io::Result<()> {
+    kill_process_group(child)
+}
+
+// Output a single task may still print, shared by its stdout and stderr readers
+// Output yang masih boleh dicetak satu tugas, dibagi oleh pembaca stdout dan stderr
+struct OutputBudget {
+    limit: usize,
+    used: AtomicUsize,
+    exceeded: Notify,
+}
+
+impl OutputBudget {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            exceeded: Notify::new(),
+        }
+    }
+
+    // Count `bytes` of output; false once the limit is passed
+    // Hitung `bytes` output; false setelah batas terlewati
+    fn take(&self, bytes: usize) -> bool {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed).saturating_add(bytes);
+        if used > self.limit {
+            self.exceeded.notify_one();
+            return false;
+        }
+        true
+    }
+
+    fn is_exceeded(&self) -> bool {
+        self.used.load(Ordering::Relaxed) > self.limit
+    }
+
+    // Resolves once `take` has refused output
+    // Selesai setelah `take` menolak output
+    async fn exceeded(&self) {
+        self.exceeded.notified().await
+    }
+}
+
+// Stderr of a task stopped for printing too much, ending with the reason
+// Stderr tugas yang dihentikan karena output terlalu banyak, diakhiri alasannya
+fn with_limit_message(mut stderr: String) -> String {
+    stderr.push_str(OUTPUT_LIMIT_MESSAGE);
+    stderr.push('\n');
+    stderr
+}
+
+// Read a pipe line by line, emitting each line as an event and collecting the full output
+// until the output budget runs out
+// Baca pipe baris demi baris, kirim tiap baris sebagai event dan kumpulkan seluruh output
+// sampai jatah output habis
+async fn forward_lines<R>(
+    pipe: Option<R>,
+    tx: mpsc::Sender<ExecutorEvent>,
+    event: fn(String) -> ExecutorEvent,
+    budget: &OutputBudget,
+) -> String
+where
+    R: AsyncRead + Unpin,
+{
+    let mut collected = String::new();
+    let Some(pipe) = pipe else {
+        return collected;
+    };
+
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if !budget.take(line.len() + 1) {
+            break;
+        }
+        collected.push_str(&line);
+        collected.push('\n');
+        // Keep collecting even if the receiver has gone away
+        // Tetap kumpulkan walaupun penerima sudah tidak ada
+        let _ = tx.send(event(line)).await;
+    }
+    collected
+}
+
+// Fraction from a `PROGRESS: <float>` line, clamped to 0.0..=1.0
+// Pecahan dari baris `PROGRESS: <float>`, dibatasi ke 0.0..=1.0
+pub fn parse_progress(line: &str) -> Option<f32> {
+    let value: f32 = line.trim().strip_prefix(PROGRESS_PREFIX)?.trim().parse().ok()?;
+    value.is_finite().then(|| value.clamp(0.0, 1.0))
+}
+
+// Output and completion events emitted while a task runs
+// Event output dan penyelesaian yang dikirim selama tugas berjalan
+#[derive(Debug, Clone)]
+pub enum ExecutorEvent {
+    Stdout(String),
+    Stderr(String),
+    Exited(ExecutionResult),
+}
+
 // Result structure returned after task execution
 // Struktur hasil yang dikembalikan setelah eksekusi tugas
+#[derive(Debug, Clone)]
 pub struct ExecutionResult {
     pub task_id: String,
     pub status: TaskStatus,
@@ -138,6 +911,18 @@ pub struct ExecutionResult {
     pub stderr: String,
     pub exit_code: Option<i32>,
     pub duration_ms: u64,
+    // Isolated directory the task ran in, if it had one
+    // Direktori terisolasi tempat tugas berjalan, jika ada
+    pub workdir: Option<PathBuf>,
+    // Whether output was cut off at `max_output_bytes`
+    // Apakah output dipotong pada `max_output_bytes`
+    pub truncated: bool,
+    // CPU time and memory the process used; only measured on Linux
+    // Waktu CPU dan memori yang dipakai proses; hanya diukur di Linux
+    pub resource_usage: Option<ResourceUsage>,
+    // The task's `output_files` as read from its isolated directory
+    // `output_files` tugas seperti dibaca dari direktori terisolasinya
+    pub output_files: Vec<FileSpec>,
 }
 
 // Unit tests for executor validation
@@ -148,10 +933,280 @@ mod tests {
 
     #[test]
     fn test_validate_command() {
-        let executor = Executor::new(PathBuf::from("/tmp"), true);
+        let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
 
         assert!(executor.validate_command("echo hello"));
         assert!(executor.validate_command("ls -la"));
         assert!(!executor.validate_command("rm -rf /"));
     }
+
+    #[tokio::test]
+    async fn test_execute_streaming_emits_lines_before_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("echo one; echo two >&2; echo three".to_string());
+
+        let (mut events, handle) = executor.execute_streaming(&task, None).await.unwrap();
+        let mut stdout_lines = Vec::new();
+        let mut stderr_lines = Vec::new();
+        let mut exited = None;
+        while let Some(event) = events.recv().await {
+            match event {
+                ExecutorEvent::Stdout(line) => stdout_lines.push(line),
+                ExecutorEvent::Stderr(line) => stderr_lines.push(line),
+                ExecutorEvent::Exited(result) => exited = Some(result),
+            }
+        }
+
+        assert_eq!(stdout_lines, vec!["one", "three"]);
+        assert_eq!(stderr_lines, vec!["two"]);
+        let result = exited.expect("missing Exited event");
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(handle.await.unwrap().unwrap().stdout, "one\nthree\n");
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(parse_progress("PROGRESS: 0.75"), Some(0.75));
+        assert_eq!(parse_progress("  PROGRESS:1.5"), Some(1.0));
+        assert_eq!(parse_progress("PROGRESS: NaN"), None);
+        assert_eq!(parse_progress("progress: 0.5"), None);
+        assert_eq!(parse_progress("done"), None);
+    }
+
+    #[tokio::test]
+    async fn test_progress_lines_reported_on_channel() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let (tx, mut rx) = mpsc::channel(PROGRESS_CHANNEL_CAPACITY);
+        executor.set_progress_channel(tx);
+        let task = Task::new("echo 'PROGRESS: 0.5'; echo hi; echo 'PROGRESS: 1'".to_string());
+
+        let result = executor.execute(&task).await.unwrap();
+        assert_eq!(result.stdout, "PROGRESS: 0.5\nhi\nPROGRESS: 1\n");
+        assert_eq!(rx.recv().await, Some((task.id.clone(), 0.5)));
+        assert_eq!(rx.recv().await, Some((task.id.clone(), 1.0)));
+    }
+
+    #[tokio::test]
+    async fn test_execute_collects_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("echo hello; exit 3".to_string());
+
+        let result = executor.execute(&task).await.unwrap();
+        assert_eq!(result.stdout, "hello\n");
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.status, TaskStatus::Failed);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn process_alive(pid: &str) -> bool {
+        // Zombies still have a /proc entry but are no longer running
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.rsplit(')').next().unwrap_or("").trim_start().starts_with('Z'),
+            Err(_) => false,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_timeout_kills_process_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let mut task = Task::new("echo $$ > sh.pid; sleep 30 & echo $! > sleep.pid; wait".to_string());
+        task.timeout = 1;
+
+        let result = executor.execute_with_timeout(&task).await.unwrap();
+        assert_eq!(result.status, TaskStatus::TimedOut);
+
+        // Give the kernel a moment to tear down the killed processes
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        for pid_file in ["sh.pid", "sleep.pid"] {
+            let pid = std::fs::read_to_string(dir.path().join(pid_file)).unwrap();
+            assert!(!process_alive(pid.trim()), "{} still running", pid_file);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_aborted_run_kills_process_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("echo $$ > sh.pid; sleep 30 & echo $! > sleep.pid; wait".to_string());
+
+        let (_events, handle) = executor.execute_streaming(&task, None).await.unwrap();
+        while !dir.path().join("sleep.pid").exists() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        for pid_file in ["sh.pid", "sleep.pid"] {
+            let pid = std::fs::read_to_string(dir.path().join(pid_file)).unwrap();
+            assert!(!process_alive(pid.trim()), "{} still running", pid_file);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_script_file_runs_without_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("job.sh"), "#!/bin/sh\necho 'a; b && $HOME'\n").unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("ignored".to_string());
+
+        let result = executor.execute_script_file(Path::new("job.sh"), &task).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.stdout, "a; b && $HOME\n");
+        assert!(!dir.path().join(SCRIPTS_DIR).exists());
+    }
+
+    #[tokio::test]
+    async fn test_script_task_runs_shipped_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            command_denylist: vec!["rm *".to_string()],
+            ..Config::default()
+        };
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::from_config(&config).unwrap());
+        let mut task = Task::new("ignored".to_string());
+        task.script_path = Some(PathBuf::from("jobs/job.sh"));
+        task.script_content = Some(b"#!/bin/sh\necho shipped\n".to_vec());
+
+        // Nothing named jobs/job.sh exists on this side; the shipped content runs
+        let result = executor.execute_isolated(&task).await.unwrap();
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.stdout, "shipped\n");
+        assert!(!dir.path().join(&task.id).join(SCRIPTS_DIR).exists());
+
+        task.script_content = Some(b"#!/bin/sh\n# clean up\nrm -rf data\n".to_vec());
+        let error = executor.execute_isolated(&task).await.unwrap_err();
+        assert!(matches!(error, crate::error::OctasklyError::Executor(ExecutorError::CommandRejected(line)) if line == "rm -rf data"));
+
+        task.script_content = None;
+        let error = executor.execute_isolated(&task).await.unwrap_err();
+        assert!(matches!(error, crate::error::OctasklyError::Executor(ExecutorError::ScriptNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_execute_script_file_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("ignored".to_string());
+
+        let error = executor.execute_script_file(Path::new("missing.sh"), &task).await.unwrap_err();
+        assert!(matches!(error, crate::error::OctasklyError::Executor(ExecutorError::ScriptNotFound(_))));
+
+        for escaping in ["/etc/passwd", "../job.sh"] {
+            let error = executor.execute_script_file(Path::new(escaping), &task).await.unwrap_err();
+            assert!(matches!(error, crate::error::OctasklyError::Executor(ExecutorError::InvalidScriptPath(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_isolated_uses_task_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default())
+            .with_cleanup_on_success(true);
+
+        let mut ok = Task::new("echo out > file.txt".to_string());
+        ok.output_files = vec![FileSpec::new("file.txt")];
+        let result = executor.execute_isolated(&ok).await.unwrap();
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.workdir, Some(dir.path().join(&ok.id)));
+        assert!(!dir.path().join(&ok.id).exists());
+        assert_eq!(result.output_files[0].content.as_deref(), Some(&b"out\n"[..]));
+
+        let mut kept = Task::new("echo out > file.txt".to_string());
+        kept.keep_workdir = true;
+        executor.execute_isolated(&kept).await.unwrap();
+        assert!(dir.path().join(&kept.id).join("file.txt").is_file());
+
+        let failed = Task::new("echo out > file.txt; exit 3".to_string());
+        let result = executor.execute_isolated(&failed).await.unwrap();
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert!(dir.path().join(&failed.id).join("file.txt").is_file());
+        assert!(!dir.path().join("file.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_output_limit_terminates_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default())
+            .with_max_output_bytes(1024);
+        let mut task = Task::new("yes octaskly; echo done".to_string());
+        task.timeout = 10;
+
+        let result = executor.execute_with_timeout(&task).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert!(result.truncated);
+        assert!(result.stdout.len() <= 1024);
+        assert!(result.stderr.ends_with("Output limit exceeded\n"));
+    }
+
+    #[tokio::test]
+    async fn test_command_policy_allowlist_and_denylist() {
+        let config = Config {
+            command_allowlist: Some(vec!["echo *".to_string(), "cargo *".to_string()]),
+            command_denylist: vec!["* --force*".to_string()],
+            ..Config::default()
+        };
+        let policy = CommandPolicy::from_config(&config).unwrap();
+        let executor = Executor::new(PathBuf::from("/tmp"), true, policy);
+
+        assert!(executor.validate_command("echo hello"));
+        assert!(executor.validate_command("cargo build --release"));
+        assert!(!executor.validate_command("cargo publish --force"));
+        assert!(!executor.validate_command("curl http://example.com"));
+
+        let error = executor.execute(&Task::new("ls /".to_string())).await.unwrap_err();
+        assert!(matches!(error, crate::error::OctasklyError::Executor(ExecutorError::CommandRejected(_))));
+
+        let config = Config {
+            command_denylist: vec!["[".to_string()],
+            ..Config::default()
+        };
+        assert!(CommandPolicy::from_config(&config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_secret_env_values_masked_in_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let mut task = Task::new("echo \"key=$API_TOKEN\"; echo \"user=$USER_NAME\" >&2".to_string());
+        task.env.insert("API_TOKEN".to_string(), "s3cr3t".to_string());
+        task.env.insert("USER_NAME".to_string(), "alice".to_string());
+
+        let (mut events, handle) = executor.execute_streaming(&task, None).await.unwrap();
+        let mut lines = Vec::new();
+        while let Some(event) = events.recv().await {
+            match event {
+                ExecutorEvent::Stdout(line) | ExecutorEvent::Stderr(line) => lines.push(line),
+                ExecutorEvent::Exited(_) => {}
+            }
+        }
+        let result = handle.await.unwrap().unwrap();
+
+        assert!(lines.contains(&"key=***".to_string()));
+        assert_eq!(result.stdout, "key=***\n");
+        assert_eq!(result.stderr, "user=alice\n");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_resource_usage_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default());
+        let task = Task::new("i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done".to_string());
+
+        let result = executor.execute(&task).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        let usage = result.resource_usage.unwrap();
+        assert!(usage.max_rss_kb > 0);
+        assert!(usage.user_cpu_ms + usage.sys_cpu_ms > 0);
+    }
 }
@@ -2,13 +2,19 @@
 // P2P network resource sharing for compute tasks
 // Koordinator Tugas Komputasi Offline - Berbagi sumber daya jaringan peer-to-peer untuk tugas komputasi
 
+pub mod client;
 pub mod cmd;
+pub mod config;
 pub mod discovery;
+pub mod error;
 pub mod executor;
+pub mod metrics;
+pub mod node;
 pub mod protocol;
 pub mod scheduler;
 pub mod security;
 pub mod state;
+pub mod telemetry;
 pub mod transport;
 pub mod tui;
 pub mod util;
@@ -27,12 +33,16 @@ pub mod resources;
 pub mod api;
 pub mod transport_quic;
 pub mod sandbox;
+pub mod webhook;
 
 pub use cmd::Command;
+pub use config::Config;
+pub use error::{OctasklyError, Result};
 pub use discovery::Discovery;
 pub use executor::Executor;
+pub use metrics::Metrics;
 pub use protocol::{Task, TaskResult, WorkerInfo};
-pub use scheduler::Scheduler;
+pub use scheduler::{Scheduler, SchedulerAlgorithm, SchedulingWeights};
 pub use state::{DispatcherState, WorkerState};
 pub use transport::Transport;
 pub use p2p::{P2PNetwork, P2PPeer};
@@ -40,7 +50,7 @@ pub use p2p_distribution::P2PDistributor;
 
 // New exports
 pub use security_enhanced::SecurityManager;
-pub use persistence::PersistentStore;
+pub use persistence::{PostgresStore, SqliteStore, StoreBackend, TaskFilter};
 pub use auth::AuthManager;
 pub use resources::ResourceLimits;
 pub use api::ApiState;
@@ -6,19 +6,25 @@
 
 use anyhow::Result;
 use clap::Parser;
-use octaskly::cmd::Cli;
-use octaskly::scheduler::Scheduler;
-use octaskly::state::{DispatcherState, WorkerState};
-use octaskly::executor::Executor;
-use octaskly::protocol::{Message, WorkerInfo};
+use crossterm::style::Stylize;
+use octaskly::client;
+use octaskly::cmd::{CertCommand, Cli, ConfigCommand};
+use octaskly::config::{self, Config, ConfigLayer};
+use octaskly::node::{run_dispatcher, run_worker};
+use octaskly::protocol::{OutputLine, Task, TaskResult, TaskStatus, WorkerInfo};
+use octaskly::transport::ca::{self, CertificateAuthority};
+use octaskly::transport::{tls, TlsClient, Transport, TransportBackend, TransportKind};
+use octaskly::transport_quic::{QuicConfig, QuicTransport};
+use octaskly::telemetry::Telemetry;
 use octaskly::util;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use tokio::time::{Duration, interval};
-use tokio::sync::RwLock;
-use tracing::{error, info, warn, debug};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+// How long a worker started with --mdns looks for a dispatcher before using --dispatcher
+// Berapa lama worker dengan --mdns mencari dispatcher sebelum memakai --dispatcher
+const MDNS_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -62,9 +68,29 @@ async fn main() -> Result<()> {
         std::process::exit(0);
     }
 
-    util::setup_logging();
-
     let cli = Cli::parse();
+    if cli.build_info {
+        println!("{}", util::build_info::summary());
+        return Ok(());
+    }
+
+    // Config commands work on files that may not load yet, so they run before loading one
+    // Perintah config bekerja pada file yang mungkin belum bisa dimuat, jadi dijalankan sebelum memuatnya
+    if let Some(octaskly::cmd::Command::Config { action }) = &cli.command {
+        return run_config_command(action, cli.config.clone());
+    }
+
+    // Settings layer: defaults < config file < OCTASKLY_* env < CLI flags
+    // Lapisan pengaturan: bawaan < file konfigurasi < env OCTASKLY_* < flag CLI
+    let config = Config::load(cli.config.clone())?;
+
+    // Spans are only exported when an OTLP endpoint is given
+    // Span hanya diekspor jika endpoint OTLP diberikan
+    let telemetry = cli.otel_endpoint.as_deref().map(Telemetry::init).transpose()?;
+    util::setup_logging(
+        if cli.verbose { "debug" } else { &config.log_level },
+        telemetry.as_ref().map(Telemetry::tracer),
+    );
     let _monitor = cli.monitor;
     let _verbose = cli.verbose;
     
@@ -85,640 +111,482 @@ async fn main() -> Result<()> {
             p2p_enabled: _,
             discovery_port: _,
             ui: _ui,
+            tls_cert,
+            tls_key,
+            mtls,
+            mdns,
+            transport,
+            insecure,
+            psk,
+            db_url,
+            api_port,
+            backup_path,
+            backup_interval_hours,
+            backup_retention,
+            scheduling_weight_cpu,
+            scheduling_weight_latency,
+            scheduling_weight_reliability,
+            scheduler_algorithm,
         } => {
             if _monitor {
                 info!("[DISPATCHER] Monitor mode enabled");
             }
-            run_dispatcher(&bind, port, workdir).await?;
+            if config.source.is_none() {
+                warn!("[DISPATCHER] No config file found, using defaults");
+            }
+            let config = config.merged(ConfigLayer {
+                bind,
+                port,
+                workdir,
+                tls_cert,
+                tls_key,
+                mtls: mtls.then_some(true),
+                mdns: mdns.then_some(true),
+                transport,
+                insecure: insecure.then_some(true),
+                psk,
+                db_url,
+                api_port,
+                backup_path,
+                backup_interval_hours,
+                backup_retention,
+                scheduling_weight_cpu,
+                scheduling_weight_latency,
+                scheduling_weight_reliability,
+                scheduler_algorithm,
+                ..Default::default()
+            });
+
+            let tls_acceptor = match (&config.tls_cert, &config.tls_key) {
+                (Some(_), None) | (None, Some(_)) => anyhow::bail!("tls_cert and tls_key must be set together"),
+                // With mTLS workers must present a certificate signed by the workdir CA
+                // Dengan mTLS worker harus menunjukkan sertifikat yang ditandatangani CA di workdir
+                _ if config.mtls => {
+                    let ca = CertificateAuthority::load_or_create(&config.workdir)?;
+                    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+                        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+                        _ => dispatcher_certificate(&ca, &config)?,
+                    };
+                    info!("[DISPATCHER] mTLS enabled; workers must trust {}", ca.cert_path().display());
+                    Some(tls::load_mtls_acceptor(&cert, &key, ca.cert_path())?)
+                }
+                (Some(cert), Some(key)) => Some(tls::load_acceptor(cert, key)?),
+                (None, None) => None,
+            };
+            // Assignments reuse pooled worker connections; idle ones are swept in the background
+            // Penugasan memakai ulang koneksi worker dari pool; koneksi idle dibersihkan di latar belakang
+            let transport: Arc<dyn TransportBackend> = match config.transport {
+                TransportKind::Tcp => {
+                    let transport = Transport::default().with_read_timeout(config.read_timeout());
+                    transport.pool().start_sweeper();
+                    Arc::new(transport)
+                }
+                TransportKind::Quic => {
+                    if config.mtls {
+                        anyhow::bail!("mTLS is only supported with --transport tcp");
+                    }
+                    // Workers generate their certificates at startup, so there is nothing to verify them against yet
+                    // Worker membuat sertifikatnya saat mulai, jadi belum ada yang bisa dipakai untuk memverifikasinya
+                    if !config.insecure {
+                        anyhow::bail!("QUIC dispatchers cannot verify worker certificates yet; pass --insecure");
+                    }
+                    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+                        (Some(cert), Some(key)) => (cert.clone(), key.clone()),
+                        _ => quic_certificate(&config)?,
+                    };
+                    info!("[DISPATCHER] QUIC certificate {}; workers can pin it with --pin-cert", cert.display());
+                    let quic = QuicConfig {
+                        local_addr: format!("{}:{}", config.bind, config.port).parse()?,
+                        cert_path: Some(cert),
+                        key_path: Some(key),
+                        insecure: config.insecure,
+                        ..Default::default()
+                    };
+                    Arc::new(QuicTransport::new(quic, true).await?)
+                }
+            };
+            run_dispatcher(&config, tls_acceptor, transport).await?;
         }
         octaskly::cmd::Command::Worker {
             name,
-            dispatcher: _,
-            dispatcher_port: _,
+            dispatcher,
+            dispatcher_port,
             max_jobs,
             cpu_cores: _,
             memory_mb: _,
             gpu: _,
             allow_shell,
+            tags,
+            tls_ca,
+            tls_cert,
+            tls_key,
+            executor,
+            mdns,
+            transport,
+            pin_cert,
+            insecure,
+            psk,
+            max_output_bytes,
         } => {
             if _monitor {
                 info!("[WORKER] Monitor mode enabled");
             }
-            run_worker(&name, allow_shell, max_jobs).await?;
-        }
-        _ => {
-            eprintln!("Usage: octaskly <dispatcher | worker | d | w>");
-            std::process::exit(1);
-        }
-    }
-
-    Ok(())
-}
-
-
-async fn run_dispatcher(bind: &str, port: u16, workdir: PathBuf) -> Result<()> {
-    // Initialize dispatcher with state management
-    // Inisialisasi dispatcher dengan manajemen status
-    info!("[DISPATCHER] Starting Octaskly Dispatcher on {}:{}", bind, port);
-
-    let dispatcher_state = Arc::new(DispatcherState::new("dispatcher".to_string(), port));
-    let scheduler = Arc::new(Scheduler::new());
-    let active_tasks: Arc<RwLock<std::collections::HashMap<String, String>>> = 
-        Arc::new(RwLock::new(std::collections::HashMap::new()));
-
-    // Initialize P2P peer discovery and task distribution
-    // Inisialisasi penemuan peer P2P dan distribusi task
-    let p2p_network = match start_p2p_discovery(
-        format!("dispatcher-{}", port),
-        "Octaskly-Dispatcher".to_string(),
-        5555,
-    ).await {
-        Ok(network) => {
-            info!("[P2P] P2P network initialized successfully");
-            Some(network)
-        }
-        Err(e) => {
-            warn!("[P2P] P2P initialization warning: {}", e);
-            None
-        }
-    };
-
-    // Initialize P2P task distributor for resource sharing
-    // Inisialisasi distributor task P2P untuk berbagi resource
-    // Default: 4 CPU cores, 8GB RAM, no GPU, 4 task slots
-    // Default: 4 core CPU, 8GB RAM, tidak ada GPU, 4 slot task
-    let p2p_distributor = Arc::new(octaskly::P2PDistributor::new(
-        format!("dispatcher-{}", port),
-        4,           // CPU cores (default estimate)
-        8192,        // RAM in MB (8GB default)
-        false,       // GPU available
-        4,           // Task slots
-    ));
-
-    // Create work directory if not exists
-    // Buat direktori kerja jika belum ada
-    util::ensure_dir(&workdir).await?;
-
-    // Create network listener on specified address and port
-    // Buat listener jaringan pada alamat dan port yang ditentukan
-    let addr = format!("{}:{}", bind, port);
-    let listener = TcpListener::bind(&addr).await?;
-    info!("[DISPATCHER] Listening on {}", addr);
-
-    let listener = Arc::new(listener);
-
-    info!("[DISPATCHER] Ready. Waiting for worker connections...");
-
-    // Spawn task to handle incoming connections from workers
-    // Jalankan task untuk menangani koneksi masuk dari worker
-    let listener_clone = listener.clone();
-    let scheduler_clone = scheduler.clone();
-    let dispatcher_state_clone = dispatcher_state.clone();
-    let active_tasks_clone = active_tasks.clone();
-    
-    tokio::spawn(async move {
-        loop {
-            match listener_clone.accept().await {
-                Ok((stream, peer_addr)) => {
-                    debug!("[DISPATCHER] Accepted connection from {}", peer_addr);
-                    
-                    let scheduler = scheduler_clone.clone();
-                    let dispatcher_state = dispatcher_state_clone.clone();
-                    let active_tasks = active_tasks_clone.clone();
-                    
-                    tokio::spawn(async move {
-                        if let Err(e) = 
-                            octaskly::transport::Transport::handle_connection(
-                                stream,
-                                move |msg| {
-                                    let scheduler = scheduler.clone();
-                                    let dispatcher_state = dispatcher_state.clone();
-                                    let active_tasks = active_tasks.clone();
-                                    
-                                    Box::pin(async move {
-                                        handle_dispatcher_message(
-                                            msg,
-                                            &scheduler,
-                                            &dispatcher_state,
-                                            &active_tasks,
-                                        )
-                                        .await
-                                    })
-                                }
-                            ).await 
-                        {
-                            error!("Connection handler error: {}", e);
-                        }
-                    });
+            if config.source.is_none() {
+                warn!("[WORKER] No config file found, using defaults");
+            }
+            let config = config.merged(ConfigLayer {
+                port: dispatcher_port,
+                max_jobs,
+                allow_shell,
+                mdns: mdns.then_some(true),
+                transport,
+                insecure: insecure.then_some(true),
+                psk,
+                max_output_bytes,
+                ..Default::default()
+            });
+
+            // Dispatchers given without a port use --dispatcher-port
+            // Dispatcher tanpa port memakai --dispatcher-port
+            let mut dispatchers: Vec<String> = dispatcher
+                .iter()
+                .map(|address| match address.contains(':') {
+                    true => address.clone(),
+                    false => format!("{}:{}", address, config.port),
+                })
+                .collect();
+
+            // Results and announcements to the dispatcher go over TLS when a CA is given;
+            // every dispatcher is verified against the first one's host name
+            // Hasil dan pengumuman ke dispatcher dikirim lewat TLS jika CA diberikan;
+            // setiap dispatcher diverifikasi dengan nama host dispatcher pertama
+            let server_name = dispatchers[0].rsplit_once(':').map_or(dispatchers[0].as_str(), |(host, _)| host);
+            let mut worker_id = None;
+            let transport: Arc<dyn TransportBackend> = match config.transport {
+                TransportKind::Tcp => {
+                    let mut transport = Transport::default().with_read_timeout(config.read_timeout());
+                    if let Some(ca) = tls_ca {
+                        let client = match (tls_cert, tls_key) {
+                            // An mTLS dispatcher only accepts the ID named in the certificate
+                            // Dispatcher mTLS hanya menerima ID yang tertera di sertifikat
+                            (Some(cert), Some(key)) => {
+                                worker_id = Some(tls::load_common_name(&cert)?);
+                                TlsClient::from_ca_file_with_client_cert(&ca, server_name, &cert, &key)?
+                            }
+                            _ => TlsClient::from_ca_file(&ca, server_name)?,
+                        };
+                        transport = transport.with_tls(client);
+                    }
+                    transport.pool().start_sweeper();
+                    Arc::new(transport)
                 }
-                Err(e) => {
-                    error!("Accept error: {}", e);
+                // QUIC always encrypts; the dispatcher is verified by CA or pinned certificate unless --insecure
+                // QUIC selalu terenkripsi; dispatcher diverifikasi lewat CA atau sertifikat tersemat kecuali --insecure
+                TransportKind::Quic => {
+                    if tls_cert.is_some() {
+                        anyhow::bail!("mTLS is only supported with --transport tcp");
+                    }
+                    if tls_ca.is_none() && pin_cert.is_none() && !config.insecure {
+                        anyhow::bail!("QUIC workers need --tls-ca, --pin-cert or --insecure to verify the dispatcher");
+                    }
+                    let quic = QuicConfig {
+                        local_addr: "0.0.0.0:0".parse()?,
+                        ca_path: tls_ca,
+                        pinned_cert_path: pin_cert,
+                        insecure: config.insecure,
+                        server_name: server_name.to_string(),
+                        ..Default::default()
+                    };
+                    Arc::new(QuicTransport::new(quic, true).await?)
                 }
-            }
-        }
-    });
-
-    // Scheduler loop - assign tasks to idle workers
-    // Loop penjadwal - tugaskan tugas ke worker yang menganggur
-    let scheduler_clone = scheduler.clone();
-    let active_tasks_clone = active_tasks.clone();
-    
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_millis(500));
-        
-        loop {
-            interval.tick().await;
-            
-            if let Some((task, mut worker)) = scheduler_clone.schedule_next_task().await {
-                debug!("[SCHEDULER] Assigning task {} to worker {}", task.id, worker.id);
-                
-                // Mark task as assigned
-                active_tasks_clone.write().await.insert(task.id.clone(), worker.id.clone());
-                
-                // Update worker current jobs
-                worker.current_jobs += 1;
-                scheduler_clone.update_worker(&worker.id, worker.clone()).await;
-                
-                // Try to send task to worker
-                let worker_addr = format!("{}:{}", worker.address, worker.port);
-                if let Ok(socket_addr) = worker_addr.parse::<SocketAddr>() {
-                    let message = Message::AssignTask(task.clone());
-                    if let Err(e) = octaskly::transport::Transport::new().send_message(socket_addr, &message).await {
-                        warn!("Failed to send task to worker {}: {}", worker.id, e);
-                        // Requeue task
-                        scheduler_clone.enqueue(task).await;
+            };
+            // Prefer a dispatcher found over mDNS, then the --dispatcher list in order
+            // Utamakan dispatcher yang ditemukan lewat mDNS, lalu daftar --dispatcher secara berurutan
+            if config.mdns {
+                match util::discover_dispatcher(MDNS_DISCOVERY_TIMEOUT).await {
+                    Ok(Some(addr)) => {
+                        info!("[WORKER] Found dispatcher {} via mDNS", addr);
+                        dispatchers.insert(0, addr.to_string());
                     }
+                    Ok(None) => info!("[WORKER] No dispatcher found via mDNS, using {:?}", dispatchers),
+                    Err(e) => warn!("[WORKER] mDNS discovery failed: {}", e),
                 }
             }
+            run_worker(&name, worker_id, &config, tags, executor, transport, dispatchers).await?;
         }
-    });
+        octaskly::cmd::Command::Submit {
+            dispatcher,
+            command,
+            timeout,
+            tags,
+            wait,
+        } => {
+            let dispatcher_addr = client::resolve(&dispatcher).await?;
+            let mut task = Task::new(command);
+            if let Some(timeout) = timeout {
+                task.timeout = timeout;
+            }
+            if let Some(tags) = tags {
+                task.required_tags = tags;
+            }
 
-    // Heartbeat cleanup loop - remove offline workers
-    // Loop pembersihan detak jantung - hapus worker yang offline
-    let scheduler_clone = scheduler.clone();
-    
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_secs(10));
-        
-        loop {
-            interval.tick().await;
-            scheduler_clone.cleanup_offline_workers(30).await;
-        }
-    });
-
-    // P2P task distribution management loop
-    // Loop manajemen distribusi task P2P
-    if let Some(_p2p_net) = p2p_network.clone() {
-        let p2p_distributor_clone = p2p_distributor.clone();
-        let p2p_network_clone = p2p_network.clone();
-        
-        tokio::spawn(async move {
-            if let Some(p2p_net) = p2p_network_clone {
-                if let Err(e) = manage_p2p_distribution(
-                    p2p_distributor_clone,
-                    p2p_net,
-                    1,
-                ).await {
-                    error!("[P2P] Task distribution error: {}", e);
-                }
+            if !wait {
+                println!("{}", client::submit_task(dispatcher_addr, task).await?);
+                return Ok(());
             }
-        });
-    }
 
-    // P2P peer discovery and resource updates
-    // Penemuan peer P2P dan pembaruan resource
-    let p2p_distributor_clone = p2p_distributor.clone();
-    
-    tokio::spawn(async move {
-        if let Err(e) = handle_p2p_peer_updates(
-            p2p_distributor_clone,
-            5,
-        ).await {
-            warn!("[P2P] Peer update error: {}", e);
+            // Mirror the remote task's output and exit code
+            // Tiru output dan kode keluar tugas jarak jauh
+            let result = client::submit_and_wait(dispatcher_addr, task).await?;
+            print!("{}", result.stdout);
+            eprint!("{}", result.stderr);
+            std::process::exit(result.exit_code.unwrap_or(1));
         }
-    });
-
-    // Handle graceful shutdown
-    // Tangani penutupan yang elegan
-    tokio::signal::ctrl_c().await?;
-    info!("[DISPATCHER] Shutting down gracefully...");
-
-    Ok(())
-}
+        octaskly::cmd::Command::Status {
+            dispatcher,
+            task_id,
+            json,
+            api_port,
+        } => {
+            let dispatcher_addr = client::resolve(&dispatcher).await?;
+            let status = client::query_task(dispatcher_addr, &task_id).await?;
+
+            // Finished tasks also carry their output and exit code
+            // Tugas yang selesai juga membawa output dan kode keluarnya
+            let result = match status {
+                TaskStatus::Pending | TaskStatus::Running => None,
+                _ => match api_port {
+                    Some(api_port) => {
+                        let api_base = format!("http://{}:{}", dispatcher_addr.ip(), api_port);
+                        Some(client::fetch_result_from_api(&api_base, &task_id).await?)
+                    }
+                    None => client::query_result(dispatcher_addr, &task_id).await?,
+                },
+            };
+
+            if json {
+                let report = serde_json::json!({
+                    "task_id": task_id,
+                    "status": status,
+                    "result": result,
+                });
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_task_status(&task_id, status, result.as_ref());
+            }
 
-// Handle incoming messages from workers at dispatcher
-// Tangani pesan masuk dari worker di dispatcher
-async fn handle_dispatcher_message(
-    msg: Message,
-    scheduler: &Scheduler,
-    dispatcher_state: &DispatcherState,
-    _active_tasks: &Arc<RwLock<std::collections::HashMap<String, String>>>,
-) -> Result<()> {
-    match msg {
-        // Register worker when it announces itself
-        // Daftarkan worker ketika mengumumkan dirinya
-        Message::WorkerAnnounce(worker_info) => {
-            info!("[DISPATCHER] Worker registered: {} ({}:{})", worker_info.name, worker_info.address, worker_info.port);
-            scheduler.register_worker(worker_info).await;
-        }
-        
-        // Task completion notification from worker
-        // Notifikasi penyelesaian tugas dari worker
-        Message::TaskCompleted(result) => {
-            info!("[DISPATCHER] Task {} completed - status: {:?}", result.task_id, result.status);
-            dispatcher_state.store_result(result.clone()).await;
-            scheduler.worker_job_completed(&result.worker_id).await;
-        }
-        
-        Message::TaskProgress { task_id, progress } => {
-            debug!("[DISPATCHER] Task {} progress: {:.1}%", task_id, progress * 100.0);
-        }
-        
-        // P2P: Resource availability announcement
-        // P2P: Pengumuman ketersediaan resource
-        Message::ResourceAnnounce(resources) => {
-            info!(
-                "[P2P] Resource announced from {}: CPU={}, RAM={}MB, GPU={}, Slots={}",
-                resources.peer_id, resources.cpu_cores, resources.ram_mb, resources.gpu_available, resources.available_slots
-            );
+            // Propagate the remote exit code
+            // Teruskan kode keluar jarak jauh
+            let exit_code = match (status, &result) {
+                (_, Some(result)) => result.exit_code.unwrap_or(1),
+                (TaskStatus::Failed | TaskStatus::Cancelled | TaskStatus::TimedOut, None) => 1,
+                _ => 0,
+            };
+            std::process::exit(exit_code);
         }
-        
-        // P2P: Peer discovery request
-        // P2P: Permintaan penemuan peer
-        Message::PeerDiscoveryRequest { requester_id, timestamp } => {
-            info!(
-                "[P2P] Peer discovery request from {} at {}",
-                requester_id, timestamp
-            );
-        }
-        
-        // P2P: Peer discovery response
-        // P2P: Respons penemuan peer
-        Message::PeerDiscoveryResponse { responder_id, resources } => {
-            info!(
-                "[P2P] Peer discovery response from {}: {}",
-                responder_id, resources.peer_id
-            );
+        octaskly::cmd::Command::Watch {
+            dispatcher,
+            task_id,
+            no_color,
+        } => {
+            let dispatcher_addr = client::resolve(&dispatcher).await?;
+            let result = client::watch_task(dispatcher_addr, &task_id, |line| {
+                print_output_line(line, !no_color)
+            })
+            .await?;
+
+            match result.exit_code {
+                Some(code) => println!("Task {} finished: {:?} (exit code {})", task_id, result.status, code),
+                None => println!("Task {} finished: {:?}", task_id, result.status),
+            }
+            std::process::exit(result.exit_code.unwrap_or(1));
         }
-        
-        // Worker heartbeat for health monitoring
-        // Detak jantung worker untuk pemantauan kesehatan
-        Message::Heartbeat { worker_id, timestamp: _ } => {
-            debug!("[DISPATCHER] Heartbeat received from {}", worker_id);
-            // Update worker last_heartbeat in scheduler
-            // Perbarui last_heartbeat worker di penjadwal
-            let workers = scheduler.get_workers().await;
-            if let Some(mut worker) = workers.iter().find(|w| w.id == worker_id).cloned() {
-                worker.last_heartbeat = chrono::Local::now().timestamp();
-                scheduler.update_worker(&worker_id, worker).await;
+        octaskly::cmd::Command::Workers { dispatcher, json } => {
+            let dispatcher_addr = client::resolve(&dispatcher).await?;
+            let workers = client::list_workers(dispatcher_addr).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&workers)?);
+            } else {
+                print_worker_table(&workers);
             }
         }
-        
+        octaskly::cmd::Command::Cert {
+            action: CertCommand::Issue { name, workdir, out, san },
+        } => {
+            let workdir = workdir.unwrap_or(config.workdir);
+            let ca = CertificateAuthority::load_or_create(&workdir)?;
+            let (cert, key) = ca.issue_to_dir(&name, &san, out.as_deref().unwrap_or(&workdir))?;
+            println!("Certificate: {}", cert.display());
+            println!("Private key: {}", key.display());
+            println!("CA:          {}", ca.cert_path().display());
+        }
         _ => {
-            warn!("Unexpected message type: {:?}", msg);
+            eprintln!("Usage: octaskly <dispatcher | worker | submit | status | watch | workers | cert | config | d | w>");
+            std::process::exit(1);
         }
     }
-    
+
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
     Ok(())
 }
 
-// Worker process initialization and main loop
-// Inisialisasi proses worker dan loop utama
-async fn run_worker(name: &str, allow_shell: bool, max_jobs: usize) -> Result<()> {
-    info!("[WORKER] Starting Worker '{}' with max_jobs={}", name, max_jobs);
+// Human-readable summary for `octaskly status`
+// Ringkasan yang mudah dibaca untuk `octaskly status`
+fn print_task_status(task_id: &str, status: TaskStatus, result: Option<&TaskResult>) {
+    println!("Task:      {}", task_id);
+    println!("Status:    {:?}", status);
 
-    let local_ip = util::get_local_ip().unwrap_or_else(|| "127.0.0.1".to_string());
-    let port = find_available_port(7879).await?;
+    let Some(result) = result else {
+        return;
+    };
+    if !result.worker_id.is_empty() {
+        println!("Worker:    {}", result.worker_id);
+    }
+    match result.exit_code {
+        Some(code) => println!("Exit code: {}", code),
+        None => println!("Exit code: -"),
+    }
+    println!("Duration:  {} ms", result.duration_ms);
 
-    let worker_state = Arc::new(WorkerState::new(name.to_string(), port));
-    let executor = Arc::new(Executor::new(PathBuf::from("./work"), allow_shell));
+    if !result.stdout.is_empty() {
+        println!("\n--- stdout ---\n{}", result.stdout.trim_end());
+    }
+    if !result.stderr.is_empty() {
+        println!("\n--- stderr ---\n{}", result.stderr.trim_end());
+    }
+}
 
-    let worker_info = WorkerInfo::new(
-        name.to_string(),
-        local_ip.clone(),
-        port,
-        max_jobs,
-    );
+// One line of `octaskly watch` output: time, stream tag, text
+// Satu baris output `octaskly watch`: waktu, tag aliran, teks
+fn print_output_line(line: &OutputLine, color: bool) {
+    let time = chrono::DateTime::from_timestamp(line.timestamp, 0)
+        .map(|at| at.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    let tag = if line.is_stderr { "[stderr]" } else { "[stdout]" };
+
+    if !color {
+        println!("{} {} {}", time, tag, line.line);
+    } else if line.is_stderr {
+        println!("{} {} {}", time.dark_grey(), tag.red(), line.line);
+    } else {
+        println!("{} {} {}", time.dark_grey(), tag.green(), line.line);
+    }
+}
 
-    info!(
-        "[WORKER] Registered at {}:{}",
-        local_ip, port
-    );
-    info!("[WORKER] Waiting for dispatcher assignment...");
-
-    // Start listening for incoming connections from dispatcher
-    // Mulai mendengarkan koneksi masuk dari dispatcher
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).await?;
-    info!("[WORKER] Listening on {}", addr);
-
-    let listener = Arc::new(listener);
-    let worker_info_announced = Arc::new(RwLock::new(false));
-    let worker_info_to_announce = worker_info.clone();
-
-    // Spawn connection handler task
-    // Jalankan task penanganan koneksi
-    let listener_clone = listener.clone();
-    let worker_state_clone = worker_state.clone();
-    let executor_clone = executor.clone();
-    let worker_info_announced_clone = worker_info_announced.clone();
-    let worker_info_for_handler = worker_info_to_announce.clone();
-    
-    tokio::spawn(async move {
-        loop {
-            match listener_clone.accept().await {
-                Ok((stream, peer_addr)) => {
-                    debug!("[WORKER] Connection established with dispatcher at {}", peer_addr);
-                    
-                    // Announce worker to dispatcher if not already done
-                    // Umumkan worker ke dispatcher jika belum dilakukan
-                    if !*worker_info_announced_clone.read().await {
-                        *worker_info_announced_clone.write().await = true;
-                        let announce_msg = Message::WorkerAnnounce(worker_info_for_handler.clone());
-                        let announce_addr = peer_addr;
-                        
-                        if let Err(e) = octaskly::transport::Transport::new().send_message(announce_addr, &announce_msg).await {
-                            warn!("Failed to announce worker: {}", e);
-                        }
-                    }
-                    
-                    let worker_state = worker_state_clone.clone();
-                    let executor = executor_clone.clone();
-                    
-                    tokio::spawn(async move {
-                        if let Err(e) = 
-                            octaskly::transport::Transport::handle_connection(
-                                stream,
-                                move |msg| {
-                                    let worker_state = worker_state.clone();
-                                    let executor = executor.clone();
-                                    
-                                    Box::pin(async move {
-                                        handle_worker_message(msg, &worker_state, &executor, peer_addr).await
-                                    })
-                                }
-                            ).await 
-                        {
-                            error!("Worker connection handler error: {}", e);
-                        }
-                    });
-                }
-                Err(e) => {
-                    error!("Accept error: {}", e);
-                }
-            }
-        }
-    });
-
-    // Heartbeat loop - send periodic heartbeats to dispatcher
-    // Loop detak jantung - kirim detak jantung berkala ke dispatcher
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_secs(5));
-        
-        loop {
-            interval.tick().await;
-            debug!("[WORKER] Heartbeat check");
-            // Note: In production, we'd track dispatcher address and send heartbeat
-            // Catatan: Dalam produksi, kami akan melacak alamat dispatcher dan mengirim heartbeat
+// Table of registered workers for `octaskly workers`
+// Tabel worker terdaftar untuk `octaskly workers`
+fn print_worker_table(workers: &[WorkerInfo]) {
+    if workers.is_empty() {
+        println!("No workers registered");
+        return;
+    }
+
+    let now = chrono::Local::now().timestamp();
+    let rows: Vec<[String; 7]> = workers
+        .iter()
+        .map(|w| {
+            [
+                w.name.clone(),
+                format!("{}:{}", w.address, w.port),
+                format!("{}/{}", w.current_jobs, w.max_jobs),
+                if w.tags.is_empty() { "-".to_string() } else { w.tags.join(",") },
+                format!("{}s ago", (now - w.last_heartbeat).max(0)),
+                w.last_rtt_ms.map_or("-".to_string(), |ms| format!("{}ms", ms)),
+                w.platform.clone(),
+            ]
+        })
+        .collect();
+
+    let header = ["NAME", "ADDRESS", "JOBS", "TAGS", "HEARTBEAT", "RTT", "PLATFORM"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
         }
-    });
+    }
 
-    // Keep running
-    // Tetap berjalan
-    tokio::signal::ctrl_c().await?;
-    info!("[WORKER] Shutting down gracefully...");
-    
-    Ok(())
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
 }
 
-// Handle task execution messages on worker
-// Tangani pesan eksekusi tugas di worker
-async fn handle_worker_message(
-    msg: Message,
-    worker_state: &WorkerState,
-    executor: &Executor,
-    dispatcher_addr: SocketAddr,
-) -> Result<()> {
-    match msg {
-        // Execute assigned task from dispatcher
-        // Jalankan tugas yang ditugaskan dari dispatcher
-        Message::AssignTask(task) => {
-            info!("[WORKER] Task received for execution: {}", task.id);
-            
-            let task_id = task.id.clone();
-            worker_state.set_current_task(Some(task.clone())).await;
-            
-            // Execute task with timeout protection
-            // Jalankan tugas dengan perlindungan timeout
-            match executor.execute_with_timeout(&task).await {
-                Ok(result) => {
-                    info!("[WORKER] Task {} execution completed successfully", task_id);
-                    
-                    let task_result = octaskly::protocol::TaskResult {
-                        task_id: task_id.clone(),
-                        worker_id: "unknown".to_string(),
-                        status: result.status,
-                        stdout: result.stdout,
-                        stderr: result.stderr,
-                        exit_code: result.exit_code,
-                        duration_ms: result.duration_ms,
-                        completed_at: chrono::Local::now().timestamp(),
-                    };
-                    
-                    // Send result back to dispatcher
-                    // Kirim hasil kembali ke dispatcher
-                    let result_msg = Message::TaskCompleted(task_result);
-                    if let Err(e) = octaskly::transport::Transport::new().send_message(dispatcher_addr, &result_msg).await {
-                        error!("[WORKER] Failed to send task result: {}", e);
-                    }
-                    
-                    worker_state.set_current_task(None).await;
-                }
-                Err(e) => {
-                    error!("Task execution failed: {}", e);
-                    worker_state.set_current_task(None).await;
-                }
+// Write a default config file, or report what is wrong with an existing one
+// Tulis file konfigurasi bawaan, atau laporkan kesalahan pada file yang ada
+fn run_config_command(action: &ConfigCommand, config_path: Option<PathBuf>) -> Result<()> {
+    match action {
+        ConfigCommand::Init { path, force } => {
+            let path = path.clone().unwrap_or_else(|| PathBuf::from(config::LOCAL_CONFIG_FILE));
+            if path.exists() && !force {
+                anyhow::bail!("{} already exists; pass --force to overwrite it", path.display());
             }
+            std::fs::write(&path, Config::template())?;
+            println!("Wrote {}", path.display());
         }
-        
-        // Task cancellation request
-        // Permintaan pembatalan tugas
-        Message::CancelTask { task_id } => {
-            info!("[WORKER] Cancel request received for task: {}", task_id);
-            worker_state.set_current_task(None).await;
-        }
-        
-        // P2P: Shared task from peer
-        // P2P: Task bersama dari peer
-        Message::P2PShareTask { task, requester_id } => {
-            info!("[P2P] Shared task received from {}: {}", requester_id, task.id);
-            
-            // For now, execute like normal task
-            let task_id = task.id.clone();
-            worker_state.set_current_task(Some(task.clone())).await;
-            
-            match executor.execute_with_timeout(&task).await {
-                Ok(result) => {
-                    info!("[P2P] Shared task {} completed", task_id);
-                    let task_result = octaskly::protocol::TaskResult {
-                        task_id: task_id.clone(),
-                        worker_id: "unknown".to_string(),
-                        status: result.status,
-                        stdout: result.stdout,
-                        stderr: result.stderr,
-                        exit_code: result.exit_code,
-                        duration_ms: result.duration_ms,
-                        completed_at: chrono::Local::now().timestamp(),
-                    };
-                    
-                    // Send result back to requester
-                    let result_msg = Message::TaskCompleted(task_result);
-                    let _ = octaskly::transport::Transport::new().send_message(dispatcher_addr, &result_msg).await;
-                }
+        ConfigCommand::Validate => {
+            let path = config_path
+                .or_else(|| Config::search_paths().into_iter().find(|candidate| candidate.is_file()))
+                .ok_or_else(|| anyhow::anyhow!("No config file found; pass --config <FILE>"))?;
+            let layer = match ConfigLayer::from_file(&path) {
+                Ok(layer) => layer,
                 Err(e) => {
-                    error!("[P2P] Shared task execution failed: {}", e);
+                    eprintln!("❌ {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let problems = Config::default().merged(layer).problems();
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("❌ {}: {}", path.display(), problem);
                 }
+                std::process::exit(1);
             }
-            
-            worker_state.set_current_task(None).await;
-        }
-        
-        // P2P: Peer discovery request
-        // P2P: Permintaan penemuan peer
-        Message::PeerDiscoveryRequest { requester_id, timestamp } => {
-            info!(
-                "[P2P] Discovery request from {} at {}",
-                requester_id, timestamp
-            );
-        }
-        
-        _ => {
-            warn!("Unexpected message type for worker: {:?}", msg);
+            println!("{} is valid", path.display());
         }
     }
-    
     Ok(())
 }
 
-/// Find an available port starting from the given port
-/// Cari port yang tersedia dimulai dari port yang diberikan
-async fn find_available_port(start_port: u16) -> Result<u16> {
-    for port in start_port..(start_port + 100) {
-        let addr = format!("0.0.0.0:{}", port);
-        match TcpListener::bind(&addr).await {
-            Ok(_) => return Ok(port),
-            Err(_) => continue,
-        }
+// Server certificate for an mTLS dispatcher without --tls-cert, issued once into the workdir
+// Sertifikat server untuk dispatcher mTLS tanpa --tls-cert, diterbitkan sekali ke workdir
+fn dispatcher_certificate(ca: &CertificateAuthority, config: &Config) -> Result<(PathBuf, PathBuf)> {
+    let (cert, key) = (config.workdir.join("dispatcher.pem"), config.workdir.join("dispatcher-key.pem"));
+    if cert.exists() && key.exists() {
+        return Ok((cert, key));
     }
-    Err(anyhow::anyhow!("No available port found"))
-}
-
-/// Start P2P peer discovery and management
-/// Mulai penemuan peer P2P dan manajemen
-async fn start_p2p_discovery(
-    peer_id: String,
-    peer_name: String,
-    broadcast_port: u16,
-) -> Result<Arc<octaskly::P2PNetwork>> {
-    let p2p_network = Arc::new(octaskly::P2PNetwork::new(
-        peer_id.clone(),
-        peer_name.clone(),
-        broadcast_port,
-    )?);
-
-    // Start mDNS service discovery
-    p2p_network.start_mdns_discovery().await?;
-
-    // Start discovery listener on broadcast port
-    p2p_network.start_discovery_listener(5555).await?;
-
-    // Start periodic peer discovery announcements
-    p2p_network.start_periodic_discovery(10).await?;
-
-    // Announce this peer to the network
-    p2p_network.announce_peer().await?;
-
-    info!(
-        "[P2P] Peer discovery started for: {} ({})",
-        peer_name, peer_id
-    );
 
-    Ok(p2p_network)
-}
-
-/// Manage P2P task distribution
-/// Kelola distribusi task P2P
-async fn manage_p2p_distribution(
-    distributor: Arc<octaskly::P2PDistributor>,
-    _p2p_network: Arc<octaskly::P2PNetwork>,
-    dispatcher_interval: u64,
-) -> Result<()> {
-    // Periodic task distribution loop
-    let mut ticker = interval(Duration::from_secs(dispatcher_interval));
-
-    loop {
-        ticker.tick().await;
-
-        // Cleanup stale peer information
-        if let Err(e) = distributor.cleanup_stale_peers(30).await {
-            warn!("[P2P] Peer cleanup error: {}", e);
-        }
-
-        // Process pending tasks and distribute to available peers
-        while let Some(task) = distributor.get_next_task().await {
-            // Find best peer for this task
-            let best_peer = distributor.find_best_peer(&task, 1, 256, false).await;
-
-            match best_peer {
-                Some(peer_id) => {
-                    if let Err(e) = distributor.assign_to_peer(task.id.clone(), peer_id.clone()).await {
-                        error!("[P2P] Failed to assign task {} to peer {}: {}", task.id, peer_id, e);
-                        distributor.enqueue_p2p_task(task).await?;
-                    } else {
-                        info!("[P2P] Task {} assigned to peer {}", task.id, peer_id);
-                    }
-                }
-                None => {
-                    warn!("[P2P] No suitable peer found for task {}, re-queueing", task.id);
-                    distributor.enqueue_p2p_task(task).await?;
-                }
-            }
-        }
-
-        // Log distribution stats periodically
-        let pending = distributor.pending_count().await;
-        let active = distributor.assignment_count().await;
-        let peers = distributor.get_all_peers().await;
-
-        debug!(
-            "[P2P] Distribution stats - Pending: {}, Active: {}, Peers: {}",
-            pending, active, peers.len()
-        );
+    let mut hostnames = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Ok(hostname) = hostname::get() {
+        hostnames.push(hostname.to_string_lossy().into_owned());
     }
+    hostnames.extend(util::get_local_ip());
+    if config.bind != "0.0.0.0" {
+        hostnames.push(config.bind.clone());
+    }
+    ca.issue_to_dir("dispatcher", &hostnames, &config.workdir)
 }
 
-/// Handle P2P peer discovery and resource updates
-/// Tangani penemuan peer P2P dan pembaruan resource
-async fn handle_p2p_peer_updates(
-    distributor: Arc<octaskly::P2PDistributor>,
-    discovery_interval: u64,
-) -> Result<()> {
-    let mut ticker = interval(Duration::from_secs(discovery_interval));
-
-    loop {
-        ticker.tick().await;
-
-        // Log current peer status
-        let peers = distributor.get_all_peers().await;
-        for peer in peers {
-            debug!(
-                "[P2P] Peer: {} - CPU: {}, RAM: {}MB, GPU: {}, Slots: {}",
-                peer.peer_id,
-                peer.cpu_cores,
-                peer.ram_mb,
-                peer.gpu_available,
-                peer.available_slots
-            );
-        }
+// Self-signed QUIC certificate for a dispatcher without --tls-cert, kept in the workdir so workers can pin it
+// Sertifikat QUIC self-signed untuk dispatcher tanpa --tls-cert, disimpan di workdir agar worker bisa menyematkannya
+fn quic_certificate(config: &Config) -> Result<(PathBuf, PathBuf)> {
+    let (cert, key) = (config.workdir.join("quic.pem"), config.workdir.join("quic-key.pem"));
+    if cert.exists() && key.exists() {
+        return Ok((cert, key));
     }
+
+    let mut hostnames = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    hostnames.extend(util::get_local_ip());
+    let (cert_pem, key_pem) = tls::generate_self_signed_cert(hostnames)?;
+    std::fs::create_dir_all(&config.workdir)?;
+    std::fs::write(&cert, cert_pem)?;
+    ca::write_private_key(&key, &key_pem)?;
+    Ok((cert, key))
 }
@@ -0,0 +1,270 @@
+use crate::protocol::{TaskResult, TaskStatus, WorkerInfo};
+use anyhow::Result;
+use prometheus::core::Collector;
+use std::collections::HashMap;
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+
+/// Upper bounds of the task duration buckets, in milliseconds
+const DURATION_BUCKETS_MS: &[f64] = &[
+    10.0, 50.0, 100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0, 30_000.0, 60_000.0, 300_000.0,
+];
+
+/// Upper bounds of the task peak memory buckets, in kilobytes
+const RSS_BUCKETS_KB: &[f64] = &[
+    1_024.0, 4_096.0, 16_384.0, 65_536.0, 262_144.0, 1_048_576.0, 4_194_304.0, 16_777_216.0,
+];
+
+/// Outcomes exported even before the first task finishes
+const REPORTED_OUTCOMES: &[TaskStatus] = &[TaskStatus::Completed, TaskStatus::Failed, TaskStatus::TimedOut];
+
+/// Dispatcher counters shared by the Prometheus endpoint and the TUI
+pub struct Metrics {
+    registry: Registry,
+    tasks_total: IntCounterVec,
+    queue_depth: IntGauge,
+    workers_total: IntGauge,
+    workers_idle: IntGauge,
+    worker_rtt_ms: IntGaugeVec,
+    results_retained: IntGaugeVec,
+    task_duration_ms: Histogram,
+    task_rss_kb: Histogram,
+    websocket_connections: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let tasks_total = IntCounterVec::new(
+            Opts::new("octaskly_tasks_total", "Finished tasks by final status"),
+            &["status"],
+        )
+        .expect("valid tasks_total metric");
+        let queue_depth = IntGauge::new("octaskly_queue_depth", "Tasks waiting for a worker").expect("valid queue_depth metric");
+        let workers_total = IntGauge::new("octaskly_workers_total", "Registered workers").expect("valid workers_total metric");
+        let workers_idle =
+            IntGauge::new("octaskly_workers_idle", "Registered workers with a free job slot").expect("valid workers_idle metric");
+        let worker_rtt_ms = IntGaugeVec::new(
+            Opts::new("octaskly_worker_rtt_ms", "Milliseconds the worker's last heartbeat took to arrive"),
+            &["worker_id"],
+        )
+        .expect("valid worker_rtt_ms metric");
+        let results_retained = IntGaugeVec::new(
+            Opts::new("octaskly_results_retained", "Finished task results held in dispatcher memory by status"),
+            &["status"],
+        )
+        .expect("valid results_retained metric");
+        let task_duration_ms = Histogram::with_opts(
+            HistogramOpts::new("octaskly_task_duration_ms", "Task execution time in milliseconds")
+                .buckets(DURATION_BUCKETS_MS.to_vec()),
+        )
+        .expect("valid task_duration_ms metric");
+        let task_rss_kb = Histogram::with_opts(
+            HistogramOpts::new("octaskly_task_rss_kb", "Peak resident memory of task processes in kilobytes")
+                .buckets(RSS_BUCKETS_KB.to_vec()),
+        )
+        .expect("valid task_rss_kb metric");
+        let websocket_connections = IntGauge::new("octaskly_websocket_connections", "Open WebSocket event streams")
+            .expect("valid websocket_connections metric");
+
+        let registry = Registry::new();
+        registry.register(Box::new(tasks_total.clone())).expect("unique tasks_total");
+        registry.register(Box::new(queue_depth.clone())).expect("unique queue_depth");
+        registry.register(Box::new(workers_total.clone())).expect("unique workers_total");
+        registry.register(Box::new(workers_idle.clone())).expect("unique workers_idle");
+        registry.register(Box::new(worker_rtt_ms.clone())).expect("unique worker_rtt_ms");
+        registry.register(Box::new(results_retained.clone())).expect("unique results_retained");
+        registry.register(Box::new(task_duration_ms.clone())).expect("unique task_duration_ms");
+        registry.register(Box::new(task_rss_kb.clone())).expect("unique task_rss_kb");
+        registry
+            .register(Box::new(websocket_connections.clone()))
+            .expect("unique websocket_connections");
+
+        for status in REPORTED_OUTCOMES {
+            tasks_total.with_label_values(&[status_label(*status)]);
+        }
+
+        Self {
+            registry,
+            tasks_total,
+            queue_depth,
+            workers_total,
+            workers_idle,
+            worker_rtt_ms,
+            results_retained,
+            task_duration_ms,
+            task_rss_kb,
+            websocket_connections,
+        }
+    }
+
+    /// Count a task's final result, its duration and, when measured, its peak memory
+    pub fn record_result(&self, result: &TaskResult) {
+        self.tasks_total.with_label_values(&[status_label(result.status)]).inc();
+        self.task_duration_ms.observe(result.duration_ms as f64);
+        if let Some(usage) = result.resource_usage {
+            self.task_rss_kb.observe(usage.max_rss_kb as f64);
+        }
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    /// Worker counts, plus the heartbeat delay of every worker that has sent one
+    pub fn set_workers(&self, workers: &[WorkerInfo]) {
+        self.workers_total.set(workers.len() as i64);
+        self.workers_idle.set(workers.iter().filter(|w| w.is_idle()).count() as i64);
+        self.worker_rtt_ms.reset();
+        for worker in workers {
+            if let Some(rtt_ms) = worker.last_rtt_ms {
+                self.worker_rtt_ms.with_label_values(&[&worker.id]).set(rtt_ms as i64);
+            }
+        }
+    }
+
+    /// Results currently held in memory, from `DispatcherState::get_results_count_by_status`
+    pub fn set_results_retained(&self, counts: &HashMap<TaskStatus, usize>) {
+        self.results_retained.reset();
+        for (status, count) in counts {
+            self.results_retained.with_label_values(&[status_label(*status)]).set(*count as i64);
+        }
+    }
+
+    pub fn websocket_opened(&self) {
+        self.websocket_connections.inc();
+    }
+
+    pub fn websocket_closed(&self) {
+        self.websocket_connections.dec();
+    }
+
+    pub fn websocket_connections(&self) -> usize {
+        self.websocket_connections.get().max(0) as usize
+    }
+
+    /// Finished tasks recorded with `status`
+    pub fn tasks_total(&self, status: TaskStatus) -> u64 {
+        self.tasks_total.with_label_values(&[status_label(status)]).get()
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.get().max(0) as usize
+    }
+
+    /// Estimate a duration quantile (0.0-1.0) from the histogram buckets, like PromQL's
+    /// `histogram_quantile`; `None` until a task has finished
+    pub fn duration_quantile(&self, quantile: f64) -> Option<f64> {
+        let count = self.task_duration_ms.get_sample_count();
+        if count == 0 {
+            return None;
+        }
+
+        let families = self.task_duration_ms.collect();
+        let buckets = families.first()?.get_metric().first()?.get_histogram().get_bucket();
+        let rank = quantile.clamp(0.0, 1.0) * count as f64;
+
+        let (mut lower_bound, mut lower_count) = (0.0, 0u64);
+        for bucket in buckets {
+            let (upper_bound, upper_count) = (bucket.get_upper_bound(), bucket.get_cumulative_count());
+            if upper_count as f64 >= rank && upper_count > lower_count {
+                let fraction = (rank - lower_count as f64) / (upper_count - lower_count) as f64;
+                return Some(lower_bound + (upper_bound - lower_bound) * fraction);
+            }
+            lower_bound = upper_bound;
+            lower_count = upper_count;
+        }
+
+        // Above the largest bucket: report its upper bound
+        Some(lower_bound)
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Value of the `status` label for a task outcome
+fn status_label(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::Running => "running",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed => "failed",
+        TaskStatus::Cancelled => "cancelled",
+        TaskStatus::TimedOut => "timed_out",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: TaskStatus, duration_ms: u64) -> TaskResult {
+        TaskResult {
+            task_id: "task-1".to_string(),
+            worker_id: "worker-1".to_string(),
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: None,
+            duration_ms,
+            completed_at: 0,
+            resource_usage: None,
+        }
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let metrics = Metrics::new();
+        metrics.record_result(&result(TaskStatus::Completed, 40));
+        metrics.record_result(&result(TaskStatus::TimedOut, 300_000));
+        metrics.set_queue_depth(3);
+        let mut worker = WorkerInfo::new("worker-1".to_string(), "127.0.0.1".to_string(), 7879, 4);
+        worker.id = "worker-1".to_string();
+        worker.last_rtt_ms = Some(12);
+        metrics.set_workers(&[worker]);
+
+        let text = metrics.render().unwrap();
+        assert!(text.contains("octaskly_tasks_total{status=\"completed\"} 1"));
+        assert!(text.contains("octaskly_tasks_total{status=\"failed\"} 0"));
+        assert!(text.contains("octaskly_tasks_total{status=\"timed_out\"} 1"));
+        assert!(text.contains("octaskly_queue_depth 3"));
+        assert!(text.contains("octaskly_workers_total 1"));
+        assert!(text.contains("octaskly_workers_idle 1"));
+        assert!(text.contains("octaskly_worker_rtt_ms{worker_id=\"worker-1\"} 12"));
+        assert!(text.contains("octaskly_task_duration_ms_count 2"));
+        assert!(text.contains("octaskly_task_rss_kb_count 0"));
+
+        metrics.set_results_retained(&HashMap::from([(TaskStatus::Failed, 2)]));
+        let text = metrics.render().unwrap();
+        assert!(text.contains("octaskly_results_retained{status=\"failed\"} 2"));
+    }
+
+    #[test]
+    fn test_duration_quantile_from_buckets() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.duration_quantile(0.5), None);
+
+        // 90 fast tasks in the (50, 100] bucket, 10 slow ones in (1000, 2500]
+        for _ in 0..90 {
+            metrics.record_result(&result(TaskStatus::Completed, 80));
+        }
+        for _ in 0..10 {
+            metrics.record_result(&result(TaskStatus::Completed, 2_000));
+        }
+
+        let p50 = metrics.duration_quantile(0.5).unwrap();
+        assert!((50.0..=100.0).contains(&p50), "p50 = {}", p50);
+        let p99 = metrics.duration_quantile(0.99).unwrap();
+        assert!((1_000.0..=2_500.0).contains(&p99), "p99 = {}", p99);
+        assert_eq!(metrics.tasks_total(TaskStatus::Completed), 100);
+    }
+}
@@ -0,0 +1,1966 @@
+// Dispatcher and worker runtimes started by the CLI, or in-process by tests
+// Runtime dispatcher dan worker yang dijalankan oleh CLI, atau di dalam proses oleh pengujian
+
+use anyhow::Result;
+use crate::api::{self, ApiState};
+use crate::auth::AuthManager;
+use crate::config::Config;
+use crate::discovery::mdns::MdnsAdvertiser;
+use crate::scheduler::{Scheduler, SchedulingWeights, WorkerMetrics, QUEUE_AGING_INTERVAL, SCHEDULED_TASK_TICK};
+use crate::state::events::{DispatcherEvent, TaskEvent};
+use crate::state::{DispatcherState, OutputUpdate, WorkerState, FILES_DIR};
+use crate::executor::{CommandPolicy, DockerExecutor, Executor, ExecutorEvent, ExecutorKind, PROGRESS_CHANNEL_CAPACITY};
+use crate::persistence::{self, AuditEvent};
+use crate::protocol::{Message, TaskResult, TaskStatus, WorkerInfo};
+use crate::transport::{tls, MessageHandler, MessageStream, Transport, TransportBackend, TransportKind, DEFAULT_MAX_MESSAGE_BYTES};
+use crate::telemetry;
+use crate::util;
+use crate::util::load::LoadSampler;
+use crate::webhook::WebhookNotifier;
+use std::sync::Arc;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tokio::time::{Duration, interval};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn, debug, Instrument};
+
+// Completed task records older than this are purged from the history database
+// Catatan tugas yang lebih lama dari ini dihapus dari basis data riwayat
+const TASK_HISTORY_RETENTION_DAYS: i64 = 30;
+
+// Bounds of the exponential backoff between rounds of dispatcher reconnection attempts
+// Batas backoff eksponensial di antara putaran percobaan koneksi ulang ke dispatcher
+const RECONNECT_BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+// How long a worker waits for the new dispatcher to accept an interrupted task
+// Berapa lama worker menunggu dispatcher baru menerima tugas yang terputus
+const RESUBMIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often the dispatcher drops in-memory results older than result_ttl_secs
+// Seberapa sering dispatcher membuang hasil di memori yang lebih lama dari result_ttl_secs
+const RESULT_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// How often the dispatcher forgets the IDs of expired API tokens
+// Seberapa sering dispatcher melupakan ID token API yang sudah kedaluwarsa
+const TOKEN_PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Run a dispatcher until its listener fails; worker traffic arrives on `transport`
+/// Jalankan dispatcher sampai listener-nya gagal; lalu lintas worker tiba lewat `transport`
+pub async fn run_dispatcher(
+    config: &Config,
+    tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+    transport: Arc<dyn TransportBackend>,
+) -> Result<()> {
+    let (bind, port, workdir) = (config.bind.as_str(), config.port, &config.workdir);
+
+    // Initialize dispatcher with state management
+    // Inisialisasi dispatcher dengan manajemen status
+    info!("[DISPATCHER] Starting Octaskly Dispatcher on {}:{}", bind, port);
+
+    // Create work directory if not exists
+    // Buat direktori kerja jika belum ada
+    util::ensure_dir(workdir).await?;
+
+    // Open task history database (SQLite or PostgreSQL) used for the audit log
+    // Buka basis data riwayat tugas (SQLite atau PostgreSQL) yang digunakan untuk log audit
+    let db_url = config
+        .db_url
+        .clone()
+        .unwrap_or_else(|| workdir.join("octaskly.db").to_string_lossy().into_owned());
+    let store = match persistence::open(&db_url).await {
+        Ok(store) => Some(store),
+        Err(e) => {
+            warn!("[DISPATCHER] Task history database unavailable: {}", e);
+            None
+        }
+    };
+
+    // Periodic online backups of the history database, oldest pruned past the retention count
+    // Cadangan daring berkala basis data riwayat, yang terlama dihapus melewati batas retensi
+    if let (Some(store), Some(backup_path)) = (&store, &config.backup_path) {
+        if config.backup_interval_hours == 0 {
+            warn!("[DISPATCHER] backup_interval_hours must be above zero; backups disabled");
+        } else {
+            info!(
+                "[DISPATCHER] Backing up task history to {} every {}h (keeping {})",
+                backup_path.display(),
+                config.backup_interval_hours,
+                config.backup_retention
+            );
+            persistence::backup::spawn_periodic_backups(
+                store.clone(),
+                backup_path.clone(),
+                Duration::from_secs(config.backup_interval_hours * 3600),
+                config.backup_retention,
+            );
+        }
+    }
+
+    // Queued tasks are saved to the database and reloaded before the first scheduling tick
+    // Tugas dalam antrian disimpan ke basis data dan dimuat ulang sebelum tick penjadwalan pertama
+    let mut scheduler = Scheduler::new(config.scheduler_algorithm)
+        .with_slow_heartbeat_ms(config.slow_heartbeat_ms)
+        .with_starvation_threshold_secs(config.starvation_threshold_secs)
+        .with_scheduling_weights(SchedulingWeights {
+            cpu: config.scheduling_weight_cpu,
+            latency: config.scheduling_weight_latency,
+            reliability: config.scheduling_weight_reliability,
+        });
+    if let Some(store) = &store {
+        if let Err(e) = scheduler.restore_queue(store.as_ref()).await {
+            warn!("[DISPATCHER] Could not restore task queue: {}", e);
+        }
+        if let Err(e) = scheduler.restore_dead_letters(store.as_ref()).await {
+            warn!("[DISPATCHER] Could not restore dead letter queue: {}", e);
+        }
+        scheduler = scheduler.with_store(store.clone());
+    }
+    let scheduler = Arc::new(scheduler);
+    let mut dispatcher_state =
+        DispatcherState::new("dispatcher".to_string(), port)
+            .with_scheduler(scheduler.clone())
+            .with_files_dir(workdir.join(FILES_DIR));
+    if let Some(store) = &store {
+        dispatcher_state = dispatcher_state.with_store(store.clone());
+    }
+    match WebhookNotifier::new(Duration::from_secs(config.webhook_timeout_secs)) {
+        Ok(webhooks) => dispatcher_state = dispatcher_state.with_webhooks(webhooks),
+        Err(e) => warn!("[DISPATCHER] Task callbacks disabled: {}", e),
+    }
+    if let Some(psk) = &config.psk {
+        info!("[DISPATCHER] Workers must present the pre-shared key to register");
+        dispatcher_state = dispatcher_state.with_psk(psk);
+    }
+    let dispatcher_state = Arc::new(dispatcher_state);
+
+    // Initialize P2P peer discovery and task distribution
+    // Inisialisasi penemuan peer P2P dan distribusi task
+    let p2p_network = match start_p2p_discovery(
+        format!("dispatcher-{}", port),
+        "Octaskly-Dispatcher".to_string(),
+        5555,
+    ).await {
+        Ok(network) => {
+            info!("[P2P] P2P network initialized successfully");
+            Some(network)
+        }
+        Err(e) => {
+            warn!("[P2P] P2P initialization warning: {}", e);
+            None
+        }
+    };
+
+    // Initialize P2P task distributor for resource sharing
+    // Inisialisasi distributor task P2P untuk berbagi resource
+    // Default: 4 CPU cores, 8GB RAM, no GPU, 4 task slots
+    // Default: 4 core CPU, 8GB RAM, tidak ada GPU, 4 slot task
+    let p2p_distributor = Arc::new(crate::P2PDistributor::new(
+        format!("dispatcher-{}", port),
+        4,           // CPU cores (default estimate)
+        8192,        // RAM in MB (8GB default)
+        false,       // GPU available
+        4,           // Task slots
+    ));
+
+    // Create network listener on specified address and port
+    // Buat listener jaringan pada alamat dan port yang ditentukan
+    let addr = format!("{}:{}", bind, port);
+    let listener = TcpListener::bind(&addr).await?;
+    info!("[DISPATCHER] Listening on {}", addr);
+
+    let listener = Arc::new(listener);
+
+    // Let workers started with --mdns find this dispatcher without an address
+    // Biarkan worker dengan --mdns menemukan dispatcher ini tanpa alamat
+    if config.mdns {
+        let ip = bind
+            .parse::<std::net::Ipv4Addr>()
+            .ok()
+            .filter(|ip| !ip.is_unspecified())
+            .or_else(|| util::get_local_ip().and_then(|ip| ip.parse().ok()))
+            .unwrap_or(std::net::Ipv4Addr::LOCALHOST);
+        if let Err(e) = MdnsAdvertiser::new(ip, port).start(scheduler.clone()) {
+            warn!("[DISPATCHER] mDNS advertisement unavailable: {}", e);
+        }
+    }
+
+    info!("[DISPATCHER] Ready. Waiting for worker connections...");
+
+    // Spawn task to handle incoming connections from workers
+    // Jalankan task untuk menangani koneksi masuk dari worker
+    let listener_clone = listener.clone();
+    let scheduler_clone = scheduler.clone();
+    let dispatcher_state_clone = dispatcher_state.clone();
+    let mtls = config.mtls;
+    let read_timeout = config.read_timeout();
+    
+    tokio::spawn(async move {
+        loop {
+            match listener_clone.accept().await {
+                Ok((stream, peer_addr)) => {
+                    debug!("[DISPATCHER] Accepted connection from {}", peer_addr);
+                    
+                    let scheduler = scheduler_clone.clone();
+                    let dispatcher_state = dispatcher_state_clone.clone();
+                    let tls_acceptor = tls_acceptor.clone();
+                    
+                    tokio::spawn(async move {
+                        // Complete the TLS handshake before reading the first message
+                        // Selesaikan handshake TLS sebelum membaca pesan pertama
+                        let (stream, peer_name): (Box<dyn MessageStream>, _) = match tls_acceptor {
+                            Some(acceptor) => match acceptor.accept(stream).await {
+                                Ok(tls_stream) => {
+                                    let peer_name = tls::peer_common_name(&tls_stream);
+                                    (Box::new(tls_stream), peer_name)
+                                }
+                                Err(e) => {
+                                    warn!("[DISPATCHER] TLS handshake with {} failed: {}", peer_addr, e);
+                                    return;
+                                }
+                            },
+                            None => (Box::new(stream), None),
+                        };
+                        if mtls && peer_name.is_none() {
+                            warn!("[DISPATCHER] Client certificate from {} has no common name", peer_addr);
+                            return;
+                        }
+
+                        // Gaps in a connection's sequence numbers go to the audit log
+                        // Celah pada nomor urut koneksi dicatat ke log audit
+                        let audit_state = dispatcher_state.clone();
+                        let on_out_of_order = move |expected, received| {
+                            let audit_state = audit_state.clone();
+                            tokio::spawn(async move {
+                                audit_state
+                                    .record_event(AuditEvent::MessageOutOfOrder {
+                                        peer: peer_addr.to_string(),
+                                        expected,
+                                        received,
+                                    })
+                                    .await
+                            });
+                        };
+
+                        if let Err(e) = 
+                            Transport::handle_stream(
+                                stream,
+                                peer_addr,
+                                None,
+                                read_timeout,
+                                DEFAULT_MAX_MESSAGE_BYTES,
+                                Some(&on_out_of_order),
+                                move |msg| {
+                                    let scheduler = scheduler.clone();
+                                    let dispatcher_state = dispatcher_state.clone();
+                                    let peer_name = peer_name.clone();
+                                    
+                                    Box::pin(async move {
+                                        check_certificate_name(peer_name.as_deref(), &msg)?;
+                                        handle_dispatcher_message(msg, &scheduler, &dispatcher_state).await
+                                    })
+                                }
+                            ).await 
+                        {
+                            error!("Connection handler error: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    error!("Accept error: {}", e);
+                }
+            }
+        }
+    });
+
+    // Over QUIC, workers reach the dispatcher on the same port number over UDP
+    // Dengan QUIC, worker menghubungi dispatcher pada nomor port yang sama lewat UDP
+    if config.transport == TransportKind::Quic {
+        let scheduler = scheduler.clone();
+        let dispatcher_state = dispatcher_state.clone();
+        let handler: MessageHandler = Arc::new(move |msg, _peer_addr| {
+            let scheduler = scheduler.clone();
+            let dispatcher_state = dispatcher_state.clone();
+            Box::pin(async move { handle_dispatcher_message(msg, &scheduler, &dispatcher_state).await })
+        });
+        let quic_transport = transport.clone();
+        tokio::spawn(async move {
+            if let Err(e) = quic_transport.serve(handler).await {
+                error!("[DISPATCHER] QUIC listener stopped: {}", e);
+            }
+        });
+        info!("[DISPATCHER] Accepting worker traffic over QUIC on {}:{}", bind, port);
+    }
+
+    // Scheduler loop - assign tasks to idle workers
+    // Loop penjadwal - tugaskan tugas ke worker yang menganggur
+    let scheduler_clone = scheduler.clone();
+    let dispatcher_state_clone = dispatcher_state.clone();
+    let transport_clone = transport.clone();
+    
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_millis(500));
+        let mut last_queue_depth = 0;
+        
+        loop {
+            // A newly registered worker triggers a pass without waiting for the tick
+            // Worker yang baru terdaftar memicu penjadwalan tanpa menunggu tick
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = scheduler_clone.rebalance_requested() => {}
+            }
+
+            // Report queue depth changes to WebSocket subscribers
+            // Laporkan perubahan kedalaman antrian ke pelanggan WebSocket
+            let queue_depth = scheduler_clone.queue_size().await;
+            if queue_depth != last_queue_depth {
+                last_queue_depth = queue_depth;
+                dispatcher_state_clone.publish_event(DispatcherEvent::QueueDepth { depth: queue_depth });
+            }
+            
+            // Skip empty ticks so an idle dispatcher does not emit scheduling spans
+            // Lewati tick kosong agar dispatcher yang menganggur tidak menghasilkan span penjadwalan
+            if queue_depth == 0 {
+                continue;
+            }
+
+            if let Some((task, worker)) = scheduler_clone.schedule_next_task().await {
+                // Worker execution spans join this span through the assignment's trace context
+                // Span eksekusi worker bergabung ke span ini melalui konteks jejak penugasan
+                let span = tracing::info_span!(
+                    "assign_tasks",
+                    task.id = %task.id,
+                    worker.id = %worker.id,
+                    task.command = %task.command
+                );
+                async {
+                    // Fill the worker's remaining slots so they travel in the same round-trip
+                    // Isi slot worker yang tersisa agar dikirim dalam satu perjalanan jaringan
+                    let mut tasks = vec![task];
+                    tasks.extend(scheduler_clone.schedule_batch(&worker).await);
+                    debug!("[SCHEDULER] Assigning {} task(s) to worker {}", tasks.len(), worker.id);
+
+                    // Input files travel inside the assignment, so the worker needs no access to the dispatcher's disk
+                    // File input ikut dalam penugasan, sehingga worker tidak perlu akses ke disk dispatcher
+                    for task in &mut tasks {
+                        dispatcher_state_clone.stage_input_files(task).await;
+                    }
+
+                    // Mark tasks as assigned
+                    for task in &tasks {
+                        dispatcher_state_clone.track_dispatched(task.clone(), &worker.id).await;
+                        dispatcher_state_clone
+                            .record_event(AuditEvent::TaskAssigned {
+                                task_id: task.id.clone(),
+                                worker_id: worker.id.clone(),
+                            })
+                            .await;
+                        dispatcher_state_clone.publish_event(DispatcherEvent::TaskStatus {
+                            task_id: task.id.clone(),
+                            status: TaskStatus::Running,
+                            worker_id: Some(worker.id.clone()),
+                        });
+                    }
+
+                    // Try to send tasks to worker
+                    let worker_addr = format!("{}:{}", worker.address, worker.port);
+                    if let Ok(socket_addr) = worker_addr.parse::<SocketAddr>() {
+                        // Connections are kept per worker, so a worker back at a new address gets a fresh one
+                        // Koneksi disimpan per worker, sehingga worker yang kembali di alamat baru mendapat koneksi baru
+                        transport_clone.bind_worker(&worker.id, socket_addr);
+                        let message = if tasks.len() == 1 {
+                            Message::AssignTask {
+                                task: tasks[0].clone(),
+                                trace_context: telemetry::current_context(),
+                            }
+                        } else {
+                            Message::BatchAssign(tasks.clone())
+                        };
+
+                        // Deliver in the background so several assignments can be in flight at once;
+                        // over QUIC each one travels on its own stream of the worker's connection
+                        // Kirim di latar belakang agar beberapa penugasan bisa berjalan bersamaan;
+                        // lewat QUIC masing-masing memakai stream sendiri pada koneksi worker
+                        let (transport, state, scheduler) =
+                            (transport_clone.clone(), dispatcher_state_clone.clone(), scheduler_clone.clone());
+                        tokio::spawn(
+                            async move {
+                                if let Err(e) = transport.send_message_with_ack(socket_addr, &message).await {
+                                    warn!("Failed to send task to worker {}: {}", worker.id, e);
+                                    // Requeue tasks and release their slots
+                                    for task in tasks {
+                                        state.take_dispatched(&task.id).await;
+                                        scheduler.worker_job_completed(&worker.id).await;
+                                        scheduler.enqueue(task).await;
+                                    }
+                                }
+                            }
+                            .in_current_span(),
+                        );
+                    }
+                }
+                .instrument(span)
+                .await;
+            }
+        }
+    });
+
+    // Scheduled task loop - release delayed and recurring tasks once they are due
+    // Loop tugas terjadwal - lepaskan tugas tertunda dan berulang setelah jatuh tempo
+    let scheduler_clone = scheduler.clone();
+
+    tokio::spawn(async move {
+        let mut interval = interval(SCHEDULED_TASK_TICK);
+
+        loop {
+            interval.tick().await;
+            if scheduler_clone.release_due_tasks().await > 0 {
+                scheduler_clone.rebalance_hint().await;
+            }
+        }
+    });
+
+    // Heartbeat cleanup loop - remove offline workers
+    // Loop pembersihan detak jantung - hapus worker yang offline
+    let scheduler_clone = scheduler.clone();
+    let dispatcher_state_clone = dispatcher_state.clone();
+    let transport_clone = transport.clone();
+    let worker_timeout_secs = config.worker_timeout_secs as i64;
+    
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(10));
+        
+        loop {
+            interval.tick().await;
+            for worker in scheduler_clone.cleanup_offline_workers(worker_timeout_secs).await {
+                transport_clone.forget_worker(&worker.id);
+                dispatcher_state_clone
+                    .record_event(AuditEvent::WorkerOffline { worker_id: worker.id.clone() })
+                    .await;
+                dispatcher_state_clone.publish_event(DispatcherEvent::WorkerDisconnected { worker_id: worker.id });
+            }
+        }
+    });
+
+    // Queue aging loop - tasks waiting past starvation_threshold_secs move up in priority
+    // Loop penuaan antrian - tugas yang menunggu melewati starvation_threshold_secs naik prioritasnya
+    let scheduler_clone = scheduler.clone();
+
+    tokio::spawn(async move {
+        let mut interval = interval(QUEUE_AGING_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            scheduler_clone.age_queue().await;
+        }
+    });
+
+    // Result expiry loop - keep finished results in memory only for result_ttl_secs
+    // Loop kedaluwarsa hasil - simpan hasil yang selesai di memori hanya selama result_ttl_secs
+    let dispatcher_state_clone = dispatcher_state.clone();
+    let result_ttl_secs = config.result_ttl_secs;
+
+    tokio::spawn(async move {
+        let mut interval = interval(RESULT_EXPIRY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            dispatcher_state_clone.expire_old_results(result_ttl_secs).await;
+        }
+    });
+
+    // REST API over the same scheduler, state and history store
+    // REST API di atas penjadwal, status, dan penyimpanan riwayat yang sama
+    match (config.api_port, &store) {
+        (Some(api_port), Some(store)) => {
+            // Without a configured secret, tokens are only valid for the lifetime of this process
+            // Tanpa secret yang dikonfigurasi, token hanya berlaku selama proses ini berjalan
+            let mut auth = AuthManager::new(
+                config.api_secret.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            );
+            if let Some(password) = &config.api_admin_password {
+                auth = auth.with_admin_password(password);
+            }
+            let auth = Arc::new(auth);
+
+            // Token pruning loop - revoked and issued token IDs are only kept until they expire
+            // Loop pemangkasan token - ID token yang dicabut dan diterbitkan hanya disimpan sampai kedaluwarsa
+            let auth_clone = auth.clone();
+            tokio::spawn(async move {
+                let mut interval = interval(TOKEN_PRUNE_INTERVAL);
+
+                loop {
+                    interval.tick().await;
+                    auth_clone.prune_expired();
+                }
+            });
+            // API keys are kept in the history database, so they survive a restart
+            // Kunci API disimpan di database riwayat, sehingga bertahan setelah restart
+            match store.load_api_keys().await {
+                Ok(records) => auth.restore_api_keys(records),
+                Err(e) => warn!("[DISPATCHER] Failed to load API keys: {}", e),
+            }
+            let api_state = ApiState {
+                scheduler: scheduler.clone(),
+                dispatcher: dispatcher_state.clone(),
+                auth,
+                store: store.clone(),
+                metrics: dispatcher_state.metrics().clone(),
+                task_events: dispatcher_state.task_events().clone(),
+                max_batch_size: config.max_batch_size,
+                rate_limiter: api::RateLimiter::new(config.rate_limit_burst, config.rate_limit_per_sec),
+                transport: transport.clone(),
+            };
+            let api_addr = format!("{}:{}", bind, api_port);
+            tokio::spawn(async move {
+                if let Err(e) = api::start_api_server(&api_addr, api_state).await {
+                    error!("[DISPATCHER] REST API stopped: {}", e);
+                }
+            });
+        }
+        (Some(_), None) => warn!("[DISPATCHER] REST API disabled: task history database unavailable"),
+        (None, _) => {}
+    }
+
+    // History cleanup loop - drop task records older than the retention window
+    // Loop pembersihan riwayat - hapus catatan tugas yang melewati masa simpan
+    if let Some(store) = store {
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(3600));
+
+            loop {
+                interval.tick().await;
+                match store.cleanup_old_tasks(TASK_HISTORY_RETENTION_DAYS).await {
+                    Ok(0) => {}
+                    Ok(removed) => info!("[DISPATCHER] Removed {} old task records", removed),
+                    Err(e) => warn!("[DISPATCHER] Task history cleanup failed: {}", e),
+                }
+            }
+        });
+    }
+
+    // P2P task distribution management loop
+    // Loop manajemen distribusi task P2P
+    if let Some(_p2p_net) = p2p_network.clone() {
+        let p2p_distributor_clone = p2p_distributor.clone();
+        let p2p_network_clone = p2p_network.clone();
+        
+        tokio::spawn(async move {
+            if let Some(p2p_net) = p2p_network_clone {
+                if let Err(e) = manage_p2p_distribution(
+                    p2p_distributor_clone,
+                    p2p_net,
+                    1,
+                ).await {
+                    error!("[P2P] Task distribution error: {}", e);
+                }
+            }
+        });
+    }
+
+    // P2P peer discovery and resource updates
+    // Penemuan peer P2P dan pembaruan resource
+    let p2p_distributor_clone = p2p_distributor.clone();
+    
+    tokio::spawn(async move {
+        if let Err(e) = handle_p2p_peer_updates(
+            p2p_distributor_clone,
+            5,
+        ).await {
+            warn!("[P2P] Peer update error: {}", e);
+        }
+    });
+
+    // Handle graceful shutdown
+    // Tangani penutupan yang elegan
+    tokio::signal::ctrl_c().await?;
+    info!("[DISPATCHER] Shutting down gracefully...");
+
+    Ok(())
+}
+
+// Handle incoming messages from workers at dispatcher; returns a reply for queries
+// Tangani pesan masuk dari worker di dispatcher; mengembalikan balasan untuk kueri
+async fn handle_dispatcher_message(
+    msg: Message,
+    scheduler: &Arc<Scheduler>,
+    dispatcher_state: &DispatcherState,
+) -> Result<Option<Message>> {
+    match msg {
+        // Register worker when it announces itself
+        // Daftarkan worker ketika mengumumkan dirinya
+        Message::WorkerAnnounce(mut worker_info) => {
+            // Drop workers without the configured pre-shared key before they are registered
+            // Tolak worker tanpa kunci pra-bagi yang dikonfigurasi sebelum didaftarkan
+            if let Err(e) = check_psk(dispatcher_state, &worker_info.id, worker_info.psk_hash.as_deref()).await {
+                warn!("[DISPATCHER] Rejected worker {} ({}:{}): {}", worker_info.name, worker_info.address, worker_info.port, e);
+                return Err(e);
+            }
+            worker_info.psk_hash = None;
+            info!("[DISPATCHER] Worker registered: {} ({}:{})", worker_info.name, worker_info.address, worker_info.port);
+            dispatcher_state.publish_event(DispatcherEvent::WorkerConnected {
+                worker_id: worker_info.id.clone(),
+                name: worker_info.name.clone(),
+            });
+            dispatcher_state.persist_worker(&worker_info).await;
+            dispatcher_state
+                .record_event(AuditEvent::WorkerRegistered {
+                    worker_id: worker_info.id.clone(),
+                    address: format!("{}:{}", worker_info.address, worker_info.port),
+                })
+                .await;
+            scheduler.register_worker(worker_info).await;
+            scheduler.rebalance_hint().await;
+        }
+        
+        // Task completion notification from worker
+        // Notifikasi penyelesaian tugas dari worker
+        Message::TaskCompleted(mut result) => {
+            info!("[DISPATCHER] Task {} completed - status: {:?}", result.task_id, result.status);
+
+            // A removed worker, or one the task was taken from, must not overwrite the task's new run
+            // Worker yang dihapus, atau yang tugasnya sudah diambil, tidak boleh menimpa jalannya tugas yang baru
+            let worker_id = dispatcher_state.dispatched_worker(&result.task_id).await;
+            let registered = scheduler.get_worker(&result.worker_id).await.is_some();
+            if !registered || worker_id.as_ref().is_some_and(|id| *id != result.worker_id) {
+                warn!(
+                    "[DISPATCHER] Ignoring result of task {} from worker {}, which no longer runs it",
+                    result.task_id, result.worker_id
+                );
+                return Ok(None);
+            }
+
+            // Free the slot and count the outcome on the worker the task was dispatched to
+            // Bebaskan slot dan hitung hasilnya pada worker tempat tugas dikirim
+            if let Some(worker_id) = &worker_id {
+                scheduler.worker_job_completed(worker_id).await;
+                match result.status {
+                    TaskStatus::Completed => scheduler.record_task_outcome(worker_id, true).await,
+                    TaskStatus::Failed | TaskStatus::TimedOut => scheduler.record_task_outcome(worker_id, false).await,
+                    _ => {}
+                }
+            }
+
+            // Retry failed or timed-out tasks with exponential backoff
+            // Ulangi tugas yang gagal atau timeout dengan backoff eksponensial
+            if let Some(mut task) = dispatcher_state.get_dispatched(&result.task_id).await {
+                if task.should_retry(result.status) {
+                    dispatcher_state.take_dispatched(&task.id).await;
+                    let delay_ms = task.next_retry_delay_ms();
+                    task.retry_count += 1;
+                    record_retry(
+                        dispatcher_state,
+                        &Message::TaskRetrying {
+                            task_id: task.id.clone(),
+                            attempt: task.retry_count,
+                        },
+                    )
+                    .await;
+                    info!(
+                        "[DISPATCHER] Retrying task {} (attempt {}/{}) in {}ms",
+                        task.id, task.retry_count, task.max_retries, delay_ms
+                    );
+
+                    let scheduler = scheduler.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                        scheduler.enqueue(task).await;
+                    });
+                    return Ok(None);
+                }
+
+                if task.max_retries > 0 && result.status == TaskStatus::TimedOut {
+                    // Retries exhausted: record as permanently failed
+                    // Percobaan ulang habis: catat sebagai gagal permanen
+                    result.status = TaskStatus::Failed;
+                }
+                // Persisted while still tracked, so the totals go to the worker it was dispatched to
+                // Disimpan selagi masih dilacak, agar total masuk ke worker tempat tugas dikirim
+                dispatcher_state.persist_result(&task, &result).await;
+                dispatcher_state.take_dispatched(&task.id).await;
+                if let Some(event) = AuditEvent::for_result(&result) {
+                    dispatcher_state.record_event(event).await;
+                }
+                dispatcher_state.notify_callback(&task, &result);
+                if task.max_retries > 0 && result.status == TaskStatus::Failed {
+                    scheduler.move_to_dlq(task).await;
+                }
+            }
+
+            dispatcher_state.metrics().record_result(&result);
+            dispatcher_state.publish_event(DispatcherEvent::TaskStatus {
+                task_id: result.task_id.clone(),
+                status: result.status,
+                worker_id,
+            });
+            dispatcher_state.store_result(result).await;
+        }
+
+        Message::TaskRetrying { .. } => {
+            record_retry(dispatcher_state, &msg).await;
+        }
+
+        // Task submitted by a client; optionally hold the reply until it finishes
+        // Tugas dikirim oleh klien; opsional tahan balasan sampai tugas selesai
+        Message::SubmitTask { task, wait } => {
+            let task_id = task.id.clone();
+            info!("[DISPATCHER] Task {} submitted: {}", task_id, task.command);
+            let submitted = AuditEvent::TaskSubmitted {
+                task_id: task_id.clone(),
+                command: task.command.clone(),
+            };
+
+            if let Err(e) = scheduler.submit(task).await {
+                warn!("[DISPATCHER] Rejected task {}: {}", task_id, e);
+                return Ok(Some(Message::TaskStatus {
+                    task_id,
+                    status: TaskStatus::Failed,
+                    progress: None,
+                }));
+            }
+
+            dispatcher_state.record_event(submitted).await;
+            dispatcher_state.publish_event(DispatcherEvent::TaskStatus {
+                task_id: task_id.clone(),
+                status: TaskStatus::Pending,
+                worker_id: None,
+            });
+
+            if !wait {
+                return Ok(Some(Message::TaskStatus {
+                    task_id,
+                    status: TaskStatus::Pending,
+                    progress: None,
+                }));
+            }
+            return Ok(dispatcher_state.wait_for_result(&task_id).await.map(Message::TaskCompleted));
+        }
+
+        // Live status lookup: finished results first, then tasks running on workers
+        // Pencarian status terkini: hasil selesai dulu, lalu tugas yang berjalan di worker
+        Message::QueryTask { task_id } => {
+            let status = match finished_result(dispatcher_state, &task_id).await {
+                Some(result) => result.status,
+                None if dispatcher_state.dispatched_worker(&task_id).await.is_some() => TaskStatus::Running,
+                None => TaskStatus::Pending,
+            };
+            return Ok(Some(Message::TaskStatus {
+                task_id,
+                status,
+                progress: None,
+            }));
+        }
+
+        Message::ListWorkers => {
+            return Ok(Some(Message::WorkerList(scheduler.get_workers().await)));
+        }
+
+        // Full result lookup; unfinished tasks get their live status instead
+        // Pencarian hasil lengkap; tugas yang belum selesai mendapat status terkininya
+        Message::QueryResult { task_id } => {
+            if let Some(result) = finished_result(dispatcher_state, &task_id).await {
+                return Ok(Some(Message::TaskCompleted(result)));
+            }
+            let status = if dispatcher_state.dispatched_worker(&task_id).await.is_some() {
+                TaskStatus::Running
+            } else {
+                TaskStatus::Pending
+            };
+            return Ok(Some(Message::TaskStatus {
+                task_id,
+                status,
+                progress: None,
+            }));
+        }
+        
+        // Progress, output and files only count from the worker the task was dispatched to
+        // Progres, output, dan file hanya dihitung dari worker tempat tugas dikirim
+        Message::TaskProgress { task_id, worker_id, .. }
+        | Message::TaskOutput { task_id, worker_id, .. }
+        | Message::TaskOutputFiles { task_id, worker_id, .. }
+            if dispatcher_state.dispatched_worker(&task_id).await.as_ref() != Some(&worker_id) =>
+        {
+            warn!("[DISPATCHER] Ignoring update of task {} from worker {}, which does not run it", task_id, worker_id);
+        }
+
+        Message::TaskProgress { task_id, progress, .. } => {
+            debug!("[DISPATCHER] Task {} progress: {:.1}%", task_id, progress * 100.0);
+            dispatcher_state.set_progress(&task_id, progress).await;
+            dispatcher_state
+                .task_events()
+                .publish(&task_id, TaskEvent::Progress(progress));
+        }
+
+        Message::TaskOutput { task_id, line, is_stderr, .. } => {
+            let stream = if is_stderr { "stderr" } else { "stdout" };
+            debug!("[DISPATCHER] Task {} {}: {}", task_id, stream, line);
+            dispatcher_state.append_output(&task_id, line, is_stderr).await;
+        }
+
+        // Output files a worker read back from the task directory
+        // File output yang dibaca kembali worker dari direktori tugas
+        Message::TaskOutputFiles { task_id, files, .. } => {
+            info!("[DISPATCHER] Task {} returned {} output file(s)", task_id, files.len());
+            dispatcher_state.store_output_files(&task_id, &files).await;
+        }
+
+        // Long-poll for a task's output; the reply is held until there is something new
+        // Long-poll output tugas; balasan ditahan sampai ada yang baru
+        Message::WatchTask { task_id, offset } => {
+            // Tasks finished before a restart only exist in the history store
+            // Tugas yang selesai sebelum restart hanya ada di penyimpanan riwayat
+            if dispatcher_state.get_result(&task_id).await.is_none() {
+                if let Some(result) = dispatcher_state.stored_result(&task_id).await {
+                    return Ok(Some(Message::TaskCompleted(result)));
+                }
+            }
+
+            let reply = match dispatcher_state.next_output(&task_id, offset).await {
+                OutputUpdate::Lines(lines) => Message::TaskOutputLines {
+                    next_offset: offset + lines.len(),
+                    task_id,
+                    lines,
+                },
+                OutputUpdate::Finished(result) => Message::TaskCompleted(result),
+            };
+            return Ok(Some(reply));
+        }
+        
+        // P2P: Resource availability announcement
+        // P2P: Pengumuman ketersediaan resource
+        Message::ResourceAnnounce(resources) => {
+            info!(
+                "[P2P] Resource announced from {}: CPU={}, RAM={}MB, GPU={}, Slots={}",
+                resources.peer_id, resources.cpu_cores, resources.ram_mb, resources.gpu_available, resources.available_slots
+            );
+        }
+        
+        // P2P: Peer discovery request
+        // P2P: Permintaan penemuan peer
+        Message::PeerDiscoveryRequest { requester_id, timestamp } => {
+            info!(
+                "[P2P] Peer discovery request from {} at {}",
+                requester_id, timestamp
+            );
+        }
+        
+        // P2P: Peer discovery response
+        // P2P: Respons penemuan peer
+        Message::PeerDiscoveryResponse { responder_id, resources } => {
+            info!(
+                "[P2P] Peer discovery response from {}: {}",
+                responder_id, resources.peer_id
+            );
+        }
+        
+        // Worker load report; doubles as a heartbeat
+        // Laporan beban worker; sekaligus berfungsi sebagai heartbeat
+        Message::WorkerStatus { worker_id, current_jobs, cpu_percent, memory_mb, load_avg_1m, draining } => {
+            debug!(
+                "[DISPATCHER] Status from {}: jobs={} cpu={:.1}% mem={}MB load={:.2} draining={}",
+                worker_id, current_jobs, cpu_percent, memory_mb, load_avg_1m, draining
+            );
+            let metrics = WorkerMetrics {
+                cpu_percent,
+                memory_mb,
+                load_avg_1m,
+            };
+            scheduler.update_worker_metrics(&worker_id, metrics).await;
+            scheduler.set_worker_draining(&worker_id, draining).await;
+        }
+
+        // Drained worker is leaving; queue again anything still dispatched to it
+        // Worker yang sudah dikosongkan pergi; antrikan ulang tugas yang masih dikirim kepadanya
+        Message::WorkerDeregister { worker_id, psk_hash } => {
+            // Only the worker itself may take its entry and its tasks away
+            // Hanya worker itu sendiri yang boleh menghapus entri dan tugasnya
+            if let Err(e) = check_psk(dispatcher_state, &worker_id, psk_hash.as_deref()).await {
+                warn!("[DISPATCHER] Rejected deregistration of worker {}: {}", worker_id, e);
+                return Err(e);
+            }
+            if scheduler.remove_worker(&worker_id).await.is_some() {
+                info!("[DISPATCHER] Worker {} deregistered", worker_id);
+                for task in dispatcher_state.dispatched_to(&worker_id).await {
+                    if let Some(task) = dispatcher_state.take_dispatched(&task.id).await {
+                        scheduler.enqueue(task).await;
+                    }
+                }
+                dispatcher_state
+                    .record_event(AuditEvent::WorkerOffline { worker_id: worker_id.clone() })
+                    .await;
+                dispatcher_state.publish_event(DispatcherEvent::WorkerDisconnected { worker_id });
+            }
+        }
+
+        Message::Heartbeat { worker_id, sent_at } => {
+            // A worker clock running ahead would make the delay negative; count it as zero
+            // Jam worker yang lebih cepat membuat jeda negatif; hitung sebagai nol
+            let rtt_ms = (chrono::Utc::now().timestamp_millis() - sent_at).max(0) as u64;
+            debug!("[DISPATCHER] Heartbeat received from {} after {}ms", worker_id, rtt_ms);
+            scheduler.record_heartbeat(&worker_id, rtt_ms).await;
+        }
+        
+        _ => {
+            warn!("Unexpected message type: {:?}", msg);
+        }
+    }
+    
+    Ok(None)
+}
+
+// Result of a finished task, from memory or, once expired there, from the history store
+// Hasil tugas yang selesai, dari memori atau, setelah kedaluwarsa di sana, dari penyimpanan riwayat
+async fn finished_result(dispatcher_state: &DispatcherState, task_id: &str) -> Option<TaskResult> {
+    match dispatcher_state.get_result(task_id).await {
+        Some(result) => Some(result),
+        None => dispatcher_state
+            .stored_result(task_id)
+            .await
+            .filter(|result| !matches!(result.status, TaskStatus::Pending | TaskStatus::Running)),
+    }
+}
+
+// Fail unless `psk_hash` matches the dispatcher's pre-shared key, recording the attempt in the audit log
+// Gagal kecuali `psk_hash` cocok dengan kunci pra-bagi dispatcher, mencatat percobaan di log audit
+async fn check_psk(dispatcher_state: &DispatcherState, worker_id: &str, psk_hash: Option<&str>) -> Result<()> {
+    if dispatcher_state.accepts_psk(psk_hash) {
+        return Ok(());
+    }
+    let reason = match psk_hash {
+        Some(_) => "wrong pre-shared key",
+        None => "missing pre-shared key",
+    };
+    dispatcher_state
+        .record_event(AuditEvent::AuthFailure {
+            user: worker_id.to_string(),
+            reason: reason.to_string(),
+        })
+        .await;
+    anyhow::bail!("worker {} rejected: {}", worker_id, reason)
+}
+
+// A worker with a client certificate may only speak for that ID
+// Worker dengan sertifikat klien hanya boleh mewakili ID tersebut
+fn check_certificate_name(peer_name: Option<&str>, msg: &Message) -> Result<()> {
+    if let (Some(name), Some(worker_id)) = (peer_name, msg.sender_worker_id()) {
+        if name != worker_id {
+            anyhow::bail!("worker ID {} does not match certificate name {}", worker_id, name);
+        }
+    }
+    Ok(())
+}
+
+// Write task retry notification to the dispatcher audit log
+// Tulis notifikasi percobaan ulang tugas ke log audit dispatcher
+async fn record_retry(dispatcher_state: &DispatcherState, msg: &Message) {
+    if let Message::TaskRetrying { task_id, attempt } = msg {
+        dispatcher_state
+            .record_event(AuditEvent::TaskRetrying {
+                task_id: task_id.clone(),
+                attempt: *attempt,
+            })
+            .await;
+    }
+}
+
+/// Worker process initialization and main loop, until a termination signal
+/// Inisialisasi proses worker dan loop utama, sampai ada sinyal penghentian
+pub async fn run_worker(
+    name: &str,
+    worker_id: Option<String>,
+    config: &Config,
+    tags: Vec<String>,
+    executor_kind: ExecutorKind,
+    transport: Arc<dyn TransportBackend>,
+    dispatchers: Vec<String>,
+) -> Result<()> {
+    let (allow_shell, max_jobs) = (config.allow_shell, config.max_jobs);
+    info!("[WORKER] Starting Worker '{}' with max_jobs={} tags={:?}", name, max_jobs, tags);
+
+    // Advertise the address on the dispatcher's subnet, not whichever interface holds the default route
+    // Umumkan alamat pada subnet dispatcher, bukan antarmuka mana pun yang memegang rute default
+    let dispatcher_ip = match dispatchers.first() {
+        Some(dispatcher) => tokio::net::lookup_host(dispatcher.as_str())
+            .await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .map(|addr| addr.ip()),
+        None => None,
+    };
+    let local_ip = match dispatcher_ip {
+        Some(ip) => util::get_best_local_ip(&ip),
+        None => util::get_local_ip(),
+    }
+    .unwrap_or_else(|| "127.0.0.1".to_string());
+
+    // A QUIC transport is already bound; its UDP port is the one to advertise.
+    // Over TCP the listener is bound here and kept, so the advertised port cannot be taken meanwhile
+    // Transport QUIC sudah terikat; port UDP-nya yang diumumkan.
+    // Lewat TCP listener diikat di sini dan disimpan, agar port yang diumumkan tidak direbut sementara itu
+    let (port, tcp_listener) = match transport.local_addr() {
+        Some(addr) => (addr.port(), None),
+        None => {
+            let (port, listener) = find_and_bind(7879).await?;
+            (port, Some(listener))
+        }
+    };
+
+    // Keep the same ID across restarts so the dispatcher updates this worker's entry instead of adding another;
+    // an mTLS worker uses the ID from its certificate
+    // Pertahankan ID yang sama setelah restart agar dispatcher memperbarui entri worker ini, bukan menambah yang baru;
+    // worker mTLS memakai ID dari sertifikatnya
+    let worker_id = match worker_id {
+        Some(id) => id,
+        None => util::load_or_create_id(&config.worker_id_file())?,
+    };
+    let worker_state = Arc::new(WorkerState::new(name.to_string(), port, Some(worker_id.clone())).with_max_jobs(max_jobs));
+    let workdir = config.workdir.join("work");
+    let policy = CommandPolicy::from_config(config)?;
+    let mut executor = Executor::new(workdir, allow_shell, policy)
+        .with_cleanup_on_success(config.cleanup_on_success)
+        .with_max_output_bytes(config.max_output_bytes)
+        .with_sensitive_env_patterns(config.sensitive_env_patterns.clone());
+    if executor_kind == ExecutorKind::Docker {
+        info!("[WORKER] Running tasks in Docker containers");
+        executor = executor.with_backend(Arc::new(DockerExecutor::new()?));
+    }
+    let (progress_tx, mut progress_rx) = mpsc::channel(PROGRESS_CHANNEL_CAPACITY);
+    executor.set_progress_channel(progress_tx);
+    let executor = Arc::new(executor);
+
+    // Progress loop - pass `PROGRESS:` lines printed by tasks on to the active dispatcher
+    // Loop progres - teruskan baris `PROGRESS:` yang dicetak tugas ke dispatcher aktif
+    let progress_state = worker_state.clone();
+    let progress_transport = transport.clone();
+    tokio::spawn(async move {
+        while let Some((task_id, progress)) = progress_rx.recv().await {
+            let Some(active) = progress_state.get_active_dispatcher().await else {
+                continue;
+            };
+            let msg = Message::TaskProgress {
+                task_id,
+                worker_id: progress_state.id.clone(),
+                progress,
+            };
+            if let Err(e) = progress_transport.send_message(active, &msg).await {
+                debug!("[WORKER] Failed to report task progress: {}", e);
+            }
+        }
+    });
+
+    let mut worker_info = WorkerInfo::new(
+        name.to_string(),
+        local_ip.clone(),
+        port,
+        max_jobs,
+    );
+    worker_info.tags = tags;
+    worker_info.id = worker_id;
+    worker_info.psk_hash = config.psk.as_deref().map(util::sha256_hex);
+
+    info!(
+        "[WORKER] Registered at {}:{}",
+        local_ip, port
+    );
+    info!("[WORKER] Waiting for dispatcher assignment...");
+
+    let worker_state_clone = worker_state.clone();
+    let executor_clone = executor.clone();
+    let status_transport = transport.clone();
+
+    if config.transport == TransportKind::Quic {
+        let serve_transport = transport.clone();
+        let handler: MessageHandler = Arc::new(move |msg, peer_addr| {
+            let worker_state = worker_state_clone.clone();
+            let executor = executor_clone.clone();
+            let transport = transport.clone();
+            Box::pin(async move { handle_worker_message(msg, &worker_state, &executor, &transport, peer_addr).await })
+        });
+        info!("[WORKER] Listening on UDP port {}", port);
+        tokio::spawn(async move {
+            if let Err(e) = serve_transport.serve(handler).await {
+                error!("[WORKER] QUIC listener stopped: {}", e);
+            }
+        });
+    } else {
+        // Start listening for incoming connections from dispatcher
+        // Mulai mendengarkan koneksi masuk dari dispatcher
+        let listener = tcp_listener.ok_or_else(|| anyhow::anyhow!("TCP worker has no listener"))?;
+        info!("[WORKER] Listening on {}", listener.local_addr()?);
+
+        let listener = Arc::new(listener);
+
+        // Spawn connection handler task
+        // Jalankan task penanganan koneksi
+        let listener_clone = listener.clone();
+    
+        tokio::spawn(async move {
+            loop {
+                match listener_clone.accept().await {
+                    Ok((stream, peer_addr)) => {
+                        debug!("[WORKER] Connection established with dispatcher at {}", peer_addr);
+                    
+                        let worker_state = worker_state_clone.clone();
+                        let executor = executor_clone.clone();
+                        let transport = transport.clone();
+                    
+                        tokio::spawn(async move {
+                            if let Err(e) = 
+                                Transport::handle_connection(
+                                    stream,
+                                    move |msg| {
+                                        let worker_state = worker_state.clone();
+                                        let executor = executor.clone();
+                                        let transport = transport.clone();
+                                    
+                                        Box::pin(async move {
+                                            handle_worker_message(msg, &worker_state, &executor, &transport, peer_addr).await
+                                        })
+                                    }
+                                ).await 
+                            {
+                                error!("Worker connection handler error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        error!("Accept error: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    // Heartbeat loop - report live load to the active dispatcher, failing over
+    // to the next one in the list when it stops answering
+    // Loop detak jantung - laporkan beban terkini ke dispatcher aktif, beralih
+    // ke dispatcher berikutnya dalam daftar saat tidak menjawab
+    let (worker_id, psk_hash) = (worker_info.id.clone(), worker_info.psk_hash.clone());
+    let heartbeat_interval = Duration::from_secs(config.heartbeat_interval_secs.max(1));
+    let heartbeat_state = worker_state.clone();
+    let heartbeat_transport = status_transport.clone();
+    tokio::spawn(async move {
+        connect_dispatcher(&dispatchers, &worker_info, &heartbeat_state, heartbeat_transport.as_ref()).await;
+        let mut interval = interval(heartbeat_interval);
+        let mut sampler = LoadSampler::new();
+        
+        loop {
+            interval.tick().await;
+            let status = worker_status(&worker_info.id, &heartbeat_state, &mut sampler);
+            let Some(active) = heartbeat_state.get_active_dispatcher().await else {
+                continue;
+            };
+            // The heartbeat's send time lets the dispatcher track how long messages take to arrive
+            // Waktu kirim heartbeat memungkinkan dispatcher melacak berapa lama pesan sampai
+            let heartbeat = Message::Heartbeat {
+                worker_id: worker_info.id.clone(),
+                sent_at: chrono::Utc::now().timestamp_millis(),
+            };
+            let report = async {
+                heartbeat_transport.send_message(active, &status).await?;
+                heartbeat_transport.send_message(active, &heartbeat).await
+            };
+            let Err(e) = report.await else {
+                continue;
+            };
+
+            warn!("[WORKER] Lost dispatcher {}: {}", active, e);
+            heartbeat_state.set_active_dispatcher(None).await;
+
+            // Interrupted tasks stop here and are queued again on the new dispatcher, which never saw them
+            // Tugas yang terputus dihentikan di sini dan diantrikan ulang di dispatcher baru, yang belum pernah melihatnya
+            let interrupted = heartbeat_state.cancel_all().await;
+            let addr = connect_dispatcher(&dispatchers, &worker_info, &heartbeat_state, heartbeat_transport.as_ref()).await;
+            for task in interrupted {
+                let task_id = task.id.clone();
+                let submit = Message::SubmitTask { task, wait: false };
+                match heartbeat_transport.request_with_timeout(addr, &submit, Some(RESUBMIT_TIMEOUT)).await {
+                    Ok(Message::TaskStatus { status: TaskStatus::Pending, .. }) => {
+                        info!("[WORKER] Resubmitted interrupted task {} to {}", task_id, addr);
+                    }
+                    Ok(reply) => warn!("[WORKER] Dispatcher {} did not accept task {}: {:?}", addr, task_id, reply),
+                    Err(e) => warn!("[WORKER] Failed to resubmit interrupted task {} to {}: {}", task_id, addr, e),
+                }
+            }
+        }
+    });
+
+    // Ctrl+C stops at once; SIGTERM finishes running tasks first and deregisters
+    // Ctrl+C langsung berhenti; SIGTERM menyelesaikan tugas yang berjalan lalu membatalkan pendaftaran
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result?;
+            info!("[WORKER] Shutting down gracefully...");
+        }
+        result = terminate_signal() => {
+            result?;
+            info!("[WORKER] SIGTERM received, draining {} running task(s)...", worker_state.running_jobs());
+            worker_state.start_draining();
+            let status = worker_status(&worker_id, &worker_state, &mut LoadSampler::new());
+            send_to_dispatcher(status_transport.as_ref(), &worker_state, &status).await;
+
+            worker_state.wait_until_idle().await;
+            send_to_dispatcher(status_transport.as_ref(), &worker_state, &Message::WorkerDeregister { worker_id, psk_hash }).await;
+            info!("[WORKER] All tasks finished, exiting");
+        }
+    }
+    
+    Ok(())
+}
+
+// Register with the first dispatcher that accepts the announcement, retrying
+// the whole list with exponential backoff until one does
+// Mendaftar ke dispatcher pertama yang menerima pengumuman, mengulang seluruh
+// daftar dengan backoff eksponensial sampai ada yang menerima
+async fn connect_dispatcher(
+    dispatchers: &[String],
+    worker_info: &WorkerInfo,
+    worker_state: &WorkerState,
+    transport: &dyn TransportBackend,
+) -> SocketAddr {
+    // A worker in maintenance stays in maintenance after re-registering
+    // Worker dalam pemeliharaan tetap dalam pemeliharaan setelah mendaftar ulang
+    let mut worker_info = worker_info.clone();
+    worker_info.maintenance = worker_state.in_maintenance();
+    let announce = Message::WorkerAnnounce(worker_info);
+    let mut backoff = RECONNECT_BACKOFF_INITIAL;
+
+    loop {
+        for dispatcher in dispatchers {
+            let addr = match tokio::net::lookup_host(dispatcher).await.map(|mut addrs| addrs.next()) {
+                Ok(Some(addr)) => addr,
+                _ => {
+                    debug!("[WORKER] Could not resolve dispatcher {}", dispatcher);
+                    continue;
+                }
+            };
+            match transport.send_message(addr, &announce).await {
+                Ok(()) => {
+                    info!("[WORKER] Registered with dispatcher {}", addr);
+                    worker_state.set_active_dispatcher(Some(addr)).await;
+                    return addr;
+                }
+                Err(e) => debug!("[WORKER] Dispatcher {} unreachable: {}", addr, e),
+            }
+        }
+
+        warn!("[WORKER] No dispatcher reachable, retrying in {}s", backoff.as_secs());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_BACKOFF_MAX);
+    }
+}
+
+// Current load report for the dispatcher
+// Laporan beban terkini untuk dispatcher
+fn worker_status(worker_id: &str, worker_state: &WorkerState, sampler: &mut LoadSampler) -> Message {
+    let load = sampler.sample();
+    Message::WorkerStatus {
+        worker_id: worker_id.to_string(),
+        current_jobs: worker_state.running_jobs(),
+        cpu_percent: load.cpu_percent,
+        memory_mb: load.memory_mb,
+        load_avg_1m: load.load_avg_1m,
+        draining: worker_state.is_draining(),
+    }
+}
+
+// Send a message to the active dispatcher, logging failures
+// Kirim pesan ke dispatcher aktif, mencatat kegagalan
+async fn send_to_dispatcher(transport: &dyn TransportBackend, worker_state: &WorkerState, msg: &Message) {
+    let Some(target) = worker_state.get_active_dispatcher().await else {
+        debug!("[WORKER] No active dispatcher");
+        return;
+    };
+    if let Err(e) = transport.send_message(target, msg).await {
+        debug!("[WORKER] Failed to send status to {}: {}", target, e);
+    }
+}
+
+// Resolves on SIGTERM; never resolves where the signal does not exist
+// Selesai saat SIGTERM; tidak pernah selesai jika sinyal tidak tersedia
+async fn terminate_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        signal(SignalKind::terminate())?.recv().await;
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    std::future::pending().await
+}
+
+// Handle task execution messages on worker
+// Tangani pesan eksekusi tugas di worker
+async fn handle_worker_message(
+    msg: Message,
+    worker_state: &Arc<WorkerState>,
+    executor: &Arc<Executor>,
+    transport: &Arc<dyn TransportBackend>,
+    peer_addr: SocketAddr,
+) -> Result<Option<Message>> {
+    // Results go to the dispatcher this worker is registered with
+    // Hasil dikirim ke dispatcher tempat worker ini terdaftar
+    let dispatcher_addr = worker_state.get_active_dispatcher().await.unwrap_or(peer_addr);
+
+    // A draining worker refuses new tasks without acking them, so the dispatcher queues them again
+    // Worker yang dikosongkan menolak tugas baru tanpa ack, sehingga dispatcher mengantrikannya ulang
+    if worker_state.is_draining() && matches!(msg, Message::AssignTask { .. } | Message::BatchAssign(_)) {
+        warn!("[WORKER] Draining, rejected new assignment");
+        return Err(anyhow::anyhow!("Worker {} is draining and accepts no new tasks", worker_state.id));
+    }
+
+    match msg {
+        // Execute assigned task from dispatcher in the background so the
+        // assignment is acknowledged immediately
+        // Jalankan tugas dari dispatcher di latar belakang agar penugasan
+        // langsung dikonfirmasi
+        Message::AssignTask { task, trace_context } => {
+            info!("[WORKER] Task received for execution: {}", task.id);
+            spawn_assigned_task(task, trace_context, worker_state, executor, transport, dispatcher_addr).await;
+        }
+
+        // Several tasks at once; each runs concurrently, bounded by max_jobs
+        // Beberapa tugas sekaligus; masing-masing berjalan bersamaan, dibatasi max_jobs
+        Message::BatchAssign(tasks) => {
+            info!("[WORKER] Batch of {} tasks received for execution", tasks.len());
+            for task in tasks {
+                spawn_assigned_task(task, None, worker_state, executor, transport, dispatcher_addr).await;
+            }
+        }
+
+        // The dispatcher already stops routing tasks here; remember the mode for re-registration
+        // Dispatcher sudah berhenti mengarahkan tugas ke sini; ingat mode untuk pendaftaran ulang
+        Message::WorkerMaintenanceMode { enable, .. } => {
+            info!("[WORKER] Maintenance mode {}", if enable { "enabled" } else { "disabled" });
+            worker_state.set_maintenance(enable);
+        }
+
+        // Task cancellation request; the task is killed and reported as cancelled
+        // Permintaan pembatalan tugas; tugas dimatikan dan dilaporkan sebagai dibatalkan
+        Message::CancelTask { task_id } => {
+            if worker_state.cancel_task(&task_id).await {
+                info!("[WORKER] Cancelling task {}", task_id);
+            } else {
+                debug!("[WORKER] Cancel request for task {} that is not running here", task_id);
+            }
+        }
+        
+        // P2P: Shared task from peer
+        // P2P: Task bersama dari peer
+        Message::P2PShareTask { task, requester_id } => {
+            info!("[P2P] Shared task received from {}: {}", requester_id, task.id);
+            
+            // For now, execute like normal task
+            let task_id = task.id.clone();
+            worker_state.set_current_task(Some(task.clone())).await;
+            
+            match executor.execute_isolated(&task).await {
+                Ok(result) => {
+                    info!("[P2P] Shared task {} completed", task_id);
+                    worker_state.record_result(result.status, result.duration_ms);
+                    let task_result = crate::protocol::TaskResult {
+                        task_id: task_id.clone(),
+                        worker_id: worker_state.id.clone(),
+                        status: result.status,
+                        stdout: result.stdout,
+                        stderr: result.stderr,
+                        exit_code: result.exit_code,
+                        duration_ms: result.duration_ms,
+                        completed_at: chrono::Local::now().timestamp(),
+                        resource_usage: result.resource_usage,
+                    };
+                    
+                    // Send result back to requester
+                    let result_msg = Message::TaskCompleted(task_result);
+                    let _ = transport.send_message(dispatcher_addr, &result_msg).await;
+                }
+                Err(e) => {
+                    error!("[P2P] Shared task execution failed: {}", e);
+                }
+            }
+            
+            worker_state.set_current_task(None).await;
+        }
+        
+        // Totals of the tasks run so far, for the dispatcher's worker details
+        // Total tugas yang sudah dijalankan, untuk detail worker di dispatcher
+        Message::QueryWorkerMetrics { worker_id } => {
+            return Ok(Some(Message::WorkerMetrics {
+                worker_id,
+                stats: worker_state.task_stats(),
+            }));
+        }
+        
+        // P2P: Peer discovery request
+        // P2P: Permintaan penemuan peer
+        Message::PeerDiscoveryRequest { requester_id, timestamp } => {
+            info!(
+                "[P2P] Discovery request from {} at {}",
+                requester_id, timestamp
+            );
+        }
+        
+        _ => {
+            warn!("Unexpected message type for worker: {:?}", msg);
+        }
+    }
+    
+    Ok(None)
+}
+
+// Run an assigned task in the background once a job slot is free
+// Jalankan tugas yang ditugaskan di latar belakang setelah slot pekerjaan tersedia
+async fn spawn_assigned_task(
+    task: crate::protocol::Task,
+    trace_context: Option<std::collections::HashMap<String, String>>,
+    worker_state: &Arc<WorkerState>,
+    executor: &Arc<Executor>,
+    transport: &Arc<dyn TransportBackend>,
+    dispatcher_addr: SocketAddr,
+) {
+    // Tracked before the task is spawned, so a cancellation arriving right after the assignment finds it
+    // Dilacak sebelum tugas dijalankan, agar pembatalan yang tiba tepat setelah penugasan menemukannya
+    let cancel = worker_state.track_cancellation(&task).await;
+    let worker_state = worker_state.clone();
+    let executor = executor.clone();
+    let transport = transport.clone();
+    tokio::spawn(async move {
+        let slot = tokio::select! {
+            slot = worker_state.job_slots.clone().acquire_owned() => slot.ok(),
+            _ = cancel.cancelled() => {
+                info!("[WORKER] Task {} cancelled before it started", task.id);
+                let stderr = "Task cancelled".to_string();
+                report_unfinished(&task.id, TaskStatus::Cancelled, stderr, &worker_state, transport.as_ref(), dispatcher_addr).await;
+                None
+            }
+        };
+        let Some(_slot) = slot else {
+            worker_state.finish_cancellation(&task.id, &cancel).await;
+            return;
+        };
+
+        // Continue the dispatcher's trace when the assignment carried one
+        // Lanjutkan jejak dispatcher jika penugasan membawanya
+        let span = tracing::info_span!(
+            "execute_task",
+            task.id = %task.id,
+            worker.id = %worker_state.id,
+            task.command = %task.command
+        );
+        if let Some(carrier) = &trace_context {
+            telemetry::set_parent(&span, carrier);
+        }
+        let task_id = task.id.clone();
+        run_assigned_task(task, &cancel, &worker_state, &executor, transport.as_ref(), dispatcher_addr)
+            .instrument(span)
+            .await;
+        worker_state.finish_cancellation(&task_id, &cancel).await;
+    });
+}
+
+// Tell the dispatcher a task ended without a result of its own, so its slot is freed
+// and a failure goes through retry and the dead letter queue
+// Beri tahu dispatcher bahwa tugas berakhir tanpa hasil sendiri, agar slotnya dibebaskan
+// dan kegagalan melewati percobaan ulang dan antrian dead letter
+async fn report_unfinished(
+    task_id: &str,
+    status: TaskStatus,
+    stderr: String,
+    worker_state: &WorkerState,
+    transport: &dyn TransportBackend,
+    dispatcher_addr: SocketAddr,
+) {
+    let task_result = TaskResult {
+        task_id: task_id.to_string(),
+        worker_id: worker_state.id.clone(),
+        status,
+        stdout: String::new(),
+        stderr,
+        exit_code: None,
+        duration_ms: 0,
+        completed_at: chrono::Local::now().timestamp(),
+        resource_usage: None,
+    };
+    if let Err(e) = transport.send_message(dispatcher_addr, &Message::TaskCompleted(task_result)).await {
+        error!("[WORKER] Failed to send task result: {}", e);
+    }
+}
+
+// Execute an assigned task and report the result to the dispatcher
+// Jalankan tugas yang ditugaskan dan laporkan hasilnya ke dispatcher
+async fn run_assigned_task(
+    task: crate::protocol::Task,
+    cancel: &CancellationToken,
+    worker_state: &WorkerState,
+    executor: &Executor,
+    transport: &dyn TransportBackend,
+    dispatcher_addr: SocketAddr,
+) {
+    let task_id = task.id.clone();
+    worker_state.set_current_task(Some(task.clone())).await;
+
+    // Write the staged input files into the task directory; a task missing one fails without running
+    // Tulis file input yang dipindahkan ke direktori tugas; tugas yang kehilangan salah satunya gagal tanpa dijalankan
+    if let Err(e) = util::files::write_all(&executor.isolated_workdir(&task), &task.input_files).await {
+        error!("[WORKER] Failed to stage input files of task {}: {:#}", task_id, e);
+        worker_state.record_result(TaskStatus::Failed, 0);
+        let stderr = format!("Failed to stage input files: {:#}", e);
+        report_unfinished(&task_id, TaskStatus::Failed, stderr, worker_state, transport, dispatcher_addr).await;
+        worker_state.set_current_task(None).await;
+        return;
+    }
+
+    // Execute task with timeout protection; the executor kills the process tree on timeout
+    // Jalankan tugas dengan perlindungan timeout; executor mematikan pohon proses saat timeout
+    let timeout = Duration::from_secs(task.timeout);
+    let (mut events, handle) = match executor.execute_isolated_streaming(&task, Some(timeout)).await {
+        Ok(streaming) => streaming,
+        Err(e) => {
+            error!("Task execution failed: {}", e);
+            worker_state.record_result(TaskStatus::Failed, 0);
+            let stderr = format!("Task execution failed: {:#}", e);
+            report_unfinished(&task_id, TaskStatus::Failed, stderr, worker_state, transport, dispatcher_addr).await;
+            worker_state.set_current_task(None).await;
+            return;
+        }
+    };
+
+    // Forward output lines to the dispatcher as they are produced
+    // Teruskan baris output ke dispatcher saat dihasilkan
+    let forward = async {
+        while let Some(event) = events.recv().await {
+            let (line, is_stderr) = match event {
+                ExecutorEvent::Stdout(line) => (line, false),
+                ExecutorEvent::Stderr(line) => (line, true),
+                ExecutorEvent::Exited(result) => return Some(result),
+            };
+            let output_msg = Message::TaskOutput {
+                task_id: task_id.clone(),
+                worker_id: worker_state.id.clone(),
+                line,
+                is_stderr,
+            };
+            if let Err(e) = transport.send_message(dispatcher_addr, &output_msg).await {
+                debug!("[WORKER] Failed to stream output for task {}: {}", task_id, e);
+            }
+        }
+        None
+    };
+
+    // Aborting the execution kills the task's processes or container
+    // Membatalkan eksekusi mematikan proses atau container tugas
+    let abort = handle.abort_handle();
+    let run = async {
+        match forward.await {
+            Some(result) => Ok(result),
+            None => handle.await.map_err(anyhow::Error::from).and_then(|result| Ok(result?)),
+        }
+    };
+    let outcome = tokio::select! {
+        outcome = run => outcome,
+        _ = cancel.cancelled() => {
+            abort.abort();
+            info!("[WORKER] Task {} cancelled", task_id);
+            worker_state.record_result(TaskStatus::Cancelled, 0);
+            let stderr = "Task cancelled".to_string();
+            report_unfinished(&task_id, TaskStatus::Cancelled, stderr, worker_state, transport, dispatcher_addr).await;
+            worker_state.set_current_task(None).await;
+            return;
+        }
+    };
+
+    match outcome {
+        Ok(result) => {
+            info!("[WORKER] Task {} execution finished", task_id);
+            worker_state.record_result(result.status, result.duration_ms);
+
+            // Acknowledged before the result is sent, so the files are stored once the task shows as finished
+            // Dikonfirmasi sebelum hasil dikirim, sehingga file sudah disimpan saat tugas tampak selesai
+            if !task.output_files.is_empty() {
+                let files_msg = Message::TaskOutputFiles {
+                    task_id: task_id.clone(),
+                    worker_id: worker_state.id.clone(),
+                    files: result.output_files,
+                };
+                if let Err(e) = transport.send_message_with_ack(dispatcher_addr, &files_msg).await {
+                    error!("[WORKER] Failed to send output files of task {}: {}", task_id, e);
+                }
+            }
+
+            let task_result = crate::protocol::TaskResult {
+                task_id: task_id.clone(),
+                worker_id: worker_state.id.clone(),
+                status: result.status,
+                stdout: result.stdout,
+                stderr: result.stderr,
+                exit_code: result.exit_code,
+                duration_ms: result.duration_ms,
+                completed_at: chrono::Local::now().timestamp(),
+                resource_usage: result.resource_usage,
+            };
+
+            // Send result back to dispatcher
+            // Kirim hasil kembali ke dispatcher
+            let result_msg = Message::TaskCompleted(task_result);
+            if let Err(e) = transport.send_message(dispatcher_addr, &result_msg).await {
+                error!("[WORKER] Failed to send task result: {}", e);
+            }
+        }
+        Err(e) => {
+            error!("Task execution failed: {}", e);
+            worker_state.record_result(TaskStatus::Failed, 0);
+            let stderr = format!("Task execution failed: {:#}", e);
+            report_unfinished(&task_id, TaskStatus::Failed, stderr, worker_state, transport, dispatcher_addr).await;
+        }
+    }
+
+    worker_state.set_current_task(None).await;
+}
+
+/// Bind a listener on `start_hint`, or on a port the OS picks if that one is taken
+/// Ikat listener pada `start_hint`, atau pada port pilihan OS jika port itu sudah dipakai
+async fn find_and_bind(start_hint: u16) -> Result<(u16, TcpListener)> {
+    let listener = match TcpListener::bind(("0.0.0.0", start_hint)).await {
+        Ok(listener) => listener,
+        Err(_) => TcpListener::bind(("0.0.0.0", 0)).await?,
+    };
+    Ok((listener.local_addr()?.port(), listener))
+}
+
+/// Start P2P peer discovery and management
+/// Mulai penemuan peer P2P dan manajemen
+async fn start_p2p_discovery(
+    peer_id: String,
+    peer_name: String,
+    broadcast_port: u16,
+) -> Result<Arc<crate::P2PNetwork>> {
+    let p2p_network = Arc::new(crate::P2PNetwork::new(
+        peer_id.clone(),
+        peer_name.clone(),
+        broadcast_port,
+    )?);
+
+    // Start mDNS service discovery
+    p2p_network.start_mdns_discovery().await?;
+
+    // Start discovery listener on broadcast port
+    p2p_network.start_discovery_listener(5555).await?;
+
+    // Start periodic peer discovery announcements
+    p2p_network.start_periodic_discovery(10).await?;
+
+    // Announce this peer to the network
+    p2p_network.announce_peer().await?;
+
+    info!(
+        "[P2P] Peer discovery started for: {} ({})",
+        peer_name, peer_id
+    );
+
+    Ok(p2p_network)
+}
+
+/// Manage P2P task distribution
+/// Kelola distribusi task P2P
+async fn manage_p2p_distribution(
+    distributor: Arc<crate::P2PDistributor>,
+    _p2p_network: Arc<crate::P2PNetwork>,
+    dispatcher_interval: u64,
+) -> Result<()> {
+    // Periodic task distribution loop
+    let mut ticker = interval(Duration::from_secs(dispatcher_interval));
+
+    loop {
+        ticker.tick().await;
+
+        // Cleanup stale peer information
+        if let Err(e) = distributor.cleanup_stale_peers(30).await {
+            warn!("[P2P] Peer cleanup error: {}", e);
+        }
+
+        // Process pending tasks and distribute to available peers
+        while let Some(task) = distributor.get_next_task().await {
+            // Find best peer for this task
+            let best_peer = distributor.find_best_peer(&task, 1, 256, false).await;
+
+            match best_peer {
+                Some(peer_id) => {
+                    if let Err(e) = distributor.assign_to_peer(task.id.clone(), peer_id.clone()).await {
+                        error!("[P2P] Failed to assign task {} to peer {}: {}", task.id, peer_id, e);
+                        distributor.enqueue_p2p_task(task).await?;
+                    } else {
+                        info!("[P2P] Task {} assigned to peer {}", task.id, peer_id);
+                    }
+                }
+                None => {
+                    warn!("[P2P] No suitable peer found for task {}, re-queueing", task.id);
+                    distributor.enqueue_p2p_task(task).await?;
+                }
+            }
+        }
+
+        // Log distribution stats periodically
+        let pending = distributor.pending_count().await;
+        let active = distributor.assignment_count().await;
+        let peers = distributor.get_all_peers().await;
+
+        debug!(
+            "[P2P] Distribution stats - Pending: {}, Active: {}, Peers: {}",
+            pending, active, peers.len()
+        );
+    }
+}
+
+/// Handle P2P peer discovery and resource updates
+/// Tangani penemuan peer P2P dan pembaruan resource
+async fn handle_p2p_peer_updates(
+    distributor: Arc<crate::P2PDistributor>,
+    discovery_interval: u64,
+) -> Result<()> {
+    let mut ticker = interval(Duration::from_secs(discovery_interval));
+
+    loop {
+        ticker.tick().await;
+
+        // Log current peer status
+        let peers = distributor.get_all_peers().await;
+        for peer in peers {
+            debug!(
+                "[P2P] Peer: {} - CPU: {}, RAM: {}MB, GPU: {}, Slots: {}",
+                peer.peer_id,
+                peer.cpu_cores,
+                peer.ram_mb,
+                peer.gpu_available,
+                peer.available_slots
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{SqliteStore, StoreBackend};
+    use crate::protocol::Task;
+
+    #[tokio::test]
+    async fn test_finished_task_status_survives_result_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store: Arc<dyn StoreBackend + Send + Sync> =
+            Arc::new(SqliteStore::new(db_path.to_str().unwrap()).await.unwrap());
+        let scheduler = Arc::new(Scheduler::default());
+        let state = DispatcherState::new("test".to_string(), 0)
+            .with_scheduler(scheduler.clone())
+            .with_store(store);
+
+        let mut worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        worker.id = "worker-1".to_string();
+        scheduler.register_worker(worker).await;
+        let task = Task::new("echo done".to_string());
+        let task_id = task.id.clone();
+        state.track_dispatched(task, "worker-1").await;
+        let query = Message::QueryTask { task_id: task_id.clone() };
+        let reply = handle_dispatcher_message(query.clone(), &scheduler, &state).await.unwrap();
+        assert!(matches!(reply, Some(Message::TaskStatus { status: TaskStatus::Running, .. })));
+
+        let result = TaskResult {
+            task_id: task_id.clone(),
+            worker_id: "worker-1".to_string(),
+            status: TaskStatus::Completed,
+            stdout: "done\n".to_string(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 5,
+            completed_at: chrono::Local::now().timestamp() - 60,
+            resource_usage: None,
+        };
+        handle_dispatcher_message(Message::TaskCompleted(result), &scheduler, &state).await.unwrap();
+        assert!(state.dispatched_worker(&task_id).await.is_none());
+
+        // Once the in-memory result expires, the status comes from the history store
+        assert_eq!(state.expire_old_results(1).await, 1);
+        let reply = handle_dispatcher_message(query, &scheduler, &state).await.unwrap();
+        assert!(matches!(reply, Some(Message::TaskStatus { status: TaskStatus::Completed, .. })));
+        let reply = handle_dispatcher_message(Message::QueryResult { task_id }, &scheduler, &state).await.unwrap();
+        assert!(matches!(reply, Some(Message::TaskCompleted(result)) if result.stdout == "done\n"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_task_lowers_score_of_the_worker_that_ran_it() {
+        let scheduler = Arc::new(Scheduler::default());
+        let state = DispatcherState::new("test".to_string(), 0).with_scheduler(scheduler.clone());
+        let mut worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        worker.current_jobs = 1;
+        scheduler.register_worker(worker.clone()).await;
+        let before = scheduler.worker_health_score(&scheduler.get_worker(&worker.id).await.unwrap());
+
+        let task = Task::new("false".to_string());
+        let task_id = task.id.clone();
+        state.track_dispatched(task, &worker.id).await;
+
+        let result = TaskResult {
+            task_id,
+            worker_id: worker.id.clone(),
+            status: TaskStatus::Failed,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(1),
+            duration_ms: 5,
+            completed_at: chrono::Local::now().timestamp(),
+            resource_usage: None,
+        };
+        handle_dispatcher_message(Message::TaskCompleted(result), &scheduler, &state).await.unwrap();
+
+        let after = scheduler.get_worker(&worker.id).await.unwrap();
+        assert_eq!(after.tasks_failed, 1);
+        assert_eq!(after.current_jobs, 0);
+        assert!(scheduler.worker_health_score(&after) < before);
+    }
+
+    #[tokio::test]
+    async fn test_result_from_worker_no_longer_running_the_task_is_ignored() {
+        let scheduler = Arc::new(Scheduler::default());
+        let state = DispatcherState::new("test".to_string(), 0).with_scheduler(scheduler.clone());
+        let old = WorkerInfo::new("old".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        let new = WorkerInfo::new("new".to_string(), "127.0.0.1".to_string(), 7880, 2);
+        scheduler.register_worker(old.clone()).await;
+        scheduler.register_worker(new.clone()).await;
+
+        // The task was taken from `old` and dispatched again to `new`
+        let task = Task::new("echo again".to_string());
+        let task_id = task.id.clone();
+        state.track_dispatched(task, &new.id).await;
+        let cancelled = |worker_id: &str| TaskResult {
+            task_id: task_id.clone(),
+            worker_id: worker_id.to_string(),
+            status: TaskStatus::Cancelled,
+            stdout: String::new(),
+            stderr: "Task cancelled".to_string(),
+            exit_code: None,
+            duration_ms: 0,
+            completed_at: chrono::Local::now().timestamp(),
+            resource_usage: None,
+        };
+        handle_dispatcher_message(Message::TaskCompleted(cancelled(&old.id)), &scheduler, &state).await.unwrap();
+        assert_eq!(state.dispatched_worker(&task_id).await, Some(new.id.clone()));
+        assert!(state.get_result(&task_id).await.is_none());
+
+        // Once `old` is removed its late results are dropped even with no dispatch left
+        scheduler.remove_worker(&old.id).await;
+        state.take_dispatched(&task_id).await;
+        handle_dispatcher_message(Message::TaskCompleted(cancelled(&old.id)), &scheduler, &state).await.unwrap();
+        assert!(state.get_result(&task_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_draining_worker_rejects_assignment_without_ack() {
+        let dir = tempfile::tempdir().unwrap();
+        let worker_state = Arc::new(WorkerState::new("w1".to_string(), 0, None));
+        let executor = Arc::new(Executor::new(dir.path().to_path_buf(), true, CommandPolicy::default()));
+        let transport: Arc<dyn TransportBackend> = Arc::new(Transport::new(crate::transport::DEFAULT_COMPRESSION_THRESHOLD));
+        let peer_addr: SocketAddr = "127.0.0.1:7878".parse().unwrap();
+        worker_state.start_draining();
+
+        let assign = Message::AssignTask { task: Task::new("echo hi".to_string()), trace_context: None };
+        assert!(handle_worker_message(assign, &worker_state, &executor, &transport, peer_addr).await.is_err());
+        let batch = Message::BatchAssign(vec![Task::new("echo hi".to_string())]);
+        assert!(handle_worker_message(batch, &worker_state, &executor, &transport, peer_addr).await.is_err());
+        assert_eq!(worker_state.running_jobs(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_task_is_reported_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let worker_state = WorkerState::new("w1".to_string(), 0, None);
+        let policy = CommandPolicy { allowlist: None, denylist: vec![glob::Pattern::new("rm *").unwrap()] };
+        let executor = Executor::new(dir.path().to_path_buf(), true, policy);
+        let transport = Transport::new(crate::transport::DEFAULT_COMPRESSION_THRESHOLD);
+        let dispatcher = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dispatcher_addr = dispatcher.local_addr().unwrap();
+
+        let task = Task::new("rm -rf /tmp/data".to_string());
+        let task_id = task.id.clone();
+        run_assigned_task(task, &CancellationToken::new(), &worker_state, &executor, &transport, dispatcher_addr).await;
+
+        let (mut stream, _) = dispatcher.accept().await.unwrap();
+        let frame = Transport::read_frame(&mut stream, None, DEFAULT_MAX_MESSAGE_BYTES).await.unwrap();
+        let message = Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES).unwrap().message;
+        let Message::TaskCompleted(result) = message else { panic!("expected a result, got {:?}", message) };
+        assert_eq!(result.task_id, task_id);
+        assert_eq!(result.worker_id, worker_state.id);
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert!(result.stderr.contains("rm -rf /tmp/data"), "stderr: {}", result.stderr);
+        assert_eq!(worker_state.running_jobs(), 0);
+    }
+
+    #[test]
+    fn test_certificate_name_must_match_claimed_worker() {
+        let result = |worker_id: &str| TaskResult {
+            task_id: "task-1".to_string(),
+            worker_id: worker_id.to_string(),
+            status: TaskStatus::Completed,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 1,
+            completed_at: 0,
+            resource_usage: None,
+        };
+        assert!(check_certificate_name(Some("worker-a"), &Message::TaskCompleted(result("worker-a"))).is_ok());
+        assert!(check_certificate_name(Some("worker-a"), &Message::TaskCompleted(result("worker-b"))).is_err());
+        assert!(check_certificate_name(None, &Message::TaskCompleted(result("worker-b"))).is_ok());
+
+        let output = Message::TaskOutput {
+            task_id: "task-1".to_string(),
+            worker_id: "worker-b".to_string(),
+            line: "spoofed".to_string(),
+            is_stderr: false,
+        };
+        assert!(check_certificate_name(Some("worker-a"), &output).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_output_from_worker_not_running_the_task_is_ignored() {
+        let scheduler = Arc::new(Scheduler::default());
+        let state = DispatcherState::new("test".to_string(), 0).with_scheduler(scheduler.clone());
+        let task = Task::new("echo hi".to_string());
+        let task_id = task.id.clone();
+        state.track_dispatched(task, "worker-a").await;
+
+        let output = |worker_id: &str, line: &str| Message::TaskOutput {
+            task_id: task_id.clone(),
+            worker_id: worker_id.to_string(),
+            line: line.to_string(),
+            is_stderr: false,
+        };
+        handle_dispatcher_message(output("worker-b", "spoofed"), &scheduler, &state).await.unwrap();
+        handle_dispatcher_message(output("worker-a", "real"), &scheduler, &state).await.unwrap();
+
+        let OutputUpdate::Lines(lines) = state.next_output(&task_id, 0).await else { panic!("task has finished") };
+        assert_eq!(lines.iter().map(|line| line.line.as_str()).collect::<Vec<_>>(), vec!["real"]);
+    }
+
+    #[tokio::test]
+    async fn test_deregister_requires_pre_shared_key() {
+        let scheduler = Arc::new(Scheduler::default());
+        let state = DispatcherState::new("test".to_string(), 0)
+            .with_scheduler(scheduler.clone())
+            .with_psk("secret");
+        let worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        scheduler.register_worker(worker.clone()).await;
+
+        let deregister = |psk: Option<&str>| Message::WorkerDeregister {
+            worker_id: worker.id.clone(),
+            psk_hash: psk.map(util::sha256_hex),
+        };
+        for psk in [None, Some("guess")] {
+            assert!(handle_dispatcher_message(deregister(psk), &scheduler, &state).await.is_err());
+            assert!(scheduler.get_worker(&worker.id).await.is_some());
+        }
+        handle_dispatcher_message(deregister(Some("secret")), &scheduler, &state).await.unwrap();
+        assert!(scheduler.get_worker(&worker.id).await.is_none());
+    }
+}
+
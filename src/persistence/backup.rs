@@ -0,0 +1,141 @@
+use super::StoreBackend;
+use crate::error::{Result, StorageError};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Start of every backup file name, followed by its UTC timestamp
+pub const BACKUP_PREFIX: &str = "octaskly-backup-";
+
+/// Extension of backup files
+pub const BACKUP_EXTENSION: &str = "db";
+
+/// `octaskly-backup-<timestamp>.db`; names sort in the order the backups were taken
+pub fn backup_file_name(at: DateTime<Utc>) -> String {
+    format!("{}{}.{}", BACKUP_PREFIX, at.format("%Y%m%dT%H%M%SZ"), BACKUP_EXTENSION)
+}
+
+fn backup_error(path: &Path) -> impl FnOnce(std::io::Error) -> StorageError + '_ {
+    move |source| StorageError::Backup {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn is_backup_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    name.starts_with(BACKUP_PREFIX) && path.extension().and_then(|ext| ext.to_str()) == Some(BACKUP_EXTENSION)
+}
+
+/// Delete all but the `keep` newest backups in `dir`, returning the deleted paths
+///
+/// Other files in the directory are left alone.
+pub fn prune_backups(dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut backups = std::fs::read_dir(dir)
+        .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<std::io::Result<Vec<_>>>())
+        .map_err(backup_error(dir))?;
+    backups.retain(|path| is_backup_file(path));
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = backups.into_iter().take(excess).collect();
+    for path in &removed {
+        std::fs::remove_file(path).map_err(backup_error(path))?;
+    }
+    Ok(removed)
+}
+
+/// Back the store up to a new timestamped file in `dir`, then apply the retention limit
+pub async fn run_backup(store: &dyn StoreBackend, dir: &Path, retention: usize) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await.map_err(backup_error(dir))?;
+    let path = dir.join(backup_file_name(Utc::now()));
+
+    let started = Instant::now();
+    info!("Starting task history backup to {}", path.display());
+    store.backup_incremental(&path).await?;
+    let size = tokio::fs::metadata(&path).await.map(|meta| meta.len()).unwrap_or_default();
+    info!(
+        "Finished task history backup to {} ({} bytes in {:?})",
+        path.display(),
+        size,
+        started.elapsed()
+    );
+
+    for removed in prune_backups(dir, retention)? {
+        info!("Deleted old backup {}", removed.display());
+    }
+    Ok(path)
+}
+
+/// Back the store up every `interval`, keeping the `retention` newest backups
+///
+/// The first backup is taken one interval after the call.
+pub fn spawn_periodic_backups(
+    store: Arc<dyn StoreBackend + Send + Sync>,
+    dir: PathBuf,
+    interval: Duration,
+    retention: usize,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_backup(store.as_ref(), &dir, retention).await {
+                warn!("Task history backup failed: {:#}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{SqliteStore, StoredTask};
+
+    #[test]
+    fn test_prune_keeps_newest_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        for hour in 0..4 {
+            std::fs::write(dir.path().join(backup_file_name(base + chrono::Duration::hours(hour))), "").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let removed = prune_backups(dir.path(), 2).unwrap();
+        let names: Vec<_> = removed.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["octaskly-backup-20240601T000000Z.db", "octaskly-backup-20240601T010000Z.db"]);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_backup_is_a_readable_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::new(dir.path().join("octaskly.db").to_str().unwrap()).await.unwrap();
+        let task = StoredTask {
+            id: "task-1".to_string(),
+            command: "true".to_string(),
+            status: "Completed".to_string(),
+            worker_id: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 1,
+            created_at: Utc::now().to_rfc3339(),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: None,
+        };
+        store.store_task(&task).await.unwrap();
+
+        let backups = dir.path().join("backups");
+        let path = run_backup(&store, &backups, 1).await.unwrap();
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with(BACKUP_PREFIX));
+
+        let restored = SqliteStore::new(path.to_str().unwrap()).await.unwrap();
+        assert_eq!(restored.get_task("task-1").await.unwrap().unwrap().command, "true");
+    }
+}
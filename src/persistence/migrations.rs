@@ -0,0 +1,185 @@
+use crate::error::StorageError;
+
+/// SQL flavour a script is rendered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+/// Placeholder in the scripts for an auto-incrementing integer primary key
+const AUTO_ID: &str = "{AUTO_ID}";
+
+/// Version 1: tasks, results, and audit log tables
+pub const V1_INITIAL_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id TEXT PRIMARY KEY,
+        command TEXT NOT NULL,
+        status TEXT NOT NULL,
+        worker_id TEXT,
+        stdout TEXT,
+        stderr TEXT,
+        exit_code INTEGER,
+        duration_ms BIGINT,
+        created_at TEXT NOT NULL,
+        completed_at TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS results (
+        task_id TEXT PRIMARY KEY,
+        worker_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        stdout TEXT,
+        stderr TEXT,
+        exit_code INTEGER,
+        duration_ms BIGINT,
+        completed_at TEXT NOT NULL,
+        FOREIGN KEY(task_id) REFERENCES tasks(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS audit_log (
+        id {AUTO_ID},
+        timestamp TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        worker_id TEXT,
+        task_id TEXT,
+        details TEXT
+    );
+";
+
+/// Version 2: task priority
+pub const V2_TASK_PRIORITY: &str = "
+    ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+";
+
+/// Version 3: number of retries a task went through
+pub const V3_TASK_RETRY_COUNT: &str = "
+    ALTER TABLE tasks ADD COLUMN retry_count INTEGER NOT NULL DEFAULT 0;
+";
+
+/// Version 4: tasks waiting in the dispatcher's queue, restored after a restart
+pub const V4_TASK_QUEUE: &str = "
+    CREATE TABLE IF NOT EXISTS task_queue (
+        position BIGINT PRIMARY KEY,
+        task_id TEXT NOT NULL,
+        task TEXT NOT NULL
+    );
+";
+
+/// Version 5: tasks that failed after exhausting their retries
+pub const V5_DEAD_LETTER: &str = "
+    CREATE TABLE IF NOT EXISTS dead_letter (
+        task_id TEXT PRIMARY KEY,
+        task TEXT NOT NULL,
+        failed_at TEXT NOT NULL
+    );
+";
+
+/// Version 6: lifetime task totals per worker
+pub const V6_WORKERS: &str = "
+    CREATE TABLE IF NOT EXISTS workers (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        platform TEXT NOT NULL,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL,
+        tasks_completed BIGINT NOT NULL DEFAULT 0,
+        tasks_failed BIGINT NOT NULL DEFAULT 0,
+        total_duration_ms BIGINT NOT NULL DEFAULT 0
+    );
+";
+
+/// Version 7: peak memory of the process that ran a task
+pub const V7_TASK_MAX_RSS: &str = "
+    ALTER TABLE tasks ADD COLUMN max_rss_kb BIGINT;
+";
+
+/// Version 8: the task a finished task was resubmitted as
+pub const V8_TASK_RESUBMITTED_AS: &str = "
+    ALTER TABLE tasks ADD COLUMN resubmitted_as TEXT;
+";
+
+/// Version 9: the full definition a task was submitted with, as JSON
+pub const V9_TASK_DEFINITION: &str = "
+    ALTER TABLE tasks ADD COLUMN definition TEXT;
+";
+
+/// Version 10: API keys, stored by the hash of the key
+pub const V10_API_KEYS: &str = "
+    CREATE TABLE IF NOT EXISTS api_keys (
+        key_hash TEXT PRIMARY KEY,
+        label TEXT NOT NULL UNIQUE,
+        record TEXT NOT NULL
+    );
+";
+
+/// Migration scripts in order; script `i` upgrades the schema from version `i` to `i + 1`
+pub const MIGRATIONS: &[&str] = &[
+    V1_INITIAL_SCHEMA,
+    V2_TASK_PRIORITY,
+    V3_TASK_RETRY_COUNT,
+    V4_TASK_QUEUE,
+    V5_DEAD_LETTER,
+    V6_WORKERS,
+    V7_TASK_MAX_RSS,
+    V8_TASK_RESUBMITTED_AS,
+    V9_TASK_DEFINITION,
+    V10_API_KEYS,
+];
+
+/// Schema version this build expects
+pub const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Single-row table recording the applied schema version
+pub const CREATE_VERSION_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)";
+
+pub const SELECT_VERSION: &str = "SELECT version FROM schema_version LIMIT 1";
+
+pub const CLEAR_VERSION: &str = "DELETE FROM schema_version";
+
+pub const INSERT_VERSION: &str = "INSERT INTO schema_version (version) VALUES ($1)";
+
+/// Render a migration script for the given database
+pub fn render(script: &str, dialect: Dialect) -> String {
+    let auto_id = match dialect {
+        Dialect::Sqlite => "INTEGER PRIMARY KEY AUTOINCREMENT",
+        Dialect::Postgres => "BIGSERIAL PRIMARY KEY",
+    };
+    script.replace(AUTO_ID, auto_id)
+}
+
+/// Migrations still to apply to a database at `from`, as `(target_version, script)`
+pub fn pending(from: u32, dialect: Dialect) -> Result<Vec<(u32, String)>, StorageError> {
+    if from > CURRENT_VERSION {
+        return Err(StorageError::SchemaTooNew {
+            found: from,
+            supported: CURRENT_VERSION,
+        });
+    }
+
+    Ok(MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(from as usize)
+        .map(|(index, script)| (index as u32 + 1, render(script, dialect)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_auto_id_per_dialect() {
+        assert!(render(V1_INITIAL_SCHEMA, Dialect::Sqlite).contains("id INTEGER PRIMARY KEY AUTOINCREMENT"));
+        assert!(render(V1_INITIAL_SCHEMA, Dialect::Postgres).contains("id BIGSERIAL PRIMARY KEY"));
+    }
+
+    #[test]
+    fn test_pending_skips_applied_versions() {
+        let versions: Vec<u32> = pending(1, Dialect::Sqlite).unwrap().into_iter().map(|(v, _)| v).collect();
+        assert_eq!(versions, vec![2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert!(pending(CURRENT_VERSION, Dialect::Postgres).unwrap().is_empty());
+        assert!(pending(CURRENT_VERSION + 1, Dialect::Sqlite).is_err());
+    }
+}
@@ -1,8 +1,22 @@
-use anyhow::Result;
-use rusqlite::{Connection, params};
+pub mod backup;
+pub mod migrations;
+pub mod postgres;
+/// Statements shared by both backends; parameters are written `$1`, `$2`, ...
+mod queries;
+pub mod sqlite;
+
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+use crate::error::{Result, StorageError};
+use async_trait::async_trait;
+use queries::SqlParam;
 use serde::{Serialize, Deserialize};
-use std::sync::{Arc, Mutex};
-use chrono::Utc;
+use std::path::Path;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use crate::auth::ApiKeyRecord;
+use crate::protocol::{Task, TaskResult, TaskStatus, WorkerInfo};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredTask {
@@ -16,307 +30,481 @@ pub struct StoredTask {
     pub duration_ms: u64,
     pub created_at: String,
     pub completed_at: Option<String>,
+    /// Peak memory of the task's process, when the worker measured it
+    pub max_rss_kb: Option<u64>,
+    /// ID of the task this one was resubmitted as, if it was
+    #[serde(default)]
+    pub resubmitted_as: Option<String>,
+    /// Full definition the task was submitted with, used to resubmit it; left out of
+    /// exports and API responses since its env may hold secrets
+    #[serde(skip)]
+    pub definition: Option<Task>,
 }
 
-/// Persistent storage for task history using SQLite
-pub struct PersistentStore {
-    conn: Arc<Mutex<Connection>>,
-}
+impl StoredTask {
+    /// History record for a task that has finished
+    ///
+    /// A task cancelled before assignment has no worker, so `worker_id` is left empty.
+    pub fn from_result(task: &Task, result: &TaskResult) -> Self {
+        Self {
+            id: task.id.clone(),
+            command: task.command.clone(),
+            status: format!("{:?}", result.status),
+            worker_id: Some(result.worker_id.clone()).filter(|id| !id.is_empty()),
+            stdout: result.stdout.clone(),
+            stderr: result.stderr.clone(),
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+            created_at: rfc3339_from_timestamp(task.created_at),
+            completed_at: Some(rfc3339_from_timestamp(result.completed_at)),
+            max_rss_kb: result.resource_usage.map(|usage| usage.max_rss_kb),
+            resubmitted_as: None,
+            definition: Some(definition_of(task)),
+        }
+    }
 
-impl PersistentStore {
-    pub fn new(db_path: &str) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-        
-        // Enable WAL mode for better concurrency
-        conn.execute("PRAGMA journal_mode = WAL", [])?;
-        
-        // Create tables
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tasks (
-                id TEXT PRIMARY KEY,
-                command TEXT NOT NULL,
-                status TEXT NOT NULL,
-                worker_id TEXT,
-                stdout TEXT,
-                stderr TEXT,
-                exit_code INTEGER,
-                duration_ms INTEGER,
-                created_at TEXT NOT NULL,
-                completed_at TEXT
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS results (
-                task_id TEXT PRIMARY KEY,
-                worker_id TEXT NOT NULL,
-                status TEXT NOT NULL,
-                stdout TEXT,
-                stderr TEXT,
-                exit_code INTEGER,
-                duration_ms INTEGER,
-                completed_at TEXT NOT NULL,
-                FOREIGN KEY(task_id) REFERENCES tasks(id)
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS audit_log (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                timestamp TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                worker_id TEXT,
-                task_id TEXT,
-                details TEXT
-            )",
-            [],
-        )?;
-
-        Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
+    /// History record for a task that is queued and has not finished yet
+    pub fn pending(task: &Task) -> Self {
+        Self {
+            id: task.id.clone(),
+            command: task.command.clone(),
+            status: format!("{:?}", TaskStatus::Pending),
+            worker_id: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: None,
+            duration_ms: 0,
+            created_at: rfc3339_from_timestamp(task.created_at),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: Some(definition_of(task)),
+        }
+    }
+
+    /// The recorded outcome, or `None` if the status is not a known `TaskStatus`
+    pub fn to_result(&self) -> Option<TaskResult> {
+        let status = serde_json::from_value(serde_json::Value::String(self.status.clone())).ok()?;
+        let completed_at = self
+            .completed_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.timestamp())
+            .unwrap_or_default();
+
+        Some(TaskResult {
+            task_id: self.id.clone(),
+            worker_id: self.worker_id.clone().unwrap_or_default(),
+            status,
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+            exit_code: self.exit_code,
+            duration_ms: self.duration_ms,
+            completed_at,
+            resource_usage: None,
         })
     }
+}
 
-    /// Store a task
-    pub fn store_task(&self, task: &StoredTask) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        
-        conn.execute(
-            "INSERT OR REPLACE INTO tasks 
-             (id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            params![
-                task.id,
-                task.command,
-                task.status,
-                task.worker_id,
-                task.stdout,
-                task.stderr,
-                task.exit_code,
-                task.duration_ms,
-                task.created_at,
-                task.completed_at,
-            ],
-        )?;
-        
-        Ok(())
+/// `task` without the contents of its staged files, which are read again when it is dispatched
+fn definition_of(task: &Task) -> Task {
+    let mut task = task.clone();
+    for file in task.input_files.iter_mut().chain(task.output_files.iter_mut()) {
+        file.content = None;
     }
+    task.script_content = None;
+    task
+}
 
-    /// Retrieve a task
-    pub fn get_task(&self, task_id: &str) -> Result<Option<StoredTask>> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut stmt = conn.prepare(
-            "SELECT id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at
-             FROM tasks WHERE id = ?1"
-        )?;
-        
-        let result = stmt.query_row(params![task_id], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                command: row.get(1)?,
-                status: row.get(2)?,
-                worker_id: row.get(3)?,
-                stdout: row.get(4)?,
-                stderr: row.get(5)?,
-                exit_code: row.get(6)?,
-                duration_ms: row.get(7)?,
-                created_at: row.get(8)?,
-                completed_at: row.get(9)?,
-            })
-        });
-        
-        match result {
-            Ok(task) => Ok(Some(task)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+fn rfc3339_from_timestamp(secs: i64) -> String {
+    DateTime::<Utc>::from_timestamp(secs, 0).unwrap_or_default().to_rfc3339()
+}
+
+/// Default page size for `TaskFilter`
+pub const DEFAULT_SEARCH_LIMIT: usize = 1000;
+
+/// Column names of a CSV export, one per `StoredTask` field
+pub const CSV_HEADER: [&str; 12] = [
+    "id",
+    "command",
+    "status",
+    "worker_id",
+    "stdout",
+    "stderr",
+    "exit_code",
+    "duration_ms",
+    "created_at",
+    "completed_at",
+    "max_rss_kb",
+    "resubmitted_as",
+];
+
+/// Write `tasks` as CSV, header first, returning the number of rows written
+///
+/// The header is written even when there are no tasks.
+pub fn write_csv<W: std::io::Write>(writer: W, tasks: &[StoredTask]) -> Result<usize> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(writer);
+    writer.write_record(CSV_HEADER)?;
+    for task in tasks {
+        writer.serialize(task)?;
     }
+    writer.flush()?;
+    Ok(tasks.len())
+}
 
-    /// Get all tasks
-    pub fn get_all_tasks(&self) -> Result<Vec<StoredTask>> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut stmt = conn.prepare(
-            "SELECT id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at
-             FROM tasks ORDER BY created_at DESC LIMIT 1000"
-        )?;
-        
-        let tasks = stmt.query_map([], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                command: row.get(1)?,
-                status: row.get(2)?,
-                worker_id: row.get(3)?,
-                stdout: row.get(4)?,
-                stderr: row.get(5)?,
-                exit_code: row.get(6)?,
-                duration_ms: row.get(7)?,
-                created_at: row.get(8)?,
-                completed_at: row.get(9)?,
-            })
-        })?;
-        
-        let mut result = Vec::new();
-        for task in tasks {
-            result.push(task?);
+/// Criteria for `StoreBackend::search_tasks`; unset fields match every task
+#[derive(Debug, Clone)]
+pub struct TaskFilter {
+    pub status: Option<String>,
+    pub worker_id: Option<String>,
+    pub exit_code: Option<i32>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl Default for TaskFilter {
+    fn default() -> Self {
+        Self {
+            status: None,
+            worker_id: None,
+            exit_code: None,
+            created_after: None,
+            created_before: None,
+            limit: DEFAULT_SEARCH_LIMIT,
+            offset: 0,
         }
-        Ok(result)
     }
+}
 
-    /// Get tasks for a worker
-    pub fn get_worker_tasks(&self, worker_id: &str) -> Result<Vec<StoredTask>> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut stmt = conn.prepare(
-            "SELECT id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at
-             FROM tasks WHERE worker_id = ?1 ORDER BY created_at DESC LIMIT 100"
-        )?;
-        
-        let tasks = stmt.query_map(params![worker_id], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                command: row.get(1)?,
-                status: row.get(2)?,
-                worker_id: row.get(3)?,
-                stdout: row.get(4)?,
-                stderr: row.get(5)?,
-                exit_code: row.get(6)?,
-                duration_ms: row.get(7)?,
-                created_at: row.get(8)?,
-                completed_at: row.get(9)?,
-            })
-        })?;
-        
-        let mut result = Vec::new();
-        for task in tasks {
-            result.push(task?);
-        }
-        Ok(result)
+impl TaskFilter {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Delete old tasks (cleanup)
-    pub fn cleanup_old_tasks(&self, days: i64) -> Result<usize> {
-        let conn = self.conn.lock().unwrap();
-        
-        let cutoff = Utc::now() - chrono::Duration::days(days);
-        let cutoff_str = cutoff.to_rfc3339();
-        
-        let rows = conn.execute(
-            "DELETE FROM tasks WHERE created_at < ?1",
-            params![cutoff_str],
-        )?;
-        
-        Ok(rows)
+    /// Filter matching every task, with no page size limit
+    pub fn all() -> Self {
+        // Bound as a signed 64-bit LIMIT, so stay within its range
+        Self::new().with_limit(i64::MAX as usize)
     }
 
-    /// Record audit log entry
-    pub fn log_event(&self, event_type: &str, worker_id: Option<&str>, task_id: Option<&str>, details: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        
-        let timestamp = Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO audit_log (timestamp, event_type, worker_id, task_id, details)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![timestamp, event_type, worker_id, task_id, details],
-        )?;
-        
-        Ok(())
+    pub fn with_status(mut self, status: impl Into<String>) -> Self {
+        self.status = Some(status.into());
+        self
     }
 
-    /// Get audit logs
-    pub fn get_audit_logs(&self, limit: usize) -> Result<Vec<(String, String, Option<String>, Option<String>, String)>> {
-        let conn = self.conn.lock().unwrap();
-        
-        let mut stmt = conn.prepare(
-            "SELECT timestamp, event_type, worker_id, task_id, details
-             FROM audit_log ORDER BY timestamp DESC LIMIT ?1"
-        )?;
-        
-        let logs = stmt.query_map(params![limit as i64], |row| {
-            Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-            ))
-        })?;
-        
-        let mut result = Vec::new();
-        for log in logs {
-            result.push(log?);
+    pub fn with_worker(mut self, worker_id: impl Into<String>) -> Self {
+        self.worker_id = Some(worker_id.into());
+        self
+    }
+
+    pub fn with_exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    /// Only tasks created at or after `time`
+    pub fn with_created_after(mut self, time: DateTime<Utc>) -> Self {
+        self.created_after = Some(time);
+        self
+    }
+
+    /// Only tasks created strictly before `time`
+    pub fn with_created_before(mut self, time: DateTime<Utc>) -> Self {
+        self.created_before = Some(time);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Build the WHERE/LIMIT tail of the query together with its bound parameters
+    fn to_sql(&self) -> (String, Vec<SqlParam>) {
+        let (mut sql, mut values) = self.where_sql();
+        values.push(SqlParam::Integer(self.limit as i64));
+        values.push(SqlParam::Integer(self.offset as i64));
+        sql.push_str(&format!(" ORDER BY created_at DESC LIMIT ${} OFFSET ${}", values.len() - 1, values.len()));
+
+        (sql, values)
+    }
+
+    /// Build the WHERE clause alone, ignoring limit and offset
+    fn where_sql(&self) -> (String, Vec<SqlParam>) {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(status) = &self.status {
+            values.push(SqlParam::Text(status.clone()));
+            clauses.push(format!("status = ${}", values.len()));
+        }
+        if let Some(worker_id) = &self.worker_id {
+            values.push(SqlParam::Text(worker_id.clone()));
+            clauses.push(format!("worker_id = ${}", values.len()));
+        }
+        if let Some(exit_code) = self.exit_code {
+            values.push(SqlParam::Integer(exit_code.into()));
+            clauses.push(format!("exit_code = ${}", values.len()));
+        }
+        // created_at is RFC 3339 in UTC, so text comparison follows time order
+        if let Some(after) = self.created_after {
+            values.push(SqlParam::Text(after.to_rfc3339()));
+            clauses.push(format!("created_at >= ${}", values.len()));
+        }
+        if let Some(before) = self.created_before {
+            values.push(SqlParam::Text(before.to_rfc3339()));
+            clauses.push(format!("created_at < ${}", values.len()));
+        }
+
+        let mut sql = String::new();
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
         }
-        Ok(result)
+
+        (sql, values)
     }
+}
 
-    /// Get statistics
-    pub fn get_stats(&self) -> Result<(usize, usize, usize)> {
-        let conn = self.conn.lock().unwrap();
-        
-        let total: usize = conn.query_row(
-            "SELECT COUNT(*) FROM tasks",
-            [],
-            |row| row.get(0),
-        )?;
-        
-        let completed: usize = conn.query_row(
-            "SELECT COUNT(*) FROM tasks WHERE status = 'Completed'",
-            [],
-            |row| row.get(0),
-        )?;
-        
-        let failed: usize = conn.query_row(
-            "SELECT COUNT(*) FROM tasks WHERE status = 'Failed'",
-            [],
-            |row| row.get(0),
-        )?;
-        
-        Ok((total, completed, failed))
+/// Lifetime task totals for one worker, kept across dispatcher restarts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkerSummary {
+    pub id: String,
+    pub name: String,
+    pub platform: String,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    pub total_duration_ms: u64,
+}
+
+impl WorkerSummary {
+    /// Mean duration of the tasks the worker finished, or 0 before the first one
+    pub fn average_duration_ms(&self) -> u64 {
+        self.total_duration_ms
+            .checked_div(self.tasks_completed + self.tasks_failed)
+            .unwrap_or(0)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_persistent_storage() {
-        let store = match PersistentStore::new(":memory:") {
-            Ok(s) => s,
-            Err(e) => {
-                println!("Failed to create store: {}", e);
-                return;
-            }
-        };
-        
-        let task = StoredTask {
-            id: "test-1".to_string(),
-            command: "echo hello".to_string(),
-            status: "Completed".to_string(),
-            worker_id: Some("worker-1".to_string()),
-            stdout: "hello".to_string(),
-            stderr: String::new(),
-            exit_code: Some(0),
-            duration_ms: 100,
-            created_at: chrono::Utc::now().to_rfc3339(),
-            completed_at: Some(chrono::Utc::now().to_rfc3339()),
-        };
-        
-        if let Err(e) = store.store_task(&task) {
-            println!("Failed to store task: {}", e);
-            return;
+/// Something worth auditing, stored as JSON in the `details` column of `audit_log`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    WorkerRegistered { worker_id: String, address: String },
+    WorkerOffline { worker_id: String },
+    TaskSubmitted { task_id: String, command: String },
+    TaskAssigned { task_id: String, worker_id: String },
+    TaskCompleted { task_id: String, duration_ms: u64 },
+    TaskFailed { task_id: String, exit_code: Option<i32> },
+    TaskRetrying { task_id: String, attempt: u32 },
+    AuthSuccess { user: String, role: String },
+    AuthFailure { user: String, reason: String },
+    MessageOutOfOrder { peer: String, expected: u64, received: u64 },
+}
+
+impl AuditEvent {
+    /// Outcome of a finished task; `None` for results that are neither a success nor a failure
+    pub fn for_result(result: &TaskResult) -> Option<Self> {
+        match result.status {
+            TaskStatus::Completed => Some(Self::TaskCompleted {
+                task_id: result.task_id.clone(),
+                duration_ms: result.duration_ms,
+            }),
+            TaskStatus::Failed | TaskStatus::TimedOut => Some(Self::TaskFailed {
+                task_id: result.task_id.clone(),
+                exit_code: result.exit_code,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Value of the `event_type` column, the same as the `type` field of the JSON
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            Self::WorkerRegistered { .. } => "worker_registered",
+            Self::WorkerOffline { .. } => "worker_offline",
+            Self::TaskSubmitted { .. } => "task_submitted",
+            Self::TaskAssigned { .. } => "task_assigned",
+            Self::TaskCompleted { .. } => "task_completed",
+            Self::TaskFailed { .. } => "task_failed",
+            Self::TaskRetrying { .. } => "task_retrying",
+            Self::AuthSuccess { .. } => "auth_success",
+            Self::AuthFailure { .. } => "auth_failure",
+            Self::MessageOutOfOrder { .. } => "message_out_of_order",
         }
-        
-        match store.get_task("test-1") {
-            Ok(Some(retrieved)) => {
-                assert_eq!(retrieved.command, "echo hello");
-            }
-            Ok(None) => panic!("Task not found"),
-            Err(e) => panic!("Failed to retrieve task: {}", e),
+    }
+
+    pub fn worker_id(&self) -> Option<&str> {
+        match self {
+            Self::WorkerRegistered { worker_id, .. }
+            | Self::WorkerOffline { worker_id }
+            | Self::TaskAssigned { worker_id, .. } => Some(worker_id),
+            _ => None,
         }
     }
+
+    pub fn task_id(&self) -> Option<&str> {
+        match self {
+            Self::TaskSubmitted { task_id, .. }
+            | Self::TaskAssigned { task_id, .. }
+            | Self::TaskCompleted { task_id, .. }
+            | Self::TaskFailed { task_id, .. }
+            | Self::TaskRetrying { task_id, .. } => Some(task_id),
+            _ => None,
+        }
+    }
+}
+
+/// Events in the `details` column of audit rows
+///
+/// Rows written before events were structured hold free-form text and are skipped.
+fn parse_audit_events(details: Vec<String>) -> Vec<AuditEvent> {
+    details.iter().filter_map(|details| serde_json::from_str(details).ok()).collect()
+}
+
+/// One audit log row: `(timestamp, event_type, worker_id, task_id, details)`
+pub type AuditLogEntry = (String, String, Option<String>, Option<String>, String);
+
+/// Task history and audit log storage, implemented by each supported database
+#[async_trait]
+pub trait StoreBackend: Send + Sync {
+    /// Store a task, replacing any existing row with the same ID
+    async fn store_task(&self, task: &StoredTask) -> Result<()>;
+
+    /// Store many tasks in one transaction, replacing existing rows with the same IDs
+    ///
+    /// Either every task is written or, on error, none are.
+    async fn bulk_insert_tasks(&self, tasks: &[StoredTask]) -> Result<()>;
+
+    /// Retrieve a task
+    async fn get_task(&self, task_id: &str) -> Result<Option<StoredTask>>;
+
+    /// Link a stored task to the task it was resubmitted as
+    async fn set_resubmitted_as(&self, task_id: &str, new_task_id: &str) -> Result<()>;
+
+    /// Find tasks matching every criterion set on `filter`, newest first
+    async fn search_tasks(&self, filter: TaskFilter) -> Result<Vec<StoredTask>>;
+
+    /// Count tasks matching `filter`, ignoring its limit and offset
+    async fn count_tasks(&self, filter: &TaskFilter) -> Result<usize>;
+
+    /// Delete tasks created more than `days` days ago
+    async fn cleanup_old_tasks(&self, days: i64) -> Result<usize>;
+
+    /// Record audit log entry
+    async fn log_event(&self, event: &AuditEvent) -> Result<()>;
+
+    /// Get the most recent audit log entries
+    async fn get_audit_logs(&self, limit: usize) -> Result<Vec<AuditLogEntry>>;
+
+    /// Get the most recent events of one type (e.g. `"task_failed"`), newest first
+    async fn get_audit_events_by_type(&self, event_type: &str, limit: usize) -> Result<Vec<AuditEvent>>;
+
+    /// Get `(total, completed, failed)` task counts and the mean run time of completed tasks
+    async fn get_stats(&self) -> Result<(usize, usize, usize, u64)>;
+
+    /// Replace the saved dispatcher queue with `tasks`, in the order they will be scheduled
+    async fn save_queue(&self, tasks: &[Task]) -> Result<()>;
+
+    /// Tasks saved by `save_queue`, in scheduling order
+    async fn load_queue(&self) -> Result<Vec<Task>>;
+
+    /// Add a task to the dead letter queue, replacing an earlier entry for it
+    async fn add_dead_letter(&self, task: &Task) -> Result<()>;
+
+    /// Drop a task from the dead letter queue
+    async fn remove_dead_letter(&self, task_id: &str) -> Result<()>;
+
+    /// Tasks in the dead letter queue, oldest failure first
+    async fn load_dead_letters(&self) -> Result<Vec<Task>>;
+
+    /// Save a newly created API key; an error if its label is taken
+    async fn add_api_key(&self, record: &ApiKeyRecord) -> Result<()>;
+
+    /// Drop the API key with `label`
+    async fn remove_api_key(&self, label: &str) -> Result<()>;
+
+    /// Every saved API key, ordered by label
+    async fn load_api_keys(&self) -> Result<Vec<ApiKeyRecord>>;
+
+    /// Record a worker registration, keeping its totals if it registered before
+    async fn upsert_worker(&self, info: &WorkerInfo) -> Result<()>;
+
+    /// Add one finished task to a worker's totals
+    async fn record_worker_task_completion(&self, worker_id: &str, duration_ms: u64, success: bool) -> Result<()>;
+
+    /// Totals for one worker; an error if the worker was never recorded
+    async fn get_worker_summary(&self, worker_id: &str) -> Result<WorkerSummary>;
+
+    /// Totals for every recorded worker, in order of first registration
+    async fn get_all_worker_summaries(&self) -> Result<Vec<WorkerSummary>>;
+
+    /// Copy the whole database to a new file at `dest_path` while it stays online
+    ///
+    /// Only the SQLite store supports this; PostgreSQL has its own backup tooling.
+    async fn backup_incremental(&self, dest_path: &Path) -> Result<()> {
+        Err(StorageError::Unsupported(format!(
+            "Cannot back up to {}: online backups need a SQLite store",
+            dest_path.display()
+        ))
+        .into())
+    }
+
+    /// Get all tasks
+    async fn get_all_tasks(&self) -> Result<Vec<StoredTask>> {
+        self.search_tasks(TaskFilter::new()).await
+    }
+
+    /// Get one page of matching tasks, newest first, with the total match count
+    ///
+    /// `page` starts at 1.
+    async fn get_tasks_paginated(&self, filter: TaskFilter, page: usize, limit: usize) -> Result<(Vec<StoredTask>, usize)> {
+        let total = self.count_tasks(&filter).await?;
+        let offset = page.saturating_sub(1) * limit;
+        let tasks = self.search_tasks(filter.with_limit(limit).with_offset(offset)).await?;
+        Ok((tasks, total))
+    }
+
+    /// Write every task to a CSV file at `path`, returning the number of rows written
+    async fn export_csv(&self, path: &Path) -> Result<usize> {
+        self.export_csv_filtered(TaskFilter::all(), path).await
+    }
+
+    /// Write the tasks matching `filter` to a CSV file at `path`, newest first
+    async fn export_csv_filtered(&self, filter: TaskFilter, path: &Path) -> Result<usize> {
+        let tasks = self.search_tasks(filter).await?;
+        let mut csv = Vec::new();
+        let rows = write_csv(&mut csv, &tasks)?;
+        tokio::fs::write(path, csv).await?;
+        Ok(rows)
+    }
+
+    /// Get tasks for a worker
+    async fn get_worker_tasks(&self, worker_id: &str) -> Result<Vec<StoredTask>> {
+        self.search_tasks(TaskFilter::new().with_worker(worker_id).with_limit(100)).await
+    }
+}
+
+/// Open the store named by `db_url`: PostgreSQL for `postgres://` URLs, otherwise a SQLite file path
+pub async fn open(db_url: &str) -> Result<Arc<dyn StoreBackend + Send + Sync>> {
+    if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresStore::new(db_url).await?))
+    } else {
+        let path = db_url.strip_prefix("sqlite://").unwrap_or(db_url);
+        Ok(Arc::new(SqliteStore::new(path).await?))
+    }
+}
+
+/// Cutoff timestamp for `cleanup_old_tasks`
+fn cleanup_cutoff(days: i64) -> String {
+    (Utc::now() - chrono::Duration::days(days)).to_rfc3339()
 }
@@ -0,0 +1,401 @@
+use super::migrations::{self, Dialect};
+use super::queries::{self, SqlParam};
+use super::{cleanup_cutoff, parse_audit_events, AuditEvent, AuditLogEntry, StoreBackend, StoredTask, TaskFilter, WorkerSummary};
+use crate::auth::ApiKeyRecord;
+use crate::protocol::{Task, WorkerInfo};
+use crate::error::{Result, StorageError};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::postgres::{PgArguments, PgPool, PgPoolOptions, PgRow};
+use sqlx::query::Query;
+use sqlx::Postgres;
+use sqlx::{Executor, Row};
+use tracing::info;
+
+/// Connections kept open to the shared database
+const MAX_CONNECTIONS: u32 = 10;
+
+fn task_from_row(row: &PgRow) -> sqlx::Result<StoredTask> {
+    Ok(StoredTask {
+        id: row.try_get(0)?,
+        command: row.try_get(1)?,
+        status: row.try_get(2)?,
+        worker_id: row.try_get(3)?,
+        stdout: row.try_get::<Option<String>, _>(4)?.unwrap_or_default(),
+        stderr: row.try_get::<Option<String>, _>(5)?.unwrap_or_default(),
+        exit_code: row.try_get(6)?,
+        duration_ms: row.try_get::<Option<i64>, _>(7)?.unwrap_or_default() as u64,
+        created_at: row.try_get(8)?,
+        completed_at: row.try_get(9)?,
+        max_rss_kb: row.try_get::<Option<i64>, _>(10)?.map(|kb| kb as u64),
+        resubmitted_as: row.try_get(11)?,
+        definition: row
+            .try_get::<Option<String>, _>(12)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    })
+}
+
+fn worker_from_row(row: &PgRow) -> sqlx::Result<WorkerSummary> {
+    Ok(WorkerSummary {
+        id: row.try_get(0)?,
+        name: row.try_get(1)?,
+        platform: row.try_get(2)?,
+        first_seen: row.try_get(3)?,
+        last_seen: row.try_get(4)?,
+        tasks_completed: row.try_get::<i64, _>(5)? as u64,
+        tasks_failed: row.try_get::<i64, _>(6)? as u64,
+        total_duration_ms: row.try_get::<i64, _>(7)? as u64,
+    })
+}
+
+fn upsert_task(task: &StoredTask) -> Query<'_, Postgres, PgArguments> {
+    sqlx::query(queries::UPSERT_TASK)
+        .bind(&task.id)
+        .bind(&task.command)
+        .bind(&task.status)
+        .bind(&task.worker_id)
+        .bind(&task.stdout)
+        .bind(&task.stderr)
+        .bind(task.exit_code)
+        .bind(task.duration_ms as i64)
+        .bind(&task.created_at)
+        .bind(&task.completed_at)
+        .bind(task.max_rss_kb.map(|kb| kb as i64))
+        .bind(task.definition.as_ref().and_then(|task| serde_json::to_string(task).ok()))
+}
+
+/// Task history stored in a PostgreSQL database shared by the cluster
+#[derive(Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Connect using a `DATABASE_URL` style connection string and migrate the schema
+    pub async fn new(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect(database_url)
+            .await?;
+        let store = Self { pool };
+
+        let from = store.migrate().await?;
+        if from < migrations::CURRENT_VERSION {
+            info!("Migrated task history schema from version {} to {}", from, migrations::CURRENT_VERSION);
+        }
+
+        Ok(store)
+    }
+
+    /// Version recorded in `schema_version`, or 0 for a fresh database
+    pub async fn schema_version(&self) -> Result<u32> {
+        sqlx::query(migrations::CREATE_VERSION_TABLE).execute(&self.pool).await?;
+        let version: Option<i32> = sqlx::query_scalar(migrations::SELECT_VERSION)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(version.unwrap_or(0) as u32)
+    }
+
+    /// Bring the schema up to `CURRENT_VERSION`, returning the version it started from
+    async fn migrate(&self) -> Result<u32> {
+        let from = self.schema_version().await?;
+
+        for (version, script) in migrations::pending(from, Dialect::Postgres)? {
+            let mut tx = self.pool.begin().await?;
+            // Through `Executor` so the future stays `Send` for callers that spawn `new`
+            (&mut *tx).execute(sqlx::raw_sql(&script)).await?;
+            sqlx::query(migrations::CLEAR_VERSION).execute(&mut *tx).await?;
+            sqlx::query(migrations::INSERT_VERSION)
+                .bind(version as i32)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+
+        Ok(from)
+    }
+}
+
+#[async_trait]
+impl StoreBackend for PostgresStore {
+    async fn store_task(&self, task: &StoredTask) -> Result<()> {
+        upsert_task(task).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn bulk_insert_tasks(&self, tasks: &[StoredTask]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for task in tasks {
+            upsert_task(task).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_task(&self, task_id: &str) -> Result<Option<StoredTask>> {
+        let row = sqlx::query(queries::SELECT_TASK_BY_ID)
+            .bind(task_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.as_ref().map(task_from_row).transpose()?)
+    }
+
+    async fn search_tasks(&self, filter: TaskFilter) -> Result<Vec<StoredTask>> {
+        let (tail, values) = filter.to_sql();
+        let sql = format!("{}{}", queries::SELECT_TASKS, tail);
+
+        let mut query = sqlx::query(&sql);
+        for value in values {
+            query = match value {
+                SqlParam::Text(text) => query.bind(text),
+                SqlParam::Integer(value) => query.bind(value),
+            };
+        }
+
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(task_from_row).collect::<sqlx::Result<_>>()?)
+    }
+
+    async fn count_tasks(&self, filter: &TaskFilter) -> Result<usize> {
+        let (clause, values) = filter.where_sql();
+        let sql = format!("{}{}", queries::COUNT_TASKS, clause);
+
+        let mut query = sqlx::query_scalar::<_, i64>(&sql);
+        for value in values {
+            query = match value {
+                SqlParam::Text(text) => query.bind(text),
+                SqlParam::Integer(value) => query.bind(value),
+            };
+        }
+
+        Ok(query.fetch_one(&self.pool).await? as usize)
+    }
+
+    async fn cleanup_old_tasks(&self, days: i64) -> Result<usize> {
+        let result = sqlx::query(queries::DELETE_TASKS_CREATED_BEFORE)
+            .bind(cleanup_cutoff(days))
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    async fn log_event(&self, event: &AuditEvent) -> Result<()> {
+        sqlx::query(queries::INSERT_AUDIT_EVENT)
+            .bind(Utc::now().to_rfc3339())
+            .bind(event.event_type())
+            .bind(event.worker_id())
+            .bind(event.task_id())
+            .bind(serde_json::to_string(event)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_audit_logs(&self, limit: usize) -> Result<Vec<AuditLogEntry>> {
+        let rows = sqlx::query(queries::SELECT_AUDIT_EVENTS)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let logs = rows
+            .iter()
+            .map(|row| {
+                Ok((
+                    row.try_get(0)?,
+                    row.try_get(1)?,
+                    row.try_get(2)?,
+                    row.try_get(3)?,
+                    row.try_get::<Option<String>, _>(4)?.unwrap_or_default(),
+                ))
+            })
+            .collect::<sqlx::Result<_>>()?;
+        Ok(logs)
+    }
+
+    async fn get_audit_events_by_type(&self, event_type: &str, limit: usize) -> Result<Vec<AuditEvent>> {
+        let details: Vec<String> = sqlx::query_scalar(queries::SELECT_AUDIT_DETAILS_BY_TYPE)
+            .bind(event_type)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(parse_audit_events(details))
+    }
+
+    async fn save_queue(&self, tasks: &[Task]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(queries::CLEAR_QUEUE).execute(&mut *tx).await?;
+        for (position, task) in tasks.iter().enumerate() {
+            sqlx::query(queries::INSERT_QUEUED_TASK)
+                .bind(position as i64)
+                .bind(&task.id)
+                .bind(serde_json::to_string(task)?)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn load_queue(&self) -> Result<Vec<Task>> {
+        let rows: Vec<String> = sqlx::query_scalar(queries::SELECT_QUEUE).fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|task| serde_json::from_str(task)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn add_dead_letter(&self, task: &Task) -> Result<()> {
+        sqlx::query(queries::INSERT_DEAD_LETTER)
+            .bind(&task.id)
+            .bind(serde_json::to_string(task)?)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_resubmitted_as(&self, task_id: &str, new_task_id: &str) -> Result<()> {
+        sqlx::query(queries::SET_TASK_RESUBMITTED_AS)
+            .bind(new_task_id)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_dead_letter(&self, task_id: &str) -> Result<()> {
+        sqlx::query(queries::DELETE_DEAD_LETTER).bind(task_id).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn load_dead_letters(&self) -> Result<Vec<Task>> {
+        let rows: Vec<String> = sqlx::query_scalar(queries::SELECT_DEAD_LETTERS).fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|task| serde_json::from_str(task)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn add_api_key(&self, record: &ApiKeyRecord) -> Result<()> {
+        sqlx::query(queries::INSERT_API_KEY)
+            .bind(&record.key_hash)
+            .bind(&record.label)
+            .bind(serde_json::to_string(record)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_api_key(&self, label: &str) -> Result<()> {
+        sqlx::query(queries::DELETE_API_KEY).bind(label).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn load_api_keys(&self) -> Result<Vec<ApiKeyRecord>> {
+        let rows: Vec<String> = sqlx::query_scalar(queries::SELECT_API_KEYS).fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(|record| serde_json::from_str(record)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn upsert_worker(&self, info: &WorkerInfo) -> Result<()> {
+        sqlx::query(queries::UPSERT_WORKER)
+            .bind(&info.id)
+            .bind(&info.name)
+            .bind(&info.platform)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_worker_task_completion(&self, worker_id: &str, duration_ms: u64, success: bool) -> Result<()> {
+        sqlx::query(queries::RECORD_WORKER_TASK)
+            .bind(worker_id)
+            .bind(Utc::now().to_rfc3339())
+            .bind(success as i64)
+            .bind(!success as i64)
+            .bind(duration_ms as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_worker_summary(&self, worker_id: &str) -> Result<WorkerSummary> {
+        let row = sqlx::query(queries::SELECT_WORKER)
+            .bind(worker_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| StorageError::WorkerNotFound(worker_id.to_string()))?;
+        Ok(worker_from_row(&row)?)
+    }
+
+    async fn get_all_worker_summaries(&self) -> Result<Vec<WorkerSummary>> {
+        let rows = sqlx::query(queries::SELECT_WORKERS).fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(worker_from_row).collect::<sqlx::Result<_>>()?)
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize, usize, u64)> {
+        let total: i64 = sqlx::query_scalar(queries::COUNT_TASKS).fetch_one(&self.pool).await?;
+        let completed: i64 = sqlx::query_scalar(queries::COUNT_TASKS_WITH_STATUS)
+            .bind("Completed")
+            .fetch_one(&self.pool)
+            .await?;
+        let failed: i64 = sqlx::query_scalar(queries::COUNT_TASKS_WITH_STATUS)
+            .bind("Failed")
+            .fetch_one(&self.pool)
+            .await?;
+        let avg_duration_ms: i64 = sqlx::query_scalar(queries::AVG_COMPLETED_DURATION_MS).fetch_one(&self.pool).await?;
+
+        Ok((total as usize, completed as usize, failed as usize, avg_duration_ms as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs against a live server only when `OCTASKLY_TEST_DATABASE_URL` is set
+    #[tokio::test]
+    async fn test_postgres_store_round_trip() {
+        let Ok(url) = std::env::var("OCTASKLY_TEST_DATABASE_URL") else {
+            println!("OCTASKLY_TEST_DATABASE_URL not set, skipping");
+            return;
+        };
+        let store = PostgresStore::new(&url).await.unwrap();
+        assert_eq!(store.schema_version().await.unwrap(), migrations::CURRENT_VERSION);
+
+        let task_id = format!("pg-test-{}", uuid::Uuid::new_v4());
+        let mut task = StoredTask {
+            id: task_id.clone(),
+            command: "echo hello".to_string(),
+            status: "Running".to_string(),
+            worker_id: Some("worker-1".to_string()),
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: None,
+            duration_ms: 0,
+            created_at: Utc::now().to_rfc3339(),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: None,
+        };
+        store.store_task(&task).await.unwrap();
+
+        // Storing again replaces the existing row
+        task.status = "Completed".to_string();
+        task.exit_code = Some(0);
+        store.store_task(&task).await.unwrap();
+
+        let stored = store.get_task(&task_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "Completed");
+        assert_eq!(stored.exit_code, Some(0));
+
+        let found = store
+            .search_tasks(TaskFilter::new().with_status("Completed").with_worker("worker-1").with_limit(1000))
+            .await
+            .unwrap();
+        assert!(found.iter().any(|t| t.id == task_id));
+
+        let event = AuditEvent::TaskSubmitted {
+            task_id: task_id.clone(),
+            command: "true".to_string(),
+        };
+        store.log_event(&event).await.unwrap();
+        assert!(!store.get_audit_logs(10).await.unwrap().is_empty());
+        assert!(store.get_stats().await.unwrap().0 >= 1);
+    }
+}
@@ -0,0 +1,95 @@
+pub const SELECT_TASKS: &str =
+    "SELECT id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at, max_rss_kb, resubmitted_as, definition FROM tasks";
+
+pub const SELECT_TASK_BY_ID: &str =
+    "SELECT id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at, max_rss_kb, resubmitted_as, definition FROM tasks WHERE id = $1";
+
+pub const UPSERT_TASK: &str = "
+    INSERT INTO tasks
+    (id, command, status, worker_id, stdout, stderr, exit_code, duration_ms, created_at, completed_at, max_rss_kb, definition)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    ON CONFLICT (id) DO UPDATE SET
+        command = excluded.command,
+        status = excluded.status,
+        worker_id = excluded.worker_id,
+        stdout = excluded.stdout,
+        stderr = excluded.stderr,
+        exit_code = excluded.exit_code,
+        duration_ms = excluded.duration_ms,
+        created_at = excluded.created_at,
+        completed_at = excluded.completed_at,
+        max_rss_kb = excluded.max_rss_kb,
+        definition = COALESCE(excluded.definition, tasks.definition)";
+
+pub const SET_TASK_RESUBMITTED_AS: &str = "UPDATE tasks SET resubmitted_as = $1 WHERE id = $2";
+
+pub const DELETE_TASKS_CREATED_BEFORE: &str = "DELETE FROM tasks WHERE created_at < $1";
+
+pub const INSERT_AUDIT_EVENT: &str = "
+    INSERT INTO audit_log (timestamp, event_type, worker_id, task_id, details)
+    VALUES ($1, $2, $3, $4, $5)";
+
+pub const SELECT_AUDIT_EVENTS: &str = "
+    SELECT timestamp, event_type, worker_id, task_id, details
+    FROM audit_log ORDER BY timestamp DESC LIMIT $1";
+
+pub const SELECT_AUDIT_DETAILS_BY_TYPE: &str = "
+    SELECT details FROM audit_log WHERE event_type = $1 ORDER BY timestamp DESC, id DESC LIMIT $2";
+
+pub const COUNT_TASKS: &str = "SELECT COUNT(*) FROM tasks";
+
+pub const COUNT_TASKS_WITH_STATUS: &str = "SELECT COUNT(*) FROM tasks WHERE status = $1";
+
+/// Mean run time of completed tasks in whole milliseconds, 0 when there are none
+pub const AVG_COMPLETED_DURATION_MS: &str =
+    "SELECT CAST(COALESCE(AVG(duration_ms), 0) AS BIGINT) FROM tasks WHERE status = 'Completed'";
+
+pub const CLEAR_QUEUE: &str = "DELETE FROM task_queue";
+
+pub const INSERT_QUEUED_TASK: &str = "INSERT INTO task_queue (position, task_id, task) VALUES ($1, $2, $3)";
+
+pub const SELECT_QUEUE: &str = "SELECT task FROM task_queue ORDER BY position";
+
+pub const INSERT_DEAD_LETTER: &str = "
+    INSERT INTO dead_letter (task_id, task, failed_at) VALUES ($1, $2, $3)
+    ON CONFLICT (task_id) DO UPDATE SET task = excluded.task, failed_at = excluded.failed_at";
+
+pub const DELETE_DEAD_LETTER: &str = "DELETE FROM dead_letter WHERE task_id = $1";
+
+pub const SELECT_DEAD_LETTERS: &str = "SELECT task FROM dead_letter ORDER BY failed_at";
+
+pub const INSERT_API_KEY: &str = "INSERT INTO api_keys (key_hash, label, record) VALUES ($1, $2, $3)";
+
+pub const DELETE_API_KEY: &str = "DELETE FROM api_keys WHERE label = $1";
+
+pub const SELECT_API_KEYS: &str = "SELECT record FROM api_keys ORDER BY label";
+
+/// Register a worker, keeping its totals and `first_seen` if it was seen before
+pub const UPSERT_WORKER: &str = "
+    INSERT INTO workers (id, name, platform, first_seen, last_seen) VALUES ($1, $2, $3, $4, $4)
+    ON CONFLICT (id) DO UPDATE SET name = excluded.name, platform = excluded.platform, last_seen = excluded.last_seen";
+
+/// Add one finished task to a worker's totals, creating the row for a worker never registered
+pub const RECORD_WORKER_TASK: &str = "
+    INSERT INTO workers (id, name, platform, first_seen, last_seen, tasks_completed, tasks_failed, total_duration_ms)
+    VALUES ($1, '', '', $2, $2, $3, $4, $5)
+    ON CONFLICT (id) DO UPDATE SET
+        last_seen = excluded.last_seen,
+        tasks_completed = workers.tasks_completed + excluded.tasks_completed,
+        tasks_failed = workers.tasks_failed + excluded.tasks_failed,
+        total_duration_ms = workers.total_duration_ms + excluded.total_duration_ms";
+
+pub const SELECT_WORKER: &str = "
+    SELECT id, name, platform, first_seen, last_seen, tasks_completed, tasks_failed, total_duration_ms
+    FROM workers WHERE id = $1";
+
+pub const SELECT_WORKERS: &str = "
+    SELECT id, name, platform, first_seen, last_seen, tasks_completed, tasks_failed, total_duration_ms
+    FROM workers ORDER BY first_seen";
+
+/// Dynamically bound parameter, converted to each driver's own value type
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Text(String),
+    Integer(i64),
+}
@@ -0,0 +1,929 @@
+use super::migrations::{self, Dialect};
+use super::queries::{self, SqlParam};
+use super::{cleanup_cutoff, parse_audit_events, AuditEvent, AuditLogEntry, StoreBackend, StoredTask, TaskFilter, WorkerSummary};
+use crate::auth::ApiKeyRecord;
+use crate::protocol::{Task, WorkerInfo};
+use crate::error::{Result, StorageError};
+use async_trait::async_trait;
+use chrono::Utc;
+use rusqlite::backup::Backup;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, OptionalExtension, Statement, TransactionBehavior};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_rusqlite::Connection;
+use tracing::info;
+
+/// Pages copied per backup step; the store's own connection can read and write between steps
+const BACKUP_PAGES_PER_STEP: i32 = 256;
+
+/// Pause between backup steps
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<StoredTask> {
+    Ok(StoredTask {
+        id: row.get(0)?,
+        command: row.get(1)?,
+        status: row.get(2)?,
+        worker_id: row.get(3)?,
+        stdout: row.get(4)?,
+        stderr: row.get(5)?,
+        exit_code: row.get(6)?,
+        duration_ms: row.get(7)?,
+        created_at: row.get(8)?,
+        completed_at: row.get(9)?,
+        max_rss_kb: row.get(10)?,
+        resubmitted_as: row.get(11)?,
+        definition: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    })
+}
+
+fn worker_from_row(row: &rusqlite::Row) -> rusqlite::Result<WorkerSummary> {
+    Ok(WorkerSummary {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        platform: row.get(2)?,
+        first_seen: row.get(3)?,
+        last_seen: row.get(4)?,
+        tasks_completed: row.get(5)?,
+        tasks_failed: row.get(6)?,
+        total_duration_ms: row.get(7)?,
+    })
+}
+
+fn upsert_task(stmt: &mut Statement, task: &StoredTask) -> rusqlite::Result<usize> {
+    stmt.execute(params![
+        task.id,
+        task.command,
+        task.status,
+        task.worker_id,
+        task.stdout,
+        task.stderr,
+        task.exit_code,
+        task.duration_ms,
+        task.created_at,
+        task.completed_at,
+        task.max_rss_kb,
+        task.definition.as_ref().and_then(|task| serde_json::to_string(task).ok()),
+    ])
+}
+
+impl From<SqlParam> for Value {
+    fn from(param: SqlParam) -> Self {
+        match param {
+            SqlParam::Text(text) => Value::Text(text),
+            SqlParam::Integer(value) => Value::Integer(value),
+        }
+    }
+}
+
+/// Version recorded in `schema_version`, or 0 for a fresh database
+pub fn schema_version(conn: &rusqlite::Connection) -> rusqlite::Result<u32> {
+    conn.execute(migrations::CREATE_VERSION_TABLE, [])?;
+    let version = conn
+        .query_row(migrations::SELECT_VERSION, [], |row| row.get(0))
+        .optional()?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Bring the schema up to `CURRENT_VERSION`, returning the version it started from
+///
+/// Each migration commits together with its version bump, so a failing script
+/// leaves the database at the last fully applied version.
+pub fn migrate(conn: &mut rusqlite::Connection) -> Result<u32> {
+    let from = schema_version(conn)?;
+
+    for (version, script) in migrations::pending(from, Dialect::Sqlite)? {
+        let tx = conn.transaction()?;
+        tx.execute_batch(&script)?;
+        tx.execute(migrations::CLEAR_VERSION, [])?;
+        tx.execute(migrations::INSERT_VERSION, [version])?;
+        tx.commit()?;
+    }
+
+    Ok(from)
+}
+
+/// Task history stored in a local SQLite file
+///
+/// Queries run on a dedicated thread owned by `tokio_rusqlite`, so they never block the async runtime.
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Connection,
+    /// Database file, or `None` for an in-memory database no other connection can open
+    path: Option<PathBuf>,
+}
+
+impl SqliteStore {
+    pub async fn new(db_path: &str) -> Result<Self> {
+        let conn = Connection::open(db_path).await?;
+
+        let from = conn
+            .call(|conn| {
+                // Enable WAL mode for better concurrency
+                conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+
+                migrate(conn).map_err(|e| tokio_rusqlite::Error::Other(e.into()))
+            })
+            .await?;
+
+        if from < migrations::CURRENT_VERSION {
+            info!("Migrated task history schema from version {} to {}", from, migrations::CURRENT_VERSION);
+        }
+
+        let path = match db_path {
+            "" | ":memory:" => None,
+            path => Some(PathBuf::from(path)),
+        };
+        Ok(Self { conn, path })
+    }
+}
+
+#[async_trait]
+impl StoreBackend for SqliteStore {
+    async fn store_task(&self, task: &StoredTask) -> Result<()> {
+        let task = task.clone();
+
+        self.conn
+            .call(move |conn| {
+                upsert_task(&mut conn.prepare(queries::UPSERT_TASK)?, &task)?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn bulk_insert_tasks(&self, tasks: &[StoredTask]) -> Result<()> {
+        let tasks = tasks.to_vec();
+
+        self.conn
+            .call(move |conn| {
+                // Take the write lock up front so the batch never fails halfway on a busy database
+                let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                {
+                    let mut stmt = tx.prepare(queries::UPSERT_TASK)?;
+                    for task in &tasks {
+                        upsert_task(&mut stmt, task)?;
+                    }
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_task(&self, task_id: &str) -> Result<Option<StoredTask>> {
+        let task_id = task_id.to_string();
+
+        let task = self
+            .conn
+            .call(move |conn| {
+                Ok(conn
+                    .query_row(queries::SELECT_TASK_BY_ID, params![task_id], task_from_row)
+                    .optional()?)
+            })
+            .await?;
+
+        Ok(task)
+    }
+
+    async fn search_tasks(&self, filter: TaskFilter) -> Result<Vec<StoredTask>> {
+        let (tail, values) = filter.to_sql();
+
+        let tasks = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(&format!("{}{}", queries::SELECT_TASKS, tail))?;
+                let tasks = stmt
+                    .query_map(params_from_iter(values.into_iter().map(Value::from)), task_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(tasks)
+            })
+            .await?;
+
+        Ok(tasks)
+    }
+
+    async fn count_tasks(&self, filter: &TaskFilter) -> Result<usize> {
+        let (clause, values) = filter.where_sql();
+
+        let count: i64 = self
+            .conn
+            .call(move |conn| {
+                Ok(conn.query_row(
+                    &format!("{}{}", queries::COUNT_TASKS, clause),
+                    params_from_iter(values.into_iter().map(Value::from)),
+                    |row| row.get(0),
+                )?)
+            })
+            .await?;
+
+        Ok(count as usize)
+    }
+
+    async fn cleanup_old_tasks(&self, days: i64) -> Result<usize> {
+        let cutoff = cleanup_cutoff(days);
+
+        let rows = self
+            .conn
+            .call(move |conn| Ok(conn.execute(queries::DELETE_TASKS_CREATED_BEFORE, params![cutoff])?))
+            .await?;
+
+        Ok(rows)
+    }
+
+    async fn log_event(&self, event: &AuditEvent) -> Result<()> {
+        let timestamp = Utc::now().to_rfc3339();
+        let event_type = event.event_type();
+        let worker_id = event.worker_id().map(str::to_string);
+        let task_id = event.task_id().map(str::to_string);
+        let details = serde_json::to_string(event)?;
+
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    queries::INSERT_AUDIT_EVENT,
+                    params![timestamp, event_type, worker_id, task_id, details],
+                )?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_logs(&self, limit: usize) -> Result<Vec<AuditLogEntry>> {
+        let logs = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(queries::SELECT_AUDIT_EVENTS)?;
+                let logs = stmt
+                    .query_map(params![limit as i64], |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(logs)
+            })
+            .await?;
+
+        Ok(logs)
+    }
+
+    async fn get_audit_events_by_type(&self, event_type: &str, limit: usize) -> Result<Vec<AuditEvent>> {
+        let event_type = event_type.to_string();
+
+        let details = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(queries::SELECT_AUDIT_DETAILS_BY_TYPE)?;
+                let details = stmt
+                    .query_map(params![event_type, limit as i64], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(details)
+            })
+            .await?;
+
+        Ok(parse_audit_events(details))
+    }
+
+    async fn save_queue(&self, tasks: &[Task]) -> Result<()> {
+        let rows = tasks
+            .iter()
+            .map(|task| Ok((task.id.clone(), serde_json::to_string(task)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(queries::CLEAR_QUEUE, [])?;
+                for (position, (task_id, task)) in rows.into_iter().enumerate() {
+                    tx.execute(queries::INSERT_QUEUED_TASK, params![position as i64, task_id, task])?;
+                }
+                tx.commit()?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load_queue(&self) -> Result<Vec<Task>> {
+        let rows = self
+            .conn
+            .call(|conn| {
+                let mut stmt = conn.prepare(queries::SELECT_QUEUE)?;
+                let rows = stmt
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .await?;
+
+        Ok(rows.iter().map(|task| serde_json::from_str(task)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn add_dead_letter(&self, task: &Task) -> Result<()> {
+        let (task_id, task) = (task.id.clone(), serde_json::to_string(task)?);
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::INSERT_DEAD_LETTER, params![task_id, task, Utc::now().to_rfc3339()])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn set_resubmitted_as(&self, task_id: &str, new_task_id: &str) -> Result<()> {
+        let (task_id, new_task_id) = (task_id.to_string(), new_task_id.to_string());
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::SET_TASK_RESUBMITTED_AS, params![new_task_id, task_id])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_dead_letter(&self, task_id: &str) -> Result<()> {
+        let task_id = task_id.to_string();
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::DELETE_DEAD_LETTER, params![task_id])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load_dead_letters(&self) -> Result<Vec<Task>> {
+        let rows = self
+            .conn
+            .call(|conn| {
+                let mut stmt = conn.prepare(queries::SELECT_DEAD_LETTERS)?;
+                let rows = stmt
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .await?;
+
+        Ok(rows.iter().map(|task| serde_json::from_str(task)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn add_api_key(&self, record: &ApiKeyRecord) -> Result<()> {
+        let (key_hash, label, record) = (record.key_hash.clone(), record.label.clone(), serde_json::to_string(record)?);
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::INSERT_API_KEY, params![key_hash, label, record])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_api_key(&self, label: &str) -> Result<()> {
+        let label = label.to_string();
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::DELETE_API_KEY, params![label])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load_api_keys(&self) -> Result<Vec<ApiKeyRecord>> {
+        let rows = self
+            .conn
+            .call(|conn| {
+                let mut stmt = conn.prepare(queries::SELECT_API_KEYS)?;
+                let rows = stmt
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .await?;
+
+        Ok(rows.iter().map(|record| serde_json::from_str(record)).collect::<serde_json::Result<_>>()?)
+    }
+
+    async fn upsert_worker(&self, info: &WorkerInfo) -> Result<()> {
+        let (id, name, platform) = (info.id.clone(), info.name.clone(), info.platform.clone());
+        self.conn
+            .call(move |conn| {
+                conn.execute(queries::UPSERT_WORKER, params![id, name, platform, Utc::now().to_rfc3339()])?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_worker_task_completion(&self, worker_id: &str, duration_ms: u64, success: bool) -> Result<()> {
+        let worker_id = worker_id.to_string();
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    queries::RECORD_WORKER_TASK,
+                    params![worker_id, Utc::now().to_rfc3339(), success as i64, !success as i64, duration_ms as i64],
+                )?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_worker_summary(&self, worker_id: &str) -> Result<WorkerSummary> {
+        let id = worker_id.to_string();
+        let summary = self
+            .conn
+            .call(move |conn| Ok(conn.query_row(queries::SELECT_WORKER, params![id], worker_from_row).optional()?))
+            .await?;
+
+        Ok(summary.ok_or_else(|| StorageError::WorkerNotFound(worker_id.to_string()))?)
+    }
+
+    async fn get_all_worker_summaries(&self) -> Result<Vec<WorkerSummary>> {
+        let summaries = self
+            .conn
+            .call(|conn| {
+                let mut stmt = conn.prepare(queries::SELECT_WORKERS)?;
+                let summaries = stmt.query_map([], worker_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(summaries)
+            })
+            .await?;
+
+        Ok(summaries)
+    }
+
+    /// Copies from a connection of its own, so history reads and writes go on during the backup
+    async fn backup_incremental(&self, dest_path: &Path) -> Result<()> {
+        let dest_path = dest_path.to_path_buf();
+
+        let Some(source_path) = self.path.clone() else {
+            self.conn
+                .call(move |conn| {
+                    let mut dest = rusqlite::Connection::open(dest_path)?;
+                    Backup::new(conn, &mut dest)?.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)?;
+                    Ok(())
+                })
+                .await?;
+            return Ok(());
+        };
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let source = rusqlite::Connection::open_with_flags(&source_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let mut dest = rusqlite::Connection::open(dest_path)?;
+            Backup::new(&source, &mut dest)?.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn get_stats(&self) -> Result<(usize, usize, usize, u64)> {
+        let stats = self
+            .conn
+            .call(|conn| {
+                let total: usize = conn.query_row(queries::COUNT_TASKS, [], |row| row.get(0))?;
+                let completed: usize =
+                    conn.query_row(queries::COUNT_TASKS_WITH_STATUS, params!["Completed"], |row| row.get(0))?;
+                let failed: usize =
+                    conn.query_row(queries::COUNT_TASKS_WITH_STATUS, params!["Failed"], |row| row.get(0))?;
+                let avg_duration_ms: u64 = conn.query_row(queries::AVG_COMPLETED_DURATION_MS, [], |row| row.get(0))?;
+
+                Ok((total, completed, failed, avg_duration_ms))
+            })
+            .await?;
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    #[tokio::test]
+    async fn test_persistent_storage() {
+        let store = match SqliteStore::new(":memory:").await {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Failed to create store: {}", e);
+                return;
+            }
+        };
+        
+        let task = StoredTask {
+            id: "test-1".to_string(),
+            command: "echo hello".to_string(),
+            status: "Completed".to_string(),
+            worker_id: Some("worker-1".to_string()),
+            stdout: "hello".to_string(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 100,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            completed_at: Some(chrono::Utc::now().to_rfc3339()),
+            max_rss_kb: Some(2048),
+            resubmitted_as: None,
+            definition: None,
+        };
+        
+        if let Err(e) = store.store_task(&task).await {
+            println!("Failed to store task: {}", e);
+            return;
+        }
+        
+        match store.get_task("test-1").await {
+            Ok(Some(retrieved)) => {
+                assert_eq!(retrieved.command, "echo hello");
+                assert_eq!(retrieved.max_rss_kb, Some(2048));
+                assert_eq!(retrieved.resubmitted_as, None);
+            }
+            Ok(None) => panic!("Task not found"),
+            Err(e) => panic!("Failed to retrieve task: {}", e),
+        }
+
+        store.set_resubmitted_as("test-1", "test-2").await.unwrap();
+        store.store_task(&task).await.unwrap();
+        let retrieved = store.get_task("test-1").await.unwrap().unwrap();
+        assert_eq!(retrieved.resubmitted_as.as_deref(), Some("test-2"));
+    }
+
+    #[tokio::test]
+    async fn test_queue_round_trip() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let tasks = vec![Task::new("echo a".to_string()), Task::new("echo b".to_string())];
+        store.save_queue(&tasks).await.unwrap();
+        store.save_queue(&tasks[1..]).await.unwrap();
+
+        let loaded = store.load_queue().await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id, tasks[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_backup_of_in_memory_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        store.save_queue(&[Task::new("echo a".to_string())]).await.unwrap();
+
+        let path = dir.path().join("backup.db");
+        store.backup_incremental(&path).await.unwrap();
+        let copy = SqliteStore::new(path.to_str().unwrap()).await.unwrap();
+        assert_eq!(copy.load_queue().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_round_trip() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let mut task = Task::new("false".to_string());
+        store.add_dead_letter(&task).await.unwrap();
+        task.retry_count = 3;
+        store.add_dead_letter(&task).await.unwrap();
+
+        let loaded = store.load_dead_letters().await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].retry_count, 3);
+
+        store.remove_dead_letter(&task.id).await.unwrap();
+        assert!(store.load_dead_letters().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_round_trip() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let record = |label: &str, key_hash: &str| ApiKeyRecord {
+            key_hash: key_hash.to_string(),
+            label: label.to_string(),
+            role: crate::auth::Role::Client,
+            permissions: vec!["create_task".to_string()],
+            created_at: 1_700_000_000,
+            last_used: None,
+        };
+        store.add_api_key(&record("ci", "hash-1")).await.unwrap();
+        store.add_api_key(&record("backup", "hash-2")).await.unwrap();
+        assert!(store.add_api_key(&record("ci", "hash-3")).await.is_err());
+
+        let loaded = store.load_api_keys().await.unwrap();
+        let labels: Vec<&str> = loaded.iter().map(|record| record.label.as_str()).collect();
+        assert_eq!(labels, vec!["backup", "ci"]);
+        assert_eq!(loaded[1].key_hash, "hash-1");
+        assert_eq!(loaded[1].permissions, vec!["create_task".to_string()]);
+
+        store.remove_api_key("ci").await.unwrap();
+        assert_eq!(store.load_api_keys().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_database_is_at_current_version() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let version = store
+            .conn
+            .call(|conn| Ok(schema_version(conn)?))
+            .await
+            .unwrap();
+        assert_eq!(version, migrations::CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_upgrades_legacy_database() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        // Databases created before versioning have the v1 tables but no schema_version
+        conn.execute_batch(&migrations::render(migrations::V1_INITIAL_SCHEMA, Dialect::Sqlite)).unwrap();
+        conn.execute(
+            "INSERT INTO tasks (id, command, status, stdout, stderr, duration_ms, created_at)
+             VALUES ('old', 'true', 'Completed', '', '', 0, '2024-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&mut conn).unwrap(), 0);
+        let (priority, retry_count): (i64, i64) = conn
+            .query_row("SELECT priority, retry_count FROM tasks WHERE id = 'old'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((priority, retry_count), (0, 0));
+
+        // Running again is a no-op
+        assert_eq!(migrate(&mut conn).unwrap(), migrations::CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_previous_version() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema_version(&conn).unwrap();
+        conn.execute_batch(&migrations::render(migrations::V1_INITIAL_SCHEMA, Dialect::Sqlite)).unwrap();
+        conn.execute("INSERT INTO schema_version (version) VALUES (1)", []).unwrap();
+        // A column clash makes the v2 script fail halfway
+        conn.execute("ALTER TABLE tasks ADD COLUMN priority TEXT", []).unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        schema_version(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            [migrations::CURRENT_VERSION + 1],
+        )
+        .unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    fn search_fixture(id: &str, status: &str, worker_id: &str, exit_code: i32, created_at: DateTime<Utc>) -> StoredTask {
+        StoredTask {
+            id: id.to_string(),
+            command: "true".to_string(),
+            status: status.to_string(),
+            worker_id: Some(worker_id.to_string()),
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(exit_code),
+            duration_ms: 10,
+            created_at: created_at.to_rfc3339(),
+            completed_at: None,
+            max_rss_kb: None,
+            resubmitted_as: None,
+            definition: None,
+        }
+    }
+
+    /// Four tasks, one day apart: a (oldest) .. d (newest)
+    async fn search_store() -> (SqliteStore, DateTime<Utc>) {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let base = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let day = chrono::Duration::days(1);
+
+        for task in [
+            search_fixture("a", "Completed", "w1", 0, base),
+            search_fixture("b", "Failed", "w1", 1, base + day),
+            search_fixture("c", "Completed", "w2", 0, base + day * 2),
+            search_fixture("d", "Failed", "w2", 2, base + day * 3),
+        ] {
+            store.store_task(&task).await.unwrap();
+        }
+        (store, base)
+    }
+
+    fn ids(tasks: Vec<StoredTask>) -> Vec<String> {
+        tasks.into_iter().map(|t| t.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_without_filter_returns_newest_first() {
+        let (store, _) = search_store().await;
+        assert_eq!(ids(store.search_tasks(TaskFilter::new()).await.unwrap()), ["d", "c", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_status() {
+        let (store, _) = search_store().await;
+        let found = store.search_tasks(TaskFilter::new().with_status("Completed")).await.unwrap();
+        assert_eq!(ids(found), ["c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_worker() {
+        let (store, _) = search_store().await;
+        let found = store.search_tasks(TaskFilter::new().with_worker("w1")).await.unwrap();
+        assert_eq!(ids(found), ["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_exit_code() {
+        let (store, _) = search_store().await;
+        let found = store.search_tasks(TaskFilter::new().with_exit_code(2)).await.unwrap();
+        assert_eq!(ids(found), ["d"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_date_range() {
+        let (store, base) = search_store().await;
+        let day = chrono::Duration::days(1);
+
+        let after = store.search_tasks(TaskFilter::new().with_created_after(base + day)).await.unwrap();
+        assert_eq!(ids(after), ["d", "c", "b"]);
+
+        let before = store.search_tasks(TaskFilter::new().with_created_before(base + day)).await.unwrap();
+        assert_eq!(ids(before), ["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_search_limit_and_offset() {
+        let (store, _) = search_store().await;
+        let page = store.search_tasks(TaskFilter::new().with_limit(2).with_offset(1)).await.unwrap();
+        assert_eq!(ids(page), ["c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_paginated_returns_page_and_total() {
+        let (store, _) = search_store().await;
+
+        let (page, total) = store.get_tasks_paginated(TaskFilter::new(), 2, 3).await.unwrap();
+        assert_eq!((ids(page), total), (vec!["a".to_string()], 4));
+
+        let (page, total) = store
+            .get_tasks_paginated(TaskFilter::new().with_worker("w2"), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!((ids(page), total), (vec!["d".to_string()], 2));
+    }
+
+    #[tokio::test]
+    async fn test_search_combined_filters() {
+        let (store, base) = search_store().await;
+        let day = chrono::Duration::days(1);
+
+        let filter = TaskFilter::new()
+            .with_status("Failed")
+            .with_worker("w2")
+            .with_created_after(base)
+            .with_created_before(base + day * 4);
+        assert_eq!(ids(store.search_tasks(filter).await.unwrap()), ["d"]);
+
+        let filter = TaskFilter::new().with_status("Completed").with_exit_code(1);
+        assert!(store.search_tasks(filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_values_are_bound_not_interpolated() {
+        let (store, _) = search_store().await;
+        let filter = TaskFilter::new().with_status("Completed' OR '1'='1");
+        assert!(store.search_tasks(filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_worker_summaries_accumulate() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        let mut worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 9000, 2);
+        store.upsert_worker(&worker).await.unwrap();
+        let first_seen = store.get_worker_summary(&worker.id).await.unwrap().first_seen;
+
+        store.record_worker_task_completion(&worker.id, 300, true).await.unwrap();
+        store.record_worker_task_completion(&worker.id, 100, false).await.unwrap();
+        worker.name = "w1-renamed".to_string();
+        store.upsert_worker(&worker).await.unwrap();
+
+        let summary = store.get_worker_summary(&worker.id).await.unwrap();
+        assert_eq!(summary.name, "w1-renamed");
+        assert_eq!(summary.first_seen, first_seen);
+        assert_eq!((summary.tasks_completed, summary.tasks_failed), (1, 1));
+        assert_eq!(summary.total_duration_ms, 400);
+        assert_eq!(summary.average_duration_ms(), 200);
+
+        // Results from a worker that never registered still count
+        store.record_worker_task_completion("ghost", 50, true).await.unwrap();
+        let all = store.get_all_worker_summaries().await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(store.get_worker_summary("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_insert_tasks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = SqliteStore::new(dir.path().join("history.db").to_str().unwrap()).await.unwrap();
+        let tasks: Vec<StoredTask> = (0..1000)
+            .map(|i| StoredTask {
+                id: format!("bulk-{}", i),
+                command: "true".to_string(),
+                status: "Pending".to_string(),
+                worker_id: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: None,
+                duration_ms: 0,
+                created_at: Utc::now().to_rfc3339(),
+                completed_at: None,
+                max_rss_kb: None,
+                resubmitted_as: None,
+                definition: None,
+            })
+            .collect();
+
+        let started = std::time::Instant::now();
+        store.bulk_insert_tasks(&tasks).await.unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_secs(1), "took {:?}", started.elapsed());
+
+        assert_eq!(store.count_tasks(&TaskFilter::new()).await.unwrap(), 1000);
+        assert_eq!(store.get_task("bulk-999").await.unwrap().unwrap().status, "Pending");
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let (store, _) = search_store().await;
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let path = dir.path().join("all.csv");
+        assert_eq!(store.export_csv(&path).await.unwrap(), 4);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().next().unwrap(), crate::persistence::CSV_HEADER.join(","));
+        assert_eq!(csv.lines().count(), 5);
+
+        let path = dir.path().join("w2.csv");
+        let filter = TaskFilter::new().with_worker("w2");
+        assert_eq!(store.export_csv_filtered(filter, &path).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_audit_events_by_type() {
+        let store = SqliteStore::new(":memory:").await.unwrap();
+        // A row from before events were structured
+        store
+            .conn
+            .call(|conn| {
+                conn.execute(
+                    queries::INSERT_AUDIT_EVENT,
+                    params![Utc::now().to_rfc3339(), "task_failed", None::<String>, "old", "exit 1"],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        for (task_id, exit_code) in [("t1", Some(1)), ("t2", None)] {
+            let event = AuditEvent::TaskFailed {
+                task_id: task_id.to_string(),
+                exit_code,
+            };
+            store.log_event(&event).await.unwrap();
+        }
+        let other = AuditEvent::AuthFailure {
+            user: "unknown".to_string(),
+            reason: "Invalid token".to_string(),
+        };
+        store.log_event(&other).await.unwrap();
+
+        let failed = store.get_audit_events_by_type("task_failed", 10).await.unwrap();
+        assert_eq!(
+            failed,
+            vec![
+                AuditEvent::TaskFailed { task_id: "t2".to_string(), exit_code: None },
+                AuditEvent::TaskFailed { task_id: "t1".to_string(), exit_code: Some(1) },
+            ]
+        );
+        assert_eq!(store.get_audit_events_by_type("auth_failure", 10).await.unwrap(), vec![other]);
+
+        let (_, event_type, _, task_id, details) = store.get_audit_logs(1).await.unwrap().remove(0);
+        assert_eq!(event_type, "auth_failure");
+        assert_eq!(task_id, None);
+        assert!(details.contains("\"type\":\"auth_failure\""));
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use uuid::Uuid;
 
 /// Represents a compute task to be executed
@@ -41,6 +42,132 @@ pub struct Task {
     /// Task creation timestamp (Unix epoch)
     /// Stempel waktu pembuatan tugas (Unix epoch)
     pub created_at: i64,
+    
+    /// Scheduling priority (higher runs first, 0 = default)
+    /// Prioritas penjadwalan (lebih tinggi dijalankan lebih dulu, 0 = default)
+    #[serde(default)]
+    pub priority: u8,
+    
+    /// Task IDs that must complete successfully before this task can run
+    /// ID tugas yang harus selesai dengan sukses sebelum tugas ini dapat berjalan
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    
+    /// Maximum automatic retries after failure or timeout
+    /// Jumlah maksimum percobaan ulang otomatis setelah gagal atau timeout
+    #[serde(default)]
+    pub max_retries: u32,
+    
+    /// Number of retries already attempted
+    /// Jumlah percobaan ulang yang sudah dilakukan
+    #[serde(default)]
+    pub retry_count: u32,
+    
+    /// Base delay before the first retry in milliseconds
+    /// Jeda dasar sebelum percobaan ulang pertama dalam milidetik
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    
+    /// Delay multiplier applied per retry attempt (exponential backoff)
+    /// Pengali jeda yang diterapkan per percobaan ulang (backoff eksponensial)
+    #[serde(default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    
+    /// Capability tags a worker must have to run this task
+    /// Tag kemampuan yang harus dimiliki worker untuk menjalankan tugas ini
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    
+    /// Container image to run the task in (Docker executor only)
+    /// Image container untuk menjalankan tugas (hanya executor Docker)
+    #[serde(default)]
+    pub container_image: Option<String>,
+
+    /// Free-form key/value labels for grouping and filtering tasks
+    /// Label kunci/nilai bebas untuk mengelompokkan dan menyaring tugas
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+
+    /// Unix timestamp before which the task is held back from the queue
+    /// Stempel waktu Unix sebelum tugas ditahan dari antrian
+    #[serde(default)]
+    pub scheduled_at: Option<i64>,
+
+    /// Cron schedule (`sec min hour day month weekday [year]`) the task repeats on
+    /// Jadwal cron (`detik menit jam hari bulan hari-minggu [tahun]`) tempat tugas berulang
+    #[serde(default)]
+    pub cron_expression: Option<String>,
+
+    /// URL the dispatcher POSTs the result to once the task finishes
+    /// URL tempat dispatcher mengirim POST hasil setelah tugas selesai
+    #[serde(default)]
+    pub callback_url: Option<String>,
+
+    /// Script run directly instead of `command`, relative to the dispatcher's files directory
+    /// Skrip yang dijalankan langsung sebagai ganti `command`, relatif terhadap direktori file dispatcher
+    #[serde(default)]
+    pub script_path: Option<PathBuf>,
+
+    /// Contents of `script_path`, read by the dispatcher and shipped with the assignment
+    /// Isi `script_path`, dibaca dispatcher dan dikirim bersama penugasan
+    #[serde(default)]
+    pub script_content: Option<Vec<u8>>,
+
+    /// Keep the task's isolated working directory even when it succeeds
+    /// Pertahankan direktori kerja terisolasi tugas meskipun berhasil
+    #[serde(default)]
+    pub keep_workdir: bool,
+
+    /// When the task first entered the dispatcher queue (Unix epoch); kept across retries
+    /// Waktu tugas pertama kali masuk antrian dispatcher (Unix epoch); tetap sama saat dicoba ulang
+    #[serde(default)]
+    pub queued_at: Option<i64>,
+
+    /// Files the dispatcher reads from its workdir and the worker writes into the task directory
+    /// File yang dibaca dispatcher dari workdir-nya dan ditulis worker ke direktori tugas
+    #[serde(default)]
+    pub input_files: Vec<FileSpec>,
+
+    /// Files the worker reads from the task directory afterwards and sends back to the dispatcher
+    /// File yang dibaca worker dari direktori tugas setelahnya dan dikirim kembali ke dispatcher
+    #[serde(default)]
+    pub output_files: Vec<FileSpec>,
+}
+
+/// A file staged between dispatcher and worker, relative to the task directory
+/// File yang dipindahkan antara dispatcher dan worker, relatif terhadap direktori tugas
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FileSpec {
+    pub path: String,
+    /// File bytes, once read; `None` until the sending side fills it in
+    /// Isi file setelah dibaca; `None` sampai pihak pengirim mengisinya
+    #[serde(default)]
+    pub content: Option<Vec<u8>>,
+}
+
+impl FileSpec {
+    /// File at `path` whose content is read when it is staged
+    /// File di `path` yang isinya dibaca saat dipindahkan
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            content: None,
+        }
+    }
+}
+
+/// Parse a cron expression as used by `Task::cron_expression`
+/// Parse ekspresi cron seperti yang dipakai `Task::cron_expression`
+pub fn parse_cron(expression: &str) -> Result<cron::Schedule, cron::error::Error> {
+    expression.parse()
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
 }
 
 impl Task {
@@ -58,8 +185,54 @@ impl Task {
             timeout: 600, // 10 minutes default
             env: HashMap::new(),
             created_at: chrono::Local::now().timestamp(),
+            priority: 0,
+            dependencies: Vec::new(),
+            max_retries: 0,
+            retry_count: 0,
+            retry_delay_ms: default_retry_delay_ms(),
+            backoff_multiplier: default_backoff_multiplier(),
+            required_tags: Vec::new(),
+            container_image: None,
+            labels: HashMap::new(),
+            scheduled_at: None,
+            cron_expression: None,
+            callback_url: None,
+            script_path: None,
+            script_content: None,
+            keep_workdir: false,
+            queued_at: None,
+            input_files: Vec::new(),
+            output_files: Vec::new(),
         }
     }
+
+    /// Whether the task is held back until a time later than `now`
+    /// Apakah tugas ditahan sampai waktu setelah `now`
+    pub fn is_scheduled_after(&self, now: i64) -> bool {
+        self.scheduled_at.is_some_and(|at| at > now)
+    }
+
+    /// Next time after `after` matching the task's cron expression, if it recurs
+    /// Waktu berikutnya setelah `after` yang cocok dengan ekspresi cron tugas, jika berulang
+    pub fn next_occurrence(&self, after: i64) -> Option<i64> {
+        let schedule = parse_cron(self.cron_expression.as_deref()?).ok()?;
+        let after = chrono::DateTime::from_timestamp(after, 0)?;
+        schedule.after(&after).next().map(|at| at.timestamp())
+    }
+
+    /// Check whether an attempt that ended with `status` should be retried
+    /// Periksa apakah percobaan yang berakhir dengan `status` perlu diulang
+    pub fn should_retry(&self, status: TaskStatus) -> bool {
+        matches!(status, TaskStatus::Failed | TaskStatus::TimedOut)
+            && self.retry_count < self.max_retries
+    }
+
+    /// Delay before the next retry: `retry_delay_ms * backoff_multiplier^retry_count`
+    /// Jeda sebelum percobaan ulang berikutnya: `retry_delay_ms * backoff_multiplier^retry_count`
+    pub fn next_retry_delay_ms(&self) -> u64 {
+        let factor = self.backoff_multiplier.powi(self.retry_count as i32);
+        (self.retry_delay_ms as f64 * factor) as u64
+    }
 }
 
 /// Represents the result of task execution
@@ -105,11 +278,37 @@ pub struct TaskResult {
     /// Completion timestamp (Unix epoch)
     /// Stempel waktu penyelesaian (Unix epoch)
     pub completed_at: i64,
+
+    /// Resources the task's process used, when the worker could measure them
+    /// Sumber daya yang dipakai proses tugas, bila worker dapat mengukurnya
+    #[serde(default)]
+    pub resource_usage: Option<ResourceUsage>,
+}
+
+/// Resources consumed by a finished task process
+/// Sumber daya yang dipakai proses tugas yang telah selesai
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// CPU time spent in user mode, in milliseconds
+    /// Waktu CPU dalam mode pengguna, dalam milidetik
+    pub user_cpu_ms: u64,
+
+    /// CPU time spent in the kernel, in milliseconds
+    /// Waktu CPU dalam kernel, dalam milidetik
+    pub sys_cpu_ms: u64,
+
+    /// Peak resident set size in kilobytes
+    /// Puncak resident set size dalam kilobyte
+    pub max_rss_kb: u64,
+
+    /// Voluntary and involuntary context switches
+    /// Context switch sukarela dan paksa
+    pub context_switches: u64,
 }
 
 /// Task execution status enumeration
 /// Enumerasi status eksekusi tugas
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TaskStatus {
     /// Waiting for worker assignment
     /// Menunggu penugasan worker
@@ -183,6 +382,46 @@ pub struct WorkerInfo {
     /// Operating system platform (linux, windows, macos)
     /// Platform sistem operasi (linux, windows, macos)
     pub platform: String,
+    
+    /// Capability tags advertised by this worker (e.g., "gpu", "rust")
+    /// Tag kemampuan yang diiklankan worker ini (mis., "gpu", "rust")
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// When the dispatcher registered this worker (Unix epoch)
+    /// Waktu dispatcher mendaftarkan worker ini (Unix epoch)
+    #[serde(default)]
+    pub connected_at: i64,
+
+    /// Worker is finishing its tasks before shutting down and takes no new ones
+    /// Worker sedang menyelesaikan tugasnya sebelum berhenti dan tidak menerima tugas baru
+    #[serde(default)]
+    pub draining: bool,
+
+    /// Milliseconds the last heartbeat took to arrive, by the worker's and dispatcher's clocks
+    /// Milidetik yang dibutuhkan heartbeat terakhir untuk tiba, menurut jam worker dan dispatcher
+    #[serde(default)]
+    pub last_rtt_ms: Option<u64>,
+
+    /// SHA-256 of the worker's pre-shared key, checked by dispatchers that require one
+    /// SHA-256 dari kunci pra-bagi worker, diperiksa oleh dispatcher yang mewajibkannya
+    #[serde(default)]
+    pub psk_hash: Option<String>,
+
+    /// Put into maintenance by an operator: keeps running tasks and stays registered, but gets no new ones
+    /// Dimasukkan ke mode pemeliharaan oleh operator: tetap menjalankan tugas dan terdaftar, tetapi tidak mendapat tugas baru
+    #[serde(default)]
+    pub maintenance: bool,
+
+    /// Tasks this worker finished successfully, as counted by the dispatcher
+    /// Tugas yang berhasil diselesaikan worker ini, menurut hitungan dispatcher
+    #[serde(default)]
+    pub tasks_completed: u64,
+
+    /// Tasks that failed or timed out on this worker, as counted by the dispatcher
+    /// Tugas yang gagal atau melewati batas waktu di worker ini, menurut hitungan dispatcher
+    #[serde(default)]
+    pub tasks_failed: u64,
 }
 
 impl WorkerInfo {
@@ -199,13 +438,27 @@ impl WorkerInfo {
             allow_shell: true,
             last_heartbeat: chrono::Local::now().timestamp(),
             platform: std::env::consts::OS.to_string(),
+            tags: Vec::new(),
+            connected_at: chrono::Local::now().timestamp(),
+            draining: false,
+            last_rtt_ms: None,
+            psk_hash: None,
+            maintenance: false,
+            tasks_completed: 0,
+            tasks_failed: 0,
         }
     }
 
-    /// Check if worker has available job slots
-    /// Periksa apakah worker memiliki slot pekerjaan tersedia
+    /// Check if worker has available job slots and is neither draining nor in maintenance
+    /// Periksa apakah worker memiliki slot pekerjaan tersedia dan tidak sedang dikosongkan atau dalam pemeliharaan
     pub fn is_idle(&self) -> bool {
-        self.current_jobs < self.max_jobs
+        !self.draining && !self.maintenance && self.current_jobs < self.max_jobs
+    }
+
+    /// Check if worker advertises every tag the task requires
+    /// Periksa apakah worker mengiklankan semua tag yang dibutuhkan tugas
+    pub fn can_run(&self, task: &Task) -> bool {
+        task.required_tags.iter().all(|tag| self.tags.contains(tag))
     }
 }
 
@@ -270,6 +523,56 @@ impl ResourceAvailability {
     }
 }
 
+/// Totals a worker has accumulated over the tasks it ran since it started
+/// Total yang dikumpulkan worker atas tugas yang dijalankannya sejak mulai
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkerTaskStats {
+    /// Tasks that finished with `TaskStatus::Completed`
+    /// Tugas yang selesai dengan `TaskStatus::Completed`
+    pub tasks_completed: u64,
+
+    /// Tasks that finished with any other status
+    /// Tugas yang selesai dengan status lainnya
+    pub tasks_failed: u64,
+
+    /// Execution time summed over every finished task
+    /// Waktu eksekusi yang dijumlahkan atas setiap tugas yang selesai
+    pub total_duration_ms: u64,
+
+    /// Finished tasks per minute since the worker started
+    /// Tugas selesai per menit sejak worker mulai
+    pub throughput_per_minute: f64,
+}
+
+impl WorkerTaskStats {
+    /// Fraction of finished tasks that did not complete successfully
+    /// Pecahan tugas selesai yang tidak berhasil
+    pub fn error_rate(&self) -> f64 {
+        let finished = self.tasks_completed + self.tasks_failed;
+        if finished == 0 {
+            return 0.0;
+        }
+        self.tasks_failed as f64 / finished as f64
+    }
+}
+
+/// One line of task output as buffered by the dispatcher
+/// Satu baris output tugas yang disangga oleh dispatcher
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputLine {
+    /// Text of the line, without the trailing newline
+    /// Teks baris, tanpa baris baru di akhir
+    pub line: String,
+
+    /// Whether the line came from stderr
+    /// Apakah baris berasal dari stderr
+    pub is_stderr: bool,
+
+    /// When the dispatcher received the line (Unix epoch)
+    /// Kapan dispatcher menerima baris (Unix epoch)
+    pub timestamp: i64,
+}
+
 /// Protocol messages for communication
 /// Pesan protokol untuk komunikasi
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -278,21 +581,149 @@ pub enum Message {
     WorkerAnnounce(WorkerInfo),
     
     /// Dispatcher assigns a task to worker
-    AssignTask(Task),
+    ///
+    /// `trace_context` carries the dispatcher's span (W3C `traceparent`) when tracing is enabled.
+    AssignTask {
+        task: Task,
+        trace_context: Option<HashMap<String, String>>,
+    },
+
+    /// Dispatcher assigns several tasks to a worker in one round-trip
+    BatchAssign(Vec<Task>),
+
+    /// Client submits a task to the dispatcher's scheduler
+    /// Klien mengirim tugas ke penjadwal dispatcher
+    ///
+    /// The reply is `TaskStatus` once queued, or `TaskCompleted` after it finishes when `wait` is set.
+    SubmitTask {
+        task: Task,
+        wait: bool,
+    },
     
     /// Worker reports task progress
     TaskProgress {
         task_id: String,
+        worker_id: String,
         progress: f32,
     },
     
+    /// Worker streams one line of task output while the task runs
+    /// Worker mengalirkan satu baris output tugas selama tugas berjalan
+    TaskOutput {
+        task_id: String,
+        worker_id: String,
+        line: String,
+        is_stderr: bool,
+    },
+    
+    /// Worker sends the task's `output_files` after it finishes, before its result
+    /// Worker mengirim `output_files` tugas setelah selesai, sebelum hasilnya
+    TaskOutputFiles {
+        task_id: String,
+        worker_id: String,
+        files: Vec<FileSpec>,
+    },
+
     /// Worker reports task completion
     TaskCompleted(TaskResult),
+
+    /// Ask the dispatcher for the live status of a task
+    /// Tanyakan status terkini sebuah tugas ke dispatcher
+    QueryTask {
+        task_id: String,
+    },
+
+    /// Ask the dispatcher for the full result of a finished task
+    /// Minta hasil lengkap tugas yang sudah selesai ke dispatcher
+    QueryResult {
+        task_id: String,
+    },
+
+    /// Wait for output of a task past line `offset`, or for the task to finish
+    /// Tunggu output tugas setelah baris `offset`, atau sampai tugas selesai
+    WatchTask {
+        task_id: String,
+        offset: usize,
+    },
+
+    /// Dispatcher's reply to `WatchTask` while the task is still producing output
+    /// Balasan dispatcher untuk `WatchTask` selama tugas masih menghasilkan output
+    TaskOutputLines {
+        task_id: String,
+        lines: Vec<OutputLine>,
+        next_offset: usize,
+    },
+
+    /// Ask the dispatcher for every registered worker
+    /// Minta semua worker yang terdaftar ke dispatcher
+    ListWorkers,
+
+    /// Dispatcher's reply to `ListWorkers`
+    /// Balasan dispatcher untuk `ListWorkers`
+    WorkerList(Vec<WorkerInfo>),
+
+    /// Dispatcher's reply to `QueryTask`
+    /// Balasan dispatcher untuk `QueryTask`
+    TaskStatus {
+        task_id: String,
+        status: TaskStatus,
+        progress: Option<f32>,
+    },
+    
+    /// Failed task is being retried (attempt starts at 1)
+    /// Tugas yang gagal sedang dicoba ulang (percobaan dimulai dari 1)
+    TaskRetrying {
+        task_id: String,
+        attempt: u32,
+    },
     
-    /// Heartbeat message
+    /// Heartbeat message; `sent_at` is the worker's clock in Unix milliseconds
+    /// Pesan heartbeat; `sent_at` adalah jam worker dalam milidetik Unix
     Heartbeat {
         worker_id: String,
-        timestamp: i64,
+        sent_at: i64,
+    },
+
+    /// Worker load report, sent on the heartbeat interval
+    /// Laporan beban worker, dikirim pada interval heartbeat
+    WorkerStatus {
+        worker_id: String,
+        current_jobs: usize,
+        cpu_percent: f32,
+        memory_mb: u64,
+        load_avg_1m: f32,
+        #[serde(default)]
+        draining: bool,
+    },
+
+    /// Ask a worker for the totals of the tasks it has run
+    /// Minta total tugas yang telah dijalankan sebuah worker
+    QueryWorkerMetrics {
+        worker_id: String,
+    },
+
+    /// Worker's reply to `QueryWorkerMetrics`
+    /// Balasan worker untuk `QueryWorkerMetrics`
+    WorkerMetrics {
+        worker_id: String,
+        stats: WorkerTaskStats,
+    },
+
+    /// Operator turned maintenance mode on or off for a worker
+    /// Operator menyalakan atau mematikan mode pemeliharaan untuk sebuah worker
+    WorkerMaintenanceMode {
+        worker_id: String,
+        enable: bool,
+    },
+
+    /// Drained worker is shutting down; the dispatcher forgets it
+    /// Worker yang sudah dikosongkan sedang berhenti; dispatcher melupakannya
+    ///
+    /// Carries the same pre-shared key proof as `WorkerAnnounce`.
+    WorkerDeregister {
+        worker_id: String,
+        #[serde(default)]
+        psk_hash: Option<String>,
     },
     
     /// Cancel a task
@@ -304,6 +735,12 @@ pub enum Message {
     Ack {
         message_id: String,
     },
+
+    /// Ephemeral X25519 public key, sent by both sides when a secured connection opens
+    /// Kunci publik X25519 sementara, dikirim kedua sisi saat koneksi aman dibuka
+    KeyExchange {
+        public_key: [u8; 32],
+    },
     
     /// P2P: Announce resource availability to peers
     /// P2P: Umumkan ketersediaan resource ke peer
@@ -338,3 +775,53 @@ pub enum Message {
         resources: ResourceAvailability,
     },
 }
+
+impl Message {
+    /// Worker ID a worker claims as its own when sending this message
+    /// ID worker yang diklaim oleh worker pengirim pesan ini
+    pub fn sender_worker_id(&self) -> Option<&str> {
+        match self {
+            Message::WorkerAnnounce(info) => Some(&info.id),
+            Message::TaskCompleted(result) => Some(&result.worker_id),
+            Message::Heartbeat { worker_id, .. }
+            | Message::WorkerStatus { worker_id, .. }
+            | Message::WorkerDeregister { worker_id, .. }
+            | Message::TaskProgress { worker_id, .. }
+            | Message::TaskOutput { worker_id, .. }
+            | Message::TaskOutputFiles { worker_id, .. } => Some(worker_id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_retry_policy() {
+        let mut task = Task::new("false".to_string());
+        assert!(!task.should_retry(TaskStatus::Failed));
+
+        task.max_retries = 2;
+        assert!(task.should_retry(TaskStatus::Failed));
+        assert!(task.should_retry(TaskStatus::TimedOut));
+        assert!(!task.should_retry(TaskStatus::Completed));
+        assert!(!task.should_retry(TaskStatus::Cancelled));
+
+        task.retry_count = 2;
+        assert!(!task.should_retry(TaskStatus::Failed));
+    }
+
+    #[test]
+    fn test_task_retry_backoff_doubles_by_default() {
+        let mut task = Task::new("false".to_string());
+        task.retry_delay_ms = 500;
+
+        assert_eq!(task.next_retry_delay_ms(), 500);
+        task.retry_count = 1;
+        assert_eq!(task.next_retry_delay_ms(), 1000);
+        task.retry_count = 3;
+        assert_eq!(task.next_retry_delay_ms(), 4000);
+    }
+}
@@ -106,12 +106,12 @@ impl ResourceLimits {
 }
 
 /// Monitor process resource usage
-#[cfg(unix)]
+#[cfg(all(target_os = "linux", feature = "procfs"))]
 pub struct ProcessMonitor {
     pid: u32,
 }
 
-#[cfg(unix)]
+#[cfg(all(target_os = "linux", feature = "procfs"))]
 impl ProcessMonitor {
     pub fn new(pid: u32) -> Self {
         Self { pid }
@@ -119,8 +119,7 @@ impl ProcessMonitor {
 
     /// Get current memory usage in MB
     pub fn get_memory_usage(&self) -> Result<u64> {
-        // Note: procfs is Linux-only, using alternative for Windows
-        // use procfs::process::Process;
+        use procfs::process::Process;
         
         let process = Process::new(self.pid as i32)?;
         let stat = process.stat()?;
@@ -134,8 +133,7 @@ impl ProcessMonitor {
 
     /// Get current CPU time in seconds
     pub fn get_cpu_time(&self) -> Result<u64> {
-        // Note: procfs is Linux-only, using alternative for Windows
-        // use procfs::process::Process;
+        use procfs::process::Process;
         
         let process = Process::new(self.pid as i32)?;
         let stat = process.stat()?;
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Mount point of the unified (v2) cgroup hierarchy
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Parent cgroup holding one child cgroup per sandboxed task
+const OCTASKLY_GROUP: &str = "octaskly";
+
+/// Transient cgroup v2 group for a single task, removed on drop
+pub struct TaskCgroup {
+    path: PathBuf,
+}
+
+impl TaskCgroup {
+    /// Whether a cgroup v2 hierarchy is mounted at `/sys/fs/cgroup`
+    pub fn is_supported() -> bool {
+        Path::new(CGROUP_ROOT).join("cgroup.controllers").exists()
+    }
+
+    /// Create `/sys/fs/cgroup/octaskly/<task_id>` and write the given limits
+    ///
+    /// `cpu_quota` is `(period_us, quota_us)` as written to `cpu.max`.
+    pub fn create(task_id: &str, cpu_quota: Option<(u64, u64)>, memory_limit_bytes: Option<u64>) -> Result<Self> {
+        if !Self::is_supported() {
+            anyhow::bail!("cgroup v2 is not mounted at {}", CGROUP_ROOT);
+        }
+
+        let root = Path::new(CGROUP_ROOT);
+        let parent = root.join(OCTASKLY_GROUP);
+        fs::create_dir_all(&parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+
+        // Delegate the cpu and memory controllers down to the per-task groups
+        for dir in [root, parent.as_path()] {
+            let control = dir.join("cgroup.subtree_control");
+            fs::write(&control, "+cpu +memory")
+                .with_context(|| format!("Failed to enable controllers in {}", control.display()))?;
+        }
+
+        let path = parent.join(task_id);
+        fs::create_dir(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let cgroup = Self { path };
+
+        if let Some((period_us, quota_us)) = cpu_quota {
+            cgroup.write("cpu.max", &format!("{} {}", quota_us, period_us))?;
+        }
+        if let Some(limit) = memory_limit_bytes {
+            cgroup.write("memory.max", &limit.to_string())?;
+            // Without this the kernel swaps instead of enforcing the limit
+            if cgroup.path.join("memory.swap.max").exists() {
+                cgroup.write("memory.swap.max", "0")?;
+            }
+        }
+
+        Ok(cgroup)
+    }
+
+    /// Path of `cgroup.procs`, for a child to join the group before exec
+    pub fn procs_path(&self) -> Result<CString> {
+        Ok(CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes())?)
+    }
+
+    /// Directory of this cgroup
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write(&self, file: &str, value: &str) -> Result<()> {
+        let target = self.path.join(file);
+        fs::write(&target, value).with_context(|| format!("Failed to write {}", target.display()))
+    }
+}
+
+impl Drop for TaskCgroup {
+    fn drop(&mut self) {
+        // Only succeeds once every process in the group has exited
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+/// Move the calling process into the cgroup whose `cgroup.procs` is `procs_path`
+///
+/// Intended for `pre_exec`: only async-signal-safe calls, no allocation.
+pub fn join_from_child(procs_path: &CString) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(procs_path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // Writing "0" moves the writing process itself
+        let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+        libc::close(fd);
+        if written != 1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
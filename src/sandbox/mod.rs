@@ -1,10 +1,31 @@
 use anyhow::Result;
-use std::process::{Command, Child};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Child, ExitStatus};
+use tempfile::TempDir;
+
+#[cfg(target_os = "linux")]
+pub mod cgroup;
+#[cfg(target_os = "linux")]
+pub mod netns;
+#[cfg(target_os = "linux")]
+pub mod seccomp;
+#[cfg(target_os = "linux")]
+pub mod userns;
+
+/// Directory under the work dir that persisted workspaces are moved to
+pub const COMPLETED_DIR: &str = "completed";
 
 /// Sandbox module for strict task isolation
 pub struct Sandbox {
     isolation_level: IsolationLevel,
     work_dir: Option<String>,
+    task_id: Option<String>,
+    cpu_quota: Option<(u64, u64)>,
+    memory_limit_bytes: Option<u64>,
+    seccomp_allowlist: Option<Vec<i64>>,
+    user_namespace: bool,
+    no_network: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -21,6 +42,12 @@ impl Sandbox {
         Self {
             isolation_level,
             work_dir: None,
+            task_id: None,
+            cpu_quota: None,
+            memory_limit_bytes: None,
+            seccomp_allowlist: None,
+            user_namespace: false,
+            no_network: false,
         }
     }
 
@@ -30,8 +57,50 @@ impl Sandbox {
         self
     }
 
+    /// Name the sandbox after a task (used for its cgroup directory)
+    pub fn with_task_id(mut self, task_id: String) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    /// Limit CPU time to `quota_us` per `period_us` (cgroup v2 `cpu.max`, Linux only)
+    pub fn with_cpu_quota(mut self, period_us: u64, quota_us: u64) -> Self {
+        self.cpu_quota = Some((period_us, quota_us));
+        self
+    }
+
+    /// Limit memory usage in bytes (cgroup v2 `memory.max`, Linux only)
+    pub fn with_memory_limit_bytes(mut self, limit: u64) -> Self {
+        self.memory_limit_bytes = Some(limit);
+        self
+    }
+
+    /// Syscall numbers allowed under `VeryStrict` seccomp filtering (Linux only)
+    ///
+    /// Replaces the default allowlist; any other syscall kills the process with SIGSYS.
+    pub fn with_seccomp_allowlist(mut self, syscalls: &[i32]) -> Self {
+        self.seccomp_allowlist = Some(syscalls.iter().map(|&nr| i64::from(nr)).collect());
+        self
+    }
+
+    /// Run the child as root inside new user and PID namespaces (Linux only)
+    ///
+    /// Always on for `VeryStrict` when the kernel supports user namespaces.
+    pub fn with_user_namespace(mut self) -> Self {
+        self.user_namespace = true;
+        self
+    }
+
+    /// Run the child in its own network namespace with only loopback (Linux only)
+    ///
+    /// Unprivileged workers also get a user namespace, which creating one requires.
+    pub fn with_no_network(mut self) -> Self {
+        self.no_network = true;
+        self
+    }
+
     /// Create a sandboxed process
-    pub fn execute_command(&self, program: &str, args: &[&str]) -> Result<Child> {
+    pub fn execute_command(&self, program: &str, args: &[&str]) -> Result<SandboxedChild> {
         let mut cmd = Command::new(program);
         
         // Set working directory if specified
@@ -73,8 +142,111 @@ impl Sandbox {
         }
 
         cmd.args(args);
+
+        #[cfg(target_os = "linux")]
+        let cgroup = self.apply_cgroup(&mut cmd)?;
+        #[cfg(target_os = "linux")]
+        self.apply_user_namespace(&mut cmd);
+        #[cfg(target_os = "linux")]
+        self.apply_network_namespace(&mut cmd);
+        // Installed last so the other pre-exec hooks are not filtered
+        #[cfg(target_os = "linux")]
+        if self.isolation_level == IsolationLevel::VeryStrict {
+            self.apply_seccomp(&mut cmd)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        if self.cpu_quota.is_some() || self.memory_limit_bytes.is_some() {
+            tracing::warn!("CPU and memory limits require Linux cgroup v2; running without them");
+        }
+        #[cfg(not(target_os = "linux"))]
+        if self.no_network {
+            anyhow::bail!("Network isolation requires Linux network namespaces");
+        }
+
         let child = cmd.spawn()?;
-        Ok(child)
+        Ok(SandboxedChild {
+            child,
+            #[cfg(target_os = "linux")]
+            cgroup,
+        })
+    }
+
+    /// Enter user and PID namespaces before exec, if requested and supported
+    #[cfg(target_os = "linux")]
+    fn apply_user_namespace(&self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        // Without root, a network namespace can only be created inside a user namespace
+        let wanted = self.user_namespace
+            || self.isolation_level == IsolationLevel::VeryStrict
+            || (self.no_network && !nix::unistd::geteuid().is_root());
+        if !wanted {
+            return;
+        }
+        if !userns::is_supported() {
+            tracing::warn!("User namespaces are not supported by this kernel; running without them");
+            return;
+        }
+
+        let maps = userns::IdMaps::for_current_user();
+        unsafe {
+            cmd.pre_exec(move || userns::enter(&maps));
+        }
+    }
+
+    /// Enter a loopback-only network namespace before exec, if requested
+    ///
+    /// Registered after the user namespace hook, so it runs inside that namespace.
+    #[cfg(target_os = "linux")]
+    fn apply_network_namespace(&self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        if !self.no_network {
+            return;
+        }
+        unsafe {
+            cmd.pre_exec(netns::enter);
+        }
+    }
+
+    /// Restrict the child to the seccomp allowlist from just before exec onwards
+    #[cfg(target_os = "linux")]
+    fn apply_seccomp(&self, cmd: &mut Command) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let allowlist = self
+            .seccomp_allowlist
+            .clone()
+            .unwrap_or_else(seccomp::default_allowlist);
+        let program = seccomp::build_filter(&allowlist)?;
+
+        unsafe {
+            cmd.pre_exec(move || seccomp::install_filter(&program));
+        }
+        Ok(())
+    }
+
+    /// Create a transient cgroup for the configured limits and make the child join it before exec
+    #[cfg(target_os = "linux")]
+    fn apply_cgroup(&self, cmd: &mut Command) -> Result<Option<cgroup::TaskCgroup>> {
+        use std::os::unix::process::CommandExt;
+
+        if self.cpu_quota.is_none() && self.memory_limit_bytes.is_none() {
+            return Ok(None);
+        }
+
+        let task_id = self
+            .task_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let task_cgroup = cgroup::TaskCgroup::create(&task_id, self.cpu_quota, self.memory_limit_bytes)?;
+        let procs_path = task_cgroup.procs_path()?;
+
+        // Joining before exec means the limits apply from the very first instruction
+        unsafe {
+            cmd.pre_exec(move || cgroup::join_from_child(&procs_path));
+        }
+        Ok(Some(task_cgroup))
     }
 
     /// Check if a path is safe to access in sandbox
@@ -99,10 +271,112 @@ impl Sandbox {
         self.isolation_level
     }
 
-    /// Create a temporary isolated directory
-    pub async fn create_isolated_workspace(&self) -> Result<String> {
-        let tempdir = tempfile::tempdir()?;
-        Ok(tempdir.path().to_string_lossy().to_string())
+    /// Create a temporary isolated directory, deleted when the returned guard is dropped
+    ///
+    /// The directory is created inside the work dir when one is set, otherwise in the
+    /// system temp directory.
+    pub async fn create_isolated_workspace(&self) -> Result<IsolatedWorkspace> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("octaskly-");
+        let dir = match &self.work_dir {
+            Some(work_dir) => {
+                tokio::fs::create_dir_all(work_dir).await?;
+                builder.tempdir_in(work_dir)?
+            }
+            None => builder.tempdir()?,
+        };
+
+        Ok(IsolatedWorkspace {
+            dir,
+            work_dir: self.work_dir.as_ref().map(PathBuf::from),
+            task_id: self.task_id.clone(),
+        })
+    }
+}
+
+/// Temporary workspace of a sandboxed task, removed on drop unless persisted
+///
+/// Dereferences to the directory's path. Call `persist` for tasks that set
+/// `Task::keep_workdir` to keep the directory after the task finishes.
+#[derive(Debug)]
+pub struct IsolatedWorkspace {
+    dir: TempDir,
+    work_dir: Option<PathBuf>,
+    task_id: Option<String>,
+}
+
+impl IsolatedWorkspace {
+    /// Path of the workspace directory
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Move the workspace to `{work_dir}/completed/{task_id}` and keep it
+    ///
+    /// Needs a sandbox built with both `with_work_dir` and `with_task_id`. A directory
+    /// left there by an earlier run of the same task is replaced.
+    pub fn persist(self) -> Result<PathBuf> {
+        let (Some(work_dir), Some(task_id)) = (&self.work_dir, &self.task_id) else {
+            anyhow::bail!("Persisting a workspace requires the sandbox's work dir and task ID");
+        };
+
+        let target = work_dir.join(COMPLETED_DIR).join(task_id);
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        std::fs::create_dir_all(work_dir.join(COMPLETED_DIR))?;
+        std::fs::rename(self.dir.path(), &target)?;
+
+        // The directory has moved; stop the guard from deleting anything
+        let _ = self.dir.keep();
+        Ok(target)
+    }
+}
+
+impl Deref for IsolatedWorkspace {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl AsRef<Path> for IsolatedWorkspace {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+/// Process spawned by a `Sandbox`, owning any cgroup created for it
+pub struct SandboxedChild {
+    child: Child,
+    #[cfg(target_os = "linux")]
+    cgroup: Option<cgroup::TaskCgroup>,
+}
+
+impl SandboxedChild {
+    /// OS process ID of the child
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Access the underlying process (e.g. to take its stdio handles)
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Forcefully terminate the child
+    pub fn kill(&mut self) -> Result<()> {
+        self.child.kill()?;
+        Ok(())
+    }
+
+    /// Wait for the child to exit, then remove its cgroup
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        let status = self.child.wait()?;
+        #[cfg(target_os = "linux")]
+        drop(self.cgroup.take());
+        Ok(status)
     }
 }
 
@@ -144,9 +418,140 @@ mod tests {
         assert!(!sandbox.is_path_allowed("/etc/passwd"));
     }
 
+    #[test]
+    fn test_sandbox_resource_limit_builders() {
+        let sandbox = Sandbox::new(IsolationLevel::Strict)
+            .with_cpu_quota(100_000, 50_000)
+            .with_memory_limit_bytes(64 * 1024 * 1024);
+        assert_eq!(sandbox.cpu_quota, Some((100_000, 50_000)));
+        assert_eq!(sandbox.memory_limit_bytes, Some(64 * 1024 * 1024));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_memory_hog_is_oom_killed() {
+        use std::os::unix::process::ExitStatusExt;
+
+        if !cgroup::TaskCgroup::is_supported() {
+            eprintln!("cgroup v2 not available, skipping");
+            return;
+        }
+
+        // `tail` buffers /dev/zero forever since it never sees a newline
+        let sandbox = Sandbox::new(IsolationLevel::None)
+            .with_task_id(format!("test-{}", uuid::Uuid::new_v4()))
+            .with_memory_limit_bytes(16 * 1024 * 1024);
+        let mut child = match sandbox.execute_command("tail", &["/dev/zero"]) {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("cannot create cgroup ({}), skipping", e);
+                return;
+            }
+        };
+
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_very_strict_runs_allowed_command() {
+        let mut child = Sandbox::new(IsolationLevel::VeryStrict)
+            .execute_command("/bin/true", &[])
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_very_strict_kills_disallowed_syscall() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Enough to exec and report failure, but not for the loader to map memory
+        let allowlist = [libc::SYS_execve, libc::SYS_write, libc::SYS_exit_group].map(|nr| nr as i32);
+        let mut child = Sandbox::new(IsolationLevel::VeryStrict)
+            .with_seccomp_allowlist(&allowlist)
+            .execute_command("/bin/true", &[])
+            .unwrap();
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGSYS));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_user_namespace_runs_as_root_pid_1() {
+        if !userns::is_supported() {
+            eprintln!("user namespaces not available, skipping");
+            return;
+        }
+
+        let mut child = Sandbox::new(IsolationLevel::Strict)
+            .with_user_namespace()
+            .execute_command("sh", &["-c", r#"test "$(id -u)" = 0 && test $$ = 1"#])
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+    }
+
     #[test]
     fn test_sandbox_default() {
         let sandbox = Sandbox::default();
         assert_eq!(sandbox.isolation_level(), IsolationLevel::Basic);
     }
+
+    #[tokio::test]
+    async fn test_isolated_workspace_removed_on_drop() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let sandbox = Sandbox::new(IsolationLevel::Basic).with_work_dir(work_dir.path().to_string_lossy().into_owned());
+
+        let workspace = sandbox.create_isolated_workspace().await.unwrap();
+        let path = workspace.to_path_buf();
+        assert!(path.starts_with(work_dir.path()));
+        std::fs::write(workspace.join("out.txt"), "data").unwrap();
+
+        drop(workspace);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_isolated_workspace_persist() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let sandbox = Sandbox::new(IsolationLevel::Basic)
+            .with_work_dir(work_dir.path().to_string_lossy().into_owned())
+            .with_task_id("task-1".to_string());
+
+        let workspace = sandbox.create_isolated_workspace().await.unwrap();
+        let original = workspace.to_path_buf();
+        std::fs::write(workspace.join("out.txt"), "data").unwrap();
+
+        let kept = workspace.persist().unwrap();
+        assert_eq!(kept, work_dir.path().join(COMPLETED_DIR).join("task-1"));
+        assert_eq!(std::fs::read_to_string(kept.join("out.txt")).unwrap(), "data");
+        assert!(!original.exists());
+
+        let anonymous = Sandbox::new(IsolationLevel::Basic).create_isolated_workspace().await.unwrap();
+        assert!(anonymous.persist().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_no_network_blocks_connections() {
+        if !nix::unistd::geteuid().is_root() && !userns::is_supported() {
+            eprintln!("network namespaces not available, skipping");
+            return;
+        }
+
+        // A listener on the host's loopback stands in for the outside network
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        assert!(std::net::TcpStream::connect(listener.local_addr().unwrap()).is_ok());
+
+        let script = format!("grep -v '^ *lo:' /proc/self/net/dev | grep -q ':' && exit 2; curl -s --max-time 2 {}", url);
+        let mut child = Sandbox::new(IsolationLevel::None)
+            .with_no_network()
+            .execute_command("sh", &["-c", &script])
+            .unwrap();
+        let status = child.wait().unwrap();
+
+        // curl exits 7 when it cannot connect; 2 would mean interfaces besides loopback
+        assert_eq!(status.code(), Some(7));
+    }
 }
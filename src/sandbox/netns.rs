@@ -0,0 +1,36 @@
+use nix::sched::{unshare, CloneFlags};
+use nix::sys::socket::{socket, AddressFamily, SockFlag, SockType};
+use std::os::fd::AsRawFd;
+
+/// Move into a new network namespace whose only interface is loopback
+///
+/// Intended for `pre_exec`, after any user namespace has been entered (creating a
+/// network namespace needs CAP_SYS_ADMIN in the current user namespace). Failing
+/// to unshare is an error so the task never runs with network access by mistake;
+/// failing to bring loopback up only leaves the child without `127.0.0.1`.
+pub fn enter() -> std::io::Result<()> {
+    unshare(CloneFlags::CLONE_NEWNET)?;
+    let _ = bring_up_loopback();
+    Ok(())
+}
+
+/// Set IFF_UP on `lo`, which starts down in a fresh namespace (`ip link set lo up`)
+fn bring_up_loopback() -> std::io::Result<()> {
+    let sock = socket(AddressFamily::Inet, SockType::Datagram, SockFlag::SOCK_CLOEXEC, None)?;
+
+    let mut request: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in request.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+
+    unsafe {
+        if libc::ioctl(sock.as_raw_fd(), libc::SIOCGIFFLAGS, &mut request) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        request.ifr_ifru.ifru_flags |= (libc::IFF_UP | libc::IFF_RUNNING) as libc::c_short;
+        if libc::ioctl(sock.as_raw_fd(), libc::SIOCSIFFLAGS, &request) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, TargetArch};
+use std::collections::BTreeMap;
+
+/// Syscalls a short-lived command needs to load, run, and exit
+pub fn default_allowlist() -> Vec<i64> {
+    let mut syscalls = vec![
+        libc::SYS_read,
+        libc::SYS_write,
+        libc::SYS_readv,
+        libc::SYS_writev,
+        libc::SYS_pread64,
+        libc::SYS_openat,
+        libc::SYS_close,
+        libc::SYS_lseek,
+        libc::SYS_fstat,
+        libc::SYS_newfstatat,
+        libc::SYS_statx,
+        libc::SYS_readlinkat,
+        libc::SYS_faccessat,
+        libc::SYS_getdents64,
+        libc::SYS_getcwd,
+        libc::SYS_fcntl,
+        libc::SYS_ioctl,
+        libc::SYS_dup3,
+        libc::SYS_pipe2,
+        libc::SYS_brk,
+        libc::SYS_mmap,
+        libc::SYS_munmap,
+        libc::SYS_mprotect,
+        libc::SYS_madvise,
+        libc::SYS_execve,
+        libc::SYS_clone,
+        libc::SYS_wait4,
+        libc::SYS_exit,
+        libc::SYS_exit_group,
+        libc::SYS_rt_sigaction,
+        libc::SYS_rt_sigprocmask,
+        libc::SYS_rt_sigreturn,
+        libc::SYS_sigaltstack,
+        libc::SYS_set_tid_address,
+        libc::SYS_set_robust_list,
+        libc::SYS_rseq,
+        libc::SYS_futex,
+        libc::SYS_prlimit64,
+        libc::SYS_getrandom,
+        libc::SYS_uname,
+        libc::SYS_getpid,
+        libc::SYS_getppid,
+        libc::SYS_gettid,
+        libc::SYS_getuid,
+        libc::SYS_geteuid,
+        libc::SYS_getgid,
+        libc::SYS_getegid,
+        libc::SYS_clock_gettime,
+        libc::SYS_clock_nanosleep,
+        libc::SYS_nanosleep,
+    ];
+
+    // Legacy entry points still used by libc on x86_64
+    #[cfg(target_arch = "x86_64")]
+    syscalls.extend([
+        libc::SYS_open,
+        libc::SYS_stat,
+        libc::SYS_lstat,
+        libc::SYS_access,
+        libc::SYS_readlink,
+        libc::SYS_dup2,
+        libc::SYS_pipe,
+        libc::SYS_fork,
+        libc::SYS_vfork,
+        libc::SYS_arch_prctl,
+    ]);
+
+    syscalls
+}
+
+/// Compile an allowlist into a BPF program that kills the process on any other syscall
+pub fn build_filter(allowlist: &[i64]) -> Result<BpfProgram> {
+    let rules = allowlist.iter().map(|&syscall| (syscall, Vec::new())).collect::<BTreeMap<_, _>>();
+
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::KillProcess,
+        SeccompAction::Allow,
+        TargetArch::try_from(std::env::consts::ARCH)?,
+    )?;
+    Ok(filter.try_into()?)
+}
+
+/// Install a compiled filter on the calling process
+///
+/// Intended for `pre_exec`: the filter is inherited across `execve`.
+pub fn install_filter(program: &BpfProgram) -> std::io::Result<()> {
+    // Required to install a filter without CAP_SYS_ADMIN
+    nix::sys::prctl::set_no_new_privs()?;
+
+    let prog = libc::sock_fprog {
+        len: program.len() as libc::c_ushort,
+        filter: program.as_ptr() as *mut libc::sock_filter,
+    };
+    let ret = unsafe {
+        libc::prctl(
+            libc::PR_SET_SECCOMP,
+            libc::SECCOMP_MODE_FILTER,
+            &prog as *const libc::sock_fprog,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
@@ -0,0 +1,100 @@
+use nix::sched::{unshare, CloneFlags};
+use std::ffi::CStr;
+use std::fs;
+
+/// Whether the kernel lets this process create user namespaces
+pub fn is_supported() -> bool {
+    let max_namespaces = fs::read_to_string("/proc/sys/user/max_user_namespaces")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+
+    // Debian-style kernels can disable unprivileged user namespaces separately
+    let unprivileged_allowed = fs::read_to_string("/proc/sys/kernel/unprivileged_userns_clone")
+        .map(|value| value.trim() != "0")
+        .unwrap_or(true);
+
+    max_namespaces > 0 && (unprivileged_allowed || nix::unistd::geteuid().is_root())
+}
+
+/// ID maps computed in the parent so the child never allocates
+pub struct IdMaps {
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+}
+
+impl IdMaps {
+    /// Map root inside the namespace to the current real UID/GID outside it
+    pub fn for_current_user() -> Self {
+        Self {
+            uid_map: format!("0 {} 1", nix::unistd::getuid()).into_bytes(),
+            gid_map: format!("0 {} 1", nix::unistd::getgid()).into_bytes(),
+        }
+    }
+}
+
+/// Move into new user and PID namespaces, then fork so the exec'd program is PID 1
+///
+/// Intended for `pre_exec`. The forked child returns `Ok` and goes on to exec; the
+/// intermediate process waits for it and exits with the same status, so the `Child`
+/// handle seen by the parent reflects the sandboxed program.
+pub fn enter(maps: &IdMaps) -> std::io::Result<()> {
+    unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWPID)?;
+
+    // setgroups must be denied before an unprivileged process may write gid_map
+    write_proc_file(c"/proc/self/setgroups", b"deny")?;
+    write_proc_file(c"/proc/self/uid_map", &maps.uid_map)?;
+    write_proc_file(c"/proc/self/gid_map", &maps.gid_map)?;
+
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    if pid == 0 {
+        // If the intermediate process is killed, take the whole namespace down with it
+        unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) };
+        return Ok(());
+    }
+
+    unsafe { relay_exit(pid) }
+}
+
+/// Wait for `pid` in the intermediate process and exit with its status
+unsafe fn relay_exit(pid: libc::pid_t) -> ! {
+    // Drop inherited descriptors (including the parent's exec-status pipe) so
+    // `spawn` returns as soon as the namespaced child has exec'd
+    if libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) != 0 {
+        for fd in 3..libc::sysconf(libc::_SC_OPEN_MAX) as libc::c_int {
+            libc::close(fd);
+        }
+    }
+
+    let mut status = 0;
+    while libc::waitpid(pid, &mut status, 0) < 0 {
+        if *libc::__errno_location() != libc::EINTR {
+            libc::_exit(127);
+        }
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+fn write_proc_file(path: &CStr, contents: &[u8]) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        libc::close(fd);
+        if written != contents.len() as isize {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -1,37 +1,741 @@
+use crate::persistence::{StoreBackend, TaskFilter};
 use crate::protocol::{Task, WorkerInfo};
-use std::collections::VecDeque;
+use crate::state::DispatcherState;
+use crate::error::{Result, SchedulerError};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{debug, info};
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tracing::{debug, info, warn};
+
+/// How often held-back tasks are checked for having come due
+/// Seberapa sering tugas yang ditahan diperiksa apakah sudah jatuh tempo
+pub const SCHEDULED_TASK_TICK: Duration = Duration::from_millis(100);
+
+/// Heartbeat delay above which a worker is considered slow, unless configured otherwise
+/// Jeda heartbeat di atas batas ini membuat worker dianggap lambat, kecuali dikonfigurasi lain
+pub const DEFAULT_SLOW_HEARTBEAT_MS: u64 = 5000;
+
+/// Consecutive slow heartbeats after which a worker is flagged in the logs
+/// Jumlah heartbeat lambat berturut-turut sebelum worker ditandai di log
+pub const SLOW_HEARTBEAT_STREAK: u32 = 3;
+
+/// Seconds of waiting that raise a queued task's priority by one, unless configured otherwise
+/// Detik menunggu yang menaikkan prioritas tugas dalam antrian sebesar satu, kecuali dikonfigurasi lain
+pub const DEFAULT_STARVATION_THRESHOLD_SECS: u64 = 300;
+
+/// How often the dispatcher recomputes the aged priority of queued tasks
+/// Seberapa sering dispatcher menghitung ulang prioritas tugas antri yang menua
+pub const QUEUE_AGING_INTERVAL: Duration = Duration::from_secs(30);
+
+// Queue entry ordered by effective priority, then by enqueue sequence (FIFO tie-break)
+// Entri antrian diurutkan berdasarkan prioritas efektif, lalu urutan masuk (FIFO)
+struct QueuedTask {
+    seq: u64,
+    // Task priority plus one per starvation threshold spent waiting
+    // Prioritas tugas ditambah satu per ambang kelaparan yang dihabiskan untuk menunggu
+    effective_priority: u64,
+    task: Task,
+}
+
+impl QueuedTask {
+    // Entry for a task entering the queue, stamping when it first did
+    // Entri untuk tugas yang masuk antrian, mencatat kapan pertama kali masuk
+    fn new(seq: u64, mut task: Task, now: i64, starvation_threshold_secs: u64) -> Self {
+        task.queued_at.get_or_insert(now);
+        let mut entry = Self {
+            seq,
+            effective_priority: 0,
+            task,
+        };
+        entry.age(now, starvation_threshold_secs);
+        entry
+    }
+
+    // Recompute the effective priority from the time waited so far
+    // Hitung ulang prioritas efektif dari waktu yang sudah ditunggu
+    fn age(&mut self, now: i64, starvation_threshold_secs: u64) {
+        let waited = self.task.queued_at.map_or(0, |at| (now - at).max(0) as u64);
+        let boost = waited.checked_div(starvation_threshold_secs).unwrap_or(0);
+        self.effective_priority = self.task.priority as u64 + boost;
+    }
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap: higher priority wins, lower sequence wins
+        // BinaryHeap adalah max-heap: prioritas lebih tinggi menang, urutan lebih kecil menang
+        self.effective_priority
+            .cmp(&other.effective_priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Worker selection strategy used when assigning tasks
+/// Strategi pemilihan worker yang digunakan saat menugaskan tugas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchedulerAlgorithm {
+    /// First idle worker in registration order
+    /// Worker menganggur pertama sesuai urutan pendaftaran
+    #[default]
+    Fifo,
+
+    /// Idle worker with the lowest `current_jobs / max_jobs` ratio
+    /// Worker menganggur dengan rasio `current_jobs / max_jobs` terendah
+    WeightedLeastConnections,
+
+    /// Rotate through idle workers in registration order
+    /// Bergiliran melalui worker menganggur sesuai urutan pendaftaran
+    RoundRobin,
+}
+
+impl std::str::FromStr for SchedulerAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fifo" => Ok(Self::Fifo),
+            "weighted-least-connections" => Ok(Self::WeightedLeastConnections),
+            "round-robin" => Ok(Self::RoundRobin),
+            other => Err(format!(
+                "unknown scheduler algorithm {:?}, expected fifo, weighted-least-connections or round-robin",
+                other
+            )),
+        }
+    }
+}
+
+/// Load figures last reported by a worker through `Message::WorkerStatus`
+/// Angka beban terakhir yang dilaporkan worker melalui `Message::WorkerStatus`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct WorkerMetrics {
+    pub cpu_percent: f32,
+    pub memory_mb: u64,
+    pub load_avg_1m: f32,
+}
+
+/// Relative weights of the parts of `Scheduler::worker_health_score`
+/// Bobot relatif dari bagian-bagian `Scheduler::worker_health_score`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchedulingWeights {
+    /// Free job slots, `1 - current_jobs / max_jobs`
+    /// Slot pekerjaan kosong, `1 - current_jobs / max_jobs`
+    pub cpu: f64,
+    /// Heartbeat latency, `1 / (1 + rtt_ms)`
+    /// Latensi heartbeat, `1 / (1 + rtt_ms)`
+    pub latency: f64,
+    /// Share of finished tasks that succeeded
+    /// Porsi tugas selesai yang berhasil
+    pub reliability: f64,
+}
+
+impl Default for SchedulingWeights {
+    fn default() -> Self {
+        Self {
+            cpu: 1.0,
+            latency: 1.0,
+            reliability: 1.0,
+        }
+    }
+}
 
 // Task scheduler managing queue and worker assignment
 // Penjadwal tugas mengelola antrian dan penugasan worker
 pub struct Scheduler {
-    queue: Arc<RwLock<VecDeque<Task>>>,
+    algorithm: SchedulerAlgorithm,
+    queue: Arc<RwLock<BinaryHeap<QueuedTask>>>,
     workers: Arc<RwLock<Vec<WorkerInfo>>>,
+    // Latest load report per worker ID
+    // Laporan beban terbaru per ID worker
+    metrics: Arc<RwLock<HashMap<String, WorkerMetrics>>>,
+    // Heartbeats taking longer than this count towards a worker's slow streak
+    // Heartbeat yang lebih lama dari ini dihitung ke rentetan lambat worker
+    slow_heartbeat_ms: u64,
+    // Consecutive slow heartbeats per worker ID
+    // Heartbeat lambat berturut-turut per ID worker
+    slow_heartbeats: Arc<RwLock<HashMap<String, u32>>>,
+    // Queued tasks gain one priority level per this many seconds of waiting; 0 disables aging
+    // Tugas antri naik satu tingkat prioritas per sekian detik menunggu; 0 menonaktifkan penuaan
+    starvation_threshold_secs: u64,
+    // How much each part of a worker's health score counts
+    // Seberapa besar setiap bagian skor kesehatan worker diperhitungkan
+    weights: SchedulingWeights,
+    next_seq: AtomicU64,
+    // Tasks waiting on unfinished dependencies, keyed by task ID
+    // Tugas yang menunggu dependensi belum selesai, dikunci oleh ID tugas
+    blocked: Arc<RwLock<HashMap<String, Task>>>,
+    // Tasks held back until their scheduled time, keyed by (time, enqueue sequence)
+    // Tugas yang ditahan sampai waktu jadwalnya, dikunci oleh (waktu, urutan masuk)
+    pending_scheduled: Arc<RwLock<BTreeMap<(i64, u64), Task>>>,
+    // Tasks that kept failing after all their retries, oldest first
+    // Tugas yang terus gagal setelah semua percobaan ulangnya, yang terlama lebih dulu
+    dead_letter_queue: Arc<RwLock<VecDeque<Task>>>,
+    // IDs of tasks that completed successfully
+    // ID tugas yang telah selesai dengan sukses
+    satisfied: Arc<RwLock<HashSet<String>>>,
+    // Index of the next worker to try in round-robin mode
+    // Indeks worker berikutnya yang dicoba dalam mode round-robin
+    next_worker: AtomicUsize,
+    // Wakes the dispatcher's scheduling loop before its next tick
+    // Membangunkan loop penjadwalan dispatcher sebelum tick berikutnya
+    rebalance: Notify,
+    // Set by an operator to stop starting tasks; queued ones wait for `resume`
+    // Diatur operator untuk berhenti memulai tugas; tugas yang antri menunggu `resume`
+    paused: Arc<AtomicBool>,
+    // Store the queue is saved to after every change, if any
+    // Penyimpanan tempat antrian disimpan setelah setiap perubahan, jika ada
+    store: Option<Arc<dyn StoreBackend + Send + Sync>>,
+    // Serializes snapshots so an older one never overwrites a newer one
+    // Menserialisasi snapshot agar yang lama tidak menimpa yang lebih baru
+    persist_lock: Mutex<()>,
 }
 
 impl Scheduler {
-    // Initialize new scheduler with empty queue and workers
-    // Inisialisasi penjadwal baru dengan antrian dan worker kosong
-    pub fn new() -> Self {
+    // Initialize new scheduler with empty queue, workers and chosen algorithm
+    // Inisialisasi penjadwal baru dengan antrian, worker kosong dan algoritma pilihan
+    pub fn new(algorithm: SchedulerAlgorithm) -> Self {
         Self {
-            queue: Arc::new(RwLock::new(VecDeque::new())),
+            algorithm,
+            queue: Arc::new(RwLock::new(BinaryHeap::new())),
             workers: Arc::new(RwLock::new(Vec::new())),
+            metrics: Arc::new(RwLock::new(HashMap::new())),
+            slow_heartbeat_ms: DEFAULT_SLOW_HEARTBEAT_MS,
+            slow_heartbeats: Arc::new(RwLock::new(HashMap::new())),
+            starvation_threshold_secs: DEFAULT_STARVATION_THRESHOLD_SECS,
+            weights: SchedulingWeights::default(),
+            next_seq: AtomicU64::new(0),
+            blocked: Arc::new(RwLock::new(HashMap::new())),
+            pending_scheduled: Arc::new(RwLock::new(BTreeMap::new())),
+            dead_letter_queue: Arc::new(RwLock::new(VecDeque::new())),
+            satisfied: Arc::new(RwLock::new(HashSet::new())),
+            next_worker: AtomicUsize::new(0),
+            rebalance: Notify::new(),
+            paused: Arc::new(AtomicBool::new(false)),
+            store: None,
+            persist_lock: Mutex::new(()),
+        }
+    }
+
+    // Log workers whose heartbeats keep taking longer than `ms` to arrive
+    // Catat worker yang heartbeat-nya terus membutuhkan lebih dari `ms` untuk tiba
+    pub fn with_slow_heartbeat_ms(mut self, ms: u64) -> Self {
+        self.slow_heartbeat_ms = ms;
+        self
+    }
+
+    // Raise a queued task's priority by one for every `secs` it waits, so low priorities still run
+    // Naikkan prioritas tugas antri satu tingkat setiap `secs` menunggu, agar prioritas rendah tetap berjalan
+    pub fn with_starvation_threshold_secs(mut self, secs: u64) -> Self {
+        self.starvation_threshold_secs = secs;
+        self
+    }
+
+    // Weigh the parts of the worker health score used to break ties between workers
+    // Bobot bagian-bagian skor kesehatan worker yang dipakai untuk memutus seri antar worker
+    pub fn with_scheduling_weights(mut self, weights: SchedulingWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    // Save the queue to `store` after every change so it survives a restart
+    // Simpan antrian ke `store` setelah setiap perubahan agar bertahan setelah restart
+    pub fn with_store(mut self, store: Arc<dyn StoreBackend + Send + Sync>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    // Write the queued tasks to `store`, in the order they will be scheduled,
+    // followed by the tasks held back until a later time
+    // Tulis tugas dalam antrian ke `store`, sesuai urutan penjadwalannya,
+    // diikuti tugas yang ditahan sampai waktu berikutnya
+    pub async fn persist_queue(&self, store: &dyn StoreBackend) -> Result<()> {
+        let _guard = self.persist_lock.lock().await;
+        let tasks: Vec<Task> = {
+            let queue = self.queue.read().await;
+            let pending_scheduled = self.pending_scheduled.read().await;
+            let mut entries: Vec<&QueuedTask> = queue.iter().collect();
+            entries.sort_by(|a, b| b.cmp(a));
+            entries
+                .into_iter()
+                .map(|entry| &entry.task)
+                .chain(pending_scheduled.values())
+                .cloned()
+                .collect()
+        };
+        store.save_queue(&tasks).await
+    }
+
+    // Reload the queue saved by `persist_queue`, plus stored tasks still marked Pending
+    // Muat ulang antrian yang disimpan `persist_queue`, serta tugas tersimpan yang masih Pending
+    //
+    // Pending tasks are rebuilt from their stored definition, or from the command alone for
+    // records written before definitions were kept. Those still waiting on a dependency that
+    // has not completed are blocked again.
+    // Tugas Pending dibangun ulang dari definisi tersimpan; yang masih menunggu dependensi diblokir lagi.
+    pub async fn restore_queue(&self, store: &dyn StoreBackend) -> Result<usize> {
+        let tasks = store.load_queue().await?;
+        let mut pending = Vec::new();
+        for stored in store.search_tasks(TaskFilter::new().with_status("Pending")).await? {
+            if tasks.iter().any(|task| task.id == stored.id) {
+                continue;
+            }
+            let task = match stored.definition {
+                Some(definition) => definition,
+                None => {
+                    let mut task = Task::new(stored.command);
+                    task.id = stored.id;
+                    if let Ok(created_at) = chrono::DateTime::parse_from_rfc3339(&stored.created_at) {
+                        task.created_at = created_at.timestamp();
+                    }
+                    task
+                }
+            };
+            pending.push(task);
+        }
+
+        // Dependencies that finished before the restart are only known to the history
+        // Dependensi yang selesai sebelum restart hanya diketahui oleh riwayat
+        let mut completed = HashSet::new();
+        for dep in pending.iter().flat_map(|task| &task.dependencies) {
+            if !completed.contains(dep) && store.get_task(dep).await?.is_some_and(|stored| stored.status == "Completed") {
+                completed.insert(dep.clone());
+            }
+        }
+
+        let restored = tasks.len() + pending.len();
+        {
+            let mut satisfied = self.satisfied.write().await;
+            let mut blocked = self.blocked.write().await;
+            let mut queue = self.queue.write().await;
+            let mut pending_scheduled = self.pending_scheduled.write().await;
+            satisfied.extend(completed);
+            for task in tasks {
+                self.place(&mut queue, &mut pending_scheduled, task);
+            }
+            for task in pending {
+                if task.dependencies.iter().all(|dep| satisfied.contains(dep)) {
+                    self.place(&mut queue, &mut pending_scheduled, task);
+                } else {
+                    blocked.insert(task.id.clone(), task);
+                }
+            }
+        }
+        if restored > 0 {
+            info!("Restored {} queued task(s)", restored);
+        }
+        Ok(restored)
+    }
+
+    // Reload the dead letter queue from `store`
+    // Muat ulang antrian surat mati dari `store`
+    pub async fn restore_dead_letters(&self, store: &dyn StoreBackend) -> Result<usize> {
+        let tasks = store.load_dead_letters().await?;
+        let restored = tasks.len();
+        self.dead_letter_queue.write().await.extend(tasks);
+        if restored > 0 {
+            warn!("{} task(s) waiting in the dead letter queue", restored);
+        }
+        Ok(restored)
+    }
+
+    // Park a task that failed after exhausting its retries until someone requeues it
+    // Parkir tugas yang gagal setelah percobaan ulangnya habis sampai diantrikan ulang
+    pub async fn move_to_dlq(&self, task: Task) {
+        warn!(
+            "Task {} moved to the dead letter queue after {} retries: {}",
+            task.id, task.retry_count, task.command
+        );
+        if let Some(store) = &self.store {
+            if let Err(e) = store.add_dead_letter(&task).await {
+                warn!("Failed to persist dead letter task {}: {}", task.id, e);
+            }
+        }
+        let mut dead_letter_queue = self.dead_letter_queue.write().await;
+        dead_letter_queue.retain(|queued| queued.id != task.id);
+        dead_letter_queue.push_back(task);
+    }
+
+    // Tasks in the dead letter queue, oldest failure first
+    // Tugas dalam antrian surat mati, kegagalan terlama lebih dulu
+    pub async fn dead_letter_tasks(&self) -> Vec<Task> {
+        self.dead_letter_queue.read().await.iter().cloned().collect()
+    }
+
+    // Take a task out of the dead letter queue and queue it again with fresh retries
+    // Keluarkan tugas dari antrian surat mati dan antrikan lagi dengan percobaan ulang baru
+    pub async fn requeue_dead_letter(&self, task_id: &str) -> Option<Task> {
+        let mut task = {
+            let mut dead_letter_queue = self.dead_letter_queue.write().await;
+            let index = dead_letter_queue.iter().position(|task| task.id == task_id)?;
+            dead_letter_queue.remove(index)?
+        };
+        if let Some(store) = &self.store {
+            if let Err(e) = store.remove_dead_letter(task_id).await {
+                warn!("Failed to remove dead letter task {}: {}", task_id, e);
+            }
+        }
+
+        task.retry_count = 0;
+        task.queued_at = None;
+        info!("Requeued dead letter task {}", task_id);
+        self.enqueue(task.clone()).await;
+        Some(task)
+    }
+
+    // Persist the queue to the attached store, logging failures
+    // Simpan antrian ke penyimpanan yang terpasang, mencatat kegagalan
+    async fn queue_changed(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = self.persist_queue(store.as_ref()).await {
+                warn!("Failed to persist task queue: {}", e);
+            }
+        }
+    }
+
+    // Get configured worker selection algorithm
+    // Dapatkan algoritma pemilihan worker yang dikonfigurasi
+    pub fn algorithm(&self) -> SchedulerAlgorithm {
+        self.algorithm
+    }
+
+    // Submit task honoring its dependencies; rejects circular dependency chains
+    // Kirim tugas dengan memperhatikan dependensinya; tolak rantai dependensi melingkar
+    pub async fn submit(&self, task: Task) -> Result<()> {
+        let satisfied = self.satisfied.read().await;
+        let mut blocked = self.blocked.write().await;
+
+        if Self::creates_cycle(&task, &blocked) {
+            return Err(SchedulerError::CircularDependency { task_id: task.id }.into());
+        }
+
+        if task.dependencies.iter().all(|dep| satisfied.contains(dep)) {
+            drop(blocked);
+            drop(satisfied);
+            self.enqueue(task).await;
+        } else {
+            info!(
+                "Task {} blocked on {} dependencies",
+                task.id,
+                task.dependencies.len()
+            );
+            blocked.insert(task.id.clone(), task);
+        }
+
+        Ok(())
+    }
+
+    // Submit several tasks at once: either all are accepted or none are
+    // Kirim beberapa tugas sekaligus: semuanya diterima atau tidak ada sama sekali
+    pub async fn submit_batch(&self, tasks: Vec<Task>) -> Result<()> {
+        let satisfied = self.satisfied.read().await;
+        let mut blocked = self.blocked.write().await;
+        let mut queue = self.queue.write().await;
+        let mut pending_scheduled = self.pending_scheduled.write().await;
+
+        // Check cycles against blocked tasks and the rest of the batch before changing anything
+        // Periksa siklus terhadap tugas yang diblokir dan sisa batch sebelum mengubah apa pun
+        let mut pending = blocked.clone();
+        pending.extend(tasks.iter().map(|task| (task.id.clone(), task.clone())));
+        if let Some(task) = tasks.iter().find(|task| Self::creates_cycle(task, &pending)) {
+            return Err(SchedulerError::CircularDependency { task_id: task.id.clone() }.into());
+        }
+
+        for task in tasks {
+            if task.dependencies.iter().all(|dep| satisfied.contains(dep)) {
+                self.place(&mut queue, &mut pending_scheduled, task);
+            } else {
+                info!(
+                    "Task {} blocked on {} dependencies",
+                    task.id,
+                    task.dependencies.len()
+                );
+                blocked.insert(task.id.clone(), task);
+            }
+        }
+        drop((satisfied, blocked, queue, pending_scheduled));
+
+        self.queue_changed().await;
+        Ok(())
+    }
+
+    // Walk dependency edges through blocked tasks looking for the new task's ID
+    // Telusuri sisi dependensi melalui tugas yang diblokir untuk mencari ID tugas baru
+    fn creates_cycle(task: &Task, blocked: &HashMap<String, Task>) -> bool {
+        let mut stack: Vec<&str> = task.dependencies.iter().map(String::as_str).collect();
+        let mut visited = HashSet::new();
+
+        while let Some(id) = stack.pop() {
+            if id == task.id {
+                return true;
+            }
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(dep_task) = blocked.get(id) {
+                stack.extend(dep_task.dependencies.iter().map(String::as_str));
+            }
+        }
+
+        false
+    }
+
+    // Record successful completion and promote tasks whose dependencies are now met
+    // Catat penyelesaian sukses dan promosikan tugas yang dependensinya kini terpenuhi
+    pub async fn notify_dependency_satisfied(&self, task_id: &str) {
+        let mut satisfied = self.satisfied.write().await;
+        satisfied.insert(task_id.to_string());
+
+        let ready: Vec<Task> = {
+            let mut blocked = self.blocked.write().await;
+            let ready_ids: Vec<String> = blocked
+                .values()
+                .filter(|t| t.dependencies.iter().all(|dep| satisfied.contains(dep)))
+                .map(|t| t.id.clone())
+                .collect();
+            ready_ids
+                .iter()
+                .filter_map(|id| blocked.remove(id))
+                .collect()
+        };
+        drop(satisfied);
+
+        for task in ready {
+            debug!("Dependencies satisfied for task {}", task.id);
+            self.enqueue(task).await;
         }
     }
 
-    // Add task to the work queue for distribution
-    // Tambahkan tugas ke antrian kerja untuk distribusi
+    // Get number of tasks waiting on dependencies
+    // Dapatkan jumlah tugas yang menunggu dependensi
+    pub async fn blocked_count(&self) -> usize {
+        self.blocked.read().await.len()
+    }
+
+    // Add task to the work queue using the task's own priority
+    // Tambahkan tugas ke antrian kerja menggunakan prioritas tugas itu sendiri
+    // A task scheduled for later is held back until `release_due_tasks` finds it due
+    // Tugas yang dijadwalkan nanti ditahan sampai `release_due_tasks` mendapati jatuh tempo
     pub async fn enqueue(&self, task: Task) {
-        info!("Enqueued task {}: {}", task.id, task.command);
-        self.queue.write().await.push_back(task);
+        {
+            let mut queue = self.queue.write().await;
+            let mut pending_scheduled = self.pending_scheduled.write().await;
+            self.place(&mut queue, &mut pending_scheduled, task);
+        }
+        self.queue_changed().await;
+    }
+
+    // Push a task onto the queue, or hold it back if its scheduled time is still ahead
+    // Dorong tugas ke antrian, atau tahan jika waktu jadwalnya belum tiba
+    fn place(
+        &self,
+        queue: &mut BinaryHeap<QueuedTask>,
+        pending_scheduled: &mut BTreeMap<(i64, u64), Task>,
+        task: Task,
+    ) {
+        let seq = self.next_seq.fetch_add(1, AtomicOrdering::Relaxed);
+        let now = chrono::Local::now().timestamp();
+        match task.scheduled_at {
+            Some(at) if task.is_scheduled_after(now) => {
+                info!("Scheduled task {} for {}: {}", task.id, at, task.command);
+                pending_scheduled.insert((at, seq), task);
+            }
+            _ => {
+                info!("Enqueued task {} (priority {}): {}", task.id, task.priority, task.command);
+                queue.push(QueuedTask::new(seq, task, now, self.starvation_threshold_secs));
+            }
+        }
+    }
+
+    // Move held-back tasks whose scheduled time has passed into the queue
+    // Pindahkan tugas yang ditahan dan sudah lewat waktu jadwalnya ke antrian
+    pub async fn release_due_tasks(&self) -> usize {
+        let now = chrono::Local::now().timestamp();
+        let released = {
+            let mut queue = self.queue.write().await;
+            let mut pending_scheduled = self.pending_scheduled.write().await;
+            let later = pending_scheduled.split_off(&(now + 1, 0));
+            let due = std::mem::replace(&mut *pending_scheduled, later);
+            let released = due.len();
+            for ((_, seq), task) in due {
+                debug!("Released scheduled task {}", task.id);
+                queue.push(QueuedTask::new(seq, task, now, self.starvation_threshold_secs));
+            }
+            released
+        };
+        if released > 0 {
+            self.queue_changed().await;
+        }
+        released
+    }
+
+    // Recompute the effective priority of every queued task, returning how many went up
+    // Hitung ulang prioritas efektif setiap tugas antri, mengembalikan jumlah yang naik
+    pub async fn age_queue(&self) -> usize {
+        self.age_queue_at(chrono::Local::now().timestamp()).await
+    }
+
+    // Age the queue as of `now` (Unix seconds), so tests need not wait for real time to pass
+    // Menuakan antrian per `now` (detik Unix), agar pengujian tidak perlu menunggu waktu berlalu
+    async fn age_queue_at(&self, now: i64) -> usize {
+        let mut queue = self.queue.write().await;
+        let mut entries = std::mem::take(&mut *queue).into_vec();
+        let mut boosted = 0;
+        for entry in &mut entries {
+            let before = entry.effective_priority;
+            entry.age(now, self.starvation_threshold_secs);
+            if entry.effective_priority > before {
+                debug!("Task {} aged to priority {}", entry.task.id, entry.effective_priority);
+                boosted += 1;
+            }
+        }
+        *queue = BinaryHeap::from(entries);
+        boosted
+    }
+
+    // Tasks held back until a later time, soonest first
+    // Tugas yang ditahan sampai waktu berikutnya, yang terdekat lebih dulu
+    pub async fn scheduled_tasks(&self) -> Vec<Task> {
+        self.pending_scheduled.read().await.values().cloned().collect()
+    }
+
+    // Hold back the next run of a recurring task that is being dispatched
+    // Tahan jalannya berikutnya dari tugas berulang yang sedang dikirim
+    //
+    // The dispatched run gives up its cron expression so a retry or a requeue
+    // of it does not schedule the series a second time
+    // Jalannya yang dikirim melepas ekspresi cron-nya agar percobaan ulang atau
+    // pengantrian ulang tidak menjadwalkan rangkaian itu dua kali
+    async fn schedule_recurrences(&self, dispatched: &mut [Task]) {
+        let now = chrono::Local::now().timestamp();
+        let mut next_runs = Vec::new();
+        for task in dispatched {
+            let Some(next_at) = task.next_occurrence(now) else { continue };
+            let mut next = task.clone();
+            next.id = uuid::Uuid::new_v4().to_string();
+            next.created_at = now;
+            next.retry_count = 0;
+            next.queued_at = None;
+            next.scheduled_at = Some(next_at);
+            task.cron_expression = None;
+            next_runs.push(next);
+        }
+        if next_runs.is_empty() {
+            return;
+        }
+
+        let mut queue = self.queue.write().await;
+        let mut pending_scheduled = self.pending_scheduled.write().await;
+        for next in next_runs {
+            self.place(&mut queue, &mut pending_scheduled, next);
+        }
+    }
+
+    // Add task to the work queue with an explicit priority
+    // Tambahkan tugas ke antrian kerja dengan prioritas eksplisit
+    pub async fn enqueue_with_priority(&self, mut task: Task, priority: u8) {
+        task.priority = priority;
+        self.enqueue(task).await;
     }
 
-    // Remove and return first task from queue (FIFO)
-    // Hapus dan kembalikan tugas pertama dari antrian (FIFO)
+    // Remove and return highest-priority task (FIFO among equal priority)
+    // Hapus dan kembalikan tugas prioritas tertinggi (FIFO untuk prioritas sama)
     pub async fn dequeue(&self) -> Option<Task> {
-        self.queue.write().await.pop_front()
+        let task = self.queue.write().await.pop().map(|entry| entry.task);
+        if task.is_some() {
+            self.queue_changed().await;
+        }
+        task
+    }
+
+    // Cancel a task that has not been assigned yet and record it as Cancelled;
+    // false if it is not waiting here. Assigned tasks are cancelled on their worker
+    // with Message::CancelTask instead
+    // Batalkan tugas yang belum ditugaskan dan catat sebagai Cancelled; false jika
+    // tidak sedang menunggu di sini. Tugas yang sudah ditugaskan dibatalkan di
+    // worker-nya dengan Message::CancelTask
+    pub async fn cancel_queued_task(&self, task_id: &str, dispatcher: &DispatcherState) -> bool {
+        match self.remove_queued_task(task_id).await {
+            Some(task) => {
+                dispatcher.mark_cancelled(&task).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Remove a task that has not been assigned yet, from the queue, the scheduled
+    // tasks or the blocked set
+    // Hapus tugas yang belum ditugaskan, dari antrian, tugas terjadwal atau
+    // himpunan yang terblokir
+    pub async fn remove_queued_task(&self, task_id: &str) -> Option<Task> {
+        let task = {
+            let mut queue = self.queue.write().await;
+            let mut pending_scheduled = self.pending_scheduled.write().await;
+            let mut entries = std::mem::take(&mut *queue).into_vec();
+            let task = entries
+                .iter()
+                .position(|entry| entry.task.id == task_id)
+                .map(|index| entries.swap_remove(index).task);
+            *queue = BinaryHeap::from(entries);
+
+            let scheduled_key = pending_scheduled
+                .iter()
+                .find(|(_, task)| task.id == task_id)
+                .map(|(key, _)| *key);
+            task.or_else(|| scheduled_key.and_then(|key| pending_scheduled.remove(&key)))
+        };
+
+        match task {
+            Some(task) => {
+                info!("Cancelled queued task {}", task_id);
+                self.queue_changed().await;
+                Some(task)
+            }
+            None => self.blocked.write().await.remove(task_id),
+        }
+    }
+
+    // Queued tasks in the order they will be assigned, highest effective priority first
+    // Tugas dalam antrian sesuai urutan penugasan, prioritas efektif tertinggi lebih dulu
+    pub async fn queued_tasks(&self) -> Vec<Task> {
+        let queue = self.queue.read().await;
+        let mut entries: Vec<&QueuedTask> = queue.iter().collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        entries.into_iter().map(|entry| entry.task.clone()).collect()
+    }
+
+    // Remove every task from the queue, returning them in assignment order
+    // Hapus semua tugas dari antrian, mengembalikannya sesuai urutan penugasan
+    pub async fn flush_queue(&self) -> Vec<Task> {
+        let tasks: Vec<Task> = std::mem::take(&mut *self.queue.write().await)
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .map(|entry| entry.task)
+            .collect();
+        if !tasks.is_empty() {
+            info!("Flushed {} queued tasks", tasks.len());
+            self.queue_changed().await;
+        }
+        tasks
     }
 
     // Get current number of pending tasks
@@ -40,11 +744,80 @@ impl Scheduler {
         self.queue.read().await.len()
     }
 
-    // Register new worker with scheduler
-    // Daftarkan worker baru dengan penjadwal
-    pub async fn register_worker(&self, worker: WorkerInfo) {
+    // Register new worker with scheduler; a worker announcing itself again replaces its entry
+    // Daftarkan worker baru dengan penjadwal; worker yang mengumumkan diri lagi menggantikan entrinya
+    pub async fn register_worker(&self, mut worker: WorkerInfo) {
         debug!("Registering worker: {}", worker.name);
-        self.workers.write().await.push(worker);
+        worker.connected_at = chrono::Local::now().timestamp();
+        let mut workers = self.workers.write().await;
+        match workers.iter_mut().find(|w| w.id == worker.id) {
+            Some(existing) => {
+                info!("Worker {} re-registered", worker.name);
+                // Task counts are the dispatcher's own and outlive the announcement
+                // Jumlah tugas adalah milik dispatcher dan bertahan melewati pengumuman
+                worker.tasks_completed = existing.tasks_completed;
+                worker.tasks_failed = existing.tasks_failed;
+                *existing = worker;
+            }
+            None => workers.push(worker),
+        }
+    }
+
+    // Ask for an immediate scheduling pass if tasks are waiting, e.g. after a worker joins
+    // Minta penjadwalan segera jika ada tugas menunggu, mis. setelah worker bergabung
+    pub async fn rebalance_hint(&self) -> bool {
+        if self.queue_size().await == 0 {
+            return false;
+        }
+        self.rebalance.notify_one();
+        true
+    }
+
+    // Stop assigning queued tasks, e.g. for a maintenance window; running tasks carry on
+    // Berhenti menugaskan tugas yang antri, mis. untuk jendela pemeliharaan; tugas berjalan tetap lanjut
+    pub fn pause(&self) {
+        if !self.paused.swap(true, AtomicOrdering::SeqCst) {
+            info!("Scheduling paused");
+        }
+    }
+
+    // Assign queued tasks again, starting with an immediate scheduling pass
+    // Tugaskan lagi tugas yang antri, dimulai dengan penjadwalan segera
+    pub fn resume(&self) {
+        if self.paused.swap(false, AtomicOrdering::SeqCst) {
+            info!("Scheduling resumed");
+        }
+        self.rebalance.notify_one();
+    }
+
+    // Whether an operator has paused scheduling
+    // Apakah operator telah menjeda penjadwalan
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(AtomicOrdering::SeqCst)
+    }
+
+    // Resolves once `rebalance_hint` requested a scheduling pass
+    // Selesai setelah `rebalance_hint` meminta penjadwalan
+    pub async fn rebalance_requested(&self) {
+        self.rebalance.notified().await;
+    }
+
+    // Look up a registered worker by ID
+    // Cari worker terdaftar berdasarkan ID
+    pub async fn get_worker(&self, worker_id: &str) -> Option<WorkerInfo> {
+        self.workers.read().await.iter().find(|w| w.id == worker_id).cloned()
+    }
+
+    // Unregister a worker and forget its load reports
+    // Batalkan pendaftaran worker dan lupakan laporan bebannya
+    pub async fn remove_worker(&self, worker_id: &str) -> Option<WorkerInfo> {
+        let mut workers = self.workers.write().await;
+        let pos = workers.iter().position(|w| w.id == worker_id)?;
+        let worker = workers.remove(pos);
+        self.metrics.write().await.remove(worker_id);
+        self.slow_heartbeats.write().await.remove(worker_id);
+        info!("Removed worker {}", worker.name);
+        Some(worker)
     }
 
     // Update worker information
@@ -56,6 +829,79 @@ impl Scheduler {
         }
     }
 
+    // Record a worker's load report; it also counts as a heartbeat
+    // Catat laporan beban worker; juga dihitung sebagai heartbeat
+    pub async fn update_worker_metrics(&self, worker_id: &str, metrics: WorkerMetrics) {
+        let mut workers = self.workers.write().await;
+        if let Some(worker) = workers.iter_mut().find(|w| w.id == worker_id) {
+            worker.last_heartbeat = chrono::Local::now().timestamp();
+        }
+        self.metrics.write().await.insert(worker_id.to_string(), metrics);
+    }
+
+    // Record a heartbeat that took `rtt_ms` to arrive, warning once a worker's
+    // heartbeats have been slow SLOW_HEARTBEAT_STREAK times in a row
+    // Catat heartbeat yang butuh `rtt_ms` untuk tiba, memberi peringatan setelah
+    // heartbeat worker lambat SLOW_HEARTBEAT_STREAK kali berturut-turut
+    pub async fn record_heartbeat(&self, worker_id: &str, rtt_ms: u64) {
+        let mut workers = self.workers.write().await;
+        let Some(worker) = workers.iter_mut().find(|w| w.id == worker_id) else {
+            return;
+        };
+        worker.last_heartbeat = chrono::Local::now().timestamp();
+        worker.last_rtt_ms = Some(rtt_ms);
+
+        let mut slow_heartbeats = self.slow_heartbeats.write().await;
+        if rtt_ms <= self.slow_heartbeat_ms {
+            slow_heartbeats.remove(worker_id);
+            return;
+        }
+        let streak = slow_heartbeats.entry(worker_id.to_string()).or_insert(0);
+        *streak += 1;
+        if *streak == SLOW_HEARTBEAT_STREAK {
+            warn!(
+                "Worker {} heartbeats keep arriving late: {}ms, over {}ms {} times in a row",
+                worker.name, rtt_ms, self.slow_heartbeat_ms, streak
+            );
+        }
+    }
+
+    // Mark a worker as draining so no new tasks are routed to it
+    // Tandai worker sedang dikosongkan agar tidak ada tugas baru yang diarahkan kepadanya
+    pub async fn set_worker_draining(&self, worker_id: &str, draining: bool) {
+        let mut workers = self.workers.write().await;
+        if let Some(worker) = workers.iter_mut().find(|w| w.id == worker_id) {
+            if worker.draining != draining {
+                info!("Worker {} draining={}", worker.name, draining);
+            }
+            worker.draining = draining;
+        }
+    }
+
+    // Turn maintenance mode on or off, returning the updated worker if it is registered
+    // Nyalakan atau matikan mode pemeliharaan, mengembalikan worker yang diperbarui jika terdaftar
+    pub async fn set_worker_maintenance(&self, worker_id: &str, enable: bool) -> Option<WorkerInfo> {
+        let mut workers = self.workers.write().await;
+        let worker = workers.iter_mut().find(|w| w.id == worker_id)?;
+        if worker.maintenance != enable {
+            info!("Worker {} maintenance={}", worker.name, enable);
+        }
+        worker.maintenance = enable;
+        Some(worker.clone())
+    }
+
+    // Get the latest load report for a worker
+    // Dapatkan laporan beban terbaru untuk worker
+    pub async fn get_worker_metrics(&self, worker_id: &str) -> Option<WorkerMetrics> {
+        self.metrics.read().await.get(worker_id).copied()
+    }
+
+    // Latest load report of every worker that sent one, keyed by worker ID
+    // Laporan beban terbaru setiap worker yang mengirimkannya, dikunci oleh ID worker
+    pub async fn all_worker_metrics(&self) -> HashMap<String, WorkerMetrics> {
+        self.metrics.read().await.clone()
+    }
+
     // Decrement worker job count on task completion
     // Kurangi jumlah pekerjaan worker saat tugas selesai
     pub async fn worker_job_completed(&self, worker_id: &str) {
@@ -67,14 +913,106 @@ impl Scheduler {
         }
     }
 
-    // Find first idle worker ready to accept tasks
-    // Temukan worker menganggur pertama yang siap menerima tugas
+    // Count a finished task towards the worker's reliability
+    // Hitung tugas yang selesai ke dalam keandalan worker
+    pub async fn record_task_outcome(&self, worker_id: &str, succeeded: bool) {
+        let mut workers = self.workers.write().await;
+        if let Some(worker) = workers.iter_mut().find(|w| w.id == worker_id) {
+            match succeeded {
+                true => worker.tasks_completed += 1,
+                false => worker.tasks_failed += 1,
+            }
+        }
+    }
+
+    // Weighted average of free slots, heartbeat latency and task success rate, from 0 (worst) to 1
+    // Rata-rata berbobot dari slot kosong, latensi heartbeat dan tingkat keberhasilan tugas, dari 0 (terburuk) hingga 1
+    pub fn worker_health_score(&self, worker: &WorkerInfo) -> f64 {
+        let utilization = 1.0 - Self::load_score(worker).min(1.0);
+        let latency = 1.0 / (1.0 + worker.last_rtt_ms.unwrap_or(0) as f64);
+        let finished = worker.tasks_completed + worker.tasks_failed;
+        let reliability = match finished {
+            0 => 1.0,
+            _ => worker.tasks_completed as f64 / finished as f64,
+        };
+
+        let SchedulingWeights { cpu, latency: latency_weight, reliability: reliability_weight } = self.weights;
+        let total = cpu + latency_weight + reliability_weight;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        (cpu * utilization + latency_weight * latency + reliability_weight * reliability) / total
+    }
+
+    // Pick an idle worker according to the configured algorithm
+    // Pilih worker menganggur sesuai algoritma yang dikonfigurasi
     pub async fn get_idle_worker(&self) -> Option<WorkerInfo> {
         let workers = self.workers.read().await;
-        workers
-            .iter()
-            .find(|w| w.is_idle())
-            .cloned()
+        let metrics = self.metrics.read().await;
+        self.select_worker(&workers, &metrics, |_| true)
+            .map(|idx| workers[idx].clone())
+    }
+
+    // Pick an idle worker whose tags satisfy the task's required tags
+    // Pilih worker menganggur yang tag-nya memenuhi tag yang dibutuhkan tugas
+    pub async fn get_idle_worker_for_task(&self, task: &Task) -> Option<WorkerInfo> {
+        let workers = self.workers.read().await;
+        let metrics = self.metrics.read().await;
+        self.select_worker(&workers, &metrics, |w| w.can_run(task))
+            .map(|idx| workers[idx].clone())
+    }
+
+    // Return index of the idle, eligible worker chosen by the configured algorithm
+    // Kembalikan indeks worker menganggur yang memenuhi syarat sesuai algoritma
+    fn select_worker<F>(
+        &self,
+        workers: &[WorkerInfo],
+        metrics: &HashMap<String, WorkerMetrics>,
+        eligible: F,
+    ) -> Option<usize>
+    where
+        F: Fn(&WorkerInfo) -> bool,
+    {
+        let available = |w: &WorkerInfo| w.is_idle() && eligible(w);
+
+        match self.algorithm {
+            SchedulerAlgorithm::Fifo => workers.iter().position(available),
+            SchedulerAlgorithm::WeightedLeastConnections => workers
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| available(w))
+                .min_by(|(_, a), (_, b)| {
+                    // Equal slot usage: prefer the healthier worker, then the one reporting less CPU load
+                    // Penggunaan slot sama: utamakan worker yang lebih sehat, lalu yang beban CPU-nya lebih rendah
+                    let cpu = |w: &WorkerInfo| metrics.get(&w.id).map_or(0.0, |m| m.cpu_percent);
+                    Self::load_score(a)
+                        .total_cmp(&Self::load_score(b))
+                        .then_with(|| self.worker_health_score(b).total_cmp(&self.worker_health_score(a)))
+                        .then_with(|| cpu(a).total_cmp(&cpu(b)))
+                })
+                .map(|(idx, _)| idx),
+            SchedulerAlgorithm::RoundRobin => {
+                if workers.is_empty() {
+                    return None;
+                }
+                let start = self.next_worker.fetch_add(1, AtomicOrdering::Relaxed);
+                (0..workers.len())
+                    .map(|offset| (start + offset) % workers.len())
+                    .find(|&idx| available(&workers[idx]))
+                    .inspect(|&idx| {
+                        self.next_worker.store(idx + 1, AtomicOrdering::Relaxed);
+                    })
+            }
+        }
+    }
+
+    // Fraction of a worker's job slots currently in use
+    // Fraksi slot pekerjaan worker yang sedang digunakan
+    fn load_score(worker: &WorkerInfo) -> f64 {
+        if worker.max_jobs == 0 {
+            return f64::INFINITY;
+        }
+        worker.current_jobs as f64 / worker.max_jobs as f64
     }
 
     // Get all workers below job capacity
@@ -90,48 +1028,120 @@ impl Scheduler {
         self.workers.read().await.clone()
     }
 
-    // Remove inactive workers based on heartbeat timeout
-    // Hapus worker tidak aktif berdasarkan timeout detak jantung
-    pub async fn cleanup_offline_workers(&self, heartbeat_timeout_secs: i64) {
+    // Remove inactive workers based on heartbeat timeout, returning the removed ones
+    // Hapus worker tidak aktif berdasarkan timeout detak jantung, kembalikan yang dihapus
+    pub async fn cleanup_offline_workers(&self, heartbeat_timeout_secs: i64) -> Vec<WorkerInfo> {
         let now = chrono::Local::now().timestamp();
         let mut workers = self.workers.write().await;
-        
-        let initial_count = workers.len();
-        workers.retain(|w| (now - w.last_heartbeat) < heartbeat_timeout_secs);
-        
-        let removed = initial_count - workers.len();
-        if removed > 0 {
-            info!("Removed {} offline workers", removed);
+
+        let (online, offline): (Vec<WorkerInfo>, Vec<WorkerInfo>) = workers
+            .drain(..)
+            .partition(|w| (now - w.last_heartbeat) < heartbeat_timeout_secs);
+        *workers = online;
+
+        if !offline.is_empty() {
+            info!("Removed {} offline workers", offline.len());
+            self.metrics
+                .write()
+                .await
+                .retain(|id, _| workers.iter().any(|w| &w.id == id));
+            self.slow_heartbeats
+                .write()
+                .await
+                .retain(|id, _| workers.iter().any(|w| &w.id == id));
         }
+        offline
     }
 
-    // Schedule next task using FIFO algorithm
-    // Jadwalkan tugas berikutnya menggunakan algoritma FIFO
+    // Schedule highest-priority pending task that has a capable idle worker
+    // Jadwalkan tugas tertunda prioritas tertinggi yang memiliki worker menganggur yang mampu
+    #[tracing::instrument(
+        skip(self),
+        fields(task.id = tracing::field::Empty, worker.id = tracing::field::Empty, task.command = tracing::field::Empty)
+    )]
     pub async fn schedule_next_task(&self) -> Option<(Task, WorkerInfo)> {
-        // Get next task from queue
-        // Dapatkan tugas berikutnya dari antrian
-        if let Some(task) = self.dequeue().await {
-            // Find an idle worker
-            // Temukan worker menganggur
-            if let Some(mut worker) = self.get_idle_worker().await {
-                worker.current_jobs += 1;
-                self.update_worker(&worker.id, worker.clone()).await;
-                info!("Scheduled task {} to worker {}", task.id, worker.name);
-                return Some((task, worker));
+        if self.is_paused() {
+            return None;
+        }
+        let mut queue = self.queue.write().await;
+        let mut workers = self.workers.write().await;
+        let metrics = self.metrics.read().await;
+
+        // Tasks without a capable worker stay queued with their original position
+        // Tugas tanpa worker yang mampu tetap di antrian dengan posisi aslinya
+        let mut skipped = Vec::new();
+        let mut scheduled = None;
+
+        while let Some(entry) = queue.pop() {
+            match self.select_worker(&workers, &metrics, |w| w.can_run(&entry.task)) {
+                Some(idx) => {
+                    workers[idx].current_jobs += 1;
+                    scheduled = Some((entry.task, workers[idx].clone()));
+                    break;
+                }
+                None => skipped.push(entry),
+            }
+        }
+        queue.extend(skipped);
+
+        if let Some((task, worker)) = &scheduled {
+            let span = tracing::Span::current();
+            span.record("task.id", task.id.as_str());
+            span.record("worker.id", worker.id.as_str());
+            span.record("task.command", task.command.as_str());
+            info!("Scheduled task {} to worker {}", task.id, worker.name);
+        }
+        drop((queue, workers, metrics));
+
+        if let Some((task, _)) = &mut scheduled {
+            self.schedule_recurrences(std::slice::from_mut(task)).await;
+            self.queue_changed().await;
+        }
+        scheduled
+    }
+
+    // Pop as many runnable tasks as the worker has free slots, reserving the slots
+    // Ambil tugas yang bisa dijalankan sebanyak slot kosong worker, sekaligus memesan slot
+    pub async fn schedule_batch(&self, worker: &WorkerInfo) -> Vec<Task> {
+        let mut queue = self.queue.write().await;
+        let mut workers = self.workers.write().await;
+
+        // Use the registry's job count, which may have moved since `worker` was cloned
+        // Gunakan jumlah pekerjaan dari registri, yang mungkin berubah sejak `worker` disalin
+        let Some(registered) = workers.iter_mut().find(|w| w.id == worker.id) else {
+            return Vec::new();
+        };
+        let capacity = registered.max_jobs.saturating_sub(registered.current_jobs);
+
+        let mut batch = Vec::new();
+        let mut skipped = Vec::new();
+        while batch.len() < capacity {
+            let Some(entry) = queue.pop() else { break };
+            if registered.can_run(&entry.task) {
+                batch.push(entry.task);
             } else {
-                // Re-queue the task if no worker available
-                // Masukkan kembali tugas jika tidak ada worker tersedia
-                self.enqueue(task).await;
+                skipped.push(entry);
             }
         }
+        queue.extend(skipped);
 
-        None
+        registered.current_jobs += batch.len();
+        if !batch.is_empty() {
+            info!("Scheduled batch of {} tasks to worker {}", batch.len(), registered.name);
+        }
+        drop((queue, workers));
+
+        if !batch.is_empty() {
+            self.schedule_recurrences(&mut batch).await;
+            self.queue_changed().await;
+        }
+        batch
     }
 }
 
 impl Default for Scheduler {
     fn default() -> Self {
-        Self::new()
+        Self::new(SchedulerAlgorithm::default())
     }
 }
 
@@ -143,7 +1153,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_scheduler_enqueue_dequeue() {
-        let scheduler = Scheduler::new();
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
         let task = Task::new("echo hello".to_string());
         let task_id = task.id.clone();
 
@@ -154,4 +1164,628 @@ mod tests {
         assert!(dequeued.is_some());
         assert_eq!(dequeued.unwrap().id, task_id);
     }
+
+    #[tokio::test]
+    async fn test_high_priority_preempts_queued_low_priority() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let low = Task::new("echo low".to_string());
+        let high = Task::new("echo high".to_string());
+        let low_id = low.id.clone();
+        let high_id = high.id.clone();
+
+        scheduler.enqueue(low).await;
+        scheduler.enqueue_with_priority(high, 10).await;
+
+        assert_eq!(scheduler.dequeue().await.unwrap().id, high_id);
+        assert_eq!(scheduler.dequeue().await.unwrap().id, low_id);
+        assert!(scheduler.dequeue().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_equal_priority_keeps_fifo_order() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let task = Task::new(format!("echo {}", i));
+            ids.push(task.id.clone());
+            scheduler.enqueue_with_priority(task, 5).await;
+        }
+
+        for id in ids {
+            assert_eq!(scheduler.dequeue().await.unwrap().id, id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schedule_next_task_picks_highest_priority() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        scheduler
+            .register_worker(WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 1))
+            .await;
+
+        scheduler.enqueue(Task::new("echo low".to_string())).await;
+        let urgent = Task::new("echo urgent".to_string());
+        let urgent_id = urgent.id.clone();
+        scheduler.enqueue_with_priority(urgent, 200).await;
+
+        let (task, _worker) = scheduler.schedule_next_task().await.unwrap();
+        assert_eq!(task.id, urgent_id);
+        assert_eq!(scheduler.queue_size().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_task_held_until_due() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let now = chrono::Local::now().timestamp();
+        let mut later = Task::new("echo later".to_string());
+        later.scheduled_at = Some(now + 3600);
+        let mut due = Task::new("echo due".to_string());
+        due.scheduled_at = Some(now - 1);
+        let due_id = due.id.clone();
+
+        scheduler.enqueue(later).await;
+        scheduler.enqueue(due).await;
+        assert_eq!(scheduler.queue_size().await, 1);
+        assert_eq!(scheduler.scheduled_tasks().await.len(), 1);
+        assert_eq!(scheduler.release_due_tasks().await, 0);
+        assert_eq!(scheduler.dequeue().await.unwrap().id, due_id);
+
+        // Pretend the hour has passed
+        let key = *scheduler.pending_scheduled.read().await.keys().next().unwrap();
+        let task = scheduler.pending_scheduled.write().await.remove(&key).unwrap();
+        scheduler.pending_scheduled.write().await.insert((now, key.1), task);
+        assert_eq!(scheduler.release_due_tasks().await, 1);
+        assert_eq!(scheduler.queue_size().await, 1);
+        assert!(scheduler.scheduled_tasks().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recurring_task_rescheduled_after_dispatch() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        scheduler
+            .register_worker(WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 1))
+            .await;
+        let mut task = Task::new("echo tick".to_string());
+        task.cron_expression = Some("* * * * * *".to_string());
+        let task_id = task.id.clone();
+        scheduler.enqueue(task).await;
+
+        let (dispatched, _worker) = scheduler.schedule_next_task().await.unwrap();
+        assert_eq!(dispatched.id, task_id);
+        assert!(dispatched.cron_expression.is_none());
+
+        let scheduled = scheduler.scheduled_tasks().await;
+        assert_eq!(scheduled.len(), 1);
+        assert_ne!(scheduled[0].id, task_id);
+        assert_eq!(scheduled[0].cron_expression.as_deref(), Some("* * * * * *"));
+        assert!(scheduled[0].scheduled_at.unwrap() > chrono::Local::now().timestamp() - 1);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_requeue_resets_retries() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let mut task = Task::new("false".to_string());
+        task.max_retries = 2;
+        task.retry_count = 2;
+        let task_id = task.id.clone();
+
+        scheduler.move_to_dlq(task).await;
+        assert_eq!(scheduler.dead_letter_tasks().await.len(), 1);
+        assert_eq!(scheduler.queue_size().await, 0);
+        assert!(scheduler.requeue_dead_letter("unknown").await.is_none());
+
+        let requeued = scheduler.requeue_dead_letter(&task_id).await.unwrap();
+        assert_eq!(requeued.retry_count, 0);
+        assert!(scheduler.dead_letter_tasks().await.is_empty());
+        assert_eq!(scheduler.dequeue().await.unwrap().id, task_id);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_task_keeps_order_of_the_rest() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let tasks: Vec<Task> = (0..3).map(|i| Task::new(format!("echo {}", i))).collect();
+        let ids: Vec<String> = tasks.iter().map(|task| task.id.clone()).collect();
+        let mut blocked = Task::new("echo blocked".to_string());
+        blocked.dependencies = vec!["missing".to_string()];
+        let blocked_id = blocked.id.clone();
+        for task in tasks {
+            scheduler.enqueue(task).await;
+        }
+        scheduler.submit(blocked).await.unwrap();
+
+        assert_eq!(scheduler.remove_queued_task(&ids[1]).await.unwrap().id, ids[1]);
+        assert!(scheduler.remove_queued_task(&ids[1]).await.is_none());
+        assert!(scheduler.remove_queued_task(&blocked_id).await.is_some());
+        assert_eq!(scheduler.blocked_count().await, 0);
+
+        assert_eq!(scheduler.dequeue().await.unwrap().id, ids[0]);
+        assert_eq!(scheduler.dequeue().await.unwrap().id, ids[2]);
+        assert!(scheduler.dequeue().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_task_records_cancellation() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let dispatcher = DispatcherState::new("test".to_string(), 0);
+        let task = Task::new("echo queued".to_string());
+        let task_id = task.id.clone();
+        scheduler.enqueue(task).await;
+
+        assert!(scheduler.cancel_queued_task(&task_id, &dispatcher).await);
+        assert!(!scheduler.cancel_queued_task(&task_id, &dispatcher).await);
+        assert_eq!(dispatcher.get_result(&task_id).await.unwrap().status, crate::protocol::TaskStatus::Cancelled);
+        assert_eq!(scheduler.queue_size().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dependent_task_blocked_until_prerequisite_completes() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let first = Task::new("echo first".to_string());
+        let mut second = Task::new("echo second".to_string());
+        second.dependencies = vec![first.id.clone()];
+        let first_id = first.id.clone();
+        let second_id = second.id.clone();
+
+        scheduler.submit(first).await.unwrap();
+        scheduler.submit(second).await.unwrap();
+        assert_eq!(scheduler.queue_size().await, 1);
+        assert_eq!(scheduler.blocked_count().await, 1);
+
+        assert_eq!(scheduler.dequeue().await.unwrap().id, first_id);
+        assert!(scheduler.dequeue().await.is_none());
+
+        scheduler.notify_dependency_satisfied(&first_id).await;
+        assert_eq!(scheduler.blocked_count().await, 0);
+        assert_eq!(scheduler.dequeue().await.unwrap().id, second_id);
+    }
+
+    #[tokio::test]
+    async fn test_circular_dependency_rejected() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let mut a = Task::new("echo a".to_string());
+        let mut b = Task::new("echo b".to_string());
+        a.dependencies = vec![b.id.clone()];
+        b.dependencies = vec![a.id.clone()];
+
+        scheduler.submit(a).await.unwrap();
+        assert!(scheduler.submit(b).await.is_err());
+        assert_eq!(scheduler.blocked_count().await, 1);
+
+        let mut selfish = Task::new("echo self".to_string());
+        selfish.dependencies = vec![selfish.id.clone()];
+        assert!(scheduler.submit(selfish).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit_batch_is_all_or_nothing() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let first = Task::new("echo first".to_string());
+        let mut second = Task::new("echo second".to_string());
+        second.dependencies = vec![first.id.clone()];
+
+        // A cycle inside the batch rejects every task in it
+        let mut a = Task::new("echo a".to_string());
+        let mut b = Task::new("echo b".to_string());
+        a.dependencies = vec![b.id.clone()];
+        b.dependencies = vec![a.id.clone()];
+        assert!(scheduler.submit_batch(vec![first.clone(), a, b]).await.is_err());
+        assert_eq!(scheduler.queue_size().await, 0);
+        assert_eq!(scheduler.blocked_count().await, 0);
+
+        scheduler.submit_batch(vec![first, second]).await.unwrap();
+        assert_eq!(scheduler.queue_size().await, 1);
+        assert_eq!(scheduler.blocked_count().await, 1);
+    }
+
+    fn worker_with_load(name: &str, current_jobs: usize, max_jobs: usize) -> WorkerInfo {
+        let mut worker = WorkerInfo::new(name.to_string(), "127.0.0.1".to_string(), 7879, max_jobs);
+        worker.current_jobs = current_jobs;
+        worker
+    }
+
+    #[tokio::test]
+    async fn test_reannounced_worker_is_registered_once() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let mut worker = worker_with_load("w1", 0, 2);
+        scheduler.register_worker(worker.clone()).await;
+        worker.max_jobs = 4;
+        scheduler.register_worker(worker.clone()).await;
+
+        let workers = scheduler.get_workers().await;
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].max_jobs, 4);
+    }
+
+    #[tokio::test]
+    async fn test_remove_worker() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let worker = worker_with_load("w1", 0, 2);
+        let worker_id = worker.id.clone();
+        scheduler.register_worker(worker).await;
+        scheduler.update_worker_metrics(&worker_id, WorkerMetrics::default()).await;
+
+        assert_eq!(scheduler.remove_worker(&worker_id).await.unwrap().name, "w1");
+        assert!(scheduler.get_worker(&worker_id).await.is_none());
+        assert!(scheduler.get_worker_metrics(&worker_id).await.is_none());
+        assert!(scheduler.remove_worker(&worker_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fifo_picks_first_idle_worker() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        scheduler.register_worker(worker_with_load("full", 2, 2)).await;
+        scheduler.register_worker(worker_with_load("busy", 3, 4)).await;
+        scheduler.register_worker(worker_with_load("empty", 0, 4)).await;
+
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "busy");
+    }
+
+    #[tokio::test]
+    async fn test_queue_survives_restart() {
+        let store = crate::persistence::SqliteStore::new(":memory:").await.unwrap();
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let first = Task::new("echo first".to_string());
+        let mut urgent = Task::new("echo urgent".to_string());
+        urgent.priority = 5;
+        scheduler.enqueue(first.clone()).await;
+        scheduler.enqueue(urgent.clone()).await;
+        scheduler.persist_queue(&store).await.unwrap();
+
+        let restarted = Scheduler::new(SchedulerAlgorithm::Fifo);
+        assert_eq!(restarted.restore_queue(&store).await.unwrap(), 2);
+        assert_eq!(restarted.dequeue().await.unwrap().id, urgent.id);
+        assert_eq!(restarted.dequeue().await.unwrap().id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rebuilds_pending_tasks_from_definition() {
+        use crate::persistence::StoredTask;
+
+        let store = crate::persistence::SqliteStore::new(":memory:").await.unwrap();
+        let first = Task::new("echo first".to_string());
+        let mut done = StoredTask::pending(&first);
+        done.status = "Completed".to_string();
+        let mut ready = Task::new("echo ready".to_string());
+        ready.priority = 5;
+        ready.dependencies = vec![first.id.clone()];
+        ready.env.insert("MODE".to_string(), "strict".to_string());
+        let mut waiting = Task::new("echo waiting".to_string());
+        waiting.dependencies = vec![ready.id.clone()];
+        store
+            .bulk_insert_tasks(&[done, StoredTask::pending(&ready), StoredTask::pending(&waiting)])
+            .await
+            .unwrap();
+
+        let restarted = Scheduler::new(SchedulerAlgorithm::Fifo);
+        assert_eq!(restarted.restore_queue(&store).await.unwrap(), 2);
+        let restored = restarted.dequeue().await.unwrap();
+        assert_eq!(restored.id, ready.id);
+        assert_eq!(restored.priority, 5);
+        assert_eq!(restored.dependencies, vec![first.id.clone()]);
+        assert_eq!(restored.env["MODE"], "strict");
+
+        // `waiting` stays blocked until the task it depends on completes
+        assert!(restarted.dequeue().await.is_none());
+        restarted.notify_dependency_satisfied(&ready.id).await;
+        let released = restarted.dequeue().await.unwrap();
+        assert_eq!(released.id, waiting.id);
+        assert_eq!(released.dependencies, vec![ready.id]);
+    }
+
+    #[tokio::test]
+    async fn test_attached_store_tracks_queue_changes() {
+        let store: Arc<dyn StoreBackend + Send + Sync> =
+            Arc::new(crate::persistence::SqliteStore::new(":memory:").await.unwrap());
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo).with_store(store.clone());
+        scheduler.enqueue(Task::new("echo a".to_string())).await;
+        scheduler.enqueue(Task::new("echo b".to_string())).await;
+        assert_eq!(store.load_queue().await.unwrap().len(), 2);
+
+        scheduler.dequeue().await;
+        assert_eq!(store.load_queue().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebalance_hint_wakes_scheduling_loop() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        assert!(!scheduler.rebalance_hint().await);
+
+        scheduler.enqueue(Task::new("echo queued".to_string())).await;
+        assert!(scheduler.rebalance_hint().await);
+        let woken = tokio::time::timeout(std::time::Duration::from_millis(100), scheduler.rebalance_requested()).await;
+        assert!(woken.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_draining_worker_gets_no_tasks() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let worker = worker_with_load("w1", 0, 2);
+        let worker_id = worker.id.clone();
+        scheduler.register_worker(worker).await;
+
+        scheduler.set_worker_draining(&worker_id, true).await;
+        assert!(scheduler.get_idle_worker().await.is_none());
+
+        scheduler.set_worker_draining(&worker_id, false).await;
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "w1");
+    }
+
+    #[test]
+    fn test_worker_health_score() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let fresh = worker_with_load("fresh", 0, 4);
+        assert_eq!(scheduler.worker_health_score(&fresh), 1.0);
+
+        let mut worn = worker_with_load("worn", 2, 4);
+        worn.last_rtt_ms = Some(1);
+        worn.tasks_completed = 3;
+        worn.tasks_failed = 1;
+        // (0.5 + 0.5 + 0.75) / 3
+        assert!((scheduler.worker_health_score(&worn) - 1.75 / 3.0).abs() < 1e-9);
+
+        let reliability_only = Scheduler::new(SchedulerAlgorithm::Fifo).with_scheduling_weights(SchedulingWeights {
+            cpu: 0.0,
+            latency: 0.0,
+            reliability: 1.0,
+        });
+        assert_eq!(reliability_only.worker_health_score(&worn), 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_health_score_breaks_load_ties() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::WeightedLeastConnections);
+        let flaky = worker_with_load("flaky", 1, 2);
+        let steady = worker_with_load("steady", 1, 2);
+        scheduler.register_worker(flaky.clone()).await;
+        scheduler.register_worker(steady).await;
+        scheduler.record_task_outcome(&flaky.id, false).await;
+
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "steady");
+
+        // Counts survive the worker announcing itself again
+        scheduler.register_worker(flaky.clone()).await;
+        assert_eq!(scheduler.get_worker(&flaky.id).await.unwrap().tasks_failed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_worker_gets_no_tasks() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let worker = worker_with_load("w1", 0, 2);
+        let worker_id = worker.id.clone();
+        scheduler.register_worker(worker).await;
+        let task = Task::new("echo hi".to_string());
+
+        assert!(scheduler.set_worker_maintenance(&worker_id, true).await.unwrap().maintenance);
+        assert!(scheduler.get_idle_worker_for_task(&task).await.is_none());
+        assert_eq!(scheduler.get_workers().await.len(), 1);
+
+        scheduler.set_worker_maintenance(&worker_id, false).await;
+        assert_eq!(scheduler.get_idle_worker_for_task(&task).await.unwrap().name, "w1");
+        assert!(scheduler.set_worker_maintenance("missing", true).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_weighted_least_connections_picks_lowest_ratio() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::WeightedLeastConnections);
+        let mut big = worker_with_load("big", 2, 8);
+        scheduler.register_worker(worker_with_load("half", 2, 4)).await;
+        scheduler.register_worker(big.clone()).await;
+        scheduler.register_worker(worker_with_load("full", 1, 1)).await;
+
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "big");
+
+        big.current_jobs = 7;
+        scheduler.update_worker(&big.id.clone(), big).await;
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "half");
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_rotates_and_skips_full_workers() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::RoundRobin);
+        scheduler.register_worker(worker_with_load("a", 0, 2)).await;
+        scheduler.register_worker(worker_with_load("b", 2, 2)).await;
+        scheduler.register_worker(worker_with_load("c", 0, 2)).await;
+
+        let mut picks = Vec::new();
+        for _ in 0..4 {
+            picks.push(scheduler.get_idle_worker().await.unwrap().name);
+        }
+        assert_eq!(picks, vec!["a", "c", "a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_task_waits_for_worker_with_required_tags() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        scheduler.register_worker(worker_with_load("cpu-only", 0, 4)).await;
+
+        let mut gpu_task = Task::new("nvidia-smi".to_string());
+        gpu_task.required_tags = vec!["gpu".to_string()];
+        let gpu_task_id = gpu_task.id.clone();
+        scheduler.enqueue_with_priority(gpu_task, 10).await;
+        let cpu_task = Task::new("echo cpu".to_string());
+        let cpu_task_id = cpu_task.id.clone();
+        scheduler.enqueue(cpu_task).await;
+
+        // GPU task is skipped, CPU task behind it still runs
+        let (task, worker) = scheduler.schedule_next_task().await.unwrap();
+        assert_eq!(task.id, cpu_task_id);
+        assert_eq!(worker.name, "cpu-only");
+        assert!(scheduler.schedule_next_task().await.is_none());
+        assert_eq!(scheduler.queue_size().await, 1);
+
+        let mut gpu_worker = worker_with_load("gpu-box", 0, 1);
+        gpu_worker.tags = vec!["gpu".to_string(), "cuda".to_string()];
+        scheduler.register_worker(gpu_worker).await;
+
+        let (task, worker) = scheduler.schedule_next_task().await.unwrap();
+        assert_eq!(task.id, gpu_task_id);
+        assert_eq!(worker.name, "gpu-box");
+    }
+
+    #[tokio::test]
+    async fn test_aging_lets_low_priority_task_overtake() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo).with_starvation_threshold_secs(60);
+        let now = chrono::Local::now().timestamp();
+        let mut starving = Task::new("echo starving".to_string());
+        starving.queued_at = Some(now);
+        let starving_id = starving.id.clone();
+        scheduler.enqueue(starving).await;
+
+        // A priority-5 task arrives every minute and runs first, until the
+        // priority-0 task has waited five thresholds
+        for minute in 0..=5 {
+            let at = now + minute * 60;
+            let mut urgent = Task::new("echo urgent".to_string());
+            urgent.priority = 5;
+            urgent.queued_at = Some(at);
+            let urgent_id = urgent.id.clone();
+            scheduler.enqueue(urgent).await;
+            scheduler.age_queue_at(at).await;
+
+            let next = scheduler.dequeue().await.unwrap();
+            match minute {
+                5 => assert_eq!(next.id, starving_id),
+                _ => assert_eq!(next.id, urgent_id),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paused_scheduler_keeps_tasks_queued() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        scheduler.register_worker(worker_with_load("w1", 0, 2)).await;
+        scheduler.enqueue(Task::new("echo held".to_string())).await;
+
+        scheduler.pause();
+        assert!(scheduler.is_paused());
+        assert!(scheduler.schedule_next_task().await.is_none());
+        assert_eq!(scheduler.queue_size().await, 1);
+
+        // Resuming wakes the scheduling loop straight away
+        scheduler.resume();
+        let woken = tokio::time::timeout(std::time::Duration::from_millis(100), scheduler.rebalance_requested()).await;
+        assert!(woken.is_ok());
+        assert!(scheduler.schedule_next_task().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_batch_fills_free_slots() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let worker = worker_with_load("w", 1, 4);
+        scheduler.register_worker(worker.clone()).await;
+        for i in 0..5 {
+            scheduler.enqueue(Task::new(format!("echo {}", i))).await;
+        }
+
+        let batch = scheduler.schedule_batch(&worker).await;
+        assert_eq!(batch.len(), 3);
+        assert_eq!(scheduler.queue_size().await, 2);
+        assert_eq!(scheduler.get_workers().await[0].current_jobs, 4);
+
+        // Worker is now full
+        assert!(scheduler.schedule_batch(&worker).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_batch_skips_tasks_worker_cannot_run() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::Fifo);
+        let worker = worker_with_load("cpu-only", 0, 4);
+        scheduler.register_worker(worker.clone()).await;
+
+        let mut gpu_task = Task::new("nvidia-smi".to_string());
+        gpu_task.required_tags = vec!["gpu".to_string()];
+        scheduler.enqueue_with_priority(gpu_task, 10).await;
+        scheduler.enqueue(Task::new("echo a".to_string())).await;
+        scheduler.enqueue(Task::new("echo b".to_string())).await;
+
+        let batch = scheduler.schedule_batch(&worker).await;
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|t| t.required_tags.is_empty()));
+        assert_eq!(scheduler.queue_size().await, 1);
+
+        let unknown = worker_with_load("unregistered", 0, 4);
+        assert!(scheduler.schedule_batch(&unknown).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cpu_percent_breaks_load_ties() {
+        let scheduler = Scheduler::new(SchedulerAlgorithm::WeightedLeastConnections);
+        let hot = worker_with_load("hot", 1, 4);
+        let cool = worker_with_load("cool", 1, 4);
+        scheduler.register_worker(hot.clone()).await;
+        scheduler.register_worker(cool.clone()).await;
+
+        let report = |cpu_percent| WorkerMetrics {
+            cpu_percent,
+            memory_mb: 512,
+            load_avg_1m: 1.0,
+        };
+        scheduler.update_worker_metrics(&hot.id, report(95.0)).await;
+        scheduler.update_worker_metrics(&cool.id, report(10.0)).await;
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "cool");
+        assert_eq!(scheduler.get_worker_metrics(&hot.id).await.unwrap().cpu_percent, 95.0);
+
+        // Slot usage still takes precedence over CPU
+        let mut busy_cool = cool.clone();
+        busy_cool.current_jobs = 3;
+        scheduler.update_worker(&cool.id, busy_cool).await;
+        assert_eq!(scheduler.get_idle_worker().await.unwrap().name, "hot");
+    }
+
+    #[tokio::test]
+    async fn test_record_heartbeat_tracks_rtt_and_slow_streak() {
+        let scheduler = Scheduler::default().with_slow_heartbeat_ms(100);
+        let worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        let worker_id = worker.id.clone();
+        scheduler.register_worker(worker).await;
+
+        scheduler.record_heartbeat(&worker_id, 40).await;
+        assert_eq!(scheduler.get_workers().await[0].last_rtt_ms, Some(40));
+        assert!(scheduler.slow_heartbeats.read().await.is_empty());
+
+        for _ in 0..SLOW_HEARTBEAT_STREAK {
+            scheduler.record_heartbeat(&worker_id, 250).await;
+        }
+        assert_eq!(scheduler.slow_heartbeats.read().await.get(&worker_id), Some(&SLOW_HEARTBEAT_STREAK));
+
+        // One timely heartbeat ends the streak
+        scheduler.record_heartbeat(&worker_id, 20).await;
+        assert!(scheduler.slow_heartbeats.read().await.is_empty());
+        assert_eq!(scheduler.get_workers().await[0].last_rtt_ms, Some(20));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_keep_workers_online() {
+        let scheduler = Scheduler::default();
+        let mut reporting = WorkerInfo::new("reporting".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        let mut silent = WorkerInfo::new("silent".to_string(), "127.0.0.1".to_string(), 7880, 2);
+        let stale = chrono::Local::now().timestamp() - 60;
+        reporting.last_heartbeat = stale;
+        silent.last_heartbeat = stale;
+        let reporting_id = reporting.id.clone();
+        scheduler.register_worker(reporting).await;
+        scheduler.register_worker(silent).await;
+
+        scheduler.record_heartbeat(&reporting_id, 5).await;
+        let removed = scheduler.cleanup_offline_workers(30).await;
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "silent");
+        assert_eq!(scheduler.get_workers().await[0].id, reporting_id);
+    }
+
+    #[tokio::test]
+    async fn test_reregistration_replaces_entry() {
+        let scheduler = Scheduler::default();
+        let worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        scheduler.register_worker(worker.clone()).await;
+
+        // A restarted worker keeps its ID but may come back on another port
+        let mut restarted = worker.clone();
+        restarted.port = 7890;
+        scheduler.register_worker(restarted).await;
+
+        let workers = scheduler.get_workers().await;
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].port, 7890);
+    }
 }
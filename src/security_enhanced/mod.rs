@@ -1,13 +1,32 @@
 use anyhow::Result;
 use aes_gcm::{Aes256Gcm, Key, Nonce, aead::Aead};
+use hkdf::Hkdf;
+use parking_lot::RwLock;
 use rand::Rng;
 use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+/// HKDF info string for per-connection session keys
+const SESSION_KEY_INFO: &[u8] = b"octaskly session key v1";
+
+/// How long a seen nonce is remembered when no TTL is configured
+pub const DEFAULT_NONCE_TTL: Duration = Duration::from_secs(300);
+
+/// AES-GCM nonces already accepted, with when they were first seen
+type NonceCache = RwLock<HashMap<[u8; 12], Instant>>;
 
 /// Enhanced security module with encryption and key management
+#[derive(Clone)]
 pub struct SecurityManager {
     preshared_key: String,
     whitelist: Vec<String>,
     cipher: Option<Aes256Gcm>,
+    nonce_cache: Arc<NonceCache>,
+    nonce_ttl: Duration,
 }
 
 impl SecurityManager {
@@ -24,9 +43,17 @@ impl SecurityManager {
             preshared_key,
             whitelist: Vec::new(),
             cipher: Some(cipher),
+            nonce_cache: Arc::new(RwLock::new(HashMap::new())),
+            nonce_ttl: DEFAULT_NONCE_TTL,
         }
     }
 
+    /// Remember seen nonces for `ttl` instead of `DEFAULT_NONCE_TTL`
+    pub fn with_nonce_ttl(mut self, ttl: Duration) -> Self {
+        self.nonce_ttl = ttl;
+        self
+    }
+
     /// Create key from password
     pub fn derive_key(password: &str) -> [u8; 32] {
         let mut hasher = Sha256::new();
@@ -38,6 +65,37 @@ impl SecurityManager {
         key
     }
 
+    /// Generate an ephemeral X25519 key pair for one connection's handshake
+    pub fn ephemeral_key() -> (EphemeralSecret, [u8; 32]) {
+        let secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+        let public_key = PublicKey::from(&secret).to_bytes();
+        (secret, public_key)
+    }
+
+    /// Manager for one connection, encrypting with a key agreed through ECDH
+    ///
+    /// The shared secret is stretched with HKDF-SHA256, salted with the pre-shared key,
+    /// so both ends must also share the PSK to talk. The session key lives only as long
+    /// as the returned manager.
+    pub fn session(&self, secret: EphemeralSecret, peer_public_key: [u8; 32]) -> Result<Self> {
+        let shared = secret.diffie_hellman(&PublicKey::from(peer_public_key));
+        if !shared.was_contributory() {
+            return Err(anyhow::anyhow!("Peer sent a low-order public key"));
+        }
+
+        let salt = Self::derive_key(&self.preshared_key);
+        let mut session_key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+            .expand(SESSION_KEY_INFO, &mut session_key)
+            .map_err(|e| anyhow::anyhow!("Session key derivation failed: {}", e))?;
+
+        let key = Key::<Aes256Gcm>::from_slice(&session_key);
+        Ok(Self {
+            cipher: Some(<Aes256Gcm as aes_gcm::KeyInit>::new(key)),
+            ..self.clone()
+        })
+    }
+
     /// Encrypt data with AES-256-GCM
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
         if let Some(cipher) = &self.cipher {
@@ -74,6 +132,52 @@ impl SecurityManager {
         }
     }
 
+    /// Reject a message whose nonce was already accepted, otherwise remember it
+    ///
+    /// Call this after `decrypt` succeeds on `encrypted_data`. Sessions derived from
+    /// this manager share its cache.
+    pub fn verify_no_replay(&self, encrypted_data: &[u8]) -> Result<()> {
+        let nonce: [u8; 12] = encrypted_data
+            .get(..12)
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid encrypted data"))?;
+
+        let mut cache = self.nonce_cache.write();
+        if cache.contains_key(&nonce) {
+            return Err(anyhow::anyhow!("replay detected"));
+        }
+        cache.insert(nonce, Instant::now());
+        Ok(())
+    }
+
+    /// Nonces currently remembered
+    pub fn tracked_nonces(&self) -> usize {
+        self.nonce_cache.read().len()
+    }
+
+    /// Periodically forget nonces older than the nonce TTL
+    ///
+    /// The task ends once every clone of this manager is dropped.
+    pub fn start_nonce_eviction(&self) -> JoinHandle<()> {
+        let cache: Weak<NonceCache> = Arc::downgrade(&self.nonce_cache);
+        let ttl = self.nonce_ttl;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ttl / 10);
+            loop {
+                interval.tick().await;
+                match cache.upgrade() {
+                    Some(cache) => Self::evict_nonces(&cache, ttl),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    fn evict_nonces(cache: &NonceCache, ttl: Duration) {
+        cache.write().retain(|_, seen_at| seen_at.elapsed() < ttl);
+    }
+
     /// Verify pre-shared key
     pub fn verify_key(&self, key: &str) -> bool {
         self.preshared_key == key
@@ -146,6 +250,69 @@ mod tests {
         assert_eq!(plaintext, decrypted.as_slice());
     }
 
+    /// Run both halves of a handshake in memory
+    fn handshake(alice: &SecurityManager, bob: &SecurityManager) -> (SecurityManager, SecurityManager) {
+        let (alice_secret, alice_public) = SecurityManager::ephemeral_key();
+        let (bob_secret, bob_public) = SecurityManager::ephemeral_key();
+        (
+            alice.session(alice_secret, bob_public).unwrap(),
+            bob.session(bob_secret, alice_public).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_session_keys_differ_per_connection() {
+        let manager = SecurityManager::new("test-key".to_string());
+        let (client_a, server_a) = handshake(&manager, &manager);
+        let (client_b, server_b) = handshake(&manager, &manager);
+
+        let encrypted = client_a.encrypt(b"task payload").unwrap();
+        assert_eq!(server_a.decrypt(&encrypted).unwrap(), b"task payload");
+        assert!(server_b.decrypt(&encrypted).is_err());
+        assert!(manager.decrypt(&encrypted).is_err());
+
+        let encrypted = server_b.encrypt(b"reply").unwrap();
+        assert_eq!(client_b.decrypt(&encrypted).unwrap(), b"reply");
+        assert!(client_a.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_session_requires_same_preshared_key() {
+        let (client, server) = handshake(
+            &SecurityManager::new("one".to_string()),
+            &SecurityManager::new("two".to_string()),
+        );
+        let encrypted = client.encrypt(b"task payload").unwrap();
+        assert!(server.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_replayed_message_rejected() {
+        let manager = SecurityManager::new("test-key".to_string());
+        let encrypted = manager.encrypt(b"assign task-1").unwrap();
+
+        manager.decrypt(&encrypted).unwrap();
+        manager.verify_no_replay(&encrypted).unwrap();
+
+        manager.decrypt(&encrypted).unwrap();
+        let err = manager.verify_no_replay(&encrypted).unwrap_err();
+        assert_eq!(err.to_string(), "replay detected");
+
+        let fresh = manager.encrypt(b"assign task-1").unwrap();
+        assert!(manager.verify_no_replay(&fresh).is_ok());
+    }
+
+    #[test]
+    fn test_evict_nonces_drops_expired() {
+        let cache: NonceCache = RwLock::new(HashMap::new());
+        cache.write().insert([1; 12], Instant::now() - Duration::from_secs(60));
+        cache.write().insert([2; 12], Instant::now());
+
+        SecurityManager::evict_nonces(&cache, Duration::from_secs(30));
+        assert_eq!(cache.read().len(), 1);
+        assert!(cache.read().contains_key(&[2; 12]));
+    }
+
     #[test]
     fn test_key_verification() {
         let manager = SecurityManager::new("secret".to_string());
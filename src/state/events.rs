@@ -0,0 +1,190 @@
+use crate::protocol::{TaskResult, TaskStatus};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+/// Events buffered per task before a slow subscriber starts lagging
+const TASK_EVENT_CAPACITY: usize = 256;
+
+/// Dispatcher events buffered before a slow subscriber starts lagging
+pub const DISPATCHER_EVENT_CAPACITY: usize = 256;
+
+/// Live update for one task, serialized as `{"type": ..., "data": ...}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum TaskEvent {
+    /// Fraction done, from 0.0 to 1.0
+    Progress(f32),
+    Stdout(String),
+    Stderr(String),
+    /// Final event; the channel is closed after it
+    Completed(TaskResult),
+}
+
+impl TaskEvent {
+    pub fn is_final(&self) -> bool {
+        matches!(self, TaskEvent::Completed(_))
+    }
+}
+
+/// Cluster-wide change pushed to WebSocket clients, serialized with a `type` tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DispatcherEvent {
+    TaskStatus {
+        task_id: String,
+        status: TaskStatus,
+        worker_id: Option<String>,
+    },
+    WorkerConnected {
+        worker_id: String,
+        name: String,
+    },
+    WorkerDisconnected {
+        worker_id: String,
+    },
+    QueueDepth {
+        depth: usize,
+    },
+    SchedulerPaused {
+        paused: bool,
+    },
+}
+
+impl DispatcherEvent {
+    /// Worker the event is about, if any
+    pub fn worker_id(&self) -> Option<&str> {
+        match self {
+            DispatcherEvent::TaskStatus { worker_id, .. } => worker_id.as_deref(),
+            DispatcherEvent::WorkerConnected { worker_id, .. } | DispatcherEvent::WorkerDisconnected { worker_id } => {
+                Some(worker_id)
+            }
+            DispatcherEvent::QueueDepth { .. } | DispatcherEvent::SchedulerPaused { .. } => None,
+        }
+    }
+}
+
+/// Predicate a subscriber sets to receive only some dispatcher events
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct EventFilter {
+    /// Only events about this worker
+    pub worker_id: Option<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &DispatcherEvent) -> bool {
+        match &self.worker_id {
+            Some(worker_id) => event.worker_id() == Some(worker_id.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Per-task broadcast channels, created when the first subscriber arrives
+#[derive(Default)]
+pub struct TaskEventHub {
+    channels: Mutex<HashMap<String, broadcast::Sender<TaskEvent>>>,
+}
+
+impl TaskEventHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive every event published for `task_id` from now on
+    pub fn subscribe(&self, task_id: &str) -> broadcast::Receiver<TaskEvent> {
+        self.channels
+            .lock()
+            .entry(task_id.to_string())
+            .or_insert_with(|| broadcast::channel(TASK_EVENT_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Deliver an event to the task's subscribers, if it has any
+    ///
+    /// The channel is dropped after the final event or once nobody listens.
+    pub fn publish(&self, task_id: &str, event: TaskEvent) {
+        let mut channels = self.channels.lock();
+        let Some(sender) = channels.get(task_id) else {
+            return;
+        };
+        let is_final = event.is_final();
+        if sender.send(event).is_err() || is_final {
+            channels.remove(task_id);
+        }
+    }
+
+    /// Drop the task's channel if no subscriber is left
+    pub fn release(&self, task_id: &str) {
+        let mut channels = self.channels.lock();
+        if channels.get(task_id).is_some_and(|sender| sender.receiver_count() == 0) {
+            channels.remove(task_id);
+        }
+    }
+
+    /// Tasks with at least one open channel
+    pub fn channel_count(&self) -> usize {
+        self.channels.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TaskStatus;
+
+    #[tokio::test]
+    async fn test_publish_until_completed() {
+        let hub = TaskEventHub::new();
+
+        // Nothing is buffered for tasks nobody watches
+        hub.publish("task-1", TaskEvent::Stdout("early".to_string()));
+        assert_eq!(hub.channel_count(), 0);
+
+        let mut events = hub.subscribe("task-1");
+        hub.publish("task-1", TaskEvent::Stdout("hello".to_string()));
+        hub.publish(
+            "task-1",
+            TaskEvent::Completed(TaskResult {
+                task_id: "task-1".to_string(),
+                worker_id: "worker-1".to_string(),
+                status: TaskStatus::Completed,
+                stdout: "hello\n".to_string(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                duration_ms: 5,
+                completed_at: 0,
+                resource_usage: None,
+            }),
+        );
+
+        assert!(matches!(events.recv().await.unwrap(), TaskEvent::Stdout(line) if line == "hello"));
+        assert!(events.recv().await.unwrap().is_final());
+        assert!(events.recv().await.is_err());
+        assert_eq!(hub.channel_count(), 0);
+    }
+
+    #[test]
+    fn test_filter_by_worker() {
+        let filter = EventFilter {
+            worker_id: Some("worker-1".to_string()),
+        };
+        assert!(filter.matches(&DispatcherEvent::WorkerDisconnected {
+            worker_id: "worker-1".to_string()
+        }));
+        assert!(!filter.matches(&DispatcherEvent::TaskStatus {
+            task_id: "task-1".to_string(),
+            status: TaskStatus::Running,
+            worker_id: Some("worker-2".to_string()),
+        }));
+        assert!(!filter.matches(&DispatcherEvent::QueueDepth { depth: 3 }));
+        assert!(EventFilter::default().matches(&DispatcherEvent::QueueDepth { depth: 3 }));
+    }
+
+    #[test]
+    fn test_event_json_shape() {
+        let json = serde_json::to_value(TaskEvent::Stderr("oops".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({"type": "stderr", "data": "oops"}));
+    }
+}
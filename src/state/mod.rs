@@ -1,8 +1,20 @@
-use crate::protocol::{Task, TaskResult};
+pub mod events;
+
+use crate::metrics::Metrics;
+use crate::persistence::{AuditEvent, StoreBackend, StoredTask};
+use crate::protocol::{FileSpec, OutputLine, Task, TaskResult, TaskStatus, WorkerInfo, WorkerTaskStats};
+use crate::scheduler::Scheduler;
+use crate::webhook::WebhookNotifier;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::debug;
+use std::time::Instant;
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
+use events::{DispatcherEvent, TaskEvent, TaskEventHub, DISPATCHER_EVENT_CAPACITY};
+use tracing::{debug, warn};
 
 // Dispatcher state container for managing tasks and workers
 // Kontainer status dispatcher untuk mengelola tugas dan worker
@@ -12,6 +24,74 @@ pub struct DispatcherState {
     pub port: u16,
     pub task_results: Arc<RwLock<HashMap<String, TaskResult>>>,
     pub completed_tasks: Arc<RwLock<Vec<Task>>>,
+    // Task definitions currently assigned to workers, keyed by task ID
+    // Definisi tugas yang sedang ditugaskan ke worker, dikunci oleh ID tugas
+    pub dispatched_tasks: Arc<RwLock<HashMap<String, Task>>>,
+    // Worker each dispatched task was sent to, keyed by task ID
+    // Worker tujuan setiap tugas yang dikirim, dikunci oleh ID tugas
+    dispatched_to: Arc<RwLock<HashMap<String, String>>>,
+    scheduler: Option<Arc<Scheduler>>,
+    store: Option<Arc<dyn StoreBackend + Send + Sync>>,
+    // Every stored result, for callers waiting on a task to finish
+    // Setiap hasil yang disimpan, untuk pemanggil yang menunggu tugas selesai
+    result_events: broadcast::Sender<TaskResult>,
+    // Output lines streamed by workers, keyed by task ID
+    // Baris output yang dialirkan worker, dikunci oleh ID tugas
+    task_output: Arc<RwLock<HashMap<String, Vec<OutputLine>>>>,
+    // ID of the task whenever a new output line is buffered
+    // ID tugas setiap kali baris output baru disangga
+    output_events: broadcast::Sender<String>,
+    // Counters exported on /metrics and shown in the TUI
+    // Penghitung yang diekspor di /metrics dan ditampilkan di TUI
+    metrics: Arc<Metrics>,
+    // Live per-task events for API subscribers
+    // Event per tugas secara langsung untuk pelanggan API
+    task_events: Arc<TaskEventHub>,
+    // Task, worker and queue changes for WebSocket subscribers
+    // Perubahan tugas, worker, dan antrian untuk pelanggan WebSocket
+    dispatcher_events: broadcast::Sender<DispatcherEvent>,
+    // Posts results to task callback URLs, if enabled
+    // Mengirim hasil ke URL callback tugas, jika diaktifkan
+    webhooks: Option<WebhookNotifier>,
+    // Latest progress fraction reported for each task, keyed by task ID
+    // Pecahan progres terbaru yang dilaporkan untuk setiap tugas, dikunci oleh ID tugas
+    task_progress: Arc<RwLock<HashMap<String, f32>>>,
+    // SHA-256 of the pre-shared key announcing workers must match, if one is required
+    // SHA-256 dari kunci pra-bagi yang harus dicocokkan worker yang mengumumkan diri, jika diwajibkan
+    psk_hash: Option<String>,
+    // Directory task input files are read from, with returned outputs kept under `outputs/`
+    // Direktori tempat file input tugas dibaca, dengan output yang dikembalikan di bawah `outputs/`
+    files_dir: Option<PathBuf>,
+}
+
+// Directory under the dispatcher workdir that task files are staged from; the workdir itself
+// also holds the CA key and the database, so it is never exposed to tasks directly
+// Direktori di bawah workdir dispatcher tempat file tugas diambil; workdir sendiri juga
+// menyimpan kunci CA dan basis data, sehingga tidak pernah diekspos langsung ke tugas
+pub const FILES_DIR: &str = "files";
+
+// Directory under the files directory that returned output files go to, one subdirectory per task
+// Direktori di bawah direktori file tempat file output disimpan, satu subdirektori per tugas
+pub const OUTPUTS_DIR: &str = "outputs";
+
+// Total bytes of output files stored for one task; larger sets are dropped whole
+// Total byte file output yang disimpan untuk satu tugas; kumpulan yang lebih besar dibuang seluruhnya
+pub const MAX_OUTPUT_FILES_BYTES: usize = 64 * 1024 * 1024;
+
+// Buffered results per subscriber before slow ones start lagging
+// Hasil yang disangga per pelanggan sebelum yang lambat mulai tertinggal
+const RESULT_EVENT_CAPACITY: usize = 256;
+
+// Output lines kept per task for watchers; later lines are only in the final result
+// Baris output yang disimpan per tugas untuk pengamat; baris berikutnya hanya ada di hasil akhir
+const MAX_BUFFERED_OUTPUT_LINES: usize = 10_000;
+
+// What a watcher sees next: new output lines, or the finished result
+// Apa yang dilihat pengamat berikutnya: baris output baru, atau hasil akhir
+#[derive(Debug, Clone)]
+pub enum OutputUpdate {
+    Lines(Vec<OutputLine>),
+    Finished(TaskResult),
 }
 
 impl DispatcherState {
@@ -24,17 +104,298 @@ impl DispatcherState {
             port,
             task_results: Arc::new(RwLock::new(HashMap::new())),
             completed_tasks: Arc::new(RwLock::new(Vec::new())),
+            dispatched_tasks: Arc::new(RwLock::new(HashMap::new())),
+            dispatched_to: Arc::new(RwLock::new(HashMap::new())),
+            scheduler: None,
+            store: None,
+            result_events: broadcast::channel(RESULT_EVENT_CAPACITY).0,
+            task_output: Arc::new(RwLock::new(HashMap::new())),
+            output_events: broadcast::channel(RESULT_EVENT_CAPACITY).0,
+            metrics: Arc::new(Metrics::new()),
+            task_events: Arc::new(TaskEventHub::new()),
+            dispatcher_events: broadcast::channel(DISPATCHER_EVENT_CAPACITY).0,
+            webhooks: None,
+            task_progress: Arc::new(RwLock::new(HashMap::new())),
+            psk_hash: None,
+            files_dir: None,
+        }
+    }
+
+    // Shared metrics for this dispatcher
+    // Metrik bersama untuk dispatcher ini
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    // Per-task event channels fed by worker output and results
+    // Kanal event per tugas yang diisi oleh output dan hasil worker
+    pub fn task_events(&self) -> &Arc<TaskEventHub> {
+        &self.task_events
+    }
+
+    // Push a dispatcher event to current subscribers (dropped if there are none)
+    // Kirim event dispatcher ke pelanggan saat ini (dibuang jika tidak ada)
+    pub fn publish_event(&self, event: DispatcherEvent) {
+        let _ = self.dispatcher_events.send(event);
+    }
+
+    // Receive dispatcher events published from now on
+    // Terima event dispatcher yang diterbitkan mulai sekarang
+    pub fn subscribe_events(&self) -> broadcast::Receiver<DispatcherEvent> {
+        self.dispatcher_events.subscribe()
+    }
+
+    // Attach scheduler so completed results release dependent tasks
+    // Lampirkan penjadwal agar hasil selesai melepaskan tugas dependen
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    // Attach persistent store used for the audit log
+    // Lampirkan penyimpanan persisten yang digunakan untuk log audit
+    pub fn with_store(mut self, store: Arc<dyn StoreBackend + Send + Sync>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    // POST finished results to the callback URL of tasks that set one
+    // Kirim POST hasil yang selesai ke URL callback tugas yang menetapkannya
+    pub fn with_webhooks(mut self, webhooks: WebhookNotifier) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    // Only accept worker announcements carrying the hash of this pre-shared key
+    // Hanya terima pengumuman worker yang membawa hash dari kunci pra-bagi ini
+    pub fn with_psk(mut self, psk: &str) -> Self {
+        self.psk_hash = Some(crate::util::sha256_hex(psk));
+        self
+    }
+
+    // Read task input files from `dir` and keep returned output files under it
+    // Baca file input tugas dari `dir` dan simpan file output yang dikembalikan di bawahnya
+    pub fn with_files_dir(mut self, dir: PathBuf) -> Self {
+        self.files_dir = Some(dir);
+        self
+    }
+
+    // Embed the content of the task's input files and script before it is sent to a worker
+    // Sematkan isi file input dan skrip tugas sebelum dikirim ke worker
+    // A file that cannot be read is left empty, and the worker then fails the task.
+    // File yang tidak bisa dibaca dibiarkan kosong, lalu worker menggagalkan tugas.
+    pub async fn stage_input_files(&self, task: &mut Task) {
+        let Some(dir) = &self.files_dir else {
+            return;
+        };
+        if let Err(e) = crate::util::files::read_contents(dir, &mut task.input_files).await {
+            warn!("Failed to stage input files of task {}: {:#}", task.id, e);
+        }
+        if let (Some(path), None) = (&task.script_path, &task.script_content) {
+            match crate::util::files::read_script(dir, path).await {
+                Ok(content) => task.script_content = Some(content),
+                Err(e) => warn!("Failed to stage script of task {}: {:#}", task.id, e),
+            }
+        }
+    }
+
+    // Write output files returned by a worker to `outputs/<task_id>/`
+    // Tulis file output yang dikembalikan worker ke `outputs/<task_id>/`
+    pub async fn store_output_files(&self, task_id: &str, files: &[FileSpec]) {
+        let Some(dir) = &self.files_dir else {
+            return;
+        };
+        let outputs = dir.join(OUTPUTS_DIR);
+        let returned: Vec<FileSpec> = files.iter().filter(|file| file.content.is_some()).cloned().collect();
+        for file in files.iter().filter(|file| file.content.is_none()) {
+            warn!("Task {} did not produce output file {}", task_id, file.path);
+        }
+        let total: usize = returned.iter().filter_map(|file| file.content.as_ref()).map(Vec::len).sum();
+        if total > MAX_OUTPUT_FILES_BYTES {
+            warn!(
+                "Dropped output files of task {}: {} bytes exceed the {} byte limit",
+                task_id, total, MAX_OUTPUT_FILES_BYTES
+            );
+            return;
+        }
+        let stored = match crate::util::files::resolve(&outputs, task_id) {
+            Ok(task_dir) => crate::util::files::write_all(&task_dir, &returned).await,
+            Err(e) => Err(e),
+        };
+        match stored {
+            Ok(()) => debug!("Stored {} output file(s) of task {}", returned.len(), task_id),
+            Err(e) => warn!("Failed to store output files of task {}: {:#}", task_id, e),
+        }
+    }
+
+    // Whether a worker announcing with this PSK hash may register
+    // Apakah worker yang mengumumkan diri dengan hash PSK ini boleh mendaftar
+    pub fn accepts_psk(&self, psk_hash: Option<&str>) -> bool {
+        match &self.psk_hash {
+            Some(expected) => psk_hash == Some(expected.as_str()),
+            None => true,
+        }
+    }
+
+    // Record an audit event if a persistent store is attached
+    // Catat event audit jika penyimpanan persisten terpasang
+    pub async fn record_event(&self, event: AuditEvent) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.log_event(&event).await {
+                warn!("Failed to write audit event {}: {}", event.event_type(), e);
+            }
+        }
+    }
+
+    // Remember a task definition and its worker while it runs
+    // Ingat definisi tugas dan worker-nya selama dijalankan
+    pub async fn track_dispatched(&self, task: Task, worker_id: &str) {
+        self.dispatched_to
+            .write()
+            .await
+            .insert(task.id.clone(), worker_id.to_string());
+        self.dispatched_tasks
+            .write()
+            .await
+            .insert(task.id.clone(), task);
+    }
+
+    // Forget and return a dispatched task definition
+    // Lupakan dan kembalikan definisi tugas yang telah dikirim
+    pub async fn take_dispatched(&self, task_id: &str) -> Option<Task> {
+        self.dispatched_to.write().await.remove(task_id);
+        self.dispatched_tasks.write().await.remove(task_id)
+    }
+
+    // Tasks currently dispatched to a worker
+    // Tugas yang sedang dikirim ke sebuah worker
+    pub async fn dispatched_to(&self, worker_id: &str) -> Vec<Task> {
+        let dispatched_to = self.dispatched_to.read().await;
+        let dispatched_tasks = self.dispatched_tasks.read().await;
+        dispatched_to
+            .iter()
+            .filter(|(_, worker)| worker.as_str() == worker_id)
+            .filter_map(|(task_id, _)| dispatched_tasks.get(task_id).cloned())
+            .collect()
+    }
+
+    // Worker a task is currently dispatched to, if any
+    // Worker tempat tugas sedang dikirim, jika ada
+    pub async fn dispatched_worker(&self, task_id: &str) -> Option<String> {
+        self.dispatched_to.read().await.get(task_id).cloned()
+    }
+
+    // Record a task that was cancelled before any worker ran it
+    // Catat tugas yang dibatalkan sebelum dijalankan oleh worker mana pun
+    pub async fn mark_cancelled(&self, task: &Task) {
+        self.mark_all_cancelled(std::slice::from_ref(task)).await;
+    }
+
+    // Record tasks cancelled before any worker ran them, written to the history in one transaction
+    // Catat tugas yang dibatalkan sebelum dijalankan worker, ditulis ke riwayat dalam satu transaksi
+    pub async fn mark_all_cancelled(&self, tasks: &[Task]) {
+        let completed_at = chrono::Local::now().timestamp();
+        let results: Vec<TaskResult> = tasks
+            .iter()
+            .map(|task| TaskResult {
+                task_id: task.id.clone(),
+                worker_id: String::new(),
+                status: TaskStatus::Cancelled,
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: None,
+                duration_ms: 0,
+                completed_at,
+                resource_usage: None,
+            })
+            .collect();
+        if let Some(store) = &self.store {
+            let stored: Vec<StoredTask> = tasks
+                .iter()
+                .zip(&results)
+                .map(|(task, result)| StoredTask::from_result(task, result))
+                .collect();
+            if let Err(e) = store.bulk_insert_tasks(&stored).await {
+                warn!("Failed to persist {} cancelled task(s): {}", stored.len(), e);
+            }
+        }
+        for result in results {
+            self.store_result(result).await;
         }
     }
 
+    // POST a finished task's result to its callback URL in the background
+    // Kirim POST hasil tugas yang selesai ke URL callback-nya di latar belakang
+    pub fn notify_callback(&self, task: &Task, result: &TaskResult) {
+        let (Some(webhooks), Some(url)) = (&self.webhooks, &task.callback_url) else {
+            return;
+        };
+        let (webhooks, url, result) = (webhooks.clone(), url.clone(), result.clone());
+        tokio::spawn(async move {
+            // Failures are logged by `deliver`
+            // Kegagalan dicatat oleh `deliver`
+            let _ = webhooks.deliver(&url, &result).await;
+        });
+    }
+
     // Store task execution result
     // Simpan hasil eksekusi tugas
     pub async fn store_result(&self, result: TaskResult) {
         debug!("Storing result for task {}", result.task_id);
+        if result.status == TaskStatus::Completed {
+            if let Some(scheduler) = &self.scheduler {
+                scheduler.notify_dependency_satisfied(&result.task_id).await;
+            }
+        }
         self.task_results
             .write()
             .await
-            .insert(result.task_id.clone(), result);
+            .insert(result.task_id.clone(), result.clone());
+        self.task_events
+            .publish(&result.task_id, TaskEvent::Completed(result.clone()));
+        let _ = self.result_events.send(result);
+    }
+
+    // Wait until a result for the task is stored (returns at once if it already is)
+    // Tunggu sampai hasil tugas disimpan (langsung kembali jika sudah ada)
+    pub async fn wait_for_result(&self, task_id: &str) -> Option<TaskResult> {
+        // Subscribe before checking so a result stored in between is not missed
+        // Berlangganan sebelum memeriksa agar hasil yang disimpan di antaranya tidak terlewat
+        let mut events = self.result_events.subscribe();
+        if let Some(result) = self.get_result(task_id).await {
+            return Some(result);
+        }
+
+        loop {
+            match events.recv().await {
+                Ok(result) if result.task_id == task_id => return Some(result),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    if let Some(result) = self.get_result(task_id).await {
+                        return Some(result);
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    // Remember the latest progress a worker reported for a task
+    // Ingat progres terbaru yang dilaporkan worker untuk sebuah tugas
+    pub async fn set_progress(&self, task_id: &str, progress: f32) {
+        self.task_progress.write().await.insert(task_id.to_string(), progress);
+    }
+
+    // Latest progress reported for a task, if it reported any
+    // Progres terbaru yang dilaporkan untuk tugas, jika pernah melapor
+    pub async fn get_progress(&self, task_id: &str) -> Option<f32> {
+        self.task_progress.read().await.get(task_id).copied()
+    }
+
+    // Definition of a task while it is dispatched to a worker
+    // Definisi tugas selama dikirim ke worker
+    pub async fn get_dispatched(&self, task_id: &str) -> Option<Task> {
+        self.dispatched_tasks.read().await.get(task_id).cloned()
     }
 
     // Retrieve result for specific task
@@ -43,6 +404,109 @@ impl DispatcherState {
         self.task_results.read().await.get(task_id).cloned()
     }
 
+    // Persist a finished task to the history store, if one is attached
+    // Simpan tugas yang selesai ke penyimpanan riwayat, jika terpasang
+    //
+    // Worker totals go to the worker the task is dispatched to; a task that never was has none to update.
+    // Total worker masuk ke worker tempat tugas dikirim; tugas yang tidak pernah dikirim tidak memperbaruinya.
+    pub async fn persist_result(&self, task: &Task, result: &TaskResult) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.store_task(&StoredTask::from_result(task, result)).await {
+                warn!("Failed to persist task {}: {}", task.id, e);
+            }
+            if let Some(worker_id) = self.dispatched_worker(&task.id).await {
+                let success = result.status == TaskStatus::Completed;
+                if let Err(e) = store
+                    .record_worker_task_completion(&worker_id, result.duration_ms, success)
+                    .await
+                {
+                    warn!("Failed to update totals for worker {}: {}", worker_id, e);
+                }
+            }
+        }
+    }
+
+    // Record a worker registration in the history store, if one is attached
+    // Catat pendaftaran worker di penyimpanan riwayat, jika terpasang
+    pub async fn persist_worker(&self, worker: &WorkerInfo) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.upsert_worker(worker).await {
+                warn!("Failed to persist worker {}: {}", worker.id, e);
+            }
+        }
+    }
+
+    // Look up a result that is only in the history store (e.g. from before a restart)
+    // Cari hasil yang hanya ada di penyimpanan riwayat (mis. dari sebelum restart)
+    pub async fn stored_result(&self, task_id: &str) -> Option<TaskResult> {
+        let store = self.store.as_ref()?;
+        match store.get_task(task_id).await {
+            Ok(task) => task?.to_result(),
+            Err(e) => {
+                warn!("Failed to load task {} from history: {}", task_id, e);
+                None
+            }
+        }
+    }
+
+    // Buffer one line of streamed task output and wake watchers
+    // Sangga satu baris output tugas yang dialirkan dan bangunkan pengamat
+    pub async fn append_output(&self, task_id: &str, line: String, is_stderr: bool) {
+        let event = if is_stderr {
+            TaskEvent::Stderr(line.clone())
+        } else {
+            TaskEvent::Stdout(line.clone())
+        };
+        self.task_events.publish(task_id, event);
+
+        let mut output = self.task_output.write().await;
+        let lines = output.entry(task_id.to_string()).or_default();
+        if lines.len() >= MAX_BUFFERED_OUTPUT_LINES {
+            return;
+        }
+        lines.push(OutputLine {
+            line,
+            is_stderr,
+            timestamp: chrono::Local::now().timestamp(),
+        });
+        drop(output);
+        let _ = self.output_events.send(task_id.to_string());
+    }
+
+    // Wait for output past line `offset`; once the task has finished and every line
+    // was delivered, return its result instead
+    // Tunggu output setelah baris `offset`; setelah tugas selesai dan semua baris
+    // terkirim, kembalikan hasilnya
+    pub async fn next_output(&self, task_id: &str, offset: usize) -> OutputUpdate {
+        // Subscribe before checking so nothing produced in between is missed
+        // Berlangganan sebelum memeriksa agar tidak ada yang terlewat di antaranya
+        let mut output_events = self.output_events.subscribe();
+        let mut result_events = self.result_events.subscribe();
+
+        loop {
+            let lines: Vec<OutputLine> = self
+                .task_output
+                .read()
+                .await
+                .get(task_id)
+                .map(|lines| lines.iter().skip(offset).cloned().collect())
+                .unwrap_or_default();
+            if !lines.is_empty() {
+                return OutputUpdate::Lines(lines);
+            }
+            if let Some(result) = self.get_result(task_id).await {
+                return OutputUpdate::Finished(result);
+            }
+
+            // Any event (or lag) just means "check again"
+            // Setiap event (atau ketertinggalan) berarti "periksa lagi"
+            tokio::select! {
+                _ = output_events.recv() => {}
+                _ = result_events.recv() => {}
+            }
+        }
+    }
+
     // Record completed task in history
     // Catat tugas selesai dalam riwayat
     pub async fn add_completed_task(&self, task: Task) {
@@ -60,8 +524,48 @@ impl DispatcherState {
     pub async fn get_history_count(&self) -> usize {
         self.task_results.read().await.len()
     }
+
+    // Forget results, with their output and progress, completed more than `ttl_secs` ago;
+    // the history store keeps them. Returns how many were removed
+    // Lupakan hasil, beserta output dan progresnya, yang selesai lebih dari `ttl_secs` lalu;
+    // penyimpanan riwayat tetap menyimpannya. Mengembalikan jumlah yang dihapus
+    pub async fn expire_old_results(&self, ttl_secs: u64) -> usize {
+        let now = chrono::Local::now().timestamp();
+        let mut expired = Vec::new();
+        self.task_results.write().await.retain(|task_id, result| {
+            let keep = now.saturating_sub(result.completed_at) <= ttl_secs as i64;
+            if !keep {
+                expired.push(task_id.clone());
+            }
+            keep
+        });
+
+        if !expired.is_empty() {
+            let mut output = self.task_output.write().await;
+            let mut progress = self.task_progress.write().await;
+            for task_id in &expired {
+                output.remove(task_id);
+                progress.remove(task_id);
+            }
+            debug!("Expired {} task results", expired.len());
+        }
+        expired.len()
+    }
+
+    // Number of results held in memory per final status
+    // Jumlah hasil yang disimpan di memori per status akhir
+    pub async fn get_results_count_by_status(&self) -> HashMap<TaskStatus, usize> {
+        let mut counts = HashMap::new();
+        for result in self.task_results.read().await.values() {
+            *counts.entry(result.status).or_insert(0) += 1;
+        }
+        counts
+    }
 }
 
+/// Concurrent task limit used until `with_max_jobs` is called
+pub const DEFAULT_MAX_JOBS: usize = 4;
+
 /// Worker state
 pub struct WorkerState {
     pub id: String,
@@ -69,16 +573,161 @@ pub struct WorkerState {
     pub port: u16,
     pub current_task: Arc<RwLock<Option<Task>>>,
     pub completed_tasks: Arc<RwLock<Vec<TaskResult>>>,
+    /// One permit per concurrently running task
+    pub job_slots: Arc<Semaphore>,
+    /// Set on SIGTERM; new assignments are rejected while current tasks finish
+    pub is_draining: Arc<AtomicBool>,
+    /// Set by the dispatcher's maintenance mode; sent along when re-announcing
+    pub maintenance: Arc<AtomicBool>,
+    /// Dispatcher this worker is registered with; `None` while reconnecting
+    pub active_dispatcher: Arc<RwLock<Option<SocketAddr>>>,
+    /// Definition and cancellation token of every accepted task, from assignment until it finishes
+    pub cancellations: Arc<RwLock<HashMap<String, (Task, CancellationToken)>>>,
+    /// Tasks that finished with `TaskStatus::Completed`
+    pub tasks_completed: Arc<AtomicU64>,
+    /// Tasks that finished with any other status
+    pub tasks_failed: Arc<AtomicU64>,
+    /// Execution time summed over every finished task
+    pub total_duration_ms: Arc<AtomicU64>,
+    started_at: Instant,
+    max_jobs: usize,
 }
 
 impl WorkerState {
-    pub fn new(name: String, port: u16) -> Self {
+    /// Worker state under `id`, e.g. one kept from a previous run, or a fresh UUID
+    pub fn new(name: String, port: u16, id: Option<String>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
             name,
             port,
             current_task: Arc::new(RwLock::new(None)),
             completed_tasks: Arc::new(RwLock::new(Vec::new())),
+            job_slots: Arc::new(Semaphore::new(DEFAULT_MAX_JOBS)),
+            is_draining: Arc::new(AtomicBool::new(false)),
+            maintenance: Arc::new(AtomicBool::new(false)),
+            active_dispatcher: Arc::new(RwLock::new(None)),
+            cancellations: Arc::new(RwLock::new(HashMap::new())),
+            tasks_completed: Arc::new(AtomicU64::new(0)),
+            tasks_failed: Arc::new(AtomicU64::new(0)),
+            total_duration_ms: Arc::new(AtomicU64::new(0)),
+            started_at: Instant::now(),
+            max_jobs: DEFAULT_MAX_JOBS,
+        }
+    }
+
+    /// Limit how many assigned tasks may run at once
+    pub fn with_max_jobs(mut self, max_jobs: usize) -> Self {
+        self.max_jobs = max_jobs.max(1);
+        self.job_slots = Arc::new(Semaphore::new(self.max_jobs));
+        self
+    }
+
+    /// Number of assigned tasks currently holding a job slot
+    pub fn running_jobs(&self) -> usize {
+        self.max_jobs - self.job_slots.available_permits()
+    }
+
+    /// Stop taking new tasks ahead of shutdown
+    pub fn start_draining(&self) {
+        self.is_draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.is_draining.load(Ordering::SeqCst)
+    }
+
+    pub fn set_maintenance(&self, enable: bool) {
+        self.maintenance.store(enable, Ordering::SeqCst);
+    }
+
+    pub fn in_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::SeqCst)
+    }
+
+    /// Wait until every accepted task, including ones queued for a slot, has finished
+    ///
+    /// The semaphore is fair, so this only completes after earlier waiters ran.
+    pub async fn wait_until_idle(&self) {
+        let _ = self.job_slots.acquire_many(self.max_jobs as u32).await;
+    }
+
+    pub async fn set_active_dispatcher(&self, addr: Option<SocketAddr>) {
+        *self.active_dispatcher.write().await = addr;
+    }
+
+    pub async fn get_active_dispatcher(&self) -> Option<SocketAddr> {
+        *self.active_dispatcher.read().await
+    }
+
+    /// Token that `cancel_task` triggers for an accepted task
+    pub async fn track_cancellation(&self, task: &Task) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.cancellations.write().await.insert(task.id.clone(), (task.clone(), token.clone()));
+        token
+    }
+
+    /// Forget the token of a task that finished
+    ///
+    /// A run cancelled by `cancel_all` leaves alone a later run of the same task that took its place.
+    pub async fn finish_cancellation(&self, task_id: &str, token: &CancellationToken) {
+        let mut cancellations = self.cancellations.write().await;
+        let replaced = token.is_cancelled() && cancellations.get(task_id).is_some_and(|(_, current)| !current.is_cancelled());
+        if !replaced {
+            cancellations.remove(task_id);
+        }
+    }
+
+    /// Cancel an accepted task, whether it runs or waits for a slot; false if there is none
+    pub async fn cancel_task(&self, task_id: &str) -> bool {
+        match self.cancellations.read().await.get(task_id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel and forget every accepted task, returning their definitions so they can be submitted elsewhere
+    pub async fn cancel_all(&self) -> Vec<Task> {
+        let cancellations = std::mem::take(&mut *self.cancellations.write().await);
+        cancellations
+            .into_values()
+            .map(|(task, token)| {
+                token.cancel();
+                task
+            })
+            .collect()
+    }
+
+    /// Count a finished task towards the worker's totals
+    pub fn record_result(&self, status: TaskStatus, duration_ms: u64) {
+        let counter = if status == TaskStatus::Completed {
+            &self.tasks_completed
+        } else {
+            &self.tasks_failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.total_duration_ms.fetch_add(duration_ms, Ordering::Relaxed);
+    }
+
+    /// Finished tasks per minute since the worker started
+    pub fn throughput_per_minute(&self) -> f64 {
+        let finished = self.tasks_completed.load(Ordering::Relaxed) + self.tasks_failed.load(Ordering::Relaxed);
+        let minutes = self.started_at.elapsed().as_secs_f64() / 60.0;
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        finished as f64 / minutes
+    }
+
+    /// Snapshot of the totals, as sent in `Message::WorkerMetrics`
+    pub fn task_stats(&self) -> WorkerTaskStats {
+        WorkerTaskStats {
+            tasks_completed: self.tasks_completed.load(Ordering::Relaxed),
+            tasks_failed: self.tasks_failed.load(Ordering::Relaxed),
+            total_duration_ms: self.total_duration_ms.load(Ordering::Relaxed),
+            throughput_per_minute: self.throughput_per_minute(),
         }
     }
 
@@ -106,6 +755,7 @@ impl WorkerState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scheduler::SchedulerAlgorithm;
 
     #[tokio::test]
     async fn test_dispatcher_state() {
@@ -113,9 +763,291 @@ mod tests {
         assert_eq!(dispatcher.get_history_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_store_output_files_caps_total_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878).with_files_dir(dir.path().to_path_buf());
+        let file = |path: &str, len: usize| FileSpec {
+            path: path.to_string(),
+            content: Some(vec![b'x'; len]),
+        };
+
+        dispatcher.store_output_files("small", &[file("out.txt", 16)]).await;
+        assert!(dir.path().join(OUTPUTS_DIR).join("small").join("out.txt").is_file());
+
+        let half = MAX_OUTPUT_FILES_BYTES / 2 + 1;
+        dispatcher.store_output_files("big", &[file("a", half), file("b", half)]).await;
+        assert!(!dir.path().join(OUTPUTS_DIR).join("big").exists());
+    }
+
+    #[tokio::test]
+    async fn test_stage_input_files_ships_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878).with_files_dir(dir.path().to_path_buf());
+        std::fs::create_dir(dir.path().join("jobs")).unwrap();
+        std::fs::write(dir.path().join("jobs/job.sh"), "#!/bin/sh\necho hi\n").unwrap();
+
+        let mut task = Task::new("ignored".to_string());
+        task.script_path = Some(PathBuf::from("jobs/job.sh"));
+        dispatcher.stage_input_files(&mut task).await;
+        assert_eq!(task.script_content.as_deref(), Some(&b"#!/bin/sh\necho hi\n"[..]));
+
+        let mut escaping = Task::new("ignored".to_string());
+        escaping.script_path = Some(PathBuf::from("../job.sh"));
+        dispatcher.stage_input_files(&mut escaping).await;
+        assert!(escaping.script_content.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_store_result_releases_dependents() {
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878)
+            .with_scheduler(scheduler.clone());
+
+        let mut dependent = Task::new("echo after".to_string());
+        dependent.dependencies = vec!["task-1".to_string()];
+        scheduler.submit(dependent).await.unwrap();
+        assert_eq!(scheduler.queue_size().await, 0);
+
+        dispatcher
+            .store_result(TaskResult {
+                task_id: "task-1".to_string(),
+                worker_id: "worker-1".to_string(),
+                status: TaskStatus::Completed,
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                duration_ms: 10,
+                completed_at: chrono::Local::now().timestamp(),
+                resource_usage: None,
+            })
+            .await;
+        assert_eq!(scheduler.queue_size().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_result() {
+        let dispatcher = Arc::new(DispatcherState::new("dispatcher-1".to_string(), 7878));
+        let result = |task_id: &str| TaskResult {
+            task_id: task_id.to_string(),
+            worker_id: "worker-1".to_string(),
+            status: TaskStatus::Completed,
+            stdout: "done".to_string(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 10,
+            completed_at: chrono::Local::now().timestamp(),
+            resource_usage: None,
+        };
+
+        let waiter = {
+            let dispatcher = dispatcher.clone();
+            tokio::spawn(async move { dispatcher.wait_for_result("task-2").await })
+        };
+        tokio::task::yield_now().await;
+        dispatcher.store_result(result("task-1")).await;
+        dispatcher.store_result(result("task-2")).await;
+        assert_eq!(waiter.await.unwrap().unwrap().task_id, "task-2");
+
+        // Already stored results are returned immediately
+        assert_eq!(dispatcher.wait_for_result("task-1").await.unwrap().stdout, "done");
+    }
+
+    #[test]
+    fn test_accepts_psk() {
+        let open = DispatcherState::new("dispatcher-1".to_string(), 7878);
+        assert!(open.accepts_psk(None));
+
+        let guarded = DispatcherState::new("dispatcher-1".to_string(), 7878).with_psk("cluster-key");
+        let hash = crate::util::sha256_hex("cluster-key");
+        assert!(guarded.accepts_psk(Some(&hash)));
+        assert!(!guarded.accepts_psk(Some(&crate::util::sha256_hex("other-key"))));
+        assert!(!guarded.accepts_psk(None));
+    }
+
+    #[tokio::test]
+    async fn test_expire_old_results() {
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878);
+        let now = chrono::Local::now().timestamp();
+        let result = |task_id: &str, status: TaskStatus, completed_at: i64| TaskResult {
+            task_id: task_id.to_string(),
+            worker_id: "worker-1".to_string(),
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: None,
+            duration_ms: 10,
+            completed_at,
+            resource_usage: None,
+        };
+        dispatcher.store_result(result("old", TaskStatus::Failed, now - 7200)).await;
+        dispatcher.store_result(result("new", TaskStatus::Completed, now - 60)).await;
+        dispatcher.append_output("old", "line".to_string(), false).await;
+        dispatcher.set_progress("old", 0.5).await;
+
+        let counts = dispatcher.get_results_count_by_status().await;
+        assert_eq!(counts.get(&TaskStatus::Failed), Some(&1));
+        assert_eq!(counts.get(&TaskStatus::Completed), Some(&1));
+
+        assert_eq!(dispatcher.expire_old_results(3600).await, 1);
+        assert!(dispatcher.get_result("old").await.is_none());
+        assert!(dispatcher.get_progress("old").await.is_none());
+        assert!(dispatcher.get_result("new").await.is_some());
+        assert_eq!(dispatcher.get_results_count_by_status().await.get(&TaskStatus::Failed), None);
+    }
+
+    #[tokio::test]
+    async fn test_next_output_streams_then_finishes() {
+        let dispatcher = Arc::new(DispatcherState::new("dispatcher-1".to_string(), 7878));
+        dispatcher.append_output("task-1", "first".to_string(), false).await;
+
+        let OutputUpdate::Lines(lines) = dispatcher.next_output("task-1", 0).await else {
+            panic!("expected buffered output");
+        };
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line, "first");
+
+        // A watcher past the buffered lines waits for the next one
+        let watcher = {
+            let dispatcher = dispatcher.clone();
+            tokio::spawn(async move { dispatcher.next_output("task-1", 1).await })
+        };
+        tokio::task::yield_now().await;
+        dispatcher.append_output("task-1", "oops".to_string(), true).await;
+        let OutputUpdate::Lines(lines) = watcher.await.unwrap() else {
+            panic!("expected new output");
+        };
+        assert!(lines[0].is_stderr);
+
+        dispatcher
+            .store_result(TaskResult {
+                task_id: "task-1".to_string(),
+                worker_id: "worker-1".to_string(),
+                status: TaskStatus::Failed,
+                stdout: "first\n".to_string(),
+                stderr: "oops\n".to_string(),
+                exit_code: Some(1),
+                duration_ms: 10,
+                completed_at: chrono::Local::now().timestamp(),
+                resource_usage: None,
+            })
+            .await;
+        let OutputUpdate::Finished(result) = dispatcher.next_output("task-1", 2).await else {
+            panic!("expected the finished result");
+        };
+        assert_eq!(result.exit_code, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_dispatched_task_tracking() {
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878);
+        let task = Task::new("echo tracked".to_string());
+        let task_id = task.id.clone();
+
+        dispatcher.track_dispatched(task, "worker-1").await;
+        assert_eq!(dispatcher.dispatched_to("worker-1").await.len(), 1);
+        assert!(dispatcher.dispatched_to("worker-2").await.is_empty());
+        assert_eq!(dispatcher.take_dispatched(&task_id).await.unwrap().id, task_id);
+        assert!(dispatcher.dispatched_to("worker-1").await.is_empty());
+        assert!(dispatcher.take_dispatched(&task_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_persist_result_credits_the_dispatched_worker() {
+        use crate::persistence::SqliteStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = Arc::new(SqliteStore::new(db_path.to_str().unwrap()).await.unwrap());
+        let dispatcher = DispatcherState::new("dispatcher-1".to_string(), 7878).with_store(store.clone());
+        let worker = WorkerInfo::new("w1".to_string(), "127.0.0.1".to_string(), 7879, 2);
+        dispatcher.persist_worker(&worker).await;
+
+        let task = Task::new("echo hi".to_string());
+        dispatcher.track_dispatched(task.clone(), &worker.id).await;
+        let result = TaskResult {
+            task_id: task.id.clone(),
+            worker_id: "unknown".to_string(),
+            status: TaskStatus::Completed,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 40,
+            completed_at: chrono::Local::now().timestamp(),
+            resource_usage: None,
+        };
+        dispatcher.persist_result(&task, &result).await;
+
+        let summary = store.get_worker_summary(&worker.id).await.unwrap();
+        assert_eq!(summary.tasks_completed, 1);
+        assert_eq!(summary.total_duration_ms, 40);
+        assert!(store.get_worker_summary("unknown").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_worker_state() {
-        let worker = WorkerState::new("worker-1".to_string(), 7879);
+        let worker = WorkerState::new("worker-1".to_string(), 7879, None);
         assert!(worker.get_current_task().await.is_none());
     }
+
+    #[tokio::test]
+    async fn test_running_jobs_counts_held_slots() {
+        let worker = WorkerState::new("worker-1".to_string(), 7879, None).with_max_jobs(2);
+        assert_eq!(worker.running_jobs(), 0);
+
+        let slot = worker.job_slots.clone().acquire_owned().await.unwrap();
+        assert_eq!(worker.running_jobs(), 1);
+        drop(slot);
+        assert_eq!(worker.running_jobs(), 0);
+    }
+
+    #[test]
+    fn test_worker_task_stats() {
+        let worker = WorkerState::new("worker-1".to_string(), 7879, None);
+        worker.record_result(TaskStatus::Completed, 100);
+        worker.record_result(TaskStatus::Completed, 200);
+        worker.record_result(TaskStatus::TimedOut, 700);
+
+        let stats = worker.task_stats();
+        assert_eq!((stats.tasks_completed, stats.tasks_failed), (2, 1));
+        assert_eq!(stats.total_duration_ms, 1000);
+        assert!((stats.error_rate() - 1.0 / 3.0).abs() < 1e-9);
+        assert!(stats.throughput_per_minute > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_running_tasks() {
+        let worker = WorkerState::new("worker-1".to_string(), 7879, None).with_max_jobs(2);
+        let slot = worker.job_slots.clone().acquire_owned().await.unwrap();
+        worker.start_draining();
+        assert!(worker.is_draining());
+
+        let idle = tokio::time::timeout(std::time::Duration::from_millis(50), worker.wait_until_idle()).await;
+        assert!(idle.is_err());
+        drop(slot);
+        worker.wait_until_idle().await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_returns_accepted_tasks() {
+        let worker = WorkerState::new("worker-1".to_string(), 7879, None);
+        let mut task = Task::new("sleep 30".to_string());
+        task.priority = 7;
+        let old_run = worker.track_cancellation(&task).await;
+
+        let interrupted = worker.cancel_all().await;
+        assert!(old_run.is_cancelled());
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].id, task.id);
+        assert_eq!(interrupted[0].priority, 7);
+
+        // The resubmitted task comes back before the cancelled run has wound down
+        let new_run = worker.track_cancellation(&task).await;
+        worker.finish_cancellation(&task.id, &old_run).await;
+        assert!(worker.cancel_task(&task.id).await);
+        assert!(new_run.is_cancelled());
+        worker.finish_cancellation(&task.id, &new_run).await;
+        assert!(!worker.cancel_task(&task.id).await);
+    }
 }
+
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Service name reported with every exported span
+pub const SERVICE_NAME: &str = "octaskly";
+
+/// Spans exported to an OTLP collector; flushes on `shutdown`
+pub struct Telemetry {
+    provider: TracerProvider,
+}
+
+impl Telemetry {
+    /// Export spans over gRPC to `endpoint` (e.g. `http://localhost:4317`)
+    ///
+    /// Also installs the W3C trace context propagator used for `AssignTask`.
+    pub fn init(endpoint: &str) -> Result<Self> {
+        let exporter = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new("service.name", SERVICE_NAME)]))
+            .build();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(provider.clone());
+        Ok(Self { provider })
+    }
+
+    /// Tracer for the `tracing-opentelemetry` layer
+    pub fn tracer(&self) -> Tracer {
+        self.provider.tracer(SERVICE_NAME)
+    }
+
+    /// Export any spans still buffered
+    pub fn shutdown(self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to flush trace spans: {}", e);
+        }
+    }
+}
+
+/// Trace context of the current span, to send along with a message
+///
+/// `None` when tracing is not enabled.
+pub fn current_context() -> Option<HashMap<String, String>> {
+    let mut carrier = HashMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+    (!carrier.is_empty()).then_some(carrier)
+}
+
+/// Make `span` a child of the remote span described by `carrier`
+pub fn set_parent(span: &Span, carrier: &HashMap<String, String>) {
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(carrier));
+    span.set_parent(context);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_context_round_trip() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let dispatcher_span = tracing::info_span!("schedule");
+            let carrier = dispatcher_span.in_scope(current_context).unwrap();
+            assert!(carrier.contains_key("traceparent"));
+
+            let worker_span = tracing::info_span!("execute");
+            set_parent(&worker_span, &carrier);
+
+            let trace_id = |span: &Span| span.context().span().span_context().trace_id();
+            assert_eq!(trace_id(&worker_span), trace_id(&dispatcher_span));
+        });
+    }
+}
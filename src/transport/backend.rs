@@ -0,0 +1,117 @@
+use super::Transport;
+use crate::protocol::Message;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tracing::{debug, error};
+
+/// Handles one incoming message from the given peer, returning an optional reply
+pub type MessageHandler =
+    Arc<dyn Fn(Message, SocketAddr) -> BoxFuture<'static, Result<Option<Message>>> + Send + Sync>;
+
+/// Wire protocol carrying messages between the dispatcher and its workers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Tcp,
+    Quic,
+}
+
+impl FromStr for TransportKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Self::Tcp),
+            "quic" => Ok(Self::Quic),
+            other => Err(format!("unknown transport {:?}, expected tcp or quic", other)),
+        }
+    }
+}
+
+/// What the dispatcher, workers and REST API need from a transport
+///
+/// Implemented by the TCP `Transport` and by `QuicTransport`. Both carry the same
+/// length-prefixed frames, so a message reads the same on either.
+#[async_trait]
+pub trait TransportBackend: Send + Sync {
+    /// Send a message without waiting for a reply
+    async fn send_message(&self, peer: SocketAddr, message: &Message) -> Result<()>;
+
+    /// Send a message and wait for the peer's `Message::Ack`, retrying on failure
+    async fn send_message_with_ack(&self, peer: SocketAddr, message: &Message) -> Result<()>;
+
+    /// Send a request and wait up to `timeout` (or indefinitely) for the reply
+    async fn request_with_timeout(&self, peer: SocketAddr, message: &Message, timeout: Option<Duration>) -> Result<Message>;
+
+    /// Read one unencrypted frame from an incoming stream and decode its message
+    async fn recv_message_from_stream(&self, stream: &mut (dyn AsyncRead + Unpin + Send)) -> Result<Message>;
+
+    /// Address this backend accepts messages on, once it is listening
+    fn local_addr(&self) -> Option<SocketAddr>;
+
+    /// Pass every message arriving on this backend's listener to `handler` until it closes
+    async fn serve(&self, handler: MessageHandler) -> Result<()>;
+
+    /// Note that worker `worker_id` is reached at `peer`, for backends that keep a connection per worker
+    fn bind_worker(&self, _worker_id: &str, _peer: SocketAddr) {}
+
+    /// Close and forget the connection kept for a worker that left
+    fn forget_worker(&self, _worker_id: &str) {}
+}
+
+#[async_trait]
+impl TransportBackend for Transport {
+    async fn send_message(&self, peer: SocketAddr, message: &Message) -> Result<()> {
+        Transport::send_message(self, peer, message).await
+    }
+
+    async fn send_message_with_ack(&self, peer: SocketAddr, message: &Message) -> Result<()> {
+        Transport::send_message_with_ack(self, peer, message).await
+    }
+
+    async fn request_with_timeout(&self, peer: SocketAddr, message: &Message, timeout: Option<Duration>) -> Result<Message> {
+        Transport::request_with_timeout(self, peer, message, timeout).await
+    }
+
+    async fn recv_message_from_stream(&self, stream: &mut (dyn AsyncRead + Unpin + Send)) -> Result<Message> {
+        let frame = Transport::read_frame(stream, self.read_timeout, self.max_message_bytes).await?;
+        Ok(Transport::decode_frame(&frame, self.max_message_bytes)?.message)
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.as_ref().and_then(|listener| listener.local_addr().ok())
+    }
+
+    /// Requires `listen` first; callers terminating TLS run their own accept loop instead
+    async fn serve(&self, handler: MessageHandler) -> Result<()> {
+        let listener = self
+            .listener
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Transport is not listening"))?;
+        let (read_timeout, max_message_bytes) = (self.read_timeout, self.max_message_bytes);
+
+        loop {
+            let (stream, peer_addr) = listener.accept().await?;
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let on_message = move |msg| handler(msg, peer_addr);
+                if let Err(e) =
+                    Transport::handle_stream(Box::new(stream), peer_addr, None, read_timeout, max_message_bytes, None, on_message)
+                        .await
+                {
+                    error!("Connection handler error for {}: {}", peer_addr, e);
+                }
+                debug!("Connection from {} finished", peer_addr);
+            });
+        }
+    }
+}
+
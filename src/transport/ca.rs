@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, SanType,
+};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// CA certificate file inside the dispatcher's workdir; workers trust it with `--tls-ca`
+pub const CA_CERT_FILE: &str = "ca.pem";
+
+/// CA private key file inside the dispatcher's workdir
+pub const CA_KEY_FILE: &str = "ca-key.pem";
+
+/// Common name of the generated CA certificate
+const CA_COMMON_NAME: &str = "Octaskly CA";
+
+/// Certificate authority that signs dispatcher and worker certificates for mTLS
+pub struct CertificateAuthority {
+    cert: Certificate,
+    cert_path: PathBuf,
+}
+
+impl CertificateAuthority {
+    /// Load the CA stored in `dir`, generating it on first use
+    pub fn load_or_create(dir: &Path) -> Result<Self> {
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+
+        if cert_path.exists() && key_path.exists() {
+            let key_pem = std::fs::read_to_string(&key_path)
+                .with_context(|| format!("Failed to read CA key {}", key_path.display()))?;
+            // The stored certificate is what peers trust; rebuilding it from the same
+            // name and key signs certificates that chain to it
+            let cert = Certificate::from_params(ca_params(KeyPair::from_pem(&key_pem)?))?;
+            return Ok(Self { cert, cert_path });
+        }
+
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let cert = Certificate::from_params(ca_params(KeyPair::generate(&rcgen::PKCS_ECDSA_P256_SHA256)?))?;
+        std::fs::write(&cert_path, cert.serialize_pem()?)
+            .with_context(|| format!("Failed to write {}", cert_path.display()))?;
+        write_private_key(&key_path, &cert.serialize_private_key_pem())?;
+        Ok(Self { cert, cert_path })
+    }
+
+    /// PEM file holding the CA certificate
+    pub fn cert_path(&self) -> &Path {
+        &self.cert_path
+    }
+
+    /// Sign a certificate whose subject common name is `name`
+    ///
+    /// `hostnames` become subject alternative names (DNS names or IP addresses);
+    /// `name` itself is always included. Returns the certificate and key as PEM.
+    pub fn issue(&self, name: &str, hostnames: &[String]) -> Result<(String, String)> {
+        let mut params = CertificateParams::default();
+        params.distinguished_name = DistinguishedName::new();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth, ExtendedKeyUsagePurpose::ClientAuth];
+
+        let mut names = vec![name.to_string()];
+        names.extend(hostnames.iter().filter(|host| host.as_str() != name).cloned());
+        params.subject_alt_names = names
+            .into_iter()
+            .map(|host| match host.parse::<IpAddr>() {
+                Ok(ip) => SanType::IpAddress(ip),
+                Err(_) => SanType::DnsName(host),
+            })
+            .collect();
+
+        let cert = Certificate::from_params(params)?;
+        Ok((cert.serialize_pem_with_signer(&self.cert)?, cert.serialize_private_key_pem()))
+    }
+
+    /// Issue a certificate for `name` into `<dir>/<name>.pem` and `<dir>/<name>-key.pem`
+    pub fn issue_to_dir(&self, name: &str, hostnames: &[String], dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            anyhow::bail!("Invalid certificate name {:?}", name);
+        }
+        let (cert_pem, key_pem) = self.issue(name, hostnames)?;
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let cert_path = dir.join(format!("{}.pem", name));
+        let key_path = dir.join(format!("{}-key.pem", name));
+        std::fs::write(&cert_path, cert_pem).with_context(|| format!("Failed to write {}", cert_path.display()))?;
+        write_private_key(&key_path, &key_pem)?;
+        Ok((cert_path, key_path))
+    }
+}
+
+fn ca_params(key_pair: KeyPair) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.alg = key_pair.algorithm();
+    params.key_pair = Some(key_pair);
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, CA_COMMON_NAME);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params
+}
+
+/// Write a private key readable only by the current user
+pub fn write_private_key(path: &Path, pem: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    std::io::Write::write_all(&mut file, pem.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::tls;
+
+    #[test]
+    fn test_ca_is_reused_across_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = CertificateAuthority::load_or_create(dir.path()).unwrap();
+        let first = std::fs::read_to_string(ca.cert_path()).unwrap();
+
+        let ca = CertificateAuthority::load_or_create(dir.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(ca.cert_path()).unwrap(), first);
+
+        let (cert_path, _) = ca.issue_to_dir("worker-1", &[], dir.path()).unwrap();
+        assert_eq!(tls::load_common_name(&cert_path).unwrap(), "worker-1");
+    }
+}
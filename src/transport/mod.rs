@@ -1,18 +1,272 @@
+use crate::error::TransportError;
 use crate::protocol::Message;
+use crate::security_enhanced::SecurityManager;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::net::{TcpListener, TcpStream};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+pub mod backend;
+pub mod ca;
+pub mod pool;
+pub mod tls;
+
+pub use backend::{MessageHandler, TransportBackend, TransportKind};
+pub use pool::ConnectionPool;
+pub use tls::TlsClient;
+
+/// Byte stream that messages can be framed over (plain TCP or TLS)
+pub trait MessageStream: AsyncRead + AsyncWrite + Unpin + Send + Sync {
+    /// Underlying TCP socket, used for connection liveness checks
+    fn tcp(&self) -> &TcpStream;
+
+    /// Session negotiated by `Transport::key_exchange`, if frames are encrypted
+    fn session(&self) -> Option<&SecurityManager> {
+        None
+    }
+
+    /// Sequence number for the next envelope sent on this connection, or 0 if it does not count them
+    fn next_sequence(&mut self) -> u64 {
+        0
+    }
+}
+
+impl MessageStream for TcpStream {
+    fn tcp(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl MessageStream for tokio_rustls::client::TlsStream<TcpStream> {
+    fn tcp(&self) -> &TcpStream {
+        self.get_ref().0
+    }
+}
+
+impl MessageStream for tokio_rustls::server::TlsStream<TcpStream> {
+    fn tcp(&self) -> &TcpStream {
+        self.get_ref().0
+    }
+}
+
+/// Stream whose frames are encrypted with a per-connection session key
+///
+/// Bytes pass through unchanged; `send_envelope` and `recv_envelope` seal each frame.
+/// The key is dropped together with the connection.
+struct SealedStream {
+    inner: Box<dyn MessageStream>,
+    session: SecurityManager,
+}
+
+impl AsyncRead for SealedStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SealedStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.inner).poll_shutdown(cx)
+    }
+}
+
+impl MessageStream for SealedStream {
+    fn tcp(&self) -> &TcpStream {
+        self.inner.tcp()
+    }
+
+    fn session(&self) -> Option<&SecurityManager> {
+        Some(&self.session)
+    }
+
+    fn next_sequence(&mut self) -> u64 {
+        self.inner.next_sequence()
+    }
+}
+
+/// Stream that numbers the envelopes sent on it, starting at 1
+///
+/// The pool wraps every outgoing connection in one so the receiver can spot gaps.
+struct SequencedStream<S> {
+    inner: S,
+    sent: u64,
+}
+
+impl<S> SequencedStream<S> {
+    fn new(inner: S) -> Self {
+        Self { inner, sent: 0 }
+    }
+}
+
+impl<S: MessageStream> AsyncRead for SequencedStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: MessageStream> AsyncWrite for SequencedStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: MessageStream> MessageStream for SequencedStream<S> {
+    fn tcp(&self) -> &TcpStream {
+        self.inner.tcp()
+    }
+
+    fn next_sequence(&mut self) -> u64 {
+        self.sent += 1;
+        self.sent
+    }
+}
+
+/// Wire frame carrying a message together with its delivery identifier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub message_id: String,
+    /// Position among the envelopes sent on this connection; 0 when the sender does not count them
+    pub sequence: u64,
+    pub message: Message,
+    /// Whether the receiver should reply with `Message::Ack`
+    pub ack_requested: bool,
+}
+
+impl Envelope {
+    pub fn new(message: Message) -> Self {
+        Self {
+            message_id: uuid::Uuid::new_v4().to_string(),
+            sequence: 0,
+            message,
+            ack_requested: false,
+        }
+    }
+
+    /// Create an envelope the receiver must acknowledge
+    pub fn requesting_ack(message: Message) -> Self {
+        Self {
+            ack_requested: true,
+            ..Self::new(message)
+        }
+    }
+}
+
+/// Serialized messages larger than this are zstd-compressed by default
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4096;
+
+/// Largest frame a peer may announce before the connection is refused
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Frame flag: payload is raw bincode
+const FRAME_RAW: u8 = 0x00;
+
+/// Frame flag: payload is zstd-compressed bincode
+const FRAME_ZSTD: u8 = 0x01;
+
+/// Frame flag: payload is another frame encrypted with the session key
+const FRAME_SEALED: u8 = 0x02;
 
 /// Network transport for P2P communication
 pub struct Transport {
     listener: Option<TcpListener>,
+    ack_timeout: Duration,
+    read_timeout: Option<Duration>,
+    max_message_bytes: usize,
+    max_retries: u32,
+    compression_threshold_bytes: usize,
+    tls: Option<TlsClient>,
+    security: Option<Arc<SecurityManager>>,
+    pool: ConnectionPool,
 }
 
 impl Transport {
-    pub fn new() -> Self {
-        Self { listener: None }
+    /// Create a transport that compresses messages larger than `compression_threshold_bytes`
+    pub fn new(compression_threshold_bytes: usize) -> Self {
+        Self {
+            listener: None,
+            ack_timeout: Duration::from_secs(5),
+            read_timeout: None,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            max_retries: 3,
+            compression_threshold_bytes,
+            tls: None,
+            security: None,
+            pool: ConnectionPool::default(),
+        }
+    }
+
+    /// Share an existing connection pool instead of the transport's own
+    pub fn with_pool(mut self, pool: ConnectionPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Get the connection pool used for outgoing messages
+    pub fn pool(&self) -> &ConnectionPool {
+        &self.pool
+    }
+
+    /// Wrap outgoing connections in TLS using the given client configuration
+    pub fn with_tls(mut self, tls: TlsClient) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Open each outgoing connection with an ECDH handshake and encrypt its frames
+    ///
+    /// The peer must accept with `handle_secure_connection` using the same pre-shared key.
+    /// Run `SecurityManager::start_nonce_eviction` to keep its replay cache bounded.
+    pub fn with_security(mut self, security: Arc<SecurityManager>) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    /// Set how long `send_message_with_ack` waits for an acknowledgment
+    pub fn with_ack_timeout(mut self, timeout: Duration) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// Give up on a reply once the peer stops sending for `timeout` mid-read
+    ///
+    /// Applies separately to the length prefix and to the frame body of each reply.
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Refuse replies whose length prefix exceeds `max_bytes`
+    pub fn with_max_message_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_message_bytes = max_bytes;
+        self
+    }
+
+    /// Set how many times `send_message_with_ack` retries after the first attempt
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
     }
 
     /// Start listening for incoming connections
@@ -29,52 +283,361 @@ impl Transport {
         self.listener.as_ref()
     }
 
-    /// Send a message to a peer
+    /// Send a message to a peer over a pooled connection
+    #[tracing::instrument(skip(self, message), fields(peer = %peer_addr))]
     pub async fn send_message(&self, peer_addr: SocketAddr, message: &Message) -> Result<()> {
-        let mut stream = TcpStream::connect(peer_addr).await?;
-        let serialized = bincode::serialize(message)?;
-        
-        // Send length prefix (4 bytes)
-        stream.write_all(&(serialized.len() as u32).to_le_bytes()).await?;
-        stream.write_all(&serialized).await?;
-        stream.flush().await?;
-        
+        let mut stream = self.connect(peer_addr).await?;
+        let mut envelope = Envelope::new(message.clone());
+        Self::send_envelope(stream.as_mut(), &mut envelope, self.compression_threshold_bytes).await?;
+        self.pool.release(peer_addr, stream);
+
         debug!("Sent message to {}", peer_addr);
         Ok(())
     }
 
-    /// Receive a message from a stream
-    pub async fn recv_message(stream: &mut TcpStream) -> Result<Message> {
+    /// Send a message and wait for the peer's `Message::Ack`, retrying on failure
+    ///
+    /// Every attempt reuses the same message ID, so delivery is at-least-once:
+    /// the peer may process a message twice if an acknowledgment is lost.
+    pub async fn send_message_with_ack(&self, peer_addr: SocketAddr, message: &Message) -> Result<()> {
+        let mut envelope = Envelope::requesting_ack(message.clone());
+        let mut last_error = None;
+
+        for attempt in 0..=self.max_retries {
+            let delivery = self.deliver(peer_addr, &mut envelope);
+            match tokio::time::timeout(self.ack_timeout, delivery).await {
+                Ok(Ok(())) => {
+                    debug!("Message {} acknowledged by {}", envelope.message_id, peer_addr);
+                    return Ok(());
+                }
+                Ok(Err(e)) => last_error = Some(e),
+                Err(_) => {
+                    last_error = Some(anyhow::anyhow!(
+                        "Timed out after {:?} waiting for ack",
+                        self.ack_timeout
+                    ))
+                }
+            }
+
+            warn!(
+                "Delivery attempt {}/{} of message {} to {} failed: {}",
+                attempt + 1,
+                self.max_retries + 1,
+                envelope.message_id,
+                peer_addr,
+                last_error.as_ref().unwrap()
+            );
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Message delivery failed")))
+    }
+
+    /// Send a request and wait for the peer's reply on the same connection
+    pub async fn request(&self, peer_addr: SocketAddr, message: &Message) -> Result<Message> {
+        self.request_with_timeout(peer_addr, message, Some(self.ack_timeout)).await
+    }
+
+    /// Like `request`, but waits up to `timeout` (or indefinitely) for the reply
+    pub async fn request_with_timeout(&self, peer_addr: SocketAddr, message: &Message, timeout: Option<Duration>) -> Result<Message> {
+        let exchange = async {
+            let mut stream = self.connect(peer_addr).await?;
+            let mut envelope = Envelope::new(message.clone());
+            Self::send_envelope(stream.as_mut(), &mut envelope, self.compression_threshold_bytes).await?;
+            let reply = Self::recv_envelope_within(stream.as_mut(), self.read_timeout, self.max_message_bytes).await?;
+            self.pool.release(peer_addr, stream);
+            Ok(reply.message)
+        };
+
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, exchange)
+                .await
+                .map_err(|_| anyhow::anyhow!("Timed out after {:?} waiting for reply", limit))?,
+            None => exchange.await,
+        }
+    }
+
+    /// Take a pooled connection, running the key exchange first on fresh ones
+    async fn connect(&self, peer_addr: SocketAddr) -> Result<Box<dyn MessageStream>> {
+        let stream = self.pool.get_or_connect(peer_addr, self.tls.as_ref()).await?;
+        match &self.security {
+            Some(security) if stream.session().is_none() => Self::key_exchange(stream, security).await,
+            _ => Ok(stream),
+        }
+    }
+
+    /// Agree on a session key with the peer; both ends call this on a new connection
+    ///
+    /// Each side sends an ephemeral public key in `Message::KeyExchange`, so every
+    /// connection gets its own key and past traffic stays private if the PSK leaks.
+    pub async fn key_exchange(mut stream: Box<dyn MessageStream>, security: &SecurityManager) -> Result<Box<dyn MessageStream>> {
+        let (secret, public_key) = SecurityManager::ephemeral_key();
+        let mut hello = Envelope::new(Message::KeyExchange { public_key });
+        Self::send_envelope(stream.as_mut(), &mut hello, DEFAULT_COMPRESSION_THRESHOLD).await?;
+
+        let peer_public_key = match Self::recv_envelope(stream.as_mut()).await?.message {
+            Message::KeyExchange { public_key } => public_key,
+            other => return Err(anyhow::anyhow!("Expected key exchange, got {:?}", other)),
+        };
+        let session = security.session(secret, peer_public_key)?;
+        Ok(Box::new(SealedStream { inner: stream, session }))
+    }
+
+    /// Write one envelope and read back the matching acknowledgment
+    async fn deliver(&self, peer_addr: SocketAddr, envelope: &mut Envelope) -> Result<()> {
+        let mut stream = self.connect(peer_addr).await?;
+        Self::send_envelope(stream.as_mut(), envelope, self.compression_threshold_bytes).await?;
+
+        let reply = Self::recv_envelope_within(stream.as_mut(), self.read_timeout, self.max_message_bytes).await?;
+        match reply.message {
+            Message::Ack { message_id } if message_id == envelope.message_id => {
+                self.pool.release(peer_addr, stream);
+                Ok(())
+            }
+            other => Err(anyhow::anyhow!("Expected ack for {}, got {:?}", envelope.message_id, other)),
+        }
+    }
+
+    /// Serialize an envelope into a frame body: 1-byte flag followed by the payload
+    pub fn encode_frame(envelope: &Envelope, compression_threshold: usize) -> Result<Vec<u8>> {
+        let serialized = bincode::serialize(envelope)?;
+
+        let mut frame = Vec::with_capacity(serialized.len() + 1);
+        if serialized.len() > compression_threshold {
+            frame.push(FRAME_ZSTD);
+            frame.extend(zstd::encode_all(serialized.as_slice(), 0)?);
+        } else {
+            frame.push(FRAME_RAW);
+            frame.extend(serialized);
+        }
+        Ok(frame)
+    }
+
+    /// Parse a frame body produced by `encode_frame`
+    ///
+    /// A compressed payload is inflated to at most `max_message_bytes`; past that the
+    /// frame fails with `TransportError::MessageTooLarge`, so a small frame cannot
+    /// expand into an arbitrarily large allocation.
+    pub fn decode_frame(frame: &[u8], max_message_bytes: usize) -> Result<Envelope> {
+        let (flag, payload) = frame
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("Empty frame"))?;
+
+        let envelope = match *flag {
+            FRAME_RAW => bincode::deserialize(payload)?,
+            FRAME_ZSTD => bincode::deserialize(&Self::decompress_within(payload, max_message_bytes)?)?,
+            other => return Err(anyhow::anyhow!("Unknown frame flag 0x{:02x}", other)),
+        };
+        Ok(envelope)
+    }
+
+    fn decompress_within(payload: &[u8], max_message_bytes: usize) -> Result<Vec<u8>> {
+        use std::io::Read;
+
+        let limit = max_message_bytes.saturating_add(1) as u64;
+        let mut decompressed = Vec::new();
+        zstd::stream::read::Decoder::new(payload)?
+            .take(limit)
+            .read_to_end(&mut decompressed)?;
+        if decompressed.len() > max_message_bytes {
+            return Err(TransportError::MessageTooLarge { len: decompressed.len() }.into());
+        }
+        Ok(decompressed)
+    }
+
+    /// Write a length-prefixed envelope to a stream, stamping it with the stream's next sequence number
+    pub async fn send_envelope(stream: &mut dyn MessageStream, envelope: &mut Envelope, compression_threshold: usize) -> Result<()> {
+        envelope.sequence = stream.next_sequence();
+        let mut frame = Self::encode_frame(envelope, compression_threshold)?;
+        if let Some(session) = stream.session() {
+            let sealed = session.encrypt(&frame)?;
+            frame = Vec::with_capacity(sealed.len() + 1);
+            frame.push(FRAME_SEALED);
+            frame.extend(sealed);
+        }
+
+        Self::write_frame(stream, &frame).await
+    }
+
+    /// Write a frame body behind its 4-byte length prefix
+    pub async fn write_frame<W: AsyncWrite + Unpin + ?Sized>(writer: &mut W, frame: &[u8]) -> Result<()> {
+        // Send length prefix (4 bytes), covering flag and payload
+        writer.write_all(&(frame.len() as u32).to_le_bytes()).await?;
+        writer.write_all(frame).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Receive a length-prefixed envelope from a stream
+    pub async fn recv_envelope(stream: &mut dyn MessageStream) -> Result<Envelope> {
+        Self::recv_envelope_within(stream, None, DEFAULT_MAX_MESSAGE_BYTES).await
+    }
+
+    /// Like `recv_envelope`, failing with `TransportError::ReadTimeout` when the length
+    /// prefix or the body does not arrive within `read_timeout`, and with
+    /// `TransportError::MessageTooLarge` before allocating a frame over `max_message_bytes`
+    pub async fn recv_envelope_within(
+        stream: &mut dyn MessageStream,
+        read_timeout: Option<Duration>,
+        max_message_bytes: usize,
+    ) -> Result<Envelope> {
+        let buf = Self::read_frame(stream, read_timeout, max_message_bytes).await?;
+        match (stream.session(), buf.first()) {
+            (Some(session), Some(&FRAME_SEALED)) => {
+                let frame = session.decrypt(&buf[1..])?;
+                session.verify_no_replay(&buf[1..])?;
+                Self::decode_frame(&frame, max_message_bytes)
+            }
+            (Some(_), _) => Err(anyhow::anyhow!("Unencrypted frame on a secured connection")),
+            (None, Some(&FRAME_SEALED)) => Err(anyhow::anyhow!("Encrypted frame without a session key")),
+            (None, _) => Self::decode_frame(&buf, max_message_bytes),
+        }
+    }
+
+    /// Read one length-prefixed frame body, still sealed if the sender encrypted it
+    pub async fn read_frame<R: AsyncRead + Unpin + ?Sized>(
+        reader: &mut R,
+        read_timeout: Option<Duration>,
+        max_message_bytes: usize,
+    ) -> Result<Vec<u8>> {
         // Read length prefix (4 bytes)
         let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).await?;
+        Self::read_exact_within(reader, &mut len_buf, read_timeout).await?;
         let len = u32::from_le_bytes(len_buf) as usize;
+        if len == 0 {
+            return Err(TransportError::InvalidFrame("zero-length message".to_string()).into());
+        }
+        if len > max_message_bytes {
+            return Err(TransportError::MessageTooLarge { len }.into());
+        }
 
-        // Read message data
+        // Read flag and message data
         let mut buf = vec![0u8; len];
-        stream.read_exact(&mut buf).await?;
+        Self::read_exact_within(reader, &mut buf, read_timeout).await?;
+        Ok(buf)
+    }
+
+    async fn read_exact_within<R: AsyncRead + Unpin + ?Sized>(
+        reader: &mut R,
+        buf: &mut [u8],
+        read_timeout: Option<Duration>,
+    ) -> Result<()> {
+        match read_timeout {
+            Some(limit) => {
+                tokio::time::timeout(limit, reader.read_exact(buf))
+                    .await
+                    .map_err(|_| TransportError::ReadTimeout(limit))??;
+            }
+            None => {
+                reader.read_exact(buf).await?;
+            }
+        }
+        Ok(())
+    }
 
-        let message = bincode::deserialize(&buf)?;
-        Ok(message)
+    /// Receive a message from a stream
+    pub async fn recv_message(stream: &mut dyn MessageStream) -> Result<Message> {
+        Ok(Self::recv_envelope(stream).await?.message)
     }
 
     /// Handle incoming connection
+    ///
+    /// A reply returned by the handler is sent back on the same connection. Messages
+    /// that request it are then acknowledged with `Message::Ack`.
     pub async fn handle_connection<F>(stream: TcpStream, handler: F) -> Result<()>
     where
-        F: Fn(Message) -> futures::future::BoxFuture<'static, Result<()>> + 'static,
+        F: Fn(Message) -> futures::future::BoxFuture<'static, Result<Option<Message>>> + 'static,
     {
-        let mut stream = stream;
         let peer_addr = stream.peer_addr()?;
+        Self::handle_stream(Box::new(stream), peer_addr, None, None, DEFAULT_MAX_MESSAGE_BYTES, None, handler).await
+    }
+
+    /// Like `handle_connection`, but runs the key exchange before reading any message
+    pub async fn handle_secure_connection<F>(stream: TcpStream, security: &SecurityManager, handler: F) -> Result<()>
+    where
+        F: Fn(Message) -> futures::future::BoxFuture<'static, Result<Option<Message>>> + 'static,
+    {
+        let peer_addr = stream.peer_addr()?;
+        Self::handle_stream(Box::new(stream), peer_addr, Some(security), None, DEFAULT_MAX_MESSAGE_BYTES, None, handler).await
+    }
+
+    /// Handle incoming messages on any framed stream (plain TCP or TLS)
+    ///
+    /// With `security`, the connection starts with `key_exchange` and every frame after
+    /// it is encrypted with the resulting session key. With `read_timeout`, a peer that
+    /// goes quiet for that long is disconnected; it is not otherwise treated as gone.
+    /// A frame announced as larger than `max_message_bytes` also ends the connection.
+    ///
+    /// Envelopes that skip ahead of or fall behind the expected sequence number are still
+    /// handled; they are logged and passed to `on_out_of_order` as `(expected, received)`.
+    pub async fn handle_stream<F>(
+        mut stream: Box<dyn MessageStream>,
+        peer_addr: SocketAddr,
+        security: Option<&SecurityManager>,
+        read_timeout: Option<Duration>,
+        max_message_bytes: usize,
+        on_out_of_order: Option<&(dyn Fn(u64, u64) + Send + Sync)>,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(Message) -> futures::future::BoxFuture<'static, Result<Option<Message>>> + 'static,
+    {
         debug!("New connection from {}", peer_addr);
+        if let Some(security) = security {
+            stream = Self::key_exchange(stream, security).await?;
+            debug!("Established session key with {}", peer_addr);
+        }
 
+        let mut expected_sequence = 0;
         loop {
-            match Self::recv_message(&mut stream).await {
-                Ok(message) => {
-                    handler(message).await?;
+            match Self::recv_envelope_within(stream.as_mut(), read_timeout, max_message_bytes).await {
+                Ok(envelope) => {
+                    if envelope.sequence > 0 {
+                        if expected_sequence > 0 && envelope.sequence != expected_sequence {
+                            warn!(
+                                "Message {} from {} has sequence {}, expected {}",
+                                envelope.message_id, peer_addr, envelope.sequence, expected_sequence
+                            );
+                            if let Some(on_out_of_order) = on_out_of_order {
+                                on_out_of_order(expected_sequence, envelope.sequence);
+                            }
+                        }
+                        expected_sequence = envelope.sequence + 1;
+                    }
+
+                    if matches!(envelope.message, Message::Ack { .. }) {
+                        debug!("Ignoring unsolicited ack from {}", peer_addr);
+                        continue;
+                    }
+
+                    if let Some(reply) = handler(envelope.message).await? {
+                        let mut reply = Envelope::new(reply);
+                        if let Err(e) = Self::send_envelope(stream.as_mut(), &mut reply, DEFAULT_COMPRESSION_THRESHOLD).await {
+                            debug!("Could not send reply to {}: {}", peer_addr, e);
+                        }
+                    }
+                    if !envelope.ack_requested {
+                        continue;
+                    }
+
+                    let mut ack = Envelope::new(Message::Ack {
+                        message_id: envelope.message_id,
+                    });
+                    if let Err(e) = Self::send_envelope(stream.as_mut(), &mut ack, DEFAULT_COMPRESSION_THRESHOLD).await {
+                        debug!("Could not send ack to {}: {}", peer_addr, e);
+                    }
+                }
+                Err(e) if matches!(
+                    e.downcast_ref(),
+                    Some(TransportError::ReadTimeout(_) | TransportError::MessageTooLarge { .. })
+                ) =>
+                {
+                    warn!("Dropping connection from {}: {}", peer_addr, e);
+                    break;
                 }
                 Err(e) => {
                     // Check if it's EOF/disconnection
-                    if e.to_string().contains("unexpected end") || 
+                    if e.to_string().contains("unexpected end") ||
+                       e.to_string().contains("early eof") ||
                        e.to_string().contains("connection") {
                         debug!("Connection closed by {}", peer_addr);
                         break;
@@ -92,17 +655,317 @@ impl Transport {
 
 impl Default for Transport {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_COMPRESSION_THRESHOLD)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn test_transport_new() {
-        let transport = Transport::new();
+        let transport = Transport::new(DEFAULT_COMPRESSION_THRESHOLD);
         assert!(transport.listener.is_none());
+        assert_eq!(transport.compression_threshold_bytes, 4096);
+    }
+
+    fn completed_message(stdout: String) -> Message {
+        Message::TaskCompleted(crate::protocol::TaskResult {
+            task_id: "task-1".to_string(),
+            worker_id: "worker-1".to_string(),
+            status: crate::protocol::TaskStatus::Completed,
+            stdout,
+            stderr: String::new(),
+            exit_code: Some(0),
+            duration_ms: 10,
+            completed_at: 0,
+            resource_usage: None,
+        })
+    }
+
+    #[test]
+    fn test_small_message_not_compressed() {
+        let envelope = Envelope::new(completed_message("hello".to_string()));
+        let frame = Transport::encode_frame(&envelope, DEFAULT_COMPRESSION_THRESHOLD).unwrap();
+        assert_eq!(frame[0], FRAME_RAW);
+
+        let decoded = Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        assert_eq!(decoded.message_id, envelope.message_id);
+    }
+
+    #[test]
+    fn test_large_message_compressed_round_trip() {
+        let stdout = "line of task output\n".repeat(10_000);
+        let envelope = Envelope::new(completed_message(stdout.clone()));
+        let frame = Transport::encode_frame(&envelope, DEFAULT_COMPRESSION_THRESHOLD).unwrap();
+        assert_eq!(frame[0], FRAME_ZSTD);
+        assert!(frame.len() < stdout.len());
+
+        match Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES).unwrap().message {
+            Message::TaskCompleted(result) => assert_eq!(result.stdout, stdout),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compressed_frame_inflating_past_limit_is_rejected() {
+        // 16 MB of zeros compresses to a few KB, well under the frame limit
+        let mut frame = vec![FRAME_ZSTD];
+        frame.extend(zstd::encode_all(vec![0u8; 16 * 1024 * 1024].as_slice(), 0).unwrap());
+        let max_message_bytes = 1024 * 1024;
+        assert!(frame.len() < max_message_bytes);
+
+        let err = Transport::decode_frame(&frame, max_message_bytes).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(TransportError::MessageTooLarge { len }) if *len == max_message_bytes + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_with_ack() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(AtomicUsize::new(0));
+
+        let counter = received.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            Transport::handle_connection(stream, move |_msg| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Ok(None) })
+            })
+            .await
+            .unwrap();
+        });
+
+        let message = Message::CancelTask {
+            task_id: "task-1".to_string(),
+        };
+        Transport::default()
+            .send_message_with_ack(addr, &message)
+            .await
+            .unwrap();
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_receives_reply_on_same_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            Transport::handle_connection(stream, |msg| {
+                Box::pin(async move {
+                    match msg {
+                        Message::QueryTask { task_id } => Ok(Some(Message::TaskStatus {
+                            task_id,
+                            status: crate::protocol::TaskStatus::Running,
+                            progress: None,
+                        })),
+                        _ => Ok(None),
+                    }
+                })
+            })
+            .await
+            .unwrap();
+        });
+
+        let transport = Transport::default();
+        let query = Message::QueryTask {
+            task_id: "task-1".to_string(),
+        };
+        for _ in 0..2 {
+            match transport.request(addr, &query).await.unwrap() {
+                Message::TaskStatus { task_id, status, .. } => {
+                    assert_eq!(task_id, "task-1");
+                    assert_eq!(status, crate::protocol::TaskStatus::Running);
+                }
+                other => panic!("Unexpected reply: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secure_connection_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let security = Arc::new(SecurityManager::new("cluster-psk".to_string()));
+
+        let server_security = security.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let security = server_security.clone();
+                tokio::spawn(async move {
+                    let _ = Transport::handle_secure_connection(stream, &security, |msg| {
+                        Box::pin(async move { Ok(Some(msg)) })
+                    })
+                    .await;
+                });
+            }
+        });
+
+        // Separate pools force two connections, each with its own handshake
+        let query = Message::QueryTask {
+            task_id: "task-1".to_string(),
+        };
+        for _ in 0..2 {
+            let transport = Transport::default().with_security(security.clone());
+            for _ in 0..2 {
+                match transport.request(addr, &query).await.unwrap() {
+                    Message::QueryTask { task_id } => assert_eq!(task_id, "task-1"),
+                    other => panic!("Unexpected reply: {:?}", other),
+                }
+            }
+        }
+
+        // A client that skips the handshake only sees the server's key, never an answer
+        let reply = Transport::default().request(addr, &query).await.unwrap();
+        assert!(matches!(reply, Message::KeyExchange { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_with_ack_retries_then_fails() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        // Peer reads messages but never acknowledges them
+        let counter = attempts.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = Transport::recv_envelope(&mut stream).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    drop(stream);
+                });
+            }
+        });
+
+        let transport = Transport::default()
+            .with_ack_timeout(Duration::from_millis(100))
+            .with_max_retries(2);
+        let message = Message::CancelTask {
+            task_id: "task-1".to_string(),
+        };
+
+        assert!(transport.send_message_with_ack(addr, &message).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_partial_frame_hits_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Peer sends a length prefix promising 64 bytes, then stalls
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(&64u32.to_le_bytes()).await.unwrap();
+            stream.write_all(&[FRAME_RAW]).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let err = Transport::recv_envelope_within(&mut stream, Some(Duration::from_millis(100)), DEFAULT_MAX_MESSAGE_BYTES)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(TransportError::ReadTimeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_and_empty_frames_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(&u32::MAX.to_le_bytes()).await.unwrap();
+            stream.write_all(&0u32.to_le_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let err = Transport::recv_envelope_within(&mut stream, None, 1024).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(TransportError::MessageTooLarge { len }) if *len == u32::MAX as usize
+        ));
+
+        let err = Transport::recv_envelope_within(&mut stream, None, 1024).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(TransportError::InvalidFrame(_))));
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_sequence_is_reported_and_handled() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handled = Arc::new(AtomicUsize::new(0));
+        let gaps = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let (handled_clone, gaps_clone) = (handled.clone(), gaps.clone());
+        let server = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            let on_out_of_order = move |expected, received| gaps_clone.lock().push((expected, received));
+            Transport::handle_stream(
+                Box::new(stream),
+                peer_addr,
+                None,
+                None,
+                DEFAULT_MAX_MESSAGE_BYTES,
+                Some(&on_out_of_order),
+                move |_msg| {
+                    handled_clone.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async { Ok(None) })
+                },
+            )
+            .await
+        });
+
+        // Frames 1, 2 and 4: the jump from 2 to 4 is a gap
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        for sequence in [1, 2, 4] {
+            let mut envelope = Envelope::new(Message::CancelTask {
+                task_id: "task-1".to_string(),
+            });
+            envelope.sequence = sequence;
+            let frame = Transport::encode_frame(&envelope, DEFAULT_COMPRESSION_THRESHOLD).unwrap();
+            stream.write_all(&(frame.len() as u32).to_le_bytes()).await.unwrap();
+            stream.write_all(&frame).await.unwrap();
+        }
+        drop(stream);
+        server.await.unwrap().unwrap();
+
+        assert_eq!(handled.load(Ordering::SeqCst), 3);
+        assert_eq!(*gaps.lock(), vec![(3, 4)]);
+    }
+
+    #[tokio::test]
+    async fn test_pooled_connection_numbers_envelopes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let first = Transport::recv_envelope(&mut stream).await.unwrap();
+            let second = Transport::recv_envelope(&mut stream).await.unwrap();
+            (first.sequence, second.sequence)
+        });
+
+        let transport = Transport::default();
+        let message = Message::CancelTask {
+            task_id: "task-1".to_string(),
+        };
+        transport.send_message(addr, &message).await.unwrap();
+        transport.send_message(addr, &message).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), (1, 2));
     }
 }
@@ -0,0 +1,210 @@
+use super::{MessageStream, SequencedStream, TlsClient};
+use anyhow::Result;
+use futures::FutureExt;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Idle connections are closed after this long by default
+pub const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(60);
+
+struct PooledConnection {
+    stream: Box<dyn MessageStream>,
+    last_used: Instant,
+}
+
+/// Shared pool of outgoing connections, one idle stream per peer
+#[derive(Clone)]
+pub struct ConnectionPool {
+    connections: Arc<RwLock<HashMap<SocketAddr, PooledConnection>>>,
+    idle_ttl: Duration,
+}
+
+impl ConnectionPool {
+    /// Create a pool that closes connections idle for longer than `idle_ttl`
+    pub fn new(idle_ttl: Duration) -> Self {
+        Self {
+            connections: Arc::new(RwLock::new(HashMap::new())),
+            idle_ttl,
+        }
+    }
+
+    /// Take the pooled stream for `addr` if it is still usable, otherwise open a new one
+    ///
+    /// The stream is checked out of the pool; hand it back with `release` once the
+    /// exchange on it has completed cleanly.
+    pub async fn get_or_connect(&self, addr: SocketAddr, tls: Option<&TlsClient>) -> Result<Box<dyn MessageStream>> {
+        let pooled = self.connections.write().remove(&addr);
+        if let Some(conn) = pooled {
+            if conn.last_used.elapsed() < self.idle_ttl && Self::is_reusable(conn.stream.tcp()) {
+                debug!("Reusing pooled connection to {}", addr);
+                return Ok(conn.stream);
+            }
+            debug!("Discarding stale pooled connection to {}", addr);
+        }
+
+        let stream = TcpStream::connect(addr).await?;
+        match tls {
+            Some(tls) => Ok(Box::new(SequencedStream::new(tls.connect(stream).await?))),
+            None => Ok(Box::new(SequencedStream::new(stream))),
+        }
+    }
+
+    /// Return a stream to the pool for later reuse
+    pub fn release(&self, addr: SocketAddr, stream: Box<dyn MessageStream>) {
+        let conn = PooledConnection {
+            stream,
+            last_used: Instant::now(),
+        };
+        // Keep the already pooled stream if a concurrent sender returned one first
+        self.connections.write().entry(addr).or_insert(conn);
+    }
+
+    /// Number of idle connections currently held
+    pub fn idle_count(&self) -> usize {
+        self.connections.read().len()
+    }
+
+    /// Close connections that have been idle for longer than the TTL
+    pub fn sweep(&self) {
+        Self::sweep_map(&self.connections, self.idle_ttl);
+    }
+
+    /// Spawn a background task that periodically sweeps idle connections
+    ///
+    /// The task exits once every clone of the pool has been dropped.
+    pub fn start_sweeper(&self) -> JoinHandle<()> {
+        let connections: Weak<_> = Arc::downgrade(&self.connections);
+        let idle_ttl = self.idle_ttl;
+        let period = (idle_ttl / 2).max(Duration::from_millis(100));
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match connections.upgrade() {
+                    Some(connections) => Self::sweep_map(&connections, idle_ttl),
+                    None => break,
+                }
+            }
+        })
+    }
+
+    fn sweep_map(connections: &RwLock<HashMap<SocketAddr, PooledConnection>>, idle_ttl: Duration) {
+        connections.write().retain(|addr, conn| {
+            let keep = conn.last_used.elapsed() < idle_ttl;
+            if !keep {
+                debug!("Closing idle connection to {}", addr);
+            }
+            keep
+        });
+    }
+
+    /// Non-blocking peek: an idle connection must have nothing to read and not be closed
+    fn is_reusable(stream: &TcpStream) -> bool {
+        let mut buf = [0u8; 1];
+        stream.peek(&mut buf).now_or_never().is_none()
+    }
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Message;
+    use crate::transport::Transport;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    fn cancel_message() -> Message {
+        Message::CancelTask {
+            task_id: "task-1".to_string(),
+        }
+    }
+
+    /// Accepts connections, counting them, and reads messages until each peer disconnects
+    async fn counting_server() -> (SocketAddr, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let messages = Arc::new(AtomicUsize::new(0));
+
+        let (conn_counter, msg_counter) = (connections.clone(), messages.clone());
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                conn_counter.fetch_add(1, Ordering::SeqCst);
+                let msg_counter = msg_counter.clone();
+                tokio::spawn(async move {
+                    let _ = Transport::handle_connection(stream, move |_msg| {
+                        msg_counter.fetch_add(1, Ordering::SeqCst);
+                        Box::pin(async { Ok(None) })
+                    })
+                    .await;
+                });
+            }
+        });
+
+        (addr, connections, messages)
+    }
+
+    #[tokio::test]
+    async fn test_pool_reuses_connection() {
+        let (addr, connections, _) = counting_server().await;
+        let transport = Transport::default();
+
+        for _ in 0..3 {
+            transport.send_message_with_ack(addr, &cancel_message()).await.unwrap();
+        }
+        transport.send_message(addr, &cancel_message()).await.unwrap();
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert_eq!(transport.pool().idle_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_reconnects_after_peer_closes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Peer reads one message per connection, then hangs up
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = Transport::recv_envelope(&mut stream).await;
+            }
+        });
+
+        let transport = Transport::default();
+        transport.send_message(addr, &cancel_message()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let pooled = transport.pool().get_or_connect(addr, None).await.unwrap();
+        let mut buf = [0u8; 1];
+        assert!(pooled.tcp().peek(&mut buf).now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_closes_idle_connections() {
+        let (addr, _, _) = counting_server().await;
+        let pool = ConnectionPool::new(Duration::from_millis(20));
+        let transport = Transport::default().with_pool(pool.clone());
+
+        transport.send_message(addr, &cancel_message()).await.unwrap();
+        assert_eq!(pool.idle_count(), 1);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        pool.sweep();
+        assert_eq!(pool.idle_count(), 0);
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+use yasna::models::ObjectIdentifier;
+
+/// X.520 `commonName` attribute
+const OID_COMMON_NAME: &[u64] = &[2, 5, 4, 3];
+
+/// Client-side TLS settings used by `Transport` for outgoing connections
+#[derive(Clone)]
+pub struct TlsClient {
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+}
+
+impl TlsClient {
+    /// Trust the CA certificate(s) in `ca_path` and verify peers against `server_name`
+    pub fn from_ca_file(ca_path: &Path, server_name: &str) -> Result<Self> {
+        let config = ClientConfig::builder()
+            .with_root_certificates(load_roots(ca_path)?)
+            .with_no_client_auth();
+        Self::from_config(config, server_name)
+    }
+
+    /// Like `from_ca_file`, also presenting a client certificate for mTLS
+    pub fn from_ca_file_with_client_cert(
+        ca_path: &Path,
+        server_name: &str,
+        cert_path: &Path,
+        key_path: &Path,
+    ) -> Result<Self> {
+        let config = ClientConfig::builder()
+            .with_root_certificates(load_roots(ca_path)?)
+            .with_client_auth_cert(load_certs(cert_path)?, load_private_key(key_path)?)?;
+        Self::from_config(config, server_name)
+    }
+
+    fn from_config(config: ClientConfig, server_name: &str) -> Result<Self> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .with_context(|| format!("Invalid TLS server name: {}", server_name))?;
+
+        Ok(Self {
+            connector: TlsConnector::from(Arc::new(config)),
+            server_name,
+        })
+    }
+
+    /// Perform the client handshake over an established TCP connection
+    pub async fn connect(&self, stream: TcpStream) -> Result<client::TlsStream<TcpStream>> {
+        Ok(self.connector.connect(self.server_name.clone(), stream).await?)
+    }
+}
+
+/// Build a server-side acceptor from PEM-encoded certificate chain and private key files
+pub fn load_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = load_certs(cert_path)?;
+    let key = load_private_key(key_path)?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(acceptor_from_config(config))
+}
+
+/// Like `load_acceptor`, also requiring clients to present a certificate signed by `ca_path`
+pub fn load_mtls_acceptor(cert_path: &Path, key_path: &Path, ca_path: &Path) -> Result<TlsAcceptor> {
+    let certs = load_certs(cert_path)?;
+    let key = load_private_key(key_path)?;
+    let verifier = WebPkiClientVerifier::builder(Arc::new(load_roots(ca_path)?)).build()?;
+
+    let config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    Ok(acceptor_from_config(config))
+}
+
+fn acceptor_from_config(mut config: ServerConfig) -> TlsAcceptor {
+    // Unread session tickets would make pooled client connections look stale
+    config.send_tls13_tickets = 0;
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Subject common name of the client certificate presented on `stream`, if any
+pub fn peer_common_name(stream: &server::TlsStream<TcpStream>) -> Option<String> {
+    let certs = stream.get_ref().1.peer_certificates()?;
+    common_name(certs.first()?)
+}
+
+/// Subject common name of the first certificate in a PEM file
+pub fn load_common_name(path: &Path) -> Result<String> {
+    let certs = load_certs(path)?;
+    common_name(&certs[0]).with_context(|| format!("Certificate {} has no common name", path.display()))
+}
+
+/// Subject common name of a DER-encoded X.509 certificate
+fn common_name(cert: &[u8]) -> Option<String> {
+    let oid = ObjectIdentifier::from_slice(OID_COMMON_NAME);
+    let mut found = None;
+
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+    yasna::parse_der(cert, |reader| {
+        reader.read_sequence(|reader| {
+            reader.next().read_sequence(|tbs| {
+                tbs.read_optional(|r| r.read_tagged(yasna::Tag::context(0), |r| r.read_der()))?;
+                for _ in 0..4 {
+                    // serialNumber, signature, issuer, validity
+                    tbs.next().read_der()?;
+                }
+                tbs.next().read_sequence_of(|rdn| {
+                    rdn.read_set_of(|attribute| {
+                        attribute.read_sequence(|attribute| {
+                            let attribute_type = attribute.next().read_oid()?;
+                            let value = attribute.next().read_tagged_der()?;
+                            if attribute_type == oid {
+                                found = String::from_utf8(value.value().to_vec()).ok();
+                            }
+                            Ok(())
+                        })
+                    })
+                })?;
+                // subjectPublicKeyInfo and optional extensions
+                while tbs.read_optional(|r| r.read_der())?.is_some() {}
+                Ok(())
+            })?;
+            reader.next().read_der()?;
+            reader.next().read_der()?;
+            Ok(())
+        })
+    })
+    .ok()?;
+
+    found
+}
+
+fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert)?;
+    }
+    Ok(roots)
+}
+
+/// Generate a self-signed certificate and private key (both PEM) for development use
+pub fn generate_self_signed_cert(hostnames: Vec<String>) -> Result<(String, String)> {
+    let cert = rcgen::generate_simple_self_signed(hostnames)?;
+    Ok((cert.serialize_pem()?, cert.serialize_private_key_pem()))
+}
+
+pub(crate) fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open certificate {}", path.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", path.display());
+    }
+    Ok(certs)
+}
+
+pub(crate) fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open private key {}", path.display()))?,
+    );
+    rustls_pemfile::private_key(&mut reader)?
+        .with_context(|| format!("No private key found in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Message;
+    use crate::transport::Transport;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_tls_round_trip_with_self_signed_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_pem, key_pem) = generate_self_signed_cert(vec!["localhost".to_string()]).unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::write(&cert_path, cert_pem).unwrap();
+        std::fs::write(&key_path, key_pem).unwrap();
+
+        let acceptor = load_acceptor(&cert_path, &key_path).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(stream).await.unwrap();
+            Transport::recv_message(&mut stream).await.unwrap()
+        });
+
+        let client = TlsClient::from_ca_file(&cert_path, "localhost").unwrap();
+        let transport = Transport::default().with_tls(client);
+        transport
+            .send_message(addr, &Message::Heartbeat { worker_id: "w1".to_string(), sent_at: 0 })
+            .await
+            .unwrap();
+
+        match server.await.unwrap() {
+            Message::Heartbeat { worker_id, .. } => assert_eq!(worker_id, "w1"),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mtls_requires_client_cert_and_exposes_common_name() {
+        use crate::transport::ca::CertificateAuthority;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ca = CertificateAuthority::load_or_create(dir.path()).unwrap();
+        let (server_cert, server_key) = ca.issue_to_dir("dispatcher", &["localhost".to_string()], dir.path()).unwrap();
+        // Certificates from a reloaded CA chain to the same root
+        let ca = CertificateAuthority::load_or_create(dir.path()).unwrap();
+        let (worker_cert, worker_key) = ca.issue_to_dir("worker-1", &[], dir.path()).unwrap();
+
+        let acceptor = load_mtls_acceptor(&server_cert, &server_key, ca.cert_path()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut names = Vec::new();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                match acceptor.accept(stream).await {
+                    Ok(mut stream) => {
+                        names.push(peer_common_name(&stream));
+                        Transport::recv_message(&mut stream).await.unwrap();
+                    }
+                    Err(_) => names.push(None),
+                }
+            }
+            names
+        });
+
+        let message = Message::Heartbeat { worker_id: "worker-1".to_string(), sent_at: 0 };
+        let anonymous = Transport::default().with_tls(TlsClient::from_ca_file(ca.cert_path(), "localhost").unwrap());
+        // With TLS 1.3 the client may only see the rejection after its first write
+        let _ = anonymous.send_message(addr, &message).await;
+
+        let client =
+            TlsClient::from_ca_file_with_client_cert(ca.cert_path(), "localhost", &worker_cert, &worker_key).unwrap();
+        Transport::default().with_tls(client).send_message(addr, &message).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), [None, Some("worker-1".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_tls_rejects_untrusted_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server_cert, server_key) = generate_self_signed_cert(vec!["localhost".to_string()]).unwrap();
+        let (other_ca, _) = generate_self_signed_cert(vec!["localhost".to_string()]).unwrap();
+        std::fs::write(dir.path().join("cert.pem"), server_cert).unwrap();
+        std::fs::write(dir.path().join("key.pem"), server_key).unwrap();
+        std::fs::write(dir.path().join("ca.pem"), other_ca).unwrap();
+
+        let acceptor = load_acceptor(&dir.path().join("cert.pem"), &dir.path().join("key.pem")).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ = acceptor.accept(stream).await;
+        });
+
+        let client = TlsClient::from_ca_file(&dir.path().join("ca.pem"), "localhost").unwrap();
+        let transport = Transport::default().with_tls(client);
+        let message = Message::Heartbeat { worker_id: "w1".to_string(), sent_at: 0 };
+        assert!(transport.send_message(addr, &message).await.is_err());
+    }
+}
@@ -1,14 +1,36 @@
+use crate::protocol::Message;
+use crate::transport::{tls, Envelope, MessageHandler, Transport, TransportBackend, DEFAULT_COMPRESSION_THRESHOLD, DEFAULT_MAX_MESSAGE_BYTES};
 use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use quinn::rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use quinn::rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use quinn::rustls::{DigitallySignedStruct, SignatureScheme};
 use quinn::{Endpoint, Connection, RecvStream, SendStream};
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::net::UdpSocket;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tracing::{debug, error, warn};
 
 /// QUIC-based transport for faster, more efficient networking
-#[allow(dead_code)]
+///
+/// Every message travels on its own stream of a cached per-worker connection, framed
+/// exactly as on the TCP `Transport`, so a slow message never holds up the next one.
+/// Fire-and-forget messages use unidirectional streams; anything awaiting a reply or
+/// an ack uses a bidirectional one.
 pub struct QuicTransport {
     endpoint: Option<Endpoint>,
     config: QuicConfig,
+    /// Open connections keyed by worker ID, or by address for peers not bound to a worker
+    connections: RwLock<HashMap<String, Connection>>,
+    /// Worker each address passed to `bind_worker` belongs to
+    worker_ids: RwLock<HashMap<SocketAddr, String>>,
 }
 
 #[derive(Clone, Debug)]
@@ -16,6 +38,22 @@ pub struct QuicConfig {
     pub local_addr: SocketAddr,
     pub idle_timeout_ms: u64,
     pub max_streams: u32,
+    /// How long `send_message_with_ack` waits for each acknowledgment
+    pub ack_timeout_ms: u64,
+    /// Retries of `send_message_with_ack` after the first attempt
+    pub max_retries: u32,
+    /// PEM certificate chain served to peers; a self-signed one is generated when unset
+    pub cert_path: Option<PathBuf>,
+    /// PEM private key matching `cert_path`
+    pub key_path: Option<PathBuf>,
+    /// PEM CA that peers' certificates must chain to
+    pub ca_path: Option<PathBuf>,
+    /// PEM certificates one of which peers must present exactly, e.g. a self-signed one
+    pub pinned_cert_path: Option<PathBuf>,
+    /// Accept any peer certificate; the connection is encrypted but the peer unauthenticated
+    pub insecure: bool,
+    /// Name peers' certificates are verified against
+    pub server_name: String,
 }
 
 impl Default for QuicConfig {
@@ -24,25 +62,95 @@ impl Default for QuicConfig {
             local_addr: "127.0.0.1:5555".parse().unwrap(),
             idle_timeout_ms: 30000,
             max_streams: 100,
+            ack_timeout_ms: 5000,
+            max_retries: 3,
+            cert_path: None,
+            key_path: None,
+            ca_path: None,
+            pinned_cert_path: None,
+            insecure: false,
+            server_name: "localhost".to_string(),
         }
     }
 }
 
 impl QuicTransport {
-    /// Create a new QUIC transport (simplified - cert generation would be in real implementation)
-    pub async fn new(config: QuicConfig, _is_server: bool) -> Result<Self> {
+    /// Create a new QUIC transport; only a server endpoint accepts incoming connections
+    ///
+    /// Outgoing connections verify peers against `ca_path`, else `pinned_cert_path`, else
+    /// accept anything when `insecure`; with none of them set the endpoint cannot connect.
+    pub async fn new(config: QuicConfig, is_server: bool) -> Result<Self> {
         // Bind UDP socket to local address
         let socket = UdpSocket::bind(config.local_addr)?;
         socket.set_nonblocking(true)?;
-        
-        let endpoint = Endpoint::new(Default::default(), None, socket, Arc::new(quinn::TokioRuntime))?;
+
+        let server_config = if is_server { Some(Self::server_config(&config)?) } else { None };
+        let mut endpoint = Endpoint::new(Default::default(), server_config, socket, Arc::new(quinn::TokioRuntime))?;
+        if let Some(client_config) = Self::client_config(&config)? {
+            endpoint.set_default_client_config(client_config);
+        }
 
         Ok(Self {
             endpoint: Some(endpoint),
             config,
+            connections: RwLock::new(HashMap::new()),
+            worker_ids: RwLock::new(HashMap::new()),
         })
     }
 
+    fn transport_config(config: &QuicConfig) -> Result<Arc<quinn::TransportConfig>> {
+        let mut transport = quinn::TransportConfig::default();
+        transport.max_idle_timeout(Some(Duration::from_millis(config.idle_timeout_ms).try_into()?));
+        transport.max_concurrent_bidi_streams(config.max_streams.into());
+        Ok(Arc::new(transport))
+    }
+
+    fn server_config(config: &QuicConfig) -> Result<quinn::ServerConfig> {
+        let (certs, key) = match (&config.cert_path, &config.key_path) {
+            (Some(cert), Some(key)) => (tls::load_certs(cert)?, tls::load_private_key(key)?),
+            (None, None) => {
+                let cert = rcgen::generate_simple_self_signed(vec![config.server_name.clone()])?;
+                let key = PrivatePkcs8KeyDer::from(cert.serialize_private_key_der());
+                (vec![CertificateDer::from(cert.serialize_der()?)], key.into())
+            }
+            _ => anyhow::bail!("cert_path and key_path must be set together"),
+        };
+
+        let mut server_config = quinn::ServerConfig::with_single_cert(certs, key)?;
+        server_config.transport_config(Self::transport_config(config)?);
+        Ok(server_config)
+    }
+
+    fn client_config(config: &QuicConfig) -> Result<Option<quinn::ClientConfig>> {
+        let pinned = match (&config.ca_path, &config.pinned_cert_path) {
+            (Some(ca), _) => {
+                let mut roots = quinn::rustls::RootCertStore::empty();
+                for cert in tls::load_certs(ca)? {
+                    roots.add(cert)?;
+                }
+                let mut client_config = quinn::ClientConfig::with_root_certificates(Arc::new(roots))?;
+                client_config.transport_config(Self::transport_config(config)?);
+                return Ok(Some(client_config));
+            }
+            (None, Some(path)) => Some(tls::load_certs(path)?),
+            (None, None) if config.insecure => {
+                warn!("QUIC peer certificates are not verified (insecure mode)");
+                None
+            }
+            (None, None) => return Ok(None),
+        };
+
+        let provider = Arc::new(quinn::rustls::crypto::ring::default_provider());
+        let crypto = quinn::rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&quinn::rustls::version::TLS13])?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PeerCertVerifier { pinned, provider }))
+            .with_no_client_auth();
+        let mut client_config = quinn::ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto)?));
+        client_config.transport_config(Self::transport_config(config)?);
+        Ok(Some(client_config))
+    }
+
     /// Accept incoming connections (server-side)
     pub async fn accept(&self) -> Result<Option<Connection>> {
         if let Some(endpoint) = &self.endpoint {
@@ -57,7 +165,13 @@ impl QuicTransport {
     /// Connect to a remote endpoint (client-side)
     pub async fn connect(&self, remote_addr: SocketAddr, hostname: &str) -> Result<Connection> {
         if let Some(endpoint) = &self.endpoint {
-            let connection = endpoint.connect(remote_addr, hostname)?.await?;
+            let connecting = endpoint.connect(remote_addr, hostname).map_err(|e| match e {
+                quinn::ConnectError::NoDefaultClientConfig => {
+                    anyhow::anyhow!("QUIC peers cannot be verified: set ca_path or pinned_cert_path, or enable insecure mode")
+                }
+                e => e.into(),
+            })?;
+            let connection = connecting.await?;
             return Ok(connection);
         }
         Err(anyhow::anyhow!("Endpoint not initialized"))
@@ -89,6 +203,224 @@ impl QuicTransport {
         let (send, recv) = connection.open_bi().await?;
         Ok((send, recv))
     }
+
+    /// Open a stream for a single message the peer does not answer
+    pub async fn open_unidirectional_send(conn: &Connection) -> Result<SendStream> {
+        Ok(conn.open_uni().await?)
+    }
+
+    /// Key of `peer`'s connection: its worker ID once bound, else its address
+    fn connection_key(&self, peer: SocketAddr) -> String {
+        self.worker_ids.read().get(&peer).cloned().unwrap_or_else(|| peer.to_string())
+    }
+
+    /// Cached connection to `peer`, reconnecting once it has closed
+    async fn connection(&self, peer: SocketAddr) -> Result<Connection> {
+        let key = self.connection_key(peer);
+        if let Some(connection) = self.connections.read().get(&key) {
+            if connection.close_reason().is_none() {
+                return Ok(connection.clone());
+            }
+        }
+
+        let connection = self.connect(peer, &self.config.server_name).await?;
+        debug!("Opened QUIC connection to {} ({})", key, peer);
+
+        // Concurrent first messages to a peer may each connect; keep whichever got cached first
+        let mut connections = self.connections.write();
+        match connections.get(&key) {
+            Some(existing) if existing.close_reason().is_none() => {
+                connection.close(0u32.into(), b"duplicate");
+                Ok(existing.clone())
+            }
+            _ => {
+                // Connections of peers that went away are dropped as new ones are made
+                connections.retain(|_, cached| cached.close_reason().is_none());
+                connections.insert(key, connection.clone());
+                Ok(connection)
+            }
+        }
+    }
+
+    /// Send an envelope on a new stream, returning the stream's receiving half
+    async fn open_with(&self, peer: SocketAddr, envelope: &Envelope) -> Result<RecvStream> {
+        let (mut send, recv) = self.connection(peer).await?.open_bi().await?;
+        let frame = Transport::encode_frame(envelope, DEFAULT_COMPRESSION_THRESHOLD)?;
+        Transport::write_frame(&mut send, &frame).await?;
+        send.finish()?;
+        Ok(recv)
+    }
+
+    /// Send an envelope and read back the first frame the peer answers with
+    async fn round_trip(&self, peer: SocketAddr, envelope: &Envelope) -> Result<Envelope> {
+        let mut recv = self.open_with(peer, envelope).await?;
+        let frame = Transport::read_frame(&mut recv, None, DEFAULT_MAX_MESSAGE_BYTES).await?;
+        Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES)
+    }
+
+    /// Pass the message on an incoming unidirectional stream to the handler; there is no way to reply
+    async fn handle_uni_stream(mut recv: RecvStream, peer_addr: SocketAddr, handler: &MessageHandler) -> Result<()> {
+        let frame = Transport::read_frame(&mut recv, None, DEFAULT_MAX_MESSAGE_BYTES).await?;
+        let envelope = Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES)?;
+        if handler(envelope.message, peer_addr).await?.is_some() {
+            debug!("Dropping reply to one-way message from {}", peer_addr);
+        }
+        Ok(())
+    }
+
+    /// Answer the message on one incoming stream: the handler's reply, then an ack if requested
+    async fn handle_stream(mut send: SendStream, mut recv: RecvStream, peer_addr: SocketAddr, handler: &MessageHandler) -> Result<()> {
+        let frame = Transport::read_frame(&mut recv, None, DEFAULT_MAX_MESSAGE_BYTES).await?;
+        let envelope = Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES)?;
+
+        if let Some(reply) = handler(envelope.message, peer_addr).await? {
+            let frame = Transport::encode_frame(&Envelope::new(reply), DEFAULT_COMPRESSION_THRESHOLD)?;
+            Transport::write_frame(&mut send, &frame).await?;
+        }
+        if envelope.ack_requested {
+            let ack = Envelope::new(Message::Ack {
+                message_id: envelope.message_id,
+            });
+            let frame = Transport::encode_frame(&ack, DEFAULT_COMPRESSION_THRESHOLD)?;
+            Transport::write_frame(&mut send, &frame).await?;
+        }
+        send.finish()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransportBackend for QuicTransport {
+    async fn send_message(&self, peer: SocketAddr, message: &Message) -> Result<()> {
+        let mut send = Self::open_unidirectional_send(&self.connection(peer).await?).await?;
+        let frame = Transport::encode_frame(&Envelope::new(message.clone()), DEFAULT_COMPRESSION_THRESHOLD)?;
+        Transport::write_frame(&mut send, &frame).await?;
+        send.finish()?;
+        debug!("Sent message to {} over QUIC", peer);
+        Ok(())
+    }
+
+    async fn send_message_with_ack(&self, peer: SocketAddr, message: &Message) -> Result<()> {
+        let envelope = Envelope::requesting_ack(message.clone());
+        let ack_timeout = Duration::from_millis(self.config.ack_timeout_ms);
+        let mut last_error = None;
+
+        for attempt in 0..=self.config.max_retries {
+            match tokio::time::timeout(ack_timeout, self.round_trip(peer, &envelope)).await {
+                Ok(Ok(reply)) => match reply.message {
+                    Message::Ack { message_id } if message_id == envelope.message_id => return Ok(()),
+                    other => last_error = Some(anyhow::anyhow!("Expected ack for {}, got {:?}", envelope.message_id, other)),
+                },
+                Ok(Err(e)) => last_error = Some(e),
+                Err(_) => last_error = Some(anyhow::anyhow!("Timed out after {:?} waiting for ack", ack_timeout)),
+            }
+
+            warn!(
+                "Delivery attempt {}/{} of message {} to {} failed: {}",
+                attempt + 1,
+                self.config.max_retries + 1,
+                envelope.message_id,
+                peer,
+                last_error.as_ref().unwrap()
+            );
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Message delivery failed")))
+    }
+
+    async fn request_with_timeout(&self, peer: SocketAddr, message: &Message, timeout: Option<Duration>) -> Result<Message> {
+        let exchange = async { Ok(self.round_trip(peer, &Envelope::new(message.clone())).await?.message) };
+
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, exchange)
+                .await
+                .map_err(|_| anyhow::anyhow!("Timed out after {:?} waiting for reply", limit))?,
+            None => exchange.await,
+        }
+    }
+
+    async fn recv_message_from_stream(&self, stream: &mut (dyn AsyncRead + Unpin + Send)) -> Result<Message> {
+        let frame = Transport::read_frame(stream, None, DEFAULT_MAX_MESSAGE_BYTES).await?;
+        Ok(Transport::decode_frame(&frame, DEFAULT_MAX_MESSAGE_BYTES)?.message)
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.endpoint.as_ref().and_then(|endpoint| endpoint.local_addr().ok())
+    }
+
+    /// A worker that comes back at a new address gets a new connection; the old one is closed
+    fn bind_worker(&self, worker_id: &str, peer: SocketAddr) {
+        let mut worker_ids = self.worker_ids.write();
+        if worker_ids.get(&peer).is_some_and(|id| id == worker_id) {
+            return;
+        }
+        worker_ids.retain(|_, id| id != worker_id);
+        worker_ids.insert(peer, worker_id.to_string());
+        drop(worker_ids);
+
+        let mut connections = self.connections.write();
+        if let Some(moved) = connections.remove(worker_id) {
+            moved.close(0u32.into(), b"worker moved");
+        }
+        // A connection made before the worker was bound now belongs to it
+        if let Some(connection) = connections.remove(&peer.to_string()) {
+            connections.insert(worker_id.to_string(), connection);
+        }
+    }
+
+    fn forget_worker(&self, worker_id: &str) {
+        self.worker_ids.write().retain(|_, id| id != worker_id);
+        if let Some(connection) = self.connections.write().remove(worker_id) {
+            connection.close(0u32.into(), b"worker left");
+        }
+    }
+
+    /// Each stream is handled in its own task, so messages on one connection never wait on each other
+    async fn serve(&self, handler: MessageHandler) -> Result<()> {
+        let endpoint = self
+            .endpoint
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Endpoint not initialized"))?;
+
+        while let Some(incoming) = endpoint.accept().await {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let connection = match incoming.await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        debug!("QUIC handshake failed: {}", e);
+                        return;
+                    }
+                };
+                let peer_addr = connection.remote_address();
+                debug!("New QUIC connection from {}", peer_addr);
+
+                loop {
+                    let handler = handler.clone();
+                    tokio::select! {
+                        stream = connection.accept_bi() => {
+                            let Ok((send, recv)) = stream else { break };
+                            tokio::spawn(async move {
+                                if let Err(e) = Self::handle_stream(send, recv, peer_addr, &handler).await {
+                                    error!("Error handling message from {}: {}", peer_addr, e);
+                                }
+                            });
+                        }
+                        stream = connection.accept_uni() => {
+                            let Ok(recv) = stream else { break };
+                            tokio::spawn(async move {
+                                if let Err(e) = Self::handle_uni_stream(recv, peer_addr, &handler).await {
+                                    error!("Error handling message from {}: {}", peer_addr, e);
+                                }
+                            });
+                        }
+                    }
+                }
+                debug!("QUIC connection from {} closed", peer_addr);
+            });
+        }
+        Ok(())
+    }
 }
 
 impl Drop for QuicTransport {
@@ -102,6 +434,55 @@ impl Drop for QuicTransport {
     }
 }
 
+/// Verifies QUIC peers without a CA: against pinned certificates, or not at all in insecure mode
+///
+/// Handshake signatures are checked either way, so the peer must hold the certificate's key.
+#[derive(Debug)]
+struct PeerCertVerifier {
+    pinned: Option<Vec<CertificateDer<'static>>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PeerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, quinn::rustls::Error> {
+        match &self.pinned {
+            Some(pinned) if !pinned.iter().any(|cert| cert.as_ref() == end_entity.as_ref()) => Err(
+                quinn::rustls::Error::InvalidCertificate(quinn::rustls::CertificateError::ApplicationVerificationFailure),
+            ),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, quinn::rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, quinn::rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,5 +511,175 @@ mod tests {
         let result = QuicTransport::new(config, false).await;
         assert!(result.is_ok());
     }
-}
 
+    /// Writes a self-signed certificate for localhost and its key into `dir`
+    fn write_cert(dir: &std::path::Path, name: &str) -> (PathBuf, PathBuf) {
+        let (cert, key) = tls::generate_self_signed_cert(vec!["localhost".to_string()]).unwrap();
+        let (cert_path, key_path) = (dir.join(format!("{}.pem", name)), dir.join(format!("{}-key.pem", name)));
+        std::fs::write(&cert_path, cert).unwrap();
+        std::fs::write(&key_path, key).unwrap();
+        (cert_path, key_path)
+    }
+
+    #[tokio::test]
+    async fn test_quic_backend_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert_path, key_path) = write_cert(dir.path(), "server");
+        let local = QuicConfig {
+            local_addr: "127.0.0.1:0".parse().unwrap(),
+            ..Default::default()
+        };
+        let server_config = QuicConfig {
+            cert_path: Some(cert_path.clone()),
+            key_path: Some(key_path),
+            ..local.clone()
+        };
+        let server = Arc::new(QuicTransport::new(server_config, true).await.unwrap());
+        let addr = server.local_addr().unwrap();
+
+        let received = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = received.clone();
+        let handler: MessageHandler = Arc::new(move |msg, _peer| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(async move {
+                Ok(match msg {
+                    Message::QueryWorkerMetrics { worker_id } => Some(Message::CancelTask { task_id: worker_id }),
+                    _ => None,
+                })
+            })
+        });
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(handler).await });
+
+        let client_config = QuicConfig {
+            pinned_cert_path: Some(cert_path),
+            ..local
+        };
+        let client: Arc<dyn TransportBackend> = Arc::new(QuicTransport::new(client_config, false).await.unwrap());
+        let cancel = Message::CancelTask {
+            task_id: "task-1".to_string(),
+        };
+        client.send_message_with_ack(addr, &cancel).await.unwrap();
+
+        let query = Message::QueryWorkerMetrics {
+            worker_id: "worker-1".to_string(),
+        };
+        match client.request_with_timeout(addr, &query, Some(Duration::from_secs(5))).await.unwrap() {
+            Message::CancelTask { task_id } => assert_eq!(task_id, "worker-1"),
+            other => panic!("unexpected reply {:?}", other),
+        }
+        assert_eq!(received.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_quic_rejects_unpinned_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (other_cert, _) = write_cert(dir.path(), "other");
+        let local = QuicConfig {
+            local_addr: "127.0.0.1:0".parse().unwrap(),
+            ..Default::default()
+        };
+        // The server presents a freshly generated certificate, not the pinned one
+        let server = Arc::new(QuicTransport::new(local.clone(), true).await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let handler: MessageHandler = Arc::new(|_msg, _peer| Box::pin(async { Ok(None) }));
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(handler).await });
+
+        let query = Message::QueryWorkerMetrics {
+            worker_id: "worker-1".to_string(),
+        };
+        let pinned = QuicTransport::new(
+            QuicConfig {
+                pinned_cert_path: Some(other_cert),
+                ..local.clone()
+            },
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(pinned.request_with_timeout(addr, &query, Some(Duration::from_secs(5))).await.is_err());
+
+        let unverified = QuicTransport::new(local.clone(), false).await.unwrap();
+        let err = unverified.request_with_timeout(addr, &query, Some(Duration::from_secs(5))).await.unwrap_err();
+        assert!(err.to_string().contains("cannot be verified"));
+
+        let insecure = QuicTransport::new(QuicConfig { insecure: true, ..local }, false).await.unwrap();
+        insecure.send_message(addr, &query).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quic_streams_are_handled_concurrently() {
+        let config = QuicConfig {
+            local_addr: "127.0.0.1:0".parse().unwrap(),
+            insecure: true,
+            ..Default::default()
+        };
+        let server = Arc::new(QuicTransport::new(config.clone(), true).await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let handler: MessageHandler = Arc::new(|_msg, _peer| {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                Ok(None)
+            })
+        });
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(handler).await });
+
+        let client = QuicTransport::new(config, false).await.unwrap();
+        let started = std::time::Instant::now();
+        let sends = (0..5).map(|i| {
+            let message = Message::CancelTask {
+                task_id: format!("task-{}", i),
+            };
+            let client = &client;
+            async move { client.send_message_with_ack(addr, &message).await }
+        });
+        for result in futures::future::join_all(sends).await {
+            result.unwrap();
+        }
+
+        // Handled one after another these would take at least 1.5s
+        assert!(started.elapsed() < Duration::from_millis(1200));
+        assert_eq!(client.connections.read().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_kept_per_worker() {
+        let config = QuicConfig {
+            local_addr: "127.0.0.1:0".parse().unwrap(),
+            insecure: true,
+            ..Default::default()
+        };
+        let handler: MessageHandler = Arc::new(|_msg, _peer| Box::pin(async { Ok(None) }));
+        let mut addrs = Vec::new();
+        let mut servers = Vec::new();
+        for _ in 0..2 {
+            let server = Arc::new(QuicTransport::new(config.clone(), true).await.unwrap());
+            addrs.push(server.local_addr().unwrap());
+            let (serving, handler) = (server.clone(), handler.clone());
+            tokio::spawn(async move { serving.serve(handler).await });
+            servers.push(server);
+        }
+
+        let dispatcher = QuicTransport::new(config, false).await.unwrap();
+        let cancel = Message::CancelTask {
+            task_id: "task-1".to_string(),
+        };
+        dispatcher.send_message_with_ack(addrs[0], &cancel).await.unwrap();
+        dispatcher.bind_worker("worker-1", addrs[0]);
+        let first = dispatcher.connections.read()["worker-1"].clone();
+
+        // The worker restarts on another port: its old connection is closed and replaced
+        dispatcher.bind_worker("worker-1", addrs[1]);
+        assert!(first.close_reason().is_some());
+        dispatcher.send_message_with_ack(addrs[1], &cancel).await.unwrap();
+        let keys: Vec<String> = dispatcher.connections.read().keys().cloned().collect();
+        assert_eq!(keys, vec!["worker-1".to_string()]);
+        assert_eq!(dispatcher.connections.read()["worker-1"].remote_address(), addrs[1]);
+
+        dispatcher.forget_worker("worker-1");
+        assert!(dispatcher.connections.read().is_empty());
+        assert!(dispatcher.worker_ids.read().is_empty());
+    }
+}
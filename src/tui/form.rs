@@ -0,0 +1,167 @@
+use crate::protocol::Task;
+use crossterm::event::KeyCode;
+
+// Parts of the submit form that take focus, top to bottom
+// Bagian formulir kirim yang dapat difokuskan, dari atas ke bawah
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitField {
+    Command,
+    Timeout,
+    Tags,
+    Button,
+}
+
+impl SubmitField {
+    const ALL: [SubmitField; 4] = [Self::Command, Self::Timeout, Self::Tags, Self::Button];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|field| *field == self).unwrap_or(0)
+    }
+
+    fn next(self) -> Self {
+        Self::ALL[(self.index() + 1).min(Self::ALL.len() - 1)]
+    }
+
+    fn prev(self) -> Self {
+        Self::ALL[self.index().saturating_sub(1)]
+    }
+}
+
+// What a key press in the form asks for
+// Apa yang diminta oleh penekanan tombol di formulir
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormAction {
+    None,
+    Submit,
+    Ignored,
+}
+
+// Text typed into the Submit tab, one buffer per field
+// Teks yang diketik di tab Submit, satu buffer per kolom
+#[derive(Debug, Clone)]
+pub struct SubmitForm {
+    pub command: String,
+    pub timeout: String,
+    pub tags: String,
+    pub focus: SubmitField,
+}
+
+impl SubmitForm {
+    pub fn new() -> Self {
+        Self {
+            command: String::new(),
+            timeout: String::new(),
+            tags: String::new(),
+            focus: SubmitField::Command,
+        }
+    }
+
+    // Edit the focused field or move the focus
+    // Ubah kolom yang difokuskan atau pindahkan fokus
+    pub fn handle_key(&mut self, key: KeyCode) -> FormAction {
+        match key {
+            KeyCode::Up => self.focus = self.focus.prev(),
+            KeyCode::Down => self.focus = self.focus.next(),
+            KeyCode::Enter if self.focus == SubmitField::Button => return FormAction::Submit,
+            KeyCode::Enter => self.focus = self.focus.next(),
+            KeyCode::Backspace => {
+                if let Some(buffer) = self.focused_buffer() {
+                    buffer.pop();
+                }
+            }
+            KeyCode::Char(c) => match self.focus {
+                SubmitField::Timeout if !c.is_ascii_digit() => {}
+                _ => match self.focused_buffer() {
+                    Some(buffer) => buffer.push(c),
+                    None => return FormAction::Ignored,
+                },
+            },
+            _ => return FormAction::Ignored,
+        }
+        FormAction::None
+    }
+
+    fn focused_buffer(&mut self) -> Option<&mut String> {
+        match self.focus {
+            SubmitField::Command => Some(&mut self.command),
+            SubmitField::Timeout => Some(&mut self.timeout),
+            SubmitField::Tags => Some(&mut self.tags),
+            SubmitField::Button => None,
+        }
+    }
+
+    // Task described by the form; an empty timeout keeps the default
+    // Tugas yang dijelaskan formulir; timeout kosong memakai nilai bawaan
+    pub fn build_task(&self) -> Result<Task, String> {
+        let command = self.command.trim();
+        if command.is_empty() {
+            return Err("Command must not be empty".to_string());
+        }
+
+        let mut task = Task::new(command.to_string());
+        if !self.timeout.is_empty() {
+            task.timeout = match self.timeout.parse() {
+                Ok(0) | Err(_) => return Err("Timeout must be a whole number of seconds above zero".to_string()),
+                Ok(timeout) => timeout,
+            };
+        }
+        task.required_tags = self
+            .tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(task)
+    }
+}
+
+impl Default for SubmitForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(form: &mut SubmitForm, text: &str) {
+        for c in text.chars() {
+            form.handle_key(KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_form_builds_task() {
+        let mut form = SubmitForm::new();
+        type_text(&mut form, "cargo build");
+        form.handle_key(KeyCode::Enter);
+        type_text(&mut form, "9x0");
+        form.handle_key(KeyCode::Down);
+        type_text(&mut form, "rust, linux ,");
+        assert_eq!(form.handle_key(KeyCode::Enter), FormAction::None);
+        assert_eq!(form.handle_key(KeyCode::Enter), FormAction::Submit);
+
+        let task = form.build_task().unwrap();
+        assert_eq!(task.command, "cargo build");
+        assert_eq!(task.timeout, 90);
+        assert_eq!(task.required_tags, vec!["rust", "linux"]);
+    }
+
+    #[test]
+    fn test_form_validation() {
+        let mut form = SubmitForm::new();
+        assert!(form.build_task().is_err());
+
+        type_text(&mut form, "ls");
+        form.handle_key(KeyCode::Down);
+        type_text(&mut form, "0");
+        assert!(form.build_task().is_err());
+
+        form.handle_key(KeyCode::Backspace);
+        let task = form.build_task().unwrap();
+        assert_eq!(task.timeout, Task::new("ls".to_string()).timeout);
+        assert!(task.required_tags.is_empty());
+    }
+}
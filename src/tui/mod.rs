@@ -1,13 +1,57 @@
-use crate::protocol::WorkerInfo;
+pub mod form;
+
+use crate::metrics::Metrics;
+use crate::persistence::{StoreBackend, StoredTask};
+use crate::protocol::{Task, TaskStatus, WorkerInfo, WorkerTaskStats};
+use crate::scheduler::{Scheduler, WorkerMetrics};
+use crate::state::events::DispatcherEvent;
+use crate::state::DispatcherState;
+use form::{FormAction, SubmitField, SubmitForm};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::{Stream, StreamExt};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Line,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Sparkline, Table, Tabs, Wrap},
     Frame, Terminal,
 };
+use std::collections::{HashMap, VecDeque};
 use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+// Rows moved by Page Up / Page Down
+// Jumlah baris yang dilewati oleh Page Up / Page Down
+pub const PAGE_ROWS: usize = 10;
+
+// How long the banner after a submission stays visible
+// Berapa lama banner setelah pengiriman tetap terlihat
+pub const NOTIFICATION_DURATION: Duration = Duration::from_secs(3);
+
+// Shortcuts listed by the `?` overlay, as (keys, action)
+// Pintasan yang ditampilkan oleh overlay `?`, sebagai (tombol, aksi)
+const SHORTCUTS: [(&str, &str); 11] = [
+    ("Tab / Shift+Tab", "Switch tabs"),
+    ("← / →", "Switch tabs"),
+    ("↑ / ↓", "Scroll lists"),
+    ("PgUp / PgDn", "Scroll by 10 rows"),
+    ("Enter", "Open worker or task detail"),
+    ("q / Esc", "Close detail"),
+    ("s", "Go to the Submit tab"),
+    ("/", "Search logs (Enter keeps, Esc clears)"),
+    ("Enter on [ Submit ]", "Submit the task"),
+    ("?", "Toggle this help"),
+    ("q / Ctrl+C", "Quit"),
+];
+
+// Seconds of task throughput kept for the sparkline
+// Jumlah detik throughput tugas yang disimpan untuk sparkline
+pub const THROUGHPUT_WINDOW: usize = 60;
 
 // Terminal UI tab types
 // Jenis tab UI terminal
@@ -15,6 +59,7 @@ pub enum Tab {
     Workers,
     Tasks,
     Logs,
+    Submit,
 }
 
 pub struct Dashboard {
@@ -23,19 +68,120 @@ pub struct Dashboard {
     logs: Vec<String>,
     workers_display: Vec<String>,
     tasks_display: Vec<String>,
+    // Data behind the worker detail, refreshed by the update_* calls
+    // Data di balik detail worker, diperbarui oleh pemanggilan update_*
+    workers: Vec<WorkerInfo>,
+    worker_metrics: HashMap<String, WorkerMetrics>,
+    worker_stats: HashMap<String, WorkerTaskStats>,
+    worker_tasks: HashMap<String, Vec<String>>,
+    // Selected row of each list, kept while switching tabs
+    // Baris terpilih setiap daftar, tetap tersimpan saat berpindah tab
+    workers_state: ListState,
+    tasks_state: ListState,
+    // Recently finished tasks listed under the task totals, as (task ID, row)
+    // Tugas yang baru selesai di bawah total tugas, sebagai (ID tugas, baris)
+    recent_tasks: Vec<(String, String)>,
+    store: Option<Arc<dyn StoreBackend + Send + Sync>>,
+    // Task opened with Enter, shown over the current tab
+    // Tugas yang dibuka dengan Enter, ditampilkan di atas tab aktif
+    selected_task_detail: Option<StoredTask>,
+    show_task_detail: bool,
+    detail_scroll: usize,
+    // Worker opened with Enter; its detail is redrawn from the latest data every frame
+    // Worker yang dibuka dengan Enter; detailnya digambar ulang dari data terbaru setiap frame
+    selected_worker: Option<String>,
+    // Submit tab: the form, where submitted tasks go, and the last outcome
+    // Tab Submit: formulir, tujuan tugas yang dikirim, dan hasil terakhir
+    submit_form: SubmitForm,
+    submit_tx: Option<mpsc::Sender<Task>>,
+    notification: Option<Notification>,
+    // Tasks completed in each of the last seconds, oldest first, and in the current one
+    // Tugas selesai pada setiap detik terakhir, terlama dulu, dan pada detik ini
+    throughput: VecDeque<u64>,
+    completed_this_second: u64,
+    show_help: bool,
+    // Scheduler paused by an operator; shown in the header
+    // Penjadwal dijeda oleh operator; ditampilkan di header
+    paused: bool,
+    // Logs tab search: the text to match and whether it is being typed
+    // Pencarian tab Logs: teks yang dicocokkan dan apakah sedang diketik
+    filter_string: String,
+    editing_filter: bool,
+    // Set by `q` outside detail views and text fields; `Ui::run` then returns
+    // Diatur oleh `q` di luar tampilan detail dan kolom teks; `Ui::run` lalu berhenti
+    quit: bool,
+}
+
+// Dashboard updates pushed by the dispatcher
+// Pembaruan dasbor yang dikirim oleh dispatcher
+//
+// A watch channel keeps only the newest event, so a burst may skip some; every
+// variant carries the full state it updates, except `TaskCompleted` and `LogLine`.
+// Kanal watch hanya menyimpan event terbaru, jadi sebagian bisa terlewat saat ramai.
+#[derive(Debug, Clone)]
+pub enum TuiEvent {
+    WorkersUpdated(Vec<WorkerInfo>),
+    TaskCompleted(String),
+    LogLine(String),
+    StatsUpdated { queue: usize, completed: usize },
+    SchedulerPaused(bool),
+}
+
+// Banner shown under the submit form
+// Banner yang ditampilkan di bawah formulir kirim
+struct Notification {
+    message: String,
+    is_error: bool,
+    shown_at: Instant,
 }
 
 impl Dashboard {
     pub fn new() -> Self {
         Self {
             current_tab: 0,
-            tabs: vec!["Workers", "Tasks", "Logs"],
+            tabs: vec!["Workers", "Tasks", "Logs", "Submit"],
             logs: Vec::new(),
             workers_display: Vec::new(),
             tasks_display: Vec::new(),
+            workers: Vec::new(),
+            worker_metrics: HashMap::new(),
+            worker_stats: HashMap::new(),
+            worker_tasks: HashMap::new(),
+            workers_state: ListState::default(),
+            tasks_state: ListState::default(),
+            recent_tasks: Vec::new(),
+            store: None,
+            selected_task_detail: None,
+            show_task_detail: false,
+            detail_scroll: 0,
+            selected_worker: None,
+            submit_form: SubmitForm::new(),
+            submit_tx: None,
+            notification: None,
+            throughput: VecDeque::with_capacity(THROUGHPUT_WINDOW),
+            completed_this_second: 0,
+            show_help: false,
+            paused: false,
+            filter_string: String::new(),
+            editing_filter: false,
+            quit: false,
         }
     }
 
+    // Send tasks from the Submit tab here; see `forward_submissions`
+    // Kirim tugas dari tab Submit ke sini; lihat `forward_submissions`
+    pub fn with_submit_channel(mut self, tx: mpsc::Sender<Task>) -> Self {
+        self.submit_tx = Some(tx);
+        self
+    }
+
+    // Load task output from this store when a task is opened
+    // Muat output tugas dari store ini saat sebuah tugas dibuka
+    pub fn with_store(mut self, store: Arc<dyn StoreBackend + Send + Sync>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     pub fn next_tab(&mut self) {
         self.current_tab = (self.current_tab + 1) % self.tabs.len();
     }
@@ -48,6 +194,248 @@ impl Dashboard {
         }
     }
 
+    // Move the cursor of the current tab's list; returns whether the key was used
+    // Gerakkan kursor daftar pada tab aktif; mengembalikan apakah tombol dipakai
+    pub async fn handle_key(&mut self, key: KeyCode) -> bool {
+        // Any key closes the help overlay
+        // Tombol apa pun menutup overlay bantuan
+        if self.show_help {
+            self.show_help = false;
+            return true;
+        }
+        if self.current_tab == 2 && self.handle_filter_key(key) {
+            return true;
+        }
+        let typing = self.current_tab == 3 && self.submit_form.focus != SubmitField::Button;
+        match key {
+            KeyCode::Char('?') if !typing => {
+                self.show_help = true;
+                return true;
+            }
+            KeyCode::Char('s') if !typing && !self.show_task_detail && self.selected_worker.is_none() => {
+                self.current_tab = 3;
+                return true;
+            }
+            _ => {}
+        }
+        if self.show_task_detail {
+            self.handle_detail_key(key);
+            return true;
+        }
+        if self.selected_worker.is_some() {
+            if matches!(key, KeyCode::Char('q') | KeyCode::Esc) {
+                self.selected_worker = None;
+            }
+            return true;
+        }
+        if self.current_tab == 3 {
+            match self.submit_form.handle_key(key) {
+                FormAction::Submit => self.submit(),
+                FormAction::None => {}
+                FormAction::Ignored => return self.handle_tab_key(key),
+            }
+            return true;
+        }
+        match key {
+            KeyCode::Enter if self.current_tab == 0 => return self.open_worker_detail(),
+            KeyCode::Enter if self.current_tab == 1 => return self.open_task_detail().await,
+            KeyCode::Up => self.scroll(-1),
+            KeyCode::Down => self.scroll(1),
+            KeyCode::PageUp => self.scroll(-(PAGE_ROWS as isize)),
+            KeyCode::PageDown => self.scroll(PAGE_ROWS as isize),
+            KeyCode::Tab | KeyCode::Right => self.next_tab(),
+            KeyCode::BackTab | KeyCode::Left => self.prev_tab(),
+            KeyCode::Char('q') => self.quit = true,
+            _ => return false,
+        }
+        true
+    }
+
+    // Whether the operator asked to leave the dashboard
+    // Apakah operator meminta keluar dari dasbor
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    // `/` starts a log search; while typing, Enter keeps it and Esc clears it
+    // `/` memulai pencarian log; saat mengetik, Enter menyimpannya dan Esc menghapusnya
+    fn handle_filter_key(&mut self, key: KeyCode) -> bool {
+        if !self.editing_filter {
+            match key {
+                KeyCode::Char('/') => {
+                    self.filter_string.clear();
+                    self.editing_filter = true;
+                }
+                KeyCode::Esc if !self.filter_string.is_empty() => self.filter_string.clear(),
+                _ => return false,
+            }
+            return true;
+        }
+        match key {
+            KeyCode::Enter => self.editing_filter = false,
+            KeyCode::Esc => {
+                self.filter_string.clear();
+                self.editing_filter = false;
+            }
+            KeyCode::Backspace => {
+                self.filter_string.pop();
+            }
+            KeyCode::Char(c) => self.filter_string.push(c),
+            _ => {}
+        }
+        true
+    }
+
+    // Logs containing the search text, ignoring case; all logs without one
+    // Log yang memuat teks pencarian, tanpa membedakan huruf besar; semua log jika kosong
+    fn filtered_logs(&self) -> Vec<&String> {
+        let needle = self.filter_string.to_lowercase();
+        self.logs
+            .iter()
+            .filter(|log| log.to_lowercase().contains(&needle))
+            .collect()
+    }
+
+    // Tab switching keys, the only ones the Submit tab does not keep for itself
+    // Tombol pindah tab, satu-satunya yang tidak dipakai sendiri oleh tab Submit
+    fn handle_tab_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Tab | KeyCode::Right => self.next_tab(),
+            KeyCode::BackTab | KeyCode::Left => self.prev_tab(),
+            _ => return false,
+        }
+        true
+    }
+
+    // Hand the form's task to the submit channel and report how it went
+    // Serahkan tugas dari formulir ke kanal kirim dan laporkan hasilnya
+    fn submit(&mut self) {
+        let outcome = self.submit_form.build_task().and_then(|task| {
+            let tx = self.submit_tx.as_ref().ok_or("Task submission is not available here")?;
+            let id = task.id.clone();
+            tx.try_send(task).map_err(|e| format!("Could not submit task: {}", e))?;
+            Ok(id)
+        });
+
+        let (message, is_error) = match outcome {
+            Ok(id) => {
+                self.submit_form = SubmitForm::new();
+                (format!("Submitted task {}", id), false)
+            }
+            Err(e) => (e, true),
+        };
+        self.add_log(message.clone());
+        self.notification = Some(Notification {
+            message,
+            is_error,
+            shown_at: Instant::now(),
+        });
+    }
+
+    // Banner text and whether it is an error, while it is still fresh
+    // Teks banner dan apakah itu galat, selama masih baru
+    fn active_notification(&self) -> Option<(&str, bool)> {
+        self.notification
+            .as_ref()
+            .filter(|n| n.shown_at.elapsed() < NOTIFICATION_DURATION)
+            .map(|n| (n.message.as_str(), n.is_error))
+    }
+
+    // Keys while the task detail is open: scroll it, or close it with q / Esc
+    // Tombol saat detail tugas terbuka: gulir, atau tutup dengan q / Esc
+    fn handle_detail_key(&mut self, key: KeyCode) {
+        let rows: isize = match key {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.close_task_detail();
+                return;
+            }
+            KeyCode::Up => -1,
+            KeyCode::Down => 1,
+            KeyCode::PageUp => -(PAGE_ROWS as isize),
+            KeyCode::PageDown => PAGE_ROWS as isize,
+            _ => return,
+        };
+        let last_line = self
+            .selected_task_detail
+            .as_ref()
+            .map_or(0, |task| Self::detail_lines(task).len().saturating_sub(1));
+        self.detail_scroll = self.detail_scroll.saturating_add_signed(rows).min(last_line);
+    }
+
+    // Fetch the selected task from the store and show it; false if there is none
+    // Ambil tugas terpilih dari store dan tampilkan; false jika tidak ada
+    async fn open_task_detail(&mut self) -> bool {
+        let (Some(store), Some(task_id)) = (self.store.clone(), self.selected_task_id()) else {
+            return false;
+        };
+        match store.get_task(&task_id).await {
+            Ok(Some(task)) => {
+                self.selected_task_detail = Some(task);
+                self.show_task_detail = true;
+                self.detail_scroll = 0;
+                true
+            }
+            Ok(None) => {
+                self.add_log(format!("Task {} is no longer stored", task_id));
+                false
+            }
+            Err(e) => {
+                self.add_log(format!("Failed to load task {}: {}", task_id, e));
+                false
+            }
+        }
+    }
+
+    // Show the worker under the cursor; false if the list is empty
+    // Tampilkan worker di bawah kursor; false jika daftar kosong
+    fn open_worker_detail(&mut self) -> bool {
+        let worker = self.workers_state.selected().and_then(|i| self.workers.get(i));
+        self.selected_worker = worker.map(|w| w.id.clone());
+        self.selected_worker.is_some()
+    }
+
+    pub fn close_task_detail(&mut self) {
+        self.show_task_detail = false;
+        self.selected_task_detail = None;
+        self.detail_scroll = 0;
+    }
+
+    // Task under the cursor in the Tasks tab; the total rows above it have none
+    // Tugas di bawah kursor pada tab Tasks; baris total di atasnya tidak punya tugas
+    fn selected_task_id(&self) -> Option<String> {
+        let row = self.tasks_state.selected()?.checked_sub(self.tasks_display.len())?;
+        self.recent_tasks.get(row).map(|(id, _)| id.clone())
+    }
+
+    fn tasks_len(&self) -> usize {
+        self.tasks_display.len() + self.recent_tasks.len()
+    }
+
+    fn scroll(&mut self, rows: isize) {
+        let (state, len) = match self.current_tab {
+            0 => (&mut self.workers_state, self.workers_display.len()),
+            1 => (&mut self.tasks_state, self.tasks_display.len() + self.recent_tasks.len()),
+            _ => return,
+        };
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+        let selected = match state.selected() {
+            Some(i) => i.saturating_add_signed(rows).min(len - 1),
+            None => 0,
+        };
+        state.select(Some(selected));
+    }
+
+    // Keep a selection inside a list that may have shrunk
+    // Jaga pilihan tetap di dalam daftar yang mungkin menyusut
+    fn clamp_selection(state: &mut ListState, len: usize) {
+        if let Some(i) = state.selected() {
+            state.select(if len == 0 { None } else { Some(i.min(len - 1)) });
+        }
+    }
+
     pub fn add_log(&mut self, message: String) {
         self.logs.push(message);
         // Keep only last 100 logs
@@ -56,36 +444,154 @@ impl Dashboard {
         }
     }
 
-    pub fn update_workers(&mut self, workers: Vec<WorkerInfo>) {
+    // Replace the worker list and the load reports shown in the worker detail
+    // Ganti daftar worker dan laporan beban yang ditampilkan di detail worker
+    pub fn update_workers(&mut self, workers: Vec<WorkerInfo>, metrics: HashMap<String, WorkerMetrics>) {
         self.workers_display.clear();
-        for worker in workers {
-            let status = if worker.is_idle() { "[IDLE]" } else { "[BUSY]" };
+        self.worker_metrics = metrics;
+        self.worker_tasks.retain(|id, _| workers.iter().any(|w| &w.id == id));
+        self.worker_stats.retain(|id, _| workers.iter().any(|w| &w.id == id));
+        for worker in &workers {
+            let status = if worker.maintenance {
+                "[MAINTENANCE]"
+            } else if worker.is_idle() {
+                "[IDLE]"
+            } else {
+                "[BUSY]"
+            };
+            let rtt = worker.last_rtt_ms.map_or("-".to_string(), |ms| format!("{}ms", ms));
             let line = format!(
-                "{:20} | {} | Jobs: {}/{} | {}",
-                worker.name, status, worker.current_jobs, worker.max_jobs, worker.platform
+                "{:20} | {} | Jobs: {}/{} | RTT: {} | {}",
+                worker.name, status, worker.current_jobs, worker.max_jobs, rtt, worker.platform
             );
             self.workers_display.push(line);
         }
+        self.workers = workers;
+        Self::clamp_selection(&mut self.workers_state, self.workers_display.len());
+    }
+
+    // Queue depth and completed count from `TuiEvent::StatsUpdated`
+    // Kedalaman antrian dan jumlah selesai dari `TuiEvent::StatsUpdated`
+    pub fn update_stats(&mut self, queue: usize, completed: usize) {
+        for (prefix, value) in [("Completed Tasks", completed), ("Queued Tasks", queue)] {
+            let line = format!("{}: {}", prefix, value);
+            match self.tasks_display.iter_mut().find(|l| l.starts_with(prefix)) {
+                Some(existing) => *existing = line,
+                None => self.tasks_display.push(line),
+            }
+        }
+        let len = self.tasks_len();
+        Self::clamp_selection(&mut self.tasks_state, len);
+    }
+
+    // Apply one dispatcher update; only the part of the dashboard it names changes
+    // Terapkan satu pembaruan dispatcher; hanya bagian dasbor yang disebut yang berubah
+    pub fn apply_event(&mut self, event: TuiEvent) {
+        match event {
+            TuiEvent::WorkersUpdated(workers) => {
+                let metrics = std::mem::take(&mut self.worker_metrics);
+                self.update_workers(workers, metrics);
+            }
+            TuiEvent::TaskCompleted(task_id) => {
+                self.completed_this_second += 1;
+                self.add_log(format!("Task {} finished", task_id));
+            }
+            TuiEvent::LogLine(line) => self.add_log(line),
+            TuiEvent::StatsUpdated { queue, completed } => self.update_stats(queue, completed),
+            TuiEvent::SchedulerPaused(paused) => self.paused = paused,
+        }
+    }
+
+    // Close the current second of the throughput window; call once per second
+    // Tutup detik saat ini pada jendela throughput; panggil sekali per detik
+    pub fn tick_throughput(&mut self) {
+        if self.throughput.len() == THROUGHPUT_WINDOW {
+            self.throughput.pop_front();
+        }
+        self.throughput.push_back(std::mem::take(&mut self.completed_this_second));
+    }
+
+    // (min, max, current) tasks per second over the window
+    // (min, maks, saat ini) tugas per detik dalam jendela
+    fn throughput_summary(&self) -> (u64, u64, u64) {
+        let min = self.throughput.iter().copied().min().unwrap_or(0);
+        let max = self.throughput.iter().copied().max().unwrap_or(0);
+        let current = self.throughput.back().copied().unwrap_or(0);
+        (min, max, current)
+    }
+
+    // Whether the last second was at least as busy as the one before
+    // Apakah detik terakhir setidaknya sesibuk detik sebelumnya
+    fn throughput_rising(&self) -> bool {
+        let mut recent = self.throughput.iter().rev();
+        match (recent.next(), recent.next()) {
+            (Some(current), Some(previous)) => current >= previous,
+            _ => true,
+        }
     }
 
-    pub fn update_tasks(&mut self, completed: usize, queued: usize) {
+    // Task totals a worker reported in `Message::WorkerMetrics`
+    // Total tugas yang dilaporkan worker dalam `Message::WorkerMetrics`
+    pub fn update_worker_stats(&mut self, worker_id: &str, stats: WorkerTaskStats) {
+        self.worker_stats.insert(worker_id.to_string(), stats);
+    }
+
+    // IDs of the tasks a worker is running right now
+    // ID tugas yang sedang dijalankan worker saat ini
+    pub fn update_worker_tasks(&mut self, worker_id: &str, task_ids: Vec<String>) {
+        self.worker_tasks.insert(worker_id.to_string(), task_ids);
+    }
+
+    pub fn update_tasks(&mut self, metrics: &Metrics) {
         self.tasks_display.clear();
-        self.tasks_display.push(format!("Completed Tasks: {}", completed));
-        self.tasks_display.push(format!("Queued Tasks: {}", queued));
+        self.tasks_display.push(format!("Completed Tasks: {}", metrics.tasks_total(TaskStatus::Completed)));
+        self.tasks_display.push(format!("Failed Tasks: {}", metrics.tasks_total(TaskStatus::Failed)));
+        self.tasks_display.push(format!("Timed Out Tasks: {}", metrics.tasks_total(TaskStatus::TimedOut)));
+        self.tasks_display.push(format!("Queued Tasks: {}", metrics.queue_depth()));
+
+        // Duration quantiles are only known once a task has finished
+        // Kuantil durasi hanya diketahui setelah ada tugas yang selesai
+        let quantiles: Vec<String> = [("p50", 0.5), ("p95", 0.95), ("p99", 0.99)]
+            .iter()
+            .filter_map(|(label, q)| Some(format!("{} {:.0}ms", label, metrics.duration_quantile(*q)?)))
+            .collect();
+        if !quantiles.is_empty() {
+            self.tasks_display.push(format!("Task Duration: {}", quantiles.join(" / ")));
+        }
+        let len = self.tasks_len();
+        Self::clamp_selection(&mut self.tasks_state, len);
     }
 
-    pub fn draw(&self, f: &mut Frame) {
+    // List these finished tasks under the totals so they can be opened
+    // Tampilkan tugas selesai ini di bawah total agar bisa dibuka
+    pub fn update_recent_tasks(&mut self, tasks: Vec<StoredTask>) {
+        self.recent_tasks = tasks
+            .into_iter()
+            .map(|task| {
+                let line = format!("{:.8} | {:10} | {}", task.id, task.status, task.command);
+                (task.id, line)
+            })
+            .collect();
+        let len = self.tasks_len();
+        Self::clamp_selection(&mut self.tasks_state, len);
+    }
+
+    pub fn draw(&mut self, f: &mut Frame) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([Constraint::Length(3), Constraint::Min(10)])
+            .constraints([Constraint::Length(3), Constraint::Min(10), Constraint::Length(5)])
             .split(f.area());
 
         // Draw tabs
         // Gambar tab
         let tab_titles: Vec<&str> = self.tabs.iter().copied().collect();
+        let mut title = vec![Span::raw("OCTASKLY Dashboard")];
+        if self.paused {
+            title.push(Span::styled(" [PAUSED]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+        }
         let tabs = Tabs::new(tab_titles)
-            .block(Block::default().borders(Borders::BOTTOM).title("OCTASKLY Dashboard"))
+            .block(Block::default().borders(Borders::BOTTOM).title(Line::from(title)))
             .select(self.current_tab)
             .style(Style::default().fg(Color::White))
             .highlight_style(
@@ -100,11 +606,119 @@ impl Dashboard {
             0 => self.draw_workers_tab(f, chunks[1]),
             1 => self.draw_tasks_tab(f, chunks[1]),
             2 => self.draw_logs_tab(f, chunks[1]),
+            3 => self.draw_submit_tab(f, chunks[1]),
             _ => {}
         }
+
+        self.draw_throughput(f, chunks[2]);
+
+        if self.show_task_detail {
+            self.draw_task_detail(f);
+        } else if self.selected_worker.is_some() {
+            self.draw_worker_detail(f);
+        }
+        if self.show_help {
+            self.draw_help(f);
+        }
+    }
+
+    // Keyboard shortcuts in a two-column table over the middle of the screen
+    // Pintasan keyboard dalam tabel dua kolom di tengah layar
+    fn draw_help(&self, f: &mut Frame) {
+        let area = centered_rect(60, 60, f.area());
+        let rows = SHORTCUTS.iter().map(|(keys, action)| Row::new([*keys, *action]));
+        let table = Table::new(rows, [Constraint::Length(22), Constraint::Min(10)])
+            .header(Row::new(["Key", "Action"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).title("Keyboard shortcuts (any key to close)"));
+
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    // Details of the opened worker in a box over the middle of the screen
+    // Detail worker yang dibuka dalam kotak di tengah layar
+    fn draw_worker_detail(&self, f: &mut Frame) {
+        let Some(worker_id) = &self.selected_worker else {
+            return;
+        };
+        let area = centered_rect(60, 50, f.area());
+        let paragraph = Paragraph::new(self.worker_detail_lines(worker_id))
+            .block(Block::default().borders(Borders::ALL).title("Worker (q/Esc to close)"))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    fn worker_detail_lines(&self, worker_id: &str) -> Vec<Line<'static>> {
+        let Some(worker) = self.workers.iter().find(|w| w.id == worker_id) else {
+            return vec![Line::raw(format!("Worker {} is no longer connected", worker_id))];
+        };
+        let current_tasks = match self.worker_tasks.get(worker_id) {
+            Some(ids) if !ids.is_empty() => ids.join(", "),
+            _ => "-".to_string(),
+        };
+
+        let mut lines = vec![
+            Line::raw(format!("ID: {}", worker.id)),
+            Line::raw(format!("Name: {}", worker.name)),
+            Line::raw(format!("Address: {}:{}", worker.address, worker.port)),
+            Line::raw(format!("Platform: {}", worker.platform)),
+            Line::raw(format!("Current tasks: {}", current_tasks)),
+        ];
+        match self.worker_metrics.get(worker_id) {
+            Some(metrics) => {
+                lines.push(Line::raw(format!("CPU: {:.1}%", metrics.cpu_percent)));
+                lines.push(Line::raw(format!("Memory: {} MB", metrics.memory_mb)));
+            }
+            None => lines.push(Line::raw("CPU / Memory: no report yet")),
+        }
+        match self.worker_stats.get(worker_id) {
+            Some(stats) => {
+                let finished = stats.tasks_completed + stats.tasks_failed;
+                let average = stats.total_duration_ms.checked_div(finished).unwrap_or(0);
+                lines.push(Line::raw(format!("Tasks completed: {}", stats.tasks_completed)));
+                lines.push(Line::raw(format!("Tasks failed: {}", stats.tasks_failed)));
+                lines.push(Line::raw(format!("Average duration: {}ms", average)));
+            }
+            None => lines.push(Line::raw("Task totals: not reported yet")),
+        }
+        lines
     }
 
-    fn draw_workers_tab(&self, f: &mut Frame, area: Rect) {
+    // Output of the opened task in a box over the middle of the screen
+    // Output tugas yang dibuka dalam kotak di tengah layar
+    fn draw_task_detail(&self, f: &mut Frame) {
+        let Some(task) = &self.selected_task_detail else {
+            return;
+        };
+        let area = centered_rect(80, 80, f.area());
+        let title = format!("Task {} (q/Esc to close)", task.id);
+        let paragraph = Paragraph::new(Self::detail_lines(task))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((self.detail_scroll.min(u16::MAX as usize) as u16, 0));
+
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    fn detail_lines(task: &StoredTask) -> Vec<Line<'static>> {
+        let exit_code = task.exit_code.map_or("-".to_string(), |code| code.to_string());
+        let mut lines = vec![
+            Line::raw(format!("Command: {}", task.command)),
+            Line::raw(format!("Status: {} | Exit code: {} | {}ms", task.status, exit_code, task.duration_ms)),
+            Line::raw(""),
+            Line::styled("stdout:", Style::default().add_modifier(Modifier::BOLD)),
+        ];
+        lines.extend(task.stdout.lines().map(|line| Line::raw(line.to_string())));
+        lines.push(Line::raw(""));
+        lines.push(Line::styled("stderr:", Style::default().add_modifier(Modifier::BOLD)));
+        lines.extend(task.stderr.lines().map(|line| Line::raw(line.to_string())));
+        lines
+    }
+
+    fn draw_workers_tab(&mut self, f: &mut Frame, area: Rect) {
         if self.workers_display.is_empty() {
             let empty_msg = Paragraph::new("No workers connected")
                 .block(Block::default().borders(Borders::ALL).title("Workers"));
@@ -120,38 +734,223 @@ impl Dashboard {
 
         let list = List::new(items)
             .block(Block::default().borders(Borders::ALL).title("Workers"))
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().fg(Color::Yellow));
 
-        f.render_widget(list, area);
+        f.render_stateful_widget(list, area, &mut self.workers_state);
     }
 
-    fn draw_tasks_tab(&self, f: &mut Frame, area: Rect) {
+    fn draw_tasks_tab(&mut self, f: &mut Frame, area: Rect) {
         let items: Vec<ListItem> = self
             .tasks_display
             .iter()
+            .chain(self.recent_tasks.iter().map(|(_, line)| line))
             .map(|t| ListItem::new(t.clone()))
             .collect();
 
         let list = List::new(items)
             .block(Block::default().borders(Borders::ALL).title("Tasks"))
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().fg(Color::Yellow));
 
-        f.render_widget(list, area);
+        f.render_stateful_widget(list, area, &mut self.tasks_state);
+    }
+
+    // Tasks completed per second over the last minute, with its min / max / current
+    // Tugas selesai per detik selama satu menit terakhir, dengan min / maks / saat ini
+    fn draw_throughput(&self, f: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(10), Constraint::Length(18)])
+            .split(area);
+
+        let color = if self.throughput_rising() { Color::Green } else { Color::Yellow };
+        let data: Vec<u64> = self.throughput.iter().copied().collect();
+        let sparkline = Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title("Throughput (tasks/s)"))
+            .data(&data)
+            .style(Style::default().fg(color));
+        f.render_widget(sparkline, columns[0]);
+
+        let (min, max, current) = self.throughput_summary();
+        let summary = Paragraph::new(vec![
+            Line::raw(format!("min {}", min)),
+            Line::raw(format!("max {}", max)),
+            Line::raw(format!("now {}", current)),
+        ])
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(summary, columns[1]);
+    }
+
+    fn draw_submit_tab(&self, f: &mut Frame, area: Rect) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let form = &self.submit_form;
+        let focused = |field: SubmitField| {
+            if form.focus == field {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            }
+        };
+        let fields = [
+            (SubmitField::Command, "Command", form.command.as_str()),
+            (SubmitField::Timeout, "Timeout (seconds)", form.timeout.as_str()),
+            (SubmitField::Tags, "Tags (comma-separated)", form.tags.as_str()),
+        ];
+        for (row, (field, title, value)) in fields.into_iter().enumerate() {
+            let input = Paragraph::new(value.to_string())
+                .block(Block::default().borders(Borders::ALL).title(title).border_style(focused(field)));
+            f.render_widget(input, rows[row]);
+        }
+        f.render_widget(Paragraph::new("[ Submit ]").style(focused(SubmitField::Button)), rows[3]);
+
+        if let Some((message, is_error)) = self.active_notification() {
+            let color = if is_error { Color::Red } else { Color::Green };
+            f.render_widget(Paragraph::new(message.to_string()).style(Style::default().fg(color)), rows[5]);
+        }
     }
 
     fn draw_logs_tab(&self, f: &mut Frame, area: Rect) {
-        let lines: Vec<Line> = self
-            .logs
+        let searching = self.editing_filter || !self.filter_string.is_empty();
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(if searching { 1 } else { 0 })])
+            .split(area);
+
+        let matches = self.filtered_logs();
+        let lines: Vec<Line> = matches
             .iter()
             .rev()
             .take(20)
-            .map(|log| Line::raw(log.clone()))
+            .map(|log| highlight_matches(log, &self.filter_string))
             .collect();
 
         let paragraph = Paragraph::new(lines)
             .block(Block::default().borders(Borders::ALL).title("Logs"));
 
-        f.render_widget(paragraph, area);
+        f.render_widget(paragraph, rows[0]);
+
+        if searching {
+            let cursor = if self.editing_filter { "_" } else { "" };
+            let status = format!("/{}{}  {} matches", self.filter_string, cursor, matches.len());
+            f.render_widget(Paragraph::new(status), rows[1]);
+        }
+    }
+}
+
+// Log line with every case-insensitive occurrence of `needle` in bold yellow
+// Baris log dengan setiap kemunculan `needle` (tanpa membedakan huruf besar) berwarna kuning tebal
+fn highlight_matches(line: &str, needle: &str) -> Line<'static> {
+    let lower = line.to_lowercase();
+    let needle = needle.to_lowercase();
+    // Lowercasing some non-ASCII text changes byte offsets; show such lines plain
+    // Huruf kecil pada sebagian teks non-ASCII mengubah offset byte; tampilkan polos
+    if needle.is_empty() || lower.len() != line.len() {
+        return Line::raw(line.to_string());
+    }
+
+    let highlight = Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow);
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, matched) in lower.match_indices(&needle) {
+        if start > last {
+            spans.push(Span::raw(line[last..start].to_string()));
+        }
+        let end = start + matched.len();
+        spans.push(Span::styled(line[start..end].to_string(), highlight));
+        last = end;
+    }
+    if last < line.len() {
+        spans.push(Span::raw(line[last..].to_string()));
+    }
+    Line::from(spans)
+}
+
+// Rect taking the given percentages of `area`, centered in it
+// Rect dengan persentase tertentu dari `area`, di tengahnya
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+// Turn dispatcher events into dashboard updates on a watch channel
+// Ubah event dispatcher menjadi pembaruan dasbor pada kanal watch
+//
+// Runs on the dispatcher side, so the TUI itself never locks the scheduler.
+// Berjalan di sisi dispatcher, sehingga TUI sendiri tidak pernah mengunci penjadwal.
+pub fn spawn_event_bridge(
+    dispatcher: &DispatcherState,
+    scheduler: Arc<Scheduler>,
+) -> (watch::Receiver<TuiEvent>, JoinHandle<()>) {
+    let mut events = dispatcher.subscribe_events();
+    let (tx, rx) = watch::channel(TuiEvent::StatsUpdated { queue: 0, completed: 0 });
+
+    let handle = tokio::spawn(async move {
+        let mut queue = 0;
+        let mut completed = 0;
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let update = match event {
+                DispatcherEvent::WorkerConnected { .. } | DispatcherEvent::WorkerDisconnected { .. } => {
+                    TuiEvent::WorkersUpdated(scheduler.get_workers().await)
+                }
+                DispatcherEvent::TaskStatus { task_id, status, .. } => match status {
+                    TaskStatus::Pending | TaskStatus::Running => continue,
+                    TaskStatus::Completed => {
+                        completed += 1;
+                        TuiEvent::TaskCompleted(task_id)
+                    }
+                    _ => TuiEvent::LogLine(format!("Task {} {:?}", task_id, status)),
+                },
+                DispatcherEvent::QueueDepth { depth } if depth != queue => {
+                    queue = depth;
+                    TuiEvent::StatsUpdated { queue, completed }
+                }
+                DispatcherEvent::QueueDepth { .. } => continue,
+                DispatcherEvent::SchedulerPaused { paused } => TuiEvent::SchedulerPaused(paused),
+            };
+            if tx.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    (rx, handle)
+}
+
+// Enqueue every task submitted from the dashboard until the channel closes
+// Masukkan setiap tugas yang dikirim dari dasbor ke antrian sampai kanal ditutup
+pub async fn forward_submissions(mut rx: mpsc::Receiver<Task>, scheduler: Arc<Scheduler>) {
+    while let Some(task) = rx.recv().await {
+        scheduler.enqueue(task).await;
     }
 }
 
@@ -177,6 +976,64 @@ impl Ui {
         })
     }
 
+    // Take over the terminal and run the dashboard until the operator quits
+    // Ambil alih terminal dan jalankan dasbor sampai operator keluar
+    //
+    // The terminal is restored on the way out, also when drawing fails.
+    // Terminal dipulihkan saat keluar, juga ketika menggambar gagal.
+    pub async fn run(&mut self, events: watch::Receiver<TuiEvent>) -> io::Result<()> {
+        crossterm::terminal::enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
+        let result = self.run_with_input(events, EventStream::new()).await;
+        crossterm::execute!(io::stdout(), crossterm::terminal::LeaveAlternateScreen)?;
+        crossterm::terminal::disable_raw_mode()?;
+        result
+    }
+
+    // Redraw on every key press and dispatcher update, until `q`, Ctrl+C, the end of
+    // `input`, or the dispatcher dropping its sender
+    // Gambar ulang pada setiap tombol dan pembaruan dispatcher, sampai `q`, Ctrl+C,
+    // akhir `input`, atau dispatcher menutup pengirimnya
+    //
+    // The throughput sparkline also advances once a second.
+    // Sparkline throughput juga bergeser sekali per detik.
+    pub async fn run_with_input<S>(&mut self, mut events: watch::Receiver<TuiEvent>, mut input: S) -> io::Result<()>
+    where
+        S: Stream<Item = io::Result<Event>> + Unpin,
+    {
+        let mut second = tokio::time::interval(Duration::from_secs(1));
+        second.tick().await;
+        self.refresh()?;
+        loop {
+            tokio::select! {
+                event = input.next() => match event {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                            break;
+                        }
+                        self.dashboard.handle_key(key.code).await;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    None => break,
+                },
+                changed = events.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let event = events.borrow_and_update().clone();
+                    self.dashboard.apply_event(event);
+                }
+                _ = second.tick() => self.dashboard.tick_throughput(),
+            }
+            if self.dashboard.should_quit() {
+                break;
+            }
+            self.refresh()?;
+        }
+        Ok(())
+    }
+
     pub fn refresh(&mut self) -> io::Result<()> {
         if let Some(terminal) = &mut self.terminal {
             terminal.draw(|f| {
@@ -194,16 +1051,42 @@ impl Ui {
         self.dashboard.prev_tab();
     }
 
+    pub async fn handle_key(&mut self, key: KeyCode) -> bool {
+        self.dashboard.handle_key(key).await
+    }
+
+    pub fn with_store(mut self, store: Arc<dyn StoreBackend + Send + Sync>) -> Self {
+        self.dashboard = self.dashboard.with_store(store);
+        self
+    }
+
+    pub fn with_submit_channel(mut self, tx: mpsc::Sender<Task>) -> Self {
+        self.dashboard = self.dashboard.with_submit_channel(tx);
+        self
+    }
+
+    pub fn update_recent_tasks(&mut self, tasks: Vec<StoredTask>) {
+        self.dashboard.update_recent_tasks(tasks);
+    }
+
     pub fn add_log(&mut self, message: String) {
         self.dashboard.add_log(message);
     }
 
-    pub fn update_workers(&mut self, workers: Vec<WorkerInfo>) {
-        self.dashboard.update_workers(workers);
+    pub fn update_workers(&mut self, workers: Vec<WorkerInfo>, metrics: HashMap<String, WorkerMetrics>) {
+        self.dashboard.update_workers(workers, metrics);
+    }
+
+    pub fn update_worker_stats(&mut self, worker_id: &str, stats: WorkerTaskStats) {
+        self.dashboard.update_worker_stats(worker_id, stats);
     }
 
-    pub fn update_tasks(&mut self, completed: usize, queued: usize) {
-        self.dashboard.update_tasks(completed, queued);
+    pub fn update_worker_tasks(&mut self, worker_id: &str, task_ids: Vec<String>) {
+        self.dashboard.update_worker_tasks(worker_id, task_ids);
+    }
+
+    pub fn update_tasks(&mut self, metrics: &Metrics) {
+        self.dashboard.update_tasks(metrics);
     }
 }
 
@@ -215,7 +1098,7 @@ mod tests {
     fn test_dashboard_new() {
         let dashboard = Dashboard::new();
         assert_eq!(dashboard.current_tab, 0);
-        assert_eq!(dashboard.tabs.len(), 3);
+        assert_eq!(dashboard.tabs.len(), 4);
     }
 
     #[test]
@@ -227,11 +1110,382 @@ mod tests {
         assert_eq!(dashboard.current_tab, 0);
     }
 
+    fn worker(name: &str) -> WorkerInfo {
+        WorkerInfo::new(name.to_string(), "127.0.0.1".to_string(), 7879, 1)
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_scrolling() {
+        let mut dashboard = Dashboard::new();
+        dashboard.update_workers((0..25).map(|i| worker(&format!("w{}", i))).collect(), HashMap::new());
+
+        dashboard.handle_key(KeyCode::Down).await;
+        assert_eq!(dashboard.workers_state.selected(), Some(0));
+        dashboard.handle_key(KeyCode::Down).await;
+        dashboard.handle_key(KeyCode::PageDown).await;
+        assert_eq!(dashboard.workers_state.selected(), Some(11));
+        dashboard.handle_key(KeyCode::PageDown).await;
+        dashboard.handle_key(KeyCode::PageDown).await;
+        assert_eq!(dashboard.workers_state.selected(), Some(24));
+        dashboard.handle_key(KeyCode::PageUp).await;
+        assert_eq!(dashboard.workers_state.selected(), Some(14));
+
+        // A shorter worker list pulls the cursor back inside it
+        dashboard.update_workers((0..5).map(|i| worker(&format!("w{}", i))).collect(), HashMap::new());
+        assert_eq!(dashboard.workers_state.selected(), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_tab_switch_keeps_scroll_position() {
+        let mut dashboard = Dashboard::new();
+        dashboard.update_workers((0..5).map(|i| worker(&format!("w{}", i))).collect(), HashMap::new());
+        dashboard.update_tasks(&Metrics::new());
+
+        dashboard.handle_key(KeyCode::Down).await;
+        dashboard.handle_key(KeyCode::Down).await;
+        dashboard.next_tab();
+        dashboard.handle_key(KeyCode::Down).await;
+        dashboard.next_tab();
+        dashboard.handle_key(KeyCode::Down).await;
+        dashboard.prev_tab();
+        dashboard.prev_tab();
+
+        assert_eq!(dashboard.current_tab, 0);
+        assert_eq!(dashboard.workers_state.selected(), Some(1));
+        assert_eq!(dashboard.tasks_state.selected(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_task_detail_modal() {
+        use crate::persistence::SqliteStore;
+        use crate::protocol::{Task, TaskResult};
+        use ratatui::backend::TestBackend;
+
+        let store = Arc::new(SqliteStore::new(":memory:").await.unwrap());
+        let task = Task::new("make test".to_string());
+        let result = TaskResult {
+            task_id: task.id.clone(),
+            worker_id: "worker-1".to_string(),
+            status: TaskStatus::Failed,
+            stdout: (1..=30).map(|i| format!("line {}\n", i)).collect(),
+            stderr: "assertion failed\n".to_string(),
+            exit_code: Some(1),
+            duration_ms: 1200,
+            completed_at: 0,
+            resource_usage: None,
+        };
+        let stored = StoredTask::from_result(&task, &result);
+        store.store_task(&stored).await.unwrap();
+
+        let mut dashboard = Dashboard::new().with_store(store);
+        dashboard.update_recent_tasks(vec![stored]);
+        dashboard.next_tab();
+        dashboard.handle_key(KeyCode::Down).await;
+        assert!(dashboard.handle_key(KeyCode::Enter).await);
+        assert!(dashboard.show_task_detail);
+        assert_eq!(dashboard.selected_task_detail.as_ref().unwrap().id, task.id);
+
+        // Scrolling moves the modal, not the task list underneath
+        dashboard.handle_key(KeyCode::PageDown).await;
+        assert_eq!(dashboard.detail_scroll, PAGE_ROWS);
+        assert_eq!(dashboard.tasks_state.selected(), Some(0));
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|f| dashboard.draw(f)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("line 20"));
+        assert!(!screen.contains("Command: make test"));
+
+        dashboard.handle_key(KeyCode::Esc).await;
+        assert!(!dashboard.show_task_detail);
+        assert!(dashboard.selected_task_detail.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_worker_detail_modal() {
+        let build_box = worker("build-box");
+        let worker_id = build_box.id.clone();
+        let load = WorkerMetrics {
+            cpu_percent: 42.5,
+            memory_mb: 2048,
+            load_avg_1m: 1.0,
+        };
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_workers(vec![build_box.clone()], HashMap::from([(worker_id.clone(), load)]));
+        dashboard.update_worker_tasks(&worker_id, vec!["task-7".to_string()]);
+        dashboard.handle_key(KeyCode::Down).await;
+        assert!(dashboard.handle_key(KeyCode::Enter).await);
+        assert_eq!(dashboard.selected_worker.as_deref(), Some(worker_id.as_str()));
+
+        let text = |dashboard: &Dashboard| -> Vec<String> {
+            dashboard.worker_detail_lines(&worker_id).iter().map(|line| line.to_string()).collect()
+        };
+        let lines = text(&dashboard);
+        assert!(lines.contains(&"Current tasks: task-7".to_string()));
+        assert!(lines.contains(&"CPU: 42.5%".to_string()));
+        assert!(lines.contains(&"Task totals: not reported yet".to_string()));
+
+        // Later reports show up without reopening the modal
+        dashboard.update_worker_stats(
+            &worker_id,
+            WorkerTaskStats {
+                tasks_completed: 3,
+                tasks_failed: 1,
+                total_duration_ms: 400,
+                throughput_per_minute: 1.0,
+            },
+        );
+        let lines = text(&dashboard);
+        assert!(lines.contains(&"Tasks failed: 1".to_string()));
+        assert!(lines.contains(&"Average duration: 100ms".to_string()));
+
+        dashboard.update_workers(Vec::new(), HashMap::new());
+        assert!(text(&dashboard)[0].ends_with("is no longer connected"));
+
+        dashboard.handle_key(KeyCode::Char('q')).await;
+        assert!(dashboard.selected_worker.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_tab_enqueues_task() {
+        let scheduler = Arc::new(Scheduler::new(crate::scheduler::SchedulerAlgorithm::Fifo));
+        let (tx, rx) = mpsc::channel(8);
+        let forwarder = tokio::spawn(forward_submissions(rx, scheduler.clone()));
+
+        let mut dashboard = Dashboard::new().with_submit_channel(tx);
+        dashboard.prev_tab();
+        assert_eq!(dashboard.tabs[dashboard.current_tab], "Submit");
+
+        // Submitting an empty form only shows an error
+        for _ in 0..3 {
+            dashboard.handle_key(KeyCode::Down).await;
+        }
+        dashboard.handle_key(KeyCode::Enter).await;
+        assert_eq!(dashboard.active_notification(), Some(("Command must not be empty", true)));
+
+        for _ in 0..3 {
+            dashboard.handle_key(KeyCode::Up).await;
+        }
+        for c in "echo hi".chars() {
+            dashboard.handle_key(KeyCode::Char(c)).await;
+        }
+        for _ in 0..3 {
+            dashboard.handle_key(KeyCode::Enter).await;
+        }
+        dashboard.handle_key(KeyCode::Enter).await;
+        let (message, is_error) = dashboard.active_notification().unwrap();
+        assert!(message.starts_with("Submitted task") && !is_error);
+        assert!(dashboard.submit_form.command.is_empty());
+
+        drop(dashboard);
+        forwarder.await.unwrap();
+        assert_eq!(scheduler.dequeue().await.unwrap().command, "echo hi");
+    }
+
+    #[test]
+    fn test_notification_expires() {
+        let mut dashboard = Dashboard::new();
+        dashboard.notification = Some(Notification {
+            message: "Submitted task".to_string(),
+            is_error: false,
+            shown_at: Instant::now() - NOTIFICATION_DURATION,
+        });
+        assert!(dashboard.active_notification().is_none());
+    }
+
+    #[test]
+    fn test_apply_event() {
+        let mut dashboard = Dashboard::new();
+        dashboard.update_tasks(&Metrics::new());
+        dashboard.apply_event(TuiEvent::StatsUpdated { queue: 7, completed: 3 });
+        assert!(dashboard.tasks_display.contains(&"Queued Tasks: 7".to_string()));
+        assert!(dashboard.tasks_display.contains(&"Completed Tasks: 3".to_string()));
+        assert_eq!(dashboard.tasks_display.iter().filter(|l| l.starts_with("Queued")).count(), 1);
+
+        dashboard.apply_event(TuiEvent::WorkersUpdated(vec![worker("w1")]));
+        assert_eq!(dashboard.workers.len(), 1);
+        assert!(dashboard.logs.is_empty());
+
+        dashboard.apply_event(TuiEvent::TaskCompleted("task-1".to_string()));
+        assert_eq!(dashboard.logs, vec!["Task task-1 finished"]);
+    }
+
+    #[tokio::test]
+    async fn test_event_bridge() {
+        let scheduler = Arc::new(Scheduler::new(crate::scheduler::SchedulerAlgorithm::Fifo));
+        let dispatcher = DispatcherState::new("dispatcher".to_string(), 0);
+        let (mut events, _bridge) = spawn_event_bridge(&dispatcher, scheduler.clone());
+
+        let w1 = worker("w1");
+        scheduler.register_worker(w1.clone()).await;
+        dispatcher.publish_event(DispatcherEvent::WorkerConnected {
+            worker_id: w1.id.clone(),
+            name: w1.name.clone(),
+        });
+        events.changed().await.unwrap();
+        assert!(matches!(&*events.borrow_and_update(), TuiEvent::WorkersUpdated(workers) if workers.len() == 1));
+
+        dispatcher.publish_event(DispatcherEvent::TaskStatus {
+            task_id: "task-1".to_string(),
+            status: TaskStatus::Completed,
+            worker_id: Some(w1.id.clone()),
+        });
+        events.changed().await.unwrap();
+        assert!(matches!(&*events.borrow_and_update(), TuiEvent::TaskCompleted(id) if id == "task-1"));
+
+        dispatcher.publish_event(DispatcherEvent::QueueDepth { depth: 4 });
+        events.changed().await.unwrap();
+        assert!(matches!(*events.borrow_and_update(), TuiEvent::StatsUpdated { queue: 4, completed: 1 }));
+    }
+
+    #[test]
+    fn test_throughput_window() {
+        let mut dashboard = Dashboard::new();
+        for per_second in [2, 5, 3] {
+            for i in 0..per_second {
+                dashboard.apply_event(TuiEvent::TaskCompleted(format!("task-{}", i)));
+            }
+            dashboard.tick_throughput();
+        }
+        assert_eq!(dashboard.throughput_summary(), (2, 5, 3));
+        assert!(!dashboard.throughput_rising());
+
+        dashboard.apply_event(TuiEvent::TaskCompleted("task-9".to_string()));
+        dashboard.apply_event(TuiEvent::TaskCompleted("task-10".to_string()));
+        dashboard.apply_event(TuiEvent::TaskCompleted("task-11".to_string()));
+        dashboard.tick_throughput();
+        assert!(dashboard.throughput_rising());
+
+        for _ in 0..THROUGHPUT_WINDOW {
+            dashboard.tick_throughput();
+        }
+        assert_eq!(dashboard.throughput.len(), THROUGHPUT_WINDOW);
+        assert_eq!(dashboard.throughput_summary(), (0, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_run_handles_key_input_until_quit() {
+        use crossterm::event::KeyEvent;
+
+        let key = |code| Ok(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        let mut ui = Ui {
+            dashboard: Dashboard::new(),
+            terminal: None,
+        };
+        let (_tx, rx) = watch::channel(TuiEvent::StatsUpdated { queue: 0, completed: 0 });
+
+        // `q` typed into the Submit form is text; on the Tasks tab it quits
+        let input = futures::stream::iter(vec![
+            key(KeyCode::Char('s')),
+            key(KeyCode::Char('q')),
+            key(KeyCode::Tab),
+            key(KeyCode::Tab),
+            key(KeyCode::Char('q')),
+            key(KeyCode::Tab),
+        ]);
+        let run = ui.run_with_input(rx.clone(), input);
+        tokio::time::timeout(Duration::from_secs(5), run).await.unwrap().unwrap();
+        assert!(ui.dashboard.should_quit());
+        assert_eq!(ui.dashboard.submit_form.command, "q");
+        assert_eq!(ui.dashboard.tabs[ui.dashboard.current_tab], "Tasks");
+
+        // Ctrl+C quits from anywhere, even while typing
+        let mut ui = Ui {
+            dashboard: Dashboard::new(),
+            terminal: None,
+        };
+        let input = futures::stream::iter(vec![
+            key(KeyCode::Char('s')),
+            Ok(Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL))),
+            key(KeyCode::Char('x')),
+        ]);
+        tokio::time::timeout(Duration::from_secs(5), ui.run_with_input(rx, input)).await.unwrap().unwrap();
+        assert!(ui.dashboard.submit_form.command.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_help_overlay_toggle() {
+        let mut dashboard = Dashboard::new();
+        assert!(dashboard.handle_key(KeyCode::Char('?')).await);
+        assert!(dashboard.show_help);
+        assert!(dashboard.handle_key(KeyCode::Char('?')).await);
+        assert!(!dashboard.show_help);
+
+        // Any other key also closes it, without acting on the key
+        dashboard.handle_key(KeyCode::Char('?')).await;
+        dashboard.handle_key(KeyCode::Tab).await;
+        assert!(!dashboard.show_help);
+        assert_eq!(dashboard.current_tab, 0);
+
+        // In the Submit form `?` and `s` are typed, not shortcuts
+        dashboard.handle_key(KeyCode::Char('s')).await;
+        assert_eq!(dashboard.tabs[dashboard.current_tab], "Submit");
+        dashboard.handle_key(KeyCode::Char('s')).await;
+        dashboard.handle_key(KeyCode::Char('?')).await;
+        assert!(!dashboard.show_help);
+        assert_eq!(dashboard.submit_form.command, "s?");
+    }
+
+    #[tokio::test]
+    async fn test_log_search() {
+        let mut dashboard = Dashboard::new();
+        for line in ["Worker w1 connected", "Task t1 FAILED", "task t2 failed again", "Task t3 finished"] {
+            dashboard.add_log(line.to_string());
+        }
+        dashboard.next_tab();
+        dashboard.next_tab();
+        assert_eq!(dashboard.tabs[dashboard.current_tab], "Logs");
+
+        dashboard.handle_key(KeyCode::Char('/')).await;
+        for c in "fail".chars() {
+            dashboard.handle_key(KeyCode::Char(c)).await;
+        }
+        dashboard.handle_key(KeyCode::Enter).await;
+        assert!(!dashboard.editing_filter);
+        assert_eq!(dashboard.filtered_logs(), vec!["Task t1 FAILED", "task t2 failed again"]);
+
+        // Once confirmed, keys are shortcuts again
+        dashboard.handle_key(KeyCode::Char('?')).await;
+        assert!(dashboard.show_help);
+        dashboard.handle_key(KeyCode::Esc).await;
+
+        dashboard.handle_key(KeyCode::Esc).await;
+        assert!(dashboard.filter_string.is_empty());
+        assert_eq!(dashboard.filtered_logs().len(), 4);
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let line = highlight_matches("Task t1 FAILED, failed", "fail");
+        let spans: Vec<(&str, bool)> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style.fg == Some(Color::Yellow)))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![("Task t1 ", false), ("FAIL", true), ("ED, ", false), ("fail", true), ("ed", false)]
+        );
+        assert_eq!(highlight_matches("plain", "").spans.len(), 1);
+    }
+
     #[test]
     fn test_dashboard_logs() {
         let mut dashboard = Dashboard::new();
         dashboard.add_log("Test log".to_string());
         assert!(!dashboard.logs.is_empty());
     }
+
+    #[test]
+    fn test_dashboard_tasks_from_metrics() {
+        let metrics = Metrics::new();
+        metrics.set_queue_depth(2);
+
+        let mut dashboard = Dashboard::new();
+        dashboard.update_tasks(&metrics);
+        assert!(dashboard.tasks_display.contains(&"Queued Tasks: 2".to_string()));
+        assert!(!dashboard.tasks_display.iter().any(|line| line.starts_with("Task Duration")));
+    }
 }
 
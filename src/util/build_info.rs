@@ -0,0 +1,22 @@
+/// Crate version from `Cargo.toml`
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit the binary was built from; a placeholder when built outside a git checkout
+pub const GIT_SHA: &str = env!("VERGEN_GIT_SHA");
+
+/// When the binary was built, as an RFC 3339 timestamp
+pub const BUILD_TIMESTAMP: &str = env!("VERGEN_BUILD_TIMESTAMP");
+
+/// Version of the compiler that built the binary
+pub const RUSTC_VERSION: &str = env!("VERGEN_RUSTC_SEMVER");
+
+/// Target triple the binary was built for
+pub const TARGET: &str = env!("VERGEN_CARGO_TARGET_TRIPLE");
+
+/// Everything above, one `name: value` line each, as printed by `--build-info`
+pub fn summary() -> String {
+    format!(
+        "octaskly {}\ncommit:    {}\nbuilt at:  {}\nrustc:     {}\ntarget:    {}",
+        VERSION, GIT_SHA, BUILD_TIMESTAMP, RUSTC_VERSION, TARGET
+    )
+}
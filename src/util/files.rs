@@ -0,0 +1,125 @@
+use crate::protocol::FileSpec;
+use anyhow::{bail, Context, Result};
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+/// Join a task-relative `path` onto `base`, rejecting paths that would leave it
+pub fn resolve(base: &Path, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    if path.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("file path {:?} must be relative and stay inside the task directory", path);
+    }
+    Ok(base.join(relative))
+}
+
+/// Read the content of every file in `files` that does not carry it yet from under `base`
+pub async fn read_contents(base: &Path, files: &mut [FileSpec]) -> Result<()> {
+    for file in files.iter_mut().filter(|file| file.content.is_none()) {
+        let path = resolve(base, &file.path)?;
+        let content = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("reading {}", path.display()))?;
+        file.content = Some(content);
+    }
+    Ok(())
+}
+
+/// Read the script at the task-relative `path` under `base`
+pub async fn read_script(base: &Path, path: &Path) -> Result<Vec<u8>> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("script path {:?} is not valid UTF-8", path))?;
+    let path = resolve(base, path)?;
+    tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("reading {}", path.display()))
+}
+
+/// Write every file in `files` under `base`, creating parent directories as needed
+///
+/// Fails on a file without content, since it was never read on the sending side.
+pub async fn write_all(base: &Path, files: &[FileSpec]) -> Result<()> {
+    for file in files {
+        let path = resolve(base, &file.path)?;
+        let Some(content) = &file.content else {
+            bail!("file {} has no content", file.path);
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, content)
+            .await
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Read the files named in `files` from under `base`; missing ones come back without content
+pub async fn collect(base: &Path, files: &[FileSpec]) -> Vec<FileSpec> {
+    let mut collected = Vec::with_capacity(files.len());
+    for file in files {
+        let content = match resolve(base, &file.path) {
+            Ok(path) => match tokio::fs::read(&path).await {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    warn!("Output file {} not collected: {}", path.display(), e);
+                    None
+                }
+            },
+            Err(e) => {
+                warn!("Output file not collected: {}", e);
+                None
+            }
+        };
+        collected.push(FileSpec {
+            path: file.path.clone(),
+            content,
+        });
+    }
+    collected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rejects_escaping_paths() {
+        let base = Path::new("/work/task");
+        assert_eq!(resolve(base, "data/in.txt").unwrap(), base.join("data/in.txt"));
+        assert!(resolve(base, "").is_err());
+        assert!(resolve(base, "/etc/passwd").is_err());
+        assert!(resolve(base, "../secret").is_err());
+        assert!(resolve(base, "data/../../secret").is_err());
+        assert!(resolve(base, "./in.txt").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_files_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        std::fs::create_dir(source.path().join("data")).unwrap();
+        std::fs::write(source.path().join("data/in.bin"), [0u8, 1, 2]).unwrap();
+
+        let mut files = vec![
+            FileSpec::new("data/in.bin"),
+            FileSpec {
+                path: "inline.txt".to_string(),
+                content: Some(b"inline".to_vec()),
+            },
+        ];
+        read_contents(source.path(), &mut files).await.unwrap();
+        assert_eq!(files[0].content.as_deref(), Some(&[0u8, 1, 2][..]));
+
+        write_all(target.path(), &files).await.unwrap();
+        assert_eq!(std::fs::read(target.path().join("inline.txt")).unwrap(), b"inline");
+
+        let collected = collect(target.path(), &[FileSpec::new("data/in.bin"), FileSpec::new("missing")]).await;
+        assert_eq!(collected[0].content.as_deref(), Some(&[0u8, 1, 2][..]));
+        assert_eq!(collected[1].content, None);
+
+        let mut missing = vec![FileSpec::new("missing")];
+        assert!(read_contents(source.path(), &mut missing).await.is_err());
+        assert!(write_all(target.path(), &missing).await.is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::Instant;
+
+/// Point-in-time load of this process and host
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadSample {
+    /// CPU used by this process and its reaped children since the previous sample
+    pub cpu_percent: f32,
+    /// Resident memory of this process
+    pub memory_mb: u64,
+    /// Host load average over the last minute
+    pub load_avg_1m: f32,
+}
+
+/// Samples process load from `/proc` on Linux; reports zeros elsewhere
+#[derive(Debug, Default)]
+pub struct LoadSampler {
+    last_cpu: Option<(u64, Instant)>,
+}
+
+impl LoadSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a sample; CPU usage is averaged over the time since the last call
+    #[cfg(target_os = "linux")]
+    pub fn sample(&mut self) -> LoadSample {
+        let (ticks_per_sec, page_size) = unsafe {
+            (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE))
+        };
+
+        let mut sample = LoadSample::default();
+        if let Some(stat) = std::fs::read_to_string("/proc/self/stat").ok().and_then(|s| parse_stat(&s)) {
+            let now = Instant::now();
+            if let Some((last_ticks, last_at)) = self.last_cpu {
+                let elapsed = now.duration_since(last_at).as_secs_f64();
+                if elapsed > 0.0 && ticks_per_sec > 0 {
+                    let used = stat.cpu_ticks.saturating_sub(last_ticks) as f64 / ticks_per_sec as f64;
+                    sample.cpu_percent = (used / elapsed * 100.0) as f32;
+                }
+            }
+            self.last_cpu = Some((stat.cpu_ticks, now));
+            sample.memory_mb = stat.rss_pages * page_size.max(0) as u64 / (1024 * 1024);
+        }
+
+        sample.load_avg_1m = std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| s.split_whitespace().next()?.parse().ok())
+            .unwrap_or(0.0);
+        sample
+    }
+
+    /// Take a sample; CPU usage is averaged over the time since the last call
+    #[cfg(not(target_os = "linux"))]
+    pub fn sample(&mut self) -> LoadSample {
+        LoadSample::default()
+    }
+}
+
+/// Fields of `/proc/<pid>/stat` needed for a load sample
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct ProcStat {
+    /// utime + stime + cutime + cstime
+    cpu_ticks: u64,
+    rss_pages: u64,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_stat(contents: &str) -> Option<ProcStat> {
+    // The command name may contain spaces, so count fields from the closing paren
+    let fields: Vec<&str> = contents.get(contents.rfind(')')? + 1..)?.split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+
+    Some(ProcStat {
+        cpu_ticks: field(14)? + field(15)? + field(16)? + field(17)?,
+        rss_pages: field(24)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat_handles_spaces_in_command() {
+        let stat = "4242 (my worker) S 1 4242 4242 0 -1 4194560 500 0 0 0 120 30 7 3 20 0 4 0 100 \
+                    123456789 2048 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 3 0 0 0 0 0";
+        let parsed = parse_stat(stat).unwrap();
+        assert_eq!(parsed.cpu_ticks, 120 + 30 + 7 + 3);
+        assert_eq!(parsed.rss_pages, 2048);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sample_reads_own_process() {
+        let mut sampler = LoadSampler::new();
+        sampler.sample();
+        let sample = sampler.sample();
+        assert!(sample.memory_mb > 0);
+        assert!(sample.cpu_percent >= 0.0);
+    }
+}
@@ -1,4 +1,10 @@
+pub mod build_info;
+pub mod files;
+pub mod load;
+
 use anyhow::Result;
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::Path;
 use tracing::info;
 
@@ -10,9 +16,43 @@ pub fn get_local_ip() -> Option<String> {
     Some(local_addr.ip().to_string())
 }
 
+/// Address of the local interface whose subnet contains `target`
+///
+/// Falls back to `get_local_ip` when no interface matches. Unlike that, this
+/// picks the LAN address a peer on the LAN can reach, even when the default
+/// route goes through a VPN interface.
+pub fn get_best_local_ip(target: &IpAddr) -> Option<String> {
+    let interfaces = if_addrs::get_if_addrs().unwrap_or_default();
+    interfaces
+        .iter()
+        .find(|interface| subnet_contains(&interface.addr, target))
+        .map(|interface| interface.ip().to_string())
+        .or_else(get_local_ip)
+}
+
+/// Whether `target` lies in the subnet of the interface address `addr`
+fn subnet_contains(addr: &if_addrs::IfAddr, target: &IpAddr) -> bool {
+    match (addr, target) {
+        (if_addrs::IfAddr::V4(v4), IpAddr::V4(target)) => {
+            let mask = u32::from(v4.netmask);
+            u32::from(v4.ip) & mask == u32::from(*target) & mask
+        }
+        (if_addrs::IfAddr::V6(v6), IpAddr::V6(target)) => {
+            let mask = u128::from(v6.netmask);
+            u128::from(v6.ip) & mask == u128::from(*target) & mask
+        }
+        _ => false,
+    }
+}
+
 /// Setup tracing/logging with suppression for --help display
-pub fn setup_logging() {
-    use tracing_subscriber::EnvFilter;
+///
+/// `default_level` is used as the filter when `RUST_LOG` is not set. With a
+/// `tracer`, this crate's spans are also exported through OpenTelemetry
+/// regardless of the log filter.
+pub fn setup_logging(default_level: &str, tracer: Option<opentelemetry_sdk::trace::Tracer>) {
+    use tracing_subscriber::filter::{EnvFilter, Targets};
+    use tracing_subscriber::prelude::*;
 
     // If --help or -h is present, don't log to avoid timestamp noise
     let args: Vec<String> = std::env::args().collect();
@@ -21,18 +61,33 @@ pub fn setup_logging() {
     }
 
     let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
-
-    tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(false)
         .with_thread_ids(false)
-        .with_level(true)
+        .with_level(true);
+    let otel_layer = tracer.map(|tracer| {
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(Targets::new().with_target("octaskly", tracing::Level::INFO))
+    });
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(env_filter))
+        .with(otel_layer)
         .init();
 
     info!("Logging initialized");
 }
 
+/// Find a dispatcher advertising itself over mDNS within `timeout`
+///
+/// When several answer, the one with the shortest task queue is chosen.
+pub async fn discover_dispatcher(timeout: std::time::Duration) -> Result<Option<std::net::SocketAddr>> {
+    let dispatchers = crate::discovery::mdns::browse(timeout).await?;
+    Ok(dispatchers.into_iter().min_by_key(|ad| ad.queue_depth).map(|ad| ad.addr))
+}
+
 /// Create directory if not exists
 pub async fn ensure_dir(path: &Path) -> Result<()> {
     if !path.exists() {
@@ -41,6 +96,29 @@ pub async fn ensure_dir(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// ID stored in `path`, or a new UUID written there if the file does not exist yet
+pub fn load_or_create_id(path: &Path) -> Result<String> {
+    if let Ok(id) = std::fs::read_to_string(path) {
+        let id = id.trim();
+        if !id.is_empty() {
+            return Ok(id.to_string());
+        }
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, &id)?;
+    Ok(id)
+}
+
+/// Hex SHA-256 of a key or token, so it need not be kept or sent in the clear
+pub fn sha256_hex(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Get platform info
 pub fn get_platform_info() -> String {
     format!(
@@ -50,6 +128,55 @@ pub fn get_platform_info() -> String {
     )
 }
 
+/// Substrings of environment variable names whose values are treated as secrets
+pub const SENSITIVE_ENV_PATTERNS: &[&str] = &["SECRET", "PASSWORD", "TOKEN", "KEY", "CREDENTIAL"];
+
+/// Replacement shown for secret values in logs and stored output
+pub const MASK: &str = "***";
+
+/// Whether `name` contains a built-in or `extra` pattern, ignoring case
+pub fn is_sensitive_env(name: &str, extra: &[String]) -> bool {
+    let name = name.to_uppercase();
+    SENSITIVE_ENV_PATTERNS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+        .any(|pattern| name.contains(&pattern.to_uppercase()))
+}
+
+/// Copy of `env` safe to log, with secret values replaced by `***`
+pub fn mask_sensitive_env(env: &HashMap<String, String>) -> HashMap<String, String> {
+    mask_sensitive_env_with(env, &[])
+}
+
+/// Like `mask_sensitive_env`, also masking names that contain an `extra` pattern
+pub fn mask_sensitive_env_with(env: &HashMap<String, String>, extra: &[String]) -> HashMap<String, String> {
+    env.iter()
+        .map(|(name, value)| match is_sensitive_env(name, extra) {
+            true => (name.clone(), MASK.to_string()),
+            false => (name.clone(), value.clone()),
+        })
+        .collect()
+}
+
+/// Values of the secret variables in `env`, longest first so overlapping secrets mask fully
+pub fn sensitive_env_values(env: &HashMap<String, String>, extra: &[String]) -> Vec<String> {
+    let mut values: Vec<String> = env
+        .iter()
+        .filter(|(name, value)| !value.is_empty() && is_sensitive_env(name, extra))
+        .map(|(_, value)| value.clone())
+        .collect();
+    values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    values
+}
+
+/// `text` with every occurrence of a `secrets` value replaced by `***`
+pub fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), MASK))
+}
+
 /// Format bytes to human readable
 pub fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
@@ -68,9 +195,57 @@ pub fn format_bytes(bytes: u64) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_load_or_create_id_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join(".worker_id");
+
+        let id = load_or_create_id(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), id);
+        assert_eq!(load_or_create_id(&path).unwrap(), id);
+    }
+
+    #[test]
+    fn test_subnet_contains() {
+        let lan = if_addrs::IfAddr::V4(if_addrs::Ifv4Addr {
+            ip: "192.168.1.20".parse().unwrap(),
+            netmask: "255.255.255.0".parse().unwrap(),
+            prefixlen: 24,
+            broadcast: None,
+        });
+        assert!(subnet_contains(&lan, &"192.168.1.5".parse().unwrap()));
+        assert!(!subnet_contains(&lan, &"10.8.0.1".parse().unwrap()));
+        assert!(!subnet_contains(&lan, &"::1".parse().unwrap()));
+
+        // Loopback is always an interface, so a local dispatcher is reached over it
+        assert_eq!(get_best_local_ip(&"127.0.0.1".parse().unwrap()).as_deref(), Some("127.0.0.1"));
+    }
+
     #[test]
     fn test_format_bytes() {
         assert!(format_bytes(512).contains("B"));
         assert!(format_bytes(1024).contains("KB"));
     }
+
+    #[test]
+    fn test_mask_sensitive_env() {
+        let env = HashMap::from([
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "abc123".to_string()),
+            ("db_password".to_string(), "hunter2".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("STRIPE_LIVE".to_string(), "sk_live".to_string()),
+        ]);
+
+        let masked = mask_sensitive_env(&env);
+        assert_eq!(masked["AWS_SECRET_ACCESS_KEY"], "***");
+        assert_eq!(masked["db_password"], "***");
+        assert_eq!(masked["PATH"], "/usr/bin");
+        assert_eq!(masked["STRIPE_LIVE"], "sk_live");
+
+        let masked = mask_sensitive_env_with(&env, &["stripe".to_string()]);
+        assert_eq!(masked["STRIPE_LIVE"], "***");
+
+        let secrets = sensitive_env_values(&env, &[]);
+        assert_eq!(mask_secrets("key=abc123 pw=hunter2 path=/usr/bin", &secrets), "key=*** pw=*** path=/usr/bin");
+    }
 }
@@ -0,0 +1,174 @@
+use crate::protocol::{TaskResult, TaskStatus};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Retries after a failed delivery before the notification is dropped
+pub const MAX_RETRIES: u32 = 3;
+
+/// Wait between delivery attempts
+pub const RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Request timeout used by `notify`
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Characters of stdout and stderr included in the payload
+pub const OUTPUT_PREVIEW_CHARS: usize = 1024;
+
+/// JSON body POSTed to a task's `callback_url` once it finishes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub task_id: String,
+    pub status: TaskStatus,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub stdout_preview: String,
+    pub stderr_preview: String,
+}
+
+impl From<&TaskResult> for WebhookPayload {
+    fn from(result: &TaskResult) -> Self {
+        Self {
+            task_id: result.task_id.clone(),
+            status: result.status,
+            exit_code: result.exit_code,
+            duration_ms: result.duration_ms,
+            stdout_preview: preview(&result.stdout),
+            stderr_preview: preview(&result.stderr),
+        }
+    }
+}
+
+fn preview(output: &str) -> String {
+    output.chars().take(OUTPUT_PREVIEW_CHARS).collect()
+}
+
+/// Delivers task results to callback URLs, retrying failed deliveries
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    retry_backoff: Duration,
+}
+
+impl WebhookNotifier {
+    /// Notifier whose requests give up after `timeout`
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("Failed to build webhook HTTP client")?;
+        Ok(Self {
+            client,
+            retry_backoff: RETRY_BACKOFF,
+        })
+    }
+
+    /// Wait `backoff` between attempts instead of `RETRY_BACKOFF`
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// POST `result` to `url` once; non-2xx responses are errors
+    pub async fn send(&self, url: &str, result: &TaskResult) -> Result<()> {
+        let response = self
+            .client
+            .post(url)
+            .json(&WebhookPayload::from(result))
+            .send()
+            .await
+            .with_context(|| format!("Failed to POST webhook to {}", url))?;
+        response
+            .error_for_status()
+            .with_context(|| format!("Webhook {} rejected the notification", url))?;
+        Ok(())
+    }
+
+    /// POST `result` to `url`, retrying up to `MAX_RETRIES` times
+    pub async fn deliver(&self, url: &str, result: &TaskResult) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.send(url, result).await {
+                Ok(()) => {
+                    debug!("Delivered webhook for task {} to {}", result.task_id, url);
+                    return Ok(());
+                }
+                Err(e) if attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    warn!(
+                        "Webhook for task {} failed, retrying ({}/{}): {:#}",
+                        result.task_id, attempt, MAX_RETRIES, e
+                    );
+                    tokio::time::sleep(self.retry_backoff).await;
+                }
+                Err(e) => {
+                    warn!("Giving up on webhook for task {}: {:#}", result.task_id, e);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// POST `result` to `url` with the default timeout and retries
+pub async fn notify(url: &str, result: &TaskResult) -> Result<()> {
+    WebhookNotifier::new(DEFAULT_TIMEOUT)?.deliver(url, result).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn result() -> TaskResult {
+        TaskResult {
+            task_id: "task-1".to_string(),
+            worker_id: "worker-1".to_string(),
+            status: TaskStatus::Failed,
+            stdout: "x".repeat(OUTPUT_PREVIEW_CHARS + 10),
+            stderr: "boom\n".to_string(),
+            exit_code: Some(2),
+            duration_ms: 42,
+            completed_at: 0,
+            resource_usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_posts_truncated_payload() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_json(serde_json::json!({
+                "task_id": "task-1",
+                "status": "Failed",
+                "exit_code": 2,
+                "duration_ms": 42,
+                "stdout_preview": "x".repeat(OUTPUT_PREVIEW_CHARS),
+                "stderr_preview": "boom\n",
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        notify(&format!("{}/hook", server.uri()), &result()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_delivery() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(u64::from(MAX_RETRIES) + 1)
+            .mount(&server)
+            .await;
+
+        let notifier = WebhookNotifier::new(DEFAULT_TIMEOUT)
+            .unwrap()
+            .with_retry_backoff(Duration::from_millis(10));
+        assert!(notifier.deliver(&server.uri(), &result()).await.is_err());
+    }
+}
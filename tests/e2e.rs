@@ -0,0 +1,127 @@
+// End-to-end tests over the network path: REST API -> dispatcher -> worker -> dispatcher
+mod harness;
+
+use harness::TestCluster;
+
+#[tokio::test]
+async fn test_workers_register_with_dispatcher() {
+    let cluster = TestCluster::start(2).await.unwrap();
+    let workers = cluster.workers().await.unwrap();
+    let mut names: Vec<_> = workers.iter().filter_map(|w| w["name"].as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["e2e-worker-0", "e2e-worker-1"]);
+}
+
+#[tokio::test]
+async fn test_submitted_task_runs_on_a_worker() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    let task_id = cluster.submit_task("echo hello from e2e").await;
+
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Completed");
+    assert_eq!(task["exit_code"], 0);
+    assert!(task["stdout"].as_str().unwrap().contains("hello from e2e"));
+    let workers = cluster.workers().await.unwrap();
+    assert_eq!(task["worker_id"], workers[0]["id"]);
+}
+
+#[tokio::test]
+async fn test_failed_task_reports_exit_code() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    let task_id = cluster.submit_task("exit 3").await;
+
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Failed");
+    assert_eq!(task["exit_code"], 3);
+}
+
+#[tokio::test]
+async fn test_tasks_spread_over_workers_all_finish() {
+    let cluster = TestCluster::start(2).await.unwrap();
+    let mut task_ids = Vec::new();
+    for i in 0..6 {
+        task_ids.push(cluster.submit_task(&format!("echo task-{}", i)).await);
+    }
+
+    for (i, task_id) in task_ids.iter().enumerate() {
+        let task = cluster.wait_for_task(task_id).await.unwrap();
+        assert_eq!(task["status"], "Completed");
+        assert!(task["stdout"].as_str().unwrap().contains(&format!("task-{}", i)));
+    }
+}
+
+#[tokio::test]
+async fn test_worker_keeps_taking_tasks_after_filling_its_slots() {
+    let cluster = TestCluster::start_with_max_jobs(1, 2).await.unwrap();
+    let worker_id = cluster.workers().await.unwrap()[0]["id"].clone();
+
+    // One at a time, so each task only runs once the previous one freed its slot
+    for i in 0..5 {
+        let task_id = cluster.submit_task(&format!("echo round-{}", i)).await;
+        let task = cluster.wait_for_task(&task_id).await.unwrap();
+        assert_eq!(task["status"], "Completed");
+        assert_eq!(task["worker_id"], worker_id);
+    }
+}
+
+#[tokio::test]
+async fn test_cancelling_a_running_task_kills_it() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    let marker = cluster.dispatcher_workdir().join("not-cancelled");
+    let task_id = cluster.submit_task(&format!("sleep 1; touch {}", marker.display())).await;
+    cluster.wait_until_running(&task_id).await.unwrap();
+
+    assert_eq!(cluster.cancel_task(&task_id).await.unwrap(), reqwest::StatusCode::ACCEPTED);
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Cancelled");
+
+    // The command would have created the marker by now had it kept running
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert!(!marker.exists());
+}
+
+#[tokio::test]
+async fn test_input_and_output_files_are_staged() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    let workdir = cluster.dispatcher_files_dir();
+    std::fs::create_dir_all(workdir.join("data")).unwrap();
+    std::fs::write(workdir.join("data/in.txt"), "staged input\n").unwrap();
+
+    let task_id = cluster
+        .submit(serde_json::json!({
+            "command": "tr a-z A-Z < data/in.txt > result.txt",
+            "input_files": ["data/in.txt"],
+            "output_files": ["result.txt"],
+        }))
+        .await;
+
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Completed");
+    let stored = workdir.join("outputs").join(&task_id).join("result.txt");
+    assert_eq!(std::fs::read_to_string(stored).unwrap(), "STAGED INPUT\n");
+}
+
+#[tokio::test]
+async fn test_missing_input_file_fails_the_task() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    let task_id = cluster
+        .submit(serde_json::json!({ "command": "cat missing.txt", "input_files": ["missing.txt"] }))
+        .await;
+
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Failed");
+    assert!(task["stderr"].as_str().unwrap().contains("missing.txt"));
+}
+
+#[tokio::test]
+async fn test_input_files_cannot_reach_the_workdir_root() {
+    let cluster = TestCluster::start(1).await.unwrap();
+    std::fs::write(cluster.dispatcher_workdir().join("ca-key.pem"), "secret").unwrap();
+    let task_id = cluster
+        .submit(serde_json::json!({ "command": "cat ca-key.pem", "input_files": ["ca-key.pem"] }))
+        .await;
+
+    let task = cluster.wait_for_task(&task_id).await.unwrap();
+    assert_eq!(task["status"], "Failed");
+    assert!(!task["stdout"].as_str().unwrap_or_default().contains("secret"));
+}
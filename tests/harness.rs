@@ -0,0 +1,217 @@
+// In-process cluster for end-to-end tests: a full dispatcher and N workers talking over loopback
+#![allow(dead_code)]
+
+use anyhow::{bail, Context, Result};
+use octaskly::auth::{AuthManager, Claims};
+use octaskly::config::Config;
+use octaskly::executor::ExecutorKind;
+use octaskly::node::{run_dispatcher, run_worker};
+use octaskly::transport::{Transport, TransportBackend};
+use serde_json::Value;
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+/// Secret the cluster's REST API signs tokens with, so tests can mint their own
+const API_SECRET: &str = "e2e-secret";
+
+/// How long `start` waits for every worker to register, and `wait_for_task` for a task to finish
+const READY_TIMEOUT: Duration = Duration::from_secs(15);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct TestCluster {
+    pub dispatcher_handle: JoinHandle<()>,
+    pub worker_handles: Vec<JoinHandle<()>>,
+    pub dispatcher_addr: SocketAddr,
+    pub api_addr: SocketAddr,
+    client: reqwest::Client,
+    token: String,
+    dir: TempDir,
+}
+
+impl TestCluster {
+    /// Start a dispatcher with its REST API and `n_workers` workers, returning once all have registered
+    pub async fn start(n_workers: usize) -> Result<TestCluster> {
+        Self::start_with_max_jobs(n_workers, Config::default().max_jobs).await
+    }
+
+    /// Like `start`, with each worker running at most `max_jobs` tasks at once
+    pub async fn start_with_max_jobs(n_workers: usize, max_jobs: usize) -> Result<TestCluster> {
+        let dir = tempfile::tempdir()?;
+        let dispatcher_addr: SocketAddr = format!("127.0.0.1:{}", free_port()?).parse()?;
+        let api_addr: SocketAddr = format!("127.0.0.1:{}", free_port()?).parse()?;
+
+        let config = Config {
+            bind: "127.0.0.1".to_string(),
+            port: dispatcher_addr.port(),
+            workdir: dir.path().join("dispatcher"),
+            api_port: Some(api_addr.port()),
+            api_secret: Some(API_SECRET.to_string()),
+            ..Default::default()
+        };
+        let dispatcher_handle = tokio::spawn(async move {
+            let transport: Arc<dyn TransportBackend> = Arc::new(Transport::default());
+            if let Err(e) = run_dispatcher(&config, None, transport).await {
+                eprintln!("test dispatcher stopped: {:#}", e);
+            }
+        });
+
+        let worker_handles = (0..n_workers)
+            .map(|i| {
+                let config = Config {
+                    port: dispatcher_addr.port(),
+                    workdir: dir.path().join(format!("worker-{}", i)),
+                    max_jobs,
+                    ..Default::default()
+                };
+                tokio::spawn(async move {
+                    let name = format!("e2e-worker-{}", i);
+                    let transport: Arc<dyn TransportBackend> = Arc::new(Transport::default());
+                    let dispatchers = vec![dispatcher_addr.to_string()];
+                    if let Err(e) =
+                        run_worker(&name, None, &config, Vec::new(), ExecutorKind::Shell, transport, dispatchers).await
+                    {
+                        eprintln!("test worker {} stopped: {:#}", i, e);
+                    }
+                })
+            })
+            .collect();
+
+        let token = AuthManager::new(API_SECRET.to_string())
+            .generate_token(&Claims::new("e2e".to_string(), "admin".to_string(), vec![]))?;
+        let cluster = TestCluster {
+            dispatcher_handle,
+            worker_handles,
+            dispatcher_addr,
+            api_addr,
+            client: reqwest::Client::new(),
+            token,
+            dir,
+        };
+        cluster.wait_for_workers(n_workers).await?;
+        Ok(cluster)
+    }
+
+    /// Submit `cmd` through the REST API, returning the new task's ID
+    pub async fn submit_task(&self, cmd: &str) -> String {
+        self.submit(serde_json::json!({ "command": cmd })).await
+    }
+
+    /// Submit a full `POST /api/v1/tasks` body, returning the new task's ID
+    pub async fn submit(&self, body: Value) -> String {
+        let response: Value = self
+            .client
+            .post(self.url("/api/v1/tasks"))
+            .json(&body)
+            .send()
+            .await
+            .expect("task submission failed")
+            .json()
+            .await
+            .expect("task submission returned no JSON");
+        response["id"].as_str().expect("task submission returned no ID").to_string()
+    }
+
+    /// `GET /api/v1/tasks/:id` once the task has left the queue and finished
+    pub async fn wait_for_task(&self, task_id: &str) -> Result<Value> {
+        let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+        loop {
+            let response = self.client.get(self.url(&format!("/api/v1/tasks/{}", task_id))).send().await?;
+            if response.status().is_success() {
+                let task: Value = response.json().await?;
+                if !matches!(task["status"].as_str(), Some("Pending" | "Running")) {
+                    return Ok(task);
+                }
+            }
+            if tokio::time::Instant::now() > deadline {
+                bail!("task {} did not finish within {:?}", task_id, READY_TIMEOUT);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Wait until the task has been dispatched to a worker
+    pub async fn wait_until_running(&self, task_id: &str) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+        loop {
+            let response = self.client.get(self.url(&format!("/api/v1/tasks/{}", task_id))).send().await?;
+            if response.status().is_success() {
+                let task: Value = response.json().await?;
+                if task["status"] == "Running" {
+                    return Ok(());
+                }
+            }
+            if tokio::time::Instant::now() > deadline {
+                bail!("task {} was not dispatched within {:?}", task_id, READY_TIMEOUT);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// `DELETE /api/v1/tasks/:id`, returning the response status
+    pub async fn cancel_task(&self, task_id: &str) -> Result<reqwest::StatusCode> {
+        let response = self.client.delete(self.url(&format!("/api/v1/tasks/{}", task_id))).send().await?;
+        Ok(response.status())
+    }
+
+    /// Workers registered with the dispatcher, from `GET /api/v1/workers`
+    pub async fn workers(&self) -> Result<Vec<Value>> {
+        let response = self
+            .client
+            .get(self.url("/api/v1/workers"))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    /// Working directory of the dispatcher
+    pub fn dispatcher_workdir(&self) -> PathBuf {
+        self.dir.path().join("dispatcher")
+    }
+
+    /// Directory under the dispatcher's workdir that task input files are staged from
+    pub fn dispatcher_files_dir(&self) -> PathBuf {
+        self.dispatcher_workdir().join("files")
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.api_addr, path)
+    }
+
+    async fn wait_for_workers(&self, n_workers: usize) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+        loop {
+            // The API comes up shortly after the dispatcher starts, so early requests may fail
+            if let Ok(workers) = self.workers().await {
+                if workers.len() == n_workers {
+                    return Ok(());
+                }
+            }
+            if tokio::time::Instant::now() > deadline {
+                let registered = self.workers().await.map(|w| w.len()).context("REST API never came up")?;
+                bail!("{} of {} workers registered within {:?}", registered, n_workers, READY_TIMEOUT);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        for handle in &self.worker_handles {
+            handle.abort();
+        }
+        self.dispatcher_handle.abort();
+    }
+}
+
+/// A port nothing is listening on right now
+fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
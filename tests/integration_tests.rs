@@ -2,8 +2,8 @@
 #[cfg(test)]
 mod integration_tests {
     use octaskly::protocol::{Task, WorkerInfo, TaskStatus};
-    use octaskly::scheduler::Scheduler;
-    use octaskly::executor::Executor;
+    use octaskly::scheduler::{Scheduler, SchedulerAlgorithm};
+    use octaskly::executor::{CommandPolicy, Executor};
     use octaskly::state::{DispatcherState, WorkerState};
     use std::path::PathBuf;
     use std::sync::Arc;
@@ -12,7 +12,7 @@ mod integration_tests {
     async fn test_dispatcher_worker_workflow() {
         // Setup dispatcher
         let _dispatcher = Arc::new(DispatcherState::new("test-dispatcher".to_string(), 7878));
-        let scheduler = Arc::new(Scheduler::new());
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
 
         // Setup worker info
         let worker = WorkerInfo::new(
@@ -31,7 +31,7 @@ mod integration_tests {
 
     #[tokio::test]
     async fn test_task_execution() {
-        let executor = Executor::new(PathBuf::from("/tmp"), true);
+        let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
         let task = Task::new("echo 'test'".to_string());
         
         let result = executor.execute_with_timeout(&task).await;
@@ -44,7 +44,7 @@ mod integration_tests {
 
     #[tokio::test]
     async fn test_task_timeout() {
-        let executor = Executor::new(PathBuf::from("/tmp"), true);
+        let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
         let mut task = Task::new("sleep 10".to_string());
         task.timeout = 1; // 1 second timeout
         
@@ -57,7 +57,7 @@ mod integration_tests {
 
     #[tokio::test]
     async fn test_task_queue_fifo() {
-        let scheduler = Arc::new(Scheduler::new());
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
         
         let task1 = Task::new("echo '1'".to_string());
         let task2 = Task::new("echo '2'".to_string());
@@ -80,7 +80,7 @@ mod integration_tests {
 
     #[tokio::test]
     async fn test_worker_state_management() {
-        let worker_state = Arc::new(WorkerState::new("test-worker".to_string(), 7879));
+        let worker_state = Arc::new(WorkerState::new("test-worker".to_string(), 7879, None));
         
         // Initially no task
         assert!(worker_state.get_current_task().await.is_none());
@@ -113,6 +113,7 @@ mod integration_tests {
             exit_code: Some(0),
             duration_ms: 100,
             completed_at: chrono::Local::now().timestamp(),
+            resource_usage: None,
         };
         
         dispatcher.store_result(result.clone()).await;
@@ -143,7 +144,7 @@ mod integration_tests {
 
     #[test]
     fn test_command_validation() {
-        let executor = Executor::new(PathBuf::from("/tmp"), true);
+        let executor = Executor::new(PathBuf::from("/tmp"), true, CommandPolicy::default());
         
         // Safe commands
         assert!(executor.validate_command("echo 'hello'"));
@@ -155,4 +156,159 @@ mod integration_tests {
         assert!(!executor.validate_command("dd if=/dev/zero"));
         assert!(!executor.validate_command(":(){:|:&};:"));
     }
+
+    #[tokio::test]
+    async fn test_gpu_task_not_dispatched_to_cpu_worker() {
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
+
+        let cpu_worker = WorkerInfo::new(
+            "cpu-worker".to_string(),
+            "127.0.0.1".to_string(),
+            7879,
+            4,
+        );
+        scheduler.register_worker(cpu_worker).await;
+
+        let mut task = Task::new("nvidia-smi".to_string());
+        task.required_tags = vec!["gpu".to_string()];
+        scheduler.enqueue(task.clone()).await;
+
+        assert!(scheduler.get_idle_worker_for_task(&task).await.is_none());
+        assert!(scheduler.schedule_next_task().await.is_none());
+        assert_eq!(scheduler.queue_size().await, 1);
+
+        let mut gpu_worker = WorkerInfo::new(
+            "gpu-worker".to_string(),
+            "127.0.0.1".to_string(),
+            7880,
+            1,
+        );
+        gpu_worker.tags = vec!["gpu".to_string()];
+        scheduler.register_worker(gpu_worker).await;
+
+        let (scheduled, worker) = scheduler.schedule_next_task().await.unwrap();
+        assert_eq!(scheduled.id, task.id);
+        assert_eq!(worker.name, "gpu-worker");
+    }
+
+    #[tokio::test]
+    async fn test_api_rate_limits_task_submissions() {
+        use octaskly::api::{self, ApiState, RateLimiter};
+        use octaskly::auth::AuthManager;
+        use octaskly::persistence::{SqliteStore, StoreBackend};
+
+        let store: Arc<dyn StoreBackend + Send + Sync> = Arc::new(SqliteStore::new(":memory:").await.unwrap());
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
+        let dispatcher = Arc::new(
+            DispatcherState::new("test-dispatcher".to_string(), 0)
+                .with_scheduler(scheduler.clone())
+                .with_store(store.clone()),
+        );
+        let state = ApiState {
+            scheduler: scheduler.clone(),
+            dispatcher: dispatcher.clone(),
+            auth: Arc::new(AuthManager::new("secret".to_string())),
+            store,
+            metrics: dispatcher.metrics().clone(),
+            task_events: dispatcher.task_events().clone(),
+            max_batch_size: 10,
+            // Bursts of 10, refilling far slower than the test runs
+            rate_limiter: RateLimiter::new(10, 0.01),
+            transport: Arc::new(octaskly::transport::Transport::default()),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/v1/tasks", listener.local_addr().unwrap());
+        tokio::spawn(api::serve_api(listener, state));
+
+        let client = reqwest::Client::new();
+        let mut responses = Vec::new();
+        for _ in 0..20 {
+            let response = client
+                .post(&url)
+                .json(&serde_json::json!({ "command": "echo hi" }))
+                .send()
+                .await
+                .unwrap();
+            responses.push(response);
+        }
+
+        assert!(responses[..10].iter().all(|r| r.status() == reqwest::StatusCode::CREATED));
+        assert_eq!(responses[10].status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = responses[10].headers()["retry-after"].to_str().unwrap().parse().unwrap();
+        assert!(retry_after >= 1);
+        assert_eq!(scheduler.queue_size().await, 10);
+
+        // Reads are not limited
+        assert!(client.get(&url).send().await.unwrap().status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_global_event_stream() {
+        use octaskly::api::{self, ApiState, RateLimiter};
+        use octaskly::auth::{AuthManager, Claims};
+        use octaskly::persistence::{SqliteStore, StoreBackend};
+        use octaskly::state::events::DispatcherEvent;
+
+        let store: Arc<dyn StoreBackend + Send + Sync> = Arc::new(SqliteStore::new(":memory:").await.unwrap());
+        let scheduler = Arc::new(Scheduler::new(SchedulerAlgorithm::Fifo));
+        let dispatcher = Arc::new(
+            DispatcherState::new("test-dispatcher".to_string(), 0)
+                .with_scheduler(scheduler.clone())
+                .with_store(store.clone()),
+        );
+        let auth = Arc::new(AuthManager::new("secret".to_string()));
+        let token = auth
+            .generate_token(&Claims::new("monitor".to_string(), "client".to_string(), vec![]))
+            .unwrap();
+        let state = ApiState {
+            scheduler,
+            dispatcher: dispatcher.clone(),
+            auth,
+            store,
+            metrics: dispatcher.metrics().clone(),
+            task_events: dispatcher.task_events().clone(),
+            max_batch_size: 10,
+            rate_limiter: RateLimiter::new(0, 0.0),
+            transport: Arc::new(octaskly::transport::Transport::default()),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(api::serve_api(listener, state));
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/v1/events", base);
+        assert_eq!(client.get(&url).send().await.unwrap().status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let mut stream = client.get(&url).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(stream.status(), reqwest::StatusCode::OK);
+
+        // A submission through the API, then events published by the dispatcher
+        let response = client
+            .post(format!("{}/api/v1/tasks", base))
+            .json(&serde_json::json!({ "command": "echo hi" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        dispatcher.publish_event(DispatcherEvent::WorkerConnected {
+            worker_id: "w1".to_string(),
+            name: "build-box".to_string(),
+        });
+        dispatcher.publish_event(DispatcherEvent::QueueDepth { depth: 1 });
+
+        let mut body = String::new();
+        while !body.contains("queue.depth_changed") {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), stream.chunk())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            body.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        let names: Vec<&str> = body.lines().filter_map(|line| line.strip_prefix("event: ")).collect();
+        assert_eq!(names, ["task.submitted", "worker.connected", "queue.depth_changed"]);
+        assert!(body.contains(r#""name":"build-box""#));
+    }
 }